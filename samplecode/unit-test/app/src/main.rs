@@ -53,6 +53,11 @@ fn main() {
         },
     };
 
+    if let Err(x) = enclave.set_args(&["unit-test", "--verbose", "caf\u{e9}"]) {
+        println!("[-] Set Enclave Args Failed {}!", x.as_str());
+        return;
+    }
+
     let mut retval = 0usize;
 
     let result = unsafe {
@@ -69,6 +74,11 @@ fn main() {
     }
     assert_eq!(retval, 0);
 
+    if let Err(x) = enclave.exit_thread() {
+        println!("[-] Exit Enclave Thread Failed {}!", x.as_str());
+        return;
+    }
+
     println!("[+] unit_test ended!");
 
     enclave.destroy();
@@ -46,6 +46,7 @@ pub use sgx_serialize::*;
 extern crate sgx_serialize_derive;
 extern crate sgx_libc;
 extern crate sgx_signal;
+extern crate sgx_tls;
 
 pub use sgx_serialize::*;
 use sgx_tunittest::*;
@@ -112,6 +113,15 @@ use test_exception::*;
 mod test_fp;
 use test_fp::*;
 
+mod test_net;
+use test_net::*;
+
+mod test_process;
+use test_process::*;
+
+mod test_tls;
+use test_tls::*;
+
 #[no_mangle]
 pub extern "C" fn test_main_entrance() -> size_t {
     rsgx_unit_tests!(
@@ -149,6 +159,22 @@ pub extern "C" fn test_main_entrance() -> size_t {
         test_ascii,
         // rts::c_str
         test_cstr,
+        test_cstr_hash,
+        test_cstr_truncate,
+        test_cstr_as_ptr_range,
+        test_cstr_join,
+        test_cstr_debug_alternate,
+        test_cstr_from_fmt,
+        test_cstr_to_string_lossy_into,
+        test_cstr_eq,
+        test_cstr_new_lossy,
+        test_cstr_to_bytes_escaped,
+        test_cstring_revalidate,
+        test_cstr_interner,
+        test_cstr_ascii_case,
+        test_cstr_unchecked_debug_asserts,
+        test_cstring_leak,
+        test_cstring_encode_decode,
         // tseal
         test_seal_unseal,
         test_number_sealing, // Thanks to @silvanegli
@@ -167,12 +193,27 @@ pub extern "C" fn test_main_entrance() -> size_t {
         test_serialize_enum,
         // std::sgxfs
         test_sgxfs,
+        test_sgxfs_len_and_metadata,
+        test_sgxfs_rekey,
+        test_sgxfs_file_map,
         // std::fs
         test_fs,
+        test_fs_read_dir_sealed_blobs,
+        test_fs_file_lock,
+        test_fs_tempfile,
+        test_fs_watcher,
+        test_fs_integrity_file,
+        test_fs_async_file,
+        test_fs_canonicalize,
         // std::fs untrusted mode
         test_fs_untrusted_fs_feature_enabled,
         // std::time
         test_std_time,
+        test_time_condvar_wait_timeout_source,
+        test_time_instant_tsc,
+        test_time_attested_system_time,
+        test_time_cached_now,
+        test_time_timer_wheel,
         // rand
         test_rand_cratesio,
         // types
@@ -180,9 +221,23 @@ pub extern "C" fn test_main_entrance() -> size_t {
         check_version,
         // env
         test_env_vars_os,
+        test_env_vars_os_exact_size,
         test_env_self_exe_path,
         test_env_current_dir,
         test_env_home_dir,
+        test_env_home_dir_from_var,
+        test_env_home_dir_without_var,
+        test_env_home_dir_passwd_dir,
+        test_env_temp_dir,
+        test_env_var_error_into_os_string,
+        test_env_join_paths_empty_component,
+        test_env_take_var,
+        test_env_watch_var,
+        test_env_vars_os_with_prefix,
+        test_env_snapshot,
+        test_env_trusted_table,
+        test_env_import_verified,
+        test_env_args,
         //path
         test_path_stat_is_correct_on_is_dir,
         test_path_fileinfo_false_when_checking_is_file_on_a_directory,
@@ -230,6 +285,16 @@ pub extern "C" fn test_main_entrance() -> size_t {
         test_thread_size_of_option_thread_id,
         test_thread_id_equal,
         test_thread_id_not_equal,
+        test_thread_stack_size,
+        test_thread_adaptive_park,
+        test_thread_dtor_order,
+        test_thread_pool,
+        test_thread_fast_rwlock,
+        test_thread_once_lock,
+        test_thread_barrier_more_threads_than_n,
+        test_thread_barrier_wait_group,
+        test_thread_switchless_sync,
+        test_thread_sleep_batched,
         //test mpsc
         test_mpsc_smoke,
         test_mpsc_drop_full,
@@ -333,6 +398,8 @@ pub extern "C" fn test_main_entrance() -> size_t {
         test_mpsc_sync_try_send2,
         test_mpsc_sync_try_send3,
         test_mpsc_sync_issue_15761,
+        test_mpmc_bounded,
+        test_mpmc_select,
         //test alignbox
         test_alignbox,
         test_alignbox_heap_init,
@@ -349,5 +416,54 @@ pub extern "C" fn test_main_entrance() -> size_t {
         test_fp64,
         //test exception
         test_exception_handler,
+        //test net
+        test_net_unix_peer_cred,
+        test_net_set_mark,
+        test_net_poll_listener_readable,
+        test_net_udp_recv_deadline,
+        test_net_tcp_bind_with_backlog,
+        test_net_udp_recv_vectored,
+        test_net_tcp_quickack,
+        test_net_tcp_eof_on_reset,
+        test_net_ip_array_conversions,
+        test_net_tcp_connect_with_retry,
+        test_net_socket_addr_setters,
+        test_net_udp_recv_tos,
+        test_net_socket_addr_from,
+        test_net_close_error,
+        test_net_test_resolver,
+        test_net_udp_peer_and_local_addr,
+        test_net_tcp_take_read_to_end,
+        test_net_ip_net_contains,
+        test_net_tcp_write_all_timeout,
+        test_net_shutdown_raw,
+        test_net_tcp_wait_readable,
+        test_net_addr_parse_error_position,
+        test_net_metrics_sink,
+        test_net_udp_send_to_vectored,
+        test_net_ipv4_predicates,
+        test_net_tcp_read_into_pooled,
+        test_net_tcp_configure,
+        test_net_host_port_parse,
+        test_net_tcp_take_error_refused,
+        test_net_nonblocking_would_block,
+        test_net_unix_listener_and_datagram,
+        test_net_resolver_cache,
+        test_net_resolver_cache_replaced,
+        test_net_epoll,
+        test_net_local_executor,
+        test_net_tcp_extended_sockopts,
+        test_net_udp_multicast_options,
+        test_net_raw_socket_ping,
+        test_net_vsock,
+        test_net_tcp_vectored,
+        test_net_tcp_send_file,
+        test_net_tcp_happy_eyeballs,
+        test_net_tcp_accept_timeout,
+        test_process_output,
+        test_process_spawn_pipes,
+        test_process_register_secret,
+        test_tls_sealed_handshake,
+        test_tls_ratls_verify,
     )
 }
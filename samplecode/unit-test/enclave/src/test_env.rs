@@ -1,5 +1,7 @@
 use std::env::*;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::string::String;
+use std::vec::Vec;
 
 pub fn test_env_vars_os() {
     let p = vars_os();
@@ -24,3 +26,370 @@ pub fn test_env_home_dir() {
     let dir = home_dir();
     println!("{:?}", dir.unwrap());
 }
+
+pub fn test_env_home_dir_from_var() {
+    let old = var_os("HOME");
+
+    set_var("HOME", "/enclave/home");
+    assert_eq!(home_dir(), Some(PathBuf::from("/enclave/home")));
+
+    // An empty `HOME` is a valid value and must not trigger the passwd lookup.
+    set_var("HOME", "");
+    assert_eq!(home_dir(), Some(PathBuf::new()));
+
+    match old {
+        Some(old) => set_var("HOME", old),
+        None => remove_var("HOME"),
+    }
+}
+
+pub fn test_env_home_dir_without_var() {
+    let old = var_os("HOME");
+
+    remove_var("HOME");
+    // Without `HOME` the directory comes from the host's passwd entry. The
+    // host here is well behaved, so this only checks that the lookup gives
+    // back a plausible path; how a bad `pw_dir` is handled is covered by
+    // `test_env_home_dir_passwd_dir`.
+    if let Some(dir) = home_dir() {
+        assert!(dir.as_os_str().is_empty() || dir.is_absolute());
+    }
+
+    if let Some(old) = old {
+        set_var("HOME", old);
+    }
+}
+
+pub fn test_env_home_dir_passwd_dir() {
+    // The host hands back `pw_dir` as a pointer into the buffer it filled.
+    let buf = b"root\0x\0/root\0/bin/sh\0".to_vec();
+    let dir = buf[7..].as_ptr() as *const _;
+    assert_eq!(passwd_dir(&buf, dir), Some(&b"/root"[..]));
+    let empty = buf[6..].as_ptr() as *const _;
+    assert_eq!(passwd_dir(&buf, empty), Some(&b""[..]));
+
+    // Pointers outside the buffer, including just past its end, are refused.
+    let other = b"/elsewhere\0".to_vec();
+    assert_eq!(passwd_dir(&buf, other.as_ptr() as *const _), None);
+    assert_eq!(passwd_dir(&buf, buf.as_ptr().wrapping_sub(1) as *const _), None);
+    assert_eq!(passwd_dir(&buf, buf.as_ptr().wrapping_add(buf.len()) as *const _), None);
+    assert_eq!(passwd_dir(&buf, std::ptr::null()), None);
+
+    // So is a directory that runs to the end of the buffer without a nul.
+    let unterminated = b"root\0/root".to_vec();
+    assert_eq!(passwd_dir(&unterminated, unterminated[5..].as_ptr() as *const _), None);
+}
+
+pub fn test_env_temp_dir() {
+    let old = var_os("TMPDIR");
+
+    set_var("TMPDIR", "/enclave/tmp");
+    assert_eq!(temp_dir(), PathBuf::from("/enclave/tmp"));
+
+    // An empty `TMPDIR` falls back to the default.
+    set_var("TMPDIR", "");
+    assert_eq!(temp_dir(), PathBuf::from("/tmp"));
+
+    remove_var("TMPDIR");
+    assert_eq!(temp_dir(), PathBuf::from("/tmp"));
+
+    set_temp_dir_default(PathBuf::from("/enclave/scratch"));
+    assert_eq!(temp_dir(), PathBuf::from("/enclave/scratch"));
+    set_var("TMPDIR", "/enclave/tmp");
+    assert_eq!(temp_dir(), PathBuf::from("/enclave/tmp"));
+    set_temp_dir_default(PathBuf::from("/tmp"));
+
+    match old {
+        Some(old) => set_var("TMPDIR", old),
+        None => remove_var("TMPDIR"),
+    }
+}
+
+pub fn test_env_vars_os_exact_size() {
+    const N: usize = 4;
+    let keys: Vec<String> = (0..N).map(|i| format!("SGX_TEST_VARS_OS_LEN_{}", i)).collect();
+
+    let before = vars_os().len();
+    for key in keys.iter() {
+        set_var(key, "1");
+    }
+
+    let mut iter = vars_os();
+    let total = before + N;
+    assert_eq!(iter.len(), total);
+    assert_eq!(iter.size_hint(), (total, Some(total)));
+    for remaining in (0..total).rev() {
+        assert!(iter.next().is_some());
+        assert_eq!(iter.len(), remaining);
+    }
+    assert!(iter.next().is_none());
+    assert_eq!(vars().len(), total);
+
+    for key in keys.iter() {
+        remove_var(key);
+    }
+    assert_eq!(vars_os().len(), before);
+}
+
+pub fn test_env_var_error_into_os_string() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let key = "SGX_TEST_VAR_NOT_UNICODE";
+    let raw = OsStr::from_bytes(b"fo\xffo");
+    set_var(key, raw);
+    let err = var(key).unwrap_err();
+    assert_eq!(err, VarError::NotUnicode(raw.to_os_string()));
+    assert_eq!(err.into_os_string().unwrap().as_bytes(), b"fo\xffo");
+
+    remove_var(key);
+    assert_eq!(var(key).unwrap_err().into_os_string(), None);
+}
+
+pub fn test_env_join_paths_empty_component() {
+    use std::ffi::OsString;
+
+    let paths = [Path::new("/bin"), Path::new(""), Path::new("/usr/bin")];
+    assert_eq!(join_paths(paths.iter()).unwrap(), OsString::from("/bin::/usr/bin"));
+    let err = join_paths_strict(paths.iter()).unwrap_err();
+    assert_eq!(format!("{}", err), "path segment is empty");
+
+    let paths = [Path::new("/bin"), Path::new("/usr/bin")];
+    assert_eq!(join_paths_strict(paths.iter()).unwrap(), OsString::from("/bin:/usr/bin"));
+
+    let paths = [Path::new("/bin"), Path::new("/usr/bi:n")];
+    assert!(join_paths(paths.iter()).is_err());
+    assert!(join_paths_strict(paths.iter()).is_err());
+}
+
+pub fn test_env_take_var() {
+    use std::ffi::OsString;
+
+    let key = "SGX_TEST_TAKE_VAR";
+    set_var(key, "VALUE");
+    assert_eq!(take_var(key), Some(OsString::from("VALUE")));
+    assert_eq!(var_os(key), None);
+
+    assert_eq!(take_var(key), None);
+}
+
+static WATCH_SET: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+static WATCH_REMOVED: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+fn watch_var_callback(value: Option<&std::ffi::OsStr>) {
+    use std::sync::atomic::Ordering;
+
+    match value {
+        Some(value) => {
+            assert_eq!(value, "ON");
+            // Reading the environment from a watcher must not deadlock.
+            assert_eq!(var("SGX_TEST_WATCH_VAR").unwrap(), "ON");
+            WATCH_SET.fetch_add(1, Ordering::SeqCst);
+        }
+        None => {
+            WATCH_REMOVED.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+pub fn test_env_watch_var() {
+    use std::sync::atomic::Ordering;
+
+    let key = "SGX_TEST_WATCH_VAR";
+    let watcher = watch_var(key, watch_var_callback);
+    set_var(key, "ON");
+    set_var("SGX_TEST_WATCH_VAR_OTHER", "OFF");
+    assert_eq!(WATCH_SET.load(Ordering::SeqCst), 1);
+
+    remove_var(key);
+    assert_eq!(WATCH_REMOVED.load(Ordering::SeqCst), 1);
+    set_var(key, "ON");
+    assert_eq!(take_var(key), Some("ON".into()));
+    assert_eq!(WATCH_SET.load(Ordering::SeqCst), 2);
+    assert_eq!(WATCH_REMOVED.load(Ordering::SeqCst), 2);
+
+    drop(watcher);
+    set_var(key, "ON");
+    assert_eq!(WATCH_SET.load(Ordering::SeqCst), 2);
+    remove_var(key);
+    remove_var("SGX_TEST_WATCH_VAR_OTHER");
+}
+
+pub fn test_env_vars_os_with_prefix() {
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+
+    let non_utf8 = OsString::from_vec(b"SGX_PREFIX_\xff".to_vec());
+    set_var("SGX_PREFIX_A", "1");
+    set_var("SGX_PREFIX_B", "2");
+    set_var(&non_utf8, "3");
+    set_var("SGX_PREFIXA", "4");
+    set_var("OTHER_SGX_PREFIX_C", "5");
+
+    let mut found: Vec<(OsString, OsString)> = vars_os_with_prefix("SGX_PREFIX_").collect();
+    found.sort();
+    assert_eq!(
+        found,
+        vec![
+            (OsString::from("SGX_PREFIX_A"), OsString::from("1")),
+            (OsString::from("SGX_PREFIX_B"), OsString::from("2")),
+            (non_utf8.clone(), OsString::from("3")),
+        ]
+    );
+
+    // The prefix only matches the name, never the value.
+    assert_eq!(vars_os_with_prefix("SGX_PREFIX_A=").len(), 0);
+    assert_eq!(vars_os_with_prefix("").len(), vars_os().len());
+
+    for key in &["SGX_PREFIX_A", "SGX_PREFIX_B", "SGX_PREFIXA", "OTHER_SGX_PREFIX_C"] {
+        remove_var(key);
+    }
+    remove_var(&non_utf8);
+}
+
+pub fn test_env_snapshot() {
+    use std::ffi::OsString;
+    use std::io::ErrorKind;
+    use std::string::ToString;
+
+    set_var("SGX_SNAPSHOT_A", "1");
+    set_var("SGX_SNAPSHOT_B", "two=2");
+    let mut exported = export_snapshot();
+    exported.sort();
+    assert!(exported.contains(&(OsString::from("SGX_SNAPSHOT_B"), OsString::from("two=2"))));
+
+    import_snapshot(Vec::<(OsString, OsString)>::new()).unwrap();
+    assert_eq!(vars_os().len(), 0);
+    assert_eq!(var_os("SGX_SNAPSHOT_A"), None);
+
+    import_snapshot(exported.clone()).unwrap();
+    let mut restored = export_snapshot();
+    restored.sort();
+    assert_eq!(restored, exported);
+
+    // An invalid entry is reported and leaves the store untouched.
+    let err = import_snapshot(vec![("SGX_SNAPSHOT_C", "3"), ("BAD=KEY", "4")]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert!(err.to_string().contains("BAD=KEY"));
+    assert!(err.to_string().contains("index 1"));
+    assert_eq!(var_os("SGX_SNAPSHOT_C"), None);
+    assert!(import_snapshot(vec![("", "5")]).is_err());
+    assert!(import_snapshot(vec![("SGX_SNAPSHOT_C", "a\0b")]).is_err());
+
+    remove_var("SGX_SNAPSHOT_A");
+    remove_var("SGX_SNAPSHOT_B");
+}
+
+// The unit tests enable `trusted_env`, so the host environment is only read
+// once and never written.
+pub fn test_env_trusted_table() {
+    use std::ffi::CString;
+
+    let host_get = |k: &str| unsafe {
+        let k = CString::new(k).unwrap();
+        !sgx_libc::ocall::getenv(k.as_ptr()).is_null()
+    };
+
+    set_var("SGX_TRUSTED_ENV", "enclave");
+    assert_eq!(var("SGX_TRUSTED_ENV").unwrap(), "enclave");
+    assert!(!host_get("SGX_TRUSTED_ENV"));
+
+    // Changes made by the host after the snapshot are not seen.
+    unsafe {
+        let k = CString::new("SGX_TRUSTED_ENV_HOST").unwrap();
+        let v = CString::new("host").unwrap();
+        assert_eq!(sgx_libc::ocall::setenv(k.as_ptr(), v.as_ptr(), 1), 0);
+        assert_eq!(var_os("SGX_TRUSTED_ENV_HOST"), None);
+        sgx_libc::ocall::unsetenv(k.as_ptr());
+    }
+
+    remove_var("SGX_TRUSTED_ENV");
+    assert_eq!(var_os("SGX_TRUSTED_ENV"), None);
+}
+
+pub fn test_env_import_verified() {
+    use sgx_tcrypto::{rsgx_sha256_slice, SgxEccHandle};
+    use std::ffi::CString;
+    use std::io::ErrorKind;
+
+    // The encoding documented on `EnvVerifier`.
+    fn encode(vars: &[(&str, Option<&str>)]) -> Vec<u8> {
+        let mut out = b"sgx_tstd env import v1\0".to_vec();
+        for &(k, v) in vars {
+            out.extend_from_slice(&(k.len() as u32).to_le_bytes());
+            out.extend_from_slice(k.as_bytes());
+            match v {
+                None => out.push(0),
+                Some(v) => {
+                    out.push(1);
+                    out.extend_from_slice(&(v.len() as u32).to_le_bytes());
+                    out.extend_from_slice(v.as_bytes());
+                }
+            }
+        }
+        out
+    }
+    let host_set = |k: &str, v: &str| unsafe {
+        let k = CString::new(k).unwrap();
+        let v = CString::new(v).unwrap();
+        assert_eq!(sgx_libc::ocall::setenv(k.as_ptr(), v.as_ptr(), 1), 0);
+    };
+
+    host_set("SGX_VERIFIED_LEVEL", "debug");
+    host_set("SGX_VERIFIED_OTHER", "ignored");
+    let allowlist = ["SGX_VERIFIED_LEVEL", "SGX_VERIFIED_MISSING"];
+    let message = encode(&[("SGX_VERIFIED_LEVEL", Some("debug")), ("SGX_VERIFIED_MISSING", None)]);
+
+    let hash = rsgx_sha256_slice(&message).unwrap();
+    import_verified(&allowlist, EnvVerifier::Sha256(&hash)).unwrap();
+    assert_eq!(var("SGX_VERIFIED_LEVEL").unwrap(), "debug");
+    assert_eq!(var_os("SGX_VERIFIED_MISSING"), None);
+    assert_eq!(var_os("SGX_VERIFIED_OTHER"), None);
+
+    let ecc = SgxEccHandle::new();
+    ecc.open().unwrap();
+    let (private, public) = ecc.create_key_pair().unwrap();
+    let signature = ecc.ecdsa_sign_slice(&message, &private).unwrap();
+    let verifier = EnvVerifier::EcdsaP256 { public_key: &public, signature: &signature };
+    import_verified(&allowlist, verifier).unwrap();
+
+    // A value changed by the host is rejected and nothing is imported.
+    remove_var("SGX_VERIFIED_LEVEL");
+    host_set("SGX_VERIFIED_LEVEL", "trace");
+    let err = import_verified(&allowlist, EnvVerifier::Sha256(&hash)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(import_verified(&allowlist, verifier).unwrap_err().kind(), ErrorKind::InvalidData);
+    assert_eq!(var_os("SGX_VERIFIED_LEVEL"), None);
+
+    // So is a variable the host adds.
+    host_set("SGX_VERIFIED_LEVEL", "debug");
+    host_set("SGX_VERIFIED_MISSING", "1");
+    let err = import_verified(&allowlist, EnvVerifier::Sha256(&hash)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    let err = import_verified(&["A", "A"], EnvVerifier::Sha256(&hash)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    let err = import_verified(&["A=B"], EnvVerifier::Sha256(&hash)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    unsafe {
+        for k in &["SGX_VERIFIED_LEVEL", "SGX_VERIFIED_OTHER", "SGX_VERIFIED_MISSING"] {
+            let k = CString::new(*k).unwrap();
+            sgx_libc::ocall::unsetenv(k.as_ptr());
+        }
+    }
+}
+
+pub fn test_env_args() {
+    // Set by the app right after creating the enclave.
+    let expected = ["unit-test", "--verbose", "caf\u{e9}"];
+    assert_eq!(args().len(), expected.len());
+    assert_eq!(args().collect::<Vec<String>>(), expected);
+    assert_eq!(args().rev().next().unwrap(), "caf\u{e9}");
+    assert_eq!(args_os().nth(1).unwrap(), "--verbose");
+
+    // Later calls cannot replace them.
+    let blob = b"other\0";
+    std::rt::t_global_args_ecall(blob.as_ptr(), blob.len());
+    assert_eq!(args().collect::<Vec<String>>(), expected);
+}
@@ -16,8 +16,8 @@
 // under the License..
 
 use sgx_rand::{Rng, StdRng};
-use std::io::{Read, Write};
-use std::sgxfs::{self, SgxFile};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::sgxfs::{self, KeyPolicy, SgxFile, SgxFileMap};
 use std::string::*;
 use std::untrusted::fs::remove_file;
 use std::untrusted::fs::File;
@@ -86,6 +86,250 @@ pub fn test_sgxfs() {
     }
 }
 
+pub fn test_sgxfs_len_and_metadata() {
+    {
+        let mut file = SgxFile::create("sgx_file_len").unwrap();
+        assert_eq!(file.len().unwrap(), 0);
+        file.write_all(&[7; 10000]).unwrap();
+        assert_eq!(file.len().unwrap(), 10000);
+
+        // Extending writes zeros and leaves the cursor in place.
+        file.seek(SeekFrom::Start(5)).unwrap();
+        file.set_len(20000).unwrap();
+        assert_eq!(file.len().unwrap(), 20000);
+        assert_eq!(file.seek(SeekFrom::Current(0)).unwrap(), 5);
+        let err = file.set_len(100).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        assert_eq!(file.len().unwrap(), 20000);
+    }
+
+    let mut file = SgxFile::open("sgx_file_len").unwrap();
+    let metadata = file.metadata().unwrap();
+    assert_eq!(metadata.len(), 20000);
+    assert!(metadata.modified().is_ok());
+    assert!(metadata.accessed().is_ok());
+
+    // Reading from the middle of the file needs no pass over the start.
+    let mut buf = [0_u8; 4];
+    file.seek(SeekFrom::Start(9998)).unwrap();
+    file.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, [7, 7, 0, 0]);
+    file.seek(SeekFrom::End(-2)).unwrap();
+    assert_eq!(file.read(&mut buf).unwrap(), 2);
+    assert!(file.set_len(30000).is_err());
+
+    drop(file);
+    sgxfs::remove("sgx_file_len").unwrap();
+}
+
+pub fn test_sgxfs_rekey() {
+    let key = [0x5a_u8; 16];
+    let data: Vec<u8> = (0..10000_u32).map(|i| i as u8).collect();
+    {
+        let mut file = SgxFile::create_ex("sgx_file_rekey", &key).unwrap();
+        file.write_all(&data).unwrap();
+    }
+
+    SgxFile::open_ex("sgx_file_rekey", &key).unwrap().rekey(KeyPolicy::AutoKey).unwrap();
+    assert!(SgxFile::open_ex("sgx_file_rekey", &key).is_err());
+    let mut read_back = Vec::new();
+    let mut file = SgxFile::open("sgx_file_rekey").unwrap();
+    file.read_to_end(&mut read_back).unwrap();
+    assert_eq!(read_back, data);
+
+    // Rotating back leaves no staging directory behind.
+    file.rekey(KeyPolicy::Key(key)).unwrap();
+    assert!(SgxFile::open("sgx_file_rekey").is_err());
+    read_back.clear();
+    let mut file = SgxFile::open_ex("sgx_file_rekey", &key).unwrap();
+    file.read_to_end(&mut read_back).unwrap();
+    assert_eq!(read_back, data);
+    assert!(std::untrusted::fs::metadata(".sgx_file_rekey.rekey").is_err());
+
+    drop(file);
+    sgxfs::remove("sgx_file_rekey").unwrap();
+}
+
+pub fn test_sgxfs_file_map() {
+    let data: Vec<u8> = (0..10000_u32).map(|i| (i % 251) as u8).collect();
+    sgxfs::write("sgx_file_map", &data).unwrap();
+
+    let map = SgxFileMap::with_capacity(SgxFile::open("sgx_file_map").unwrap(), 2).unwrap();
+    assert_eq!(map.len(), 10000);
+    assert_eq!(map.page_count(), 3);
+    assert_eq!(map.page(2).unwrap().len(), 10000 - 2 * SgxFileMap::PAGE_SIZE);
+    assert_eq!(map.page(3).unwrap_err().kind(), ErrorKind::InvalidInput);
+
+    // Reads spanning pages, with a cache smaller than the file.
+    let mut buf = [0_u8; 5000];
+    map.read_exact_at(&mut buf, 3000).unwrap();
+    assert_eq!(&buf[..], &data[3000..8000]);
+    let page = map.page(0).unwrap();
+    assert_eq!(&page[..], &data[..SgxFileMap::PAGE_SIZE]);
+    map.clear_cache();
+    assert_eq!(&page[..], &data[..SgxFileMap::PAGE_SIZE]);
+
+    assert_eq!(map.read_at(&mut buf, 9000).unwrap(), 1000);
+    assert_eq!(&buf[..1000], &data[9000..]);
+    assert_eq!(map.read_at(&mut buf, 20000).unwrap(), 0);
+    assert_eq!(
+        map.read_exact_at(&mut buf, 9000).unwrap_err().kind(),
+        ErrorKind::UnexpectedEof
+    );
+
+    drop(map);
+    sgxfs::remove("sgx_file_map").unwrap();
+}
+
+pub fn test_fs_read_dir_sealed_blobs() {
+    use std::untrusted::fs;
+
+    let dir = "sealed_blobs/nested";
+    fs::create_dir_all(dir).unwrap();
+    for name in &["a", "b", "c"] {
+        let mut file = SgxFile::create(format!("{}/{}", dir, name)).unwrap();
+        file.write_all(name.as_bytes()).unwrap();
+    }
+
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let entry = entry.unwrap();
+        assert!(entry.file_type().unwrap().is_file());
+        assert!(entry.metadata().unwrap().len() > 0);
+        let mut contents = String::new();
+        SgxFile::open(entry.path()).unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(entry.file_name().to_str(), Some(&contents[..]));
+        names.push(contents);
+    }
+    names.sort();
+    assert_eq!(names, ["a", "b", "c"]);
+
+    fs::remove_dir_all("sealed_blobs").unwrap();
+    assert!(fs::read_dir("sealed_blobs").is_err());
+}
+
+pub fn test_fs_file_lock() {
+    use std::untrusted::fs;
+
+    fs::write("file_lock", b"state").unwrap();
+    // Separate opens give separate open file descriptions, which is what
+    // flock locks belong to, just as for two enclave instances.
+    let first = File::open("file_lock").unwrap();
+    let second = File::open("file_lock").unwrap();
+
+    first.lock_exclusive().unwrap();
+    assert!(!second.try_lock_shared().unwrap());
+    assert!(!second.try_lock_exclusive().unwrap());
+    first.unlock().unwrap();
+
+    assert!(second.try_lock_shared().unwrap());
+    first.lock_shared().unwrap();
+    assert!(!first.try_lock_exclusive().unwrap());
+    second.unlock().unwrap();
+    assert!(first.try_lock_exclusive().unwrap());
+
+    drop(first);
+    assert!(second.try_lock_exclusive().unwrap());
+    drop(second);
+    remove_file("file_lock").unwrap();
+}
+
+pub fn test_fs_tempfile() {
+    use std::os::unix::fs::PermissionsExt;
+    use std::untrusted::fs::{self, TempDir};
+
+    let dir = TempDir::new_in(".").unwrap();
+    assert!(dir.path().is_dir());
+    let mode = fs::metadata(dir.path()).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o700);
+    let other = TempDir::new_in(".").unwrap();
+    assert_ne!(dir.path(), other.path());
+
+    // The file is unlinked as soon as it is created.
+    let mut file = fs::tempfile_in(dir.path()).unwrap();
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    file.write_all(b"scratch").unwrap();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "scratch");
+
+    fs::write(dir.path().join("blob"), b"x").unwrap();
+    let path = dir.path().to_path_buf();
+    drop(dir);
+    assert!(!path.exists());
+
+    let kept = other.into_path();
+    assert!(kept.is_dir());
+    fs::remove_dir(kept).unwrap();
+}
+
+pub fn test_fs_watcher() {
+    use std::untrusted::fs::{self, TempDir, WatchMask, Watcher};
+
+    let dir = TempDir::new_in(".").unwrap();
+    let mut watcher = Watcher::new().unwrap();
+    let wd = watcher
+        .add_watch(dir.path(), WatchMask::CREATE | WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO)
+        .unwrap();
+
+    fs::write(dir.path().join("config.toml"), b"level = 1").unwrap();
+    let mut seen = WatchMask::from_bits(0);
+    while !seen.contains(WatchMask::CREATE | WatchMask::CLOSE_WRITE) {
+        for event in watcher.read_events().unwrap() {
+            assert_eq!(event.wd(), wd);
+            assert_eq!(event.name().and_then(|n| n.to_str()), Some("config.toml"));
+            seen |= event.mask();
+        }
+    }
+
+    watcher.set_nonblocking(true).unwrap();
+    assert_eq!(watcher.read_events().unwrap_err().kind(), ErrorKind::WouldBlock);
+    watcher.remove_watch(wd).unwrap();
+    watcher.set_nonblocking(false).unwrap();
+    let events = watcher.read_events().unwrap();
+    assert!(events.iter().any(|e| e.mask().contains(WatchMask::IGNORED) && e.name().is_none()));
+}
+
+pub fn test_fs_integrity_file() {
+    use std::os::unix::fs::FileExt;
+    use std::untrusted::fs::{self, IntegrityFile, OpenOptions};
+
+    let data: Vec<u8> = (0..20000_u32).map(|i| (i % 253) as u8).collect();
+    fs::write("integrity_file", &data).unwrap();
+    let root = IntegrityFile::build("integrity_file").unwrap().root();
+
+    let mut file = IntegrityFile::open("integrity_file").unwrap();
+    assert_eq!(file.len(), 20000);
+    let mut buf = [0_u8; 6000];
+    file.read_exact_at(&mut buf, 3000).unwrap();
+    assert_eq!(&buf[..], &data[3000..9000]);
+    let mut all = Vec::new();
+    file.read_to_end(&mut all).unwrap();
+    assert_eq!(all, data);
+    file.seek(SeekFrom::End(-10)).unwrap();
+    assert_eq!(file.read(&mut buf).unwrap(), 10);
+    drop(file);
+
+    // Flip a byte in the last block behind the enclave's back.
+    let host = OpenOptions::new().write(true).open("integrity_file").unwrap();
+    host.write_all_at(&[data[17000] ^ 0xff], 17000).unwrap();
+    let file = IntegrityFile::open_with_root("integrity_file", &root).unwrap();
+    file.read_exact_at(&mut buf[..100], 0).unwrap();
+    let err = file.read_exact_at(&mut buf[..100], 16950).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    // Changing the length is caught when the file is opened.
+    host.set_len(19000).unwrap();
+    let err = IntegrityFile::open("integrity_file").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    drop(host);
+    fs::remove_file("integrity_file").unwrap();
+    fs::remove_file("integrity_file.merkle").unwrap();
+    sgxfs::remove("integrity_file.root").unwrap();
+}
+
 pub fn test_fs() {
     {
         let f = File::create("foo.txt");
@@ -128,3 +372,95 @@ pub fn test_fs_untrusted_fs_feature_enabled() {
         assert!(f.is_ok());
     }
 }
+
+pub fn test_fs_async_file() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::{AsyncFile, LocalExecutor};
+    use std::untrusted::fs;
+
+    let executor = LocalExecutor::new().unwrap();
+    let file = Rc::new(AsyncFile::from_std(fs::tempfile_in(".").unwrap()));
+    let written = Rc::new(Cell::new(0));
+
+    // Writes to disjoint ranges run concurrently on the host threads.
+    for i in 0..8u8 {
+        let file = file.clone();
+        let written = written.clone();
+        executor.spawn(async move {
+            file.write_all_at(&[i; 512], i as u64 * 512).await.unwrap();
+            written.set(written.get() + 1);
+        });
+    }
+    executor
+        .block_on(async {
+            while written.get() < 8 {
+                file.sync_data().await.unwrap();
+            }
+        })
+        .unwrap();
+    let mut contents = vec![0u8; 8 * 512];
+    executor.block_on(file.read_exact_at(&mut contents, 0)).unwrap().unwrap();
+    for (i, chunk) in contents.chunks(512).enumerate() {
+        assert!(chunk.iter().all(|&b| b == i as u8));
+    }
+
+    let mut buf = [0u8; 16];
+    let n = executor.block_on(file.read_at(&mut buf, 8 * 512)).unwrap().unwrap();
+    assert_eq!(n, 0);
+    let err = executor.block_on(file.read_exact_at(&mut buf, 8 * 512 - 8)).unwrap();
+    assert_eq!(err.unwrap_err().kind(), ErrorKind::UnexpectedEof);
+
+    // Operations need a running executor.
+    let err = executor_less_read(&file);
+    assert_eq!(err.kind(), ErrorKind::Other);
+}
+
+fn executor_less_read(file: &std::task::AsyncFile) -> std::io::Error {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    struct Noop;
+    impl Wake for Noop {
+        fn wake(self: std::sync::Arc<Self>) {}
+    }
+
+    let waker = Waker::from(std::sync::Arc::new(Noop));
+    let mut cx = Context::from_waker(&waker);
+    let mut buf = [0u8; 1];
+    let mut read = Box::pin(file.read_at(&mut buf, 0));
+    match Pin::as_mut(&mut read).poll(&mut cx) {
+        Poll::Ready(result) => result.unwrap_err(),
+        Poll::Pending => panic!("read_at made progress without an executor"),
+    }
+}
+
+pub fn test_fs_canonicalize() {
+    use std::os::unix::fs::symlink;
+    use std::path::{Component, Path};
+    use std::untrusted::fs::{self, TempDir};
+
+    let dir = TempDir::new_in(".").unwrap();
+    let target = dir.path().join("target");
+    fs::write(&target, b"x").unwrap();
+    let link = dir.path().join("link");
+    symlink("target", &link).unwrap();
+
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("target"));
+    assert!(fs::read_link(&target).is_err());
+
+    let resolved = fs::canonicalize(&link).unwrap();
+    assert_eq!(resolved, fs::canonicalize(&target).unwrap());
+    assert!(resolved.is_absolute());
+    assert!(resolved.components().all(|c| matches!(c, Component::RootDir | Component::Normal(_))));
+    assert!(resolved.ends_with("target"));
+
+    let dotted = dir.path().join(".").join("..").join(dir.path().file_name().unwrap()).join("link");
+    assert_eq!(fs::canonicalize(&dotted).unwrap(), resolved);
+    assert_eq!(fs::canonicalize("/").unwrap(), Path::new("/"));
+    assert_eq!(
+        fs::canonicalize(dir.path().join("missing")).unwrap_err().kind(),
+        ErrorKind::NotFound
+    );
+}
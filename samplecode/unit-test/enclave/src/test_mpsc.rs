@@ -1239,3 +1239,86 @@ pub fn test_mpsc_sync_issue_15761() {
         repro()
     }
 }
+
+pub fn test_mpmc_bounded() {
+    use std::sync::mpmc::{self, SendTimeoutError};
+    use std::vec::Vec;
+
+    let (tx, rx) = mpmc::bounded(2);
+    assert_eq!(tx.capacity(), 2);
+    tx.try_send(1).unwrap();
+    tx.send(2).unwrap();
+    assert!(tx.is_full());
+    assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+    assert_eq!(tx.send_timeout(3, Duration::from_millis(10)), Err(SendTimeoutError::Timeout(3)));
+    assert_eq!(rx.recv(), Ok(1));
+    assert_eq!(rx.try_recv(), Ok(2));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    assert_eq!(rx.recv_timeout(Duration::from_millis(10)), Err(RecvTimeoutError::Timeout));
+
+    // Every message reaches exactly one of the consumers.
+    let consumers: Vec<_> = (0..3)
+        .map(|_| {
+            let rx = rx.clone();
+            thread::spawn(move || rx.iter().collect::<Vec<u32>>())
+        })
+        .collect();
+    drop(rx);
+    let producers: Vec<_> = (0..2)
+        .map(|p| {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for i in 0..100 {
+                    tx.send(p * 100 + i).unwrap();
+                }
+            })
+        })
+        .collect();
+    drop(tx);
+    for p in producers {
+        p.join().unwrap();
+    }
+    let mut all: Vec<u32> = consumers.into_iter().flat_map(|c| c.join().unwrap()).collect();
+    all.sort();
+    assert_eq!(all, (0..200).collect::<Vec<u32>>());
+
+    let (tx, rx) = mpmc::bounded::<u32>(1);
+    drop(rx);
+    assert_eq!(tx.send(1), Err(SendError(1)));
+}
+
+pub fn test_mpmc_select() {
+    use std::sync::mpmc::{self, ReadyTimeoutError, Select, TryReadyError};
+
+    let (tx1, rx1) = mpmc::bounded::<u32>(1);
+    let (tx2, rx2) = mpmc::bounded::<&'static str>(1);
+
+    let mut sel = Select::new();
+    let i1 = sel.recv(&rx1);
+    let i2 = sel.recv(&rx2);
+    assert_eq!(sel.try_ready(), Err(TryReadyError));
+    assert_eq!(sel.ready_timeout(Duration::from_millis(10)), Err(ReadyTimeoutError));
+
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        tx2.send("hi").unwrap();
+        tx2
+    });
+    assert_eq!(sel.ready(), i2);
+    assert_eq!(rx2.try_recv(), Ok("hi"));
+    let tx2 = t.join().unwrap();
+
+    // Both ready: reported in turn.
+    tx1.send(1).unwrap();
+    tx2.send("again").unwrap();
+    let first = sel.ready();
+    let second = sel.ready();
+    assert!(first != second);
+    assert_eq!(rx1.try_recv(), Ok(1));
+    assert_eq!(rx2.try_recv(), Ok("again"));
+
+    // A disconnected channel is ready too.
+    drop(tx1);
+    assert_eq!(sel.ready(), i1);
+    assert_eq!(rx1.try_recv(), Err(TryRecvError::Disconnected));
+}
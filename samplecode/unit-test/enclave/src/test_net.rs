@@ -0,0 +1,1320 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use std::io::{self, ErrorKind, IoSlice, IoSliceMut, Read, Seek, SeekFrom, Write};
+use std::net::{
+    self, BufferPool, Events, HostPort, Interest, IpAddr, IpNet, Ipv4Addr, Ipv4Net, Ipv6Addr,
+    Ipv6Net, Poll, PollFd, RawDomain, RawSocket, RetryPolicy, Shutdown, SocketAddr,
+    SocketAddrV4, SocketAddrV6, SocketKind, SocketOp, SocketOptions, TcpListener, TcpStream,
+    ToSocketAddrs, UdpSocket, VsockAddr, VsockListener, VsockStream, CONNECTION_ATTEMPT_DELAY,
+    IPPROTO_ICMP,
+};
+use std::mem;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixDatagram, UnixListener, UnixStream};
+use std::cell::Cell;
+use std::future::{self, Future};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sgxfs::{self, SgxFile};
+use std::string::ToString;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{self, AsyncTcpListener, AsyncTcpStream, Context, LocalExecutor};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::untrusted::time::InstantEx;
+use std::vec::Vec;
+
+pub fn test_net_unix_peer_cred() {
+    let (a, b) = UnixStream::pair().unwrap();
+    let uid = unsafe { sgx_libc::ocall::getuid() };
+    let pid = unsafe { sgx_libc::ocall::getpid() };
+
+    let cred_a = a.peer_cred().unwrap();
+    assert_eq!(cred_a.uid, uid);
+    assert_eq!(cred_a.pid, Some(pid));
+
+    let cred_b = b.peer_cred().unwrap();
+    assert_eq!(cred_a, cred_b);
+}
+
+pub fn test_net_set_mark() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    match socket.set_mark(42) {
+        Ok(()) => assert_eq!(socket.mark().unwrap(), 42),
+        // The host lacks CAP_NET_ADMIN.
+        Err(ref e) if e.kind() == ErrorKind::PermissionDenied => return,
+        Err(e) => panic!("unexpected error: {}", e),
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    stream.set_mark(7).unwrap();
+    assert_eq!(stream.mark().unwrap(), 7);
+}
+
+pub fn test_net_poll_listener_readable() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut fds = [PollFd::new(&listener, Interest::READABLE)];
+    assert_eq!(net::poll(&mut fds, Some(Duration::from_millis(0))).unwrap(), 0);
+    assert!(!fds[0].is_readable());
+
+    let t = thread::spawn(move || TcpStream::connect(addr).unwrap());
+
+    assert_eq!(net::poll(&mut fds, Some(Duration::from_secs(5))).unwrap(), 1);
+    assert!(fds[0].is_readable());
+    assert!(!fds[0].is_writable());
+
+    let _stream = t.join().unwrap();
+    listener.accept().unwrap();
+}
+
+pub fn test_net_udp_recv_deadline() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
+    let mut buf = [0; 16];
+
+    let start = Instant::now();
+    let err = socket
+        .recv_from_deadline(&mut buf, start + Duration::from_millis(50))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert_eq!(socket.read_timeout().unwrap(), Some(Duration::from_secs(30)));
+
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    sender.send_to(b"ping", socket.local_addr().unwrap()).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let (n, from) = socket.recv_from_deadline(&mut buf, deadline).unwrap();
+    assert_eq!(&buf[..n], b"ping");
+    assert_eq!(from, sender.local_addr().unwrap());
+
+    socket.connect(from).unwrap();
+    sender.send_to(b"pong", socket.local_addr().unwrap()).unwrap();
+    let n = socket.recv_deadline(&mut buf, deadline).unwrap();
+    assert_eq!(&buf[..n], b"pong");
+}
+
+pub fn test_net_tcp_bind_with_backlog() {
+    let listener = TcpListener::bind_with_backlog("127.0.0.1:0", 4).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let t = thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"hi").unwrap();
+    });
+
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut buf = [0; 2];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hi");
+    t.join().unwrap();
+
+    // Non-positive backlogs are clamped rather than rejected.
+    let listener = TcpListener::bind_with_backlog("127.0.0.1:0", 0).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let _stream = TcpStream::connect(addr).unwrap();
+    listener.accept().unwrap();
+}
+
+pub fn test_net_udp_recv_vectored() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    sender.send_to(b"headbody", addr).unwrap();
+    let mut header = [0; 4];
+    let mut body = [0; 8];
+    let mut bufs = [IoSliceMut::new(&mut header), IoSliceMut::new(&mut body)];
+    let (n, truncated, from) = socket.recv_from_vectored(&mut bufs).unwrap();
+    assert_eq!(n, 8);
+    assert!(!truncated);
+    assert_eq!(from, sender.local_addr().unwrap());
+    assert_eq!(&header, b"head");
+    assert_eq!(&body[..4], b"body");
+
+    socket.connect(from).unwrap();
+    sender.send_to(b"headbody-too-long", addr).unwrap();
+    let mut header = [0; 4];
+    let mut body = [0; 4];
+    let mut bufs = [IoSliceMut::new(&mut header), IoSliceMut::new(&mut body)];
+    let (n, truncated) = socket.recv_vectored(&mut bufs).unwrap();
+    assert_eq!(n, 8);
+    assert!(truncated);
+    assert_eq!(&header, b"head");
+    assert_eq!(&body, b"body");
+}
+
+pub fn test_net_tcp_quickack() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+    stream.set_quickack(false).unwrap();
+    assert_eq!(stream.quickack().unwrap(), false);
+    stream.set_quickack(true).unwrap();
+    assert_eq!(stream.quickack().unwrap(), true);
+}
+
+// Returns the accepting side of a connection whose peer sent `data` and then
+// reset the connection.
+fn reset_by_peer() -> TcpStream {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let t = thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"data").unwrap();
+        // Leave the server's reply unread and close abortively, which makes
+        // the kernel answer with a RST.
+        let mut fds = [PollFd::new(&stream, Interest::READABLE)];
+        net::poll(&mut fds, Some(Duration::from_secs(5))).unwrap();
+        stream.set_linger(Some(Duration::from_secs(0))).unwrap();
+    });
+
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut buf = [0; 4];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"data");
+    stream.write_all(b"reply").unwrap();
+    t.join().unwrap();
+    thread::sleep(Duration::from_millis(100));
+    stream
+}
+
+pub fn test_net_tcp_eof_on_reset() {
+    let mut buf = [0; 16];
+
+    let mut stream = reset_by_peer();
+    assert!(!stream.eof_on_reset());
+    let err = stream.read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ConnectionReset);
+
+    let mut stream = reset_by_peer();
+    stream.set_eof_on_reset(true);
+    assert!(stream.eof_on_reset());
+    assert_eq!(stream.read(&mut buf).unwrap(), 0);
+}
+
+pub fn test_net_ip_array_conversions() {
+    let octets = [192u8, 168, 0, 1];
+    let v4 = Ipv4Addr::from(octets);
+    assert_eq!(v4, Ipv4Addr::new(192, 168, 0, 1));
+    assert_eq!(<[u8; 4]>::from(v4), octets);
+    assert_eq!(IpAddr::from(octets), IpAddr::V4(v4));
+
+    let octets = [
+        0x20u8, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x12, 0x34,
+    ];
+    let v6 = Ipv6Addr::from(octets);
+    assert_eq!(v6, Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0x1234));
+    assert_eq!(<[u8; 16]>::from(v6), octets);
+    assert_eq!(IpAddr::from(octets), IpAddr::V6(v6));
+
+    let segments = [0x2001u16, 0xdb8, 0, 0, 0, 0, 0, 0x1234];
+    let v6 = Ipv6Addr::from(segments);
+    assert_eq!(<[u16; 8]>::from(v6), segments);
+    assert_eq!(<[u8; 16]>::from(v6), octets);
+    assert_eq!(IpAddr::from(segments), IpAddr::V6(v6));
+}
+
+pub fn test_net_tcp_connect_with_retry() {
+    // Reserve a port, then release it so that nothing is listening yet.
+    let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+    let err = TcpStream::connect_with_retry(addr, &RetryPolicy::default()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        let listener = TcpListener::bind(addr).unwrap();
+        listener.accept().unwrap();
+    });
+
+    let policy = RetryPolicy::new(100, Duration::from_millis(50));
+    let stream = TcpStream::connect_with_retry(addr, &policy).unwrap();
+    assert_eq!(stream.peer_addr().unwrap(), addr);
+    t.join().unwrap();
+}
+
+pub fn test_net_socket_addr_setters() {
+    let mut v4 = SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 80);
+    v4.set_ip(Ipv4Addr::new(10, 0, 0, 1));
+    v4.set_port(8080);
+    assert_eq!(v4, SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 8080));
+
+    let mut v6 = SocketAddrV6::new(Ipv6Addr::LOCALHOST, 80, 7, 3);
+    v6.set_ip(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+    v6.set_port(8443);
+    assert_eq!(v6, SocketAddrV6::new(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1), 8443, 7, 3));
+
+    let mut addr = SocketAddr::V4(v4);
+    addr.set_port(1080);
+    addr.set_ip(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)));
+    assert_eq!(addr, SocketAddr::from(([192, 168, 1, 1], 1080)));
+
+    let mut addr = SocketAddr::V6(v6);
+    addr.set_port(1080);
+    addr.set_ip(IpAddr::V6(Ipv6Addr::LOCALHOST));
+    assert_eq!(addr, SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::LOCALHOST, 1080, 7, 3)));
+
+    // Cross-family changes switch the variant and keep the port.
+    addr.set_ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+    assert_eq!(addr, SocketAddr::from(([127, 0, 0, 1], 1080)));
+    addr.set_ip(IpAddr::V6(Ipv6Addr::LOCALHOST));
+    assert_eq!(addr, SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::LOCALHOST, 1080, 0, 0)));
+}
+
+pub fn test_net_udp_recv_tos() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap();
+    let mut buf = [0; 8];
+
+    let tos: i32 = 0xb8;
+    let ret = unsafe {
+        sgx_libc::ocall::setsockopt(
+            sender.as_raw_fd(),
+            sgx_libc::IPPROTO_IP,
+            sgx_libc::IP_TOS,
+            &tos as *const _ as *const sgx_libc::c_void,
+            mem::size_of_val(&tos) as sgx_libc::socklen_t,
+        )
+    };
+    assert_eq!(ret, 0);
+
+    assert_eq!(socket.recv_tos().unwrap(), false);
+    sender.send_to(b"off", addr).unwrap();
+    let (n, from, received) = socket.recv_from_with_tos(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"off");
+    assert_eq!(from, sender.local_addr().unwrap());
+    assert_eq!(received, 0);
+
+    socket.set_recv_tos(true).unwrap();
+    assert_eq!(socket.recv_tos().unwrap(), true);
+    sender.send_to(b"on", addr).unwrap();
+    let (n, _, received) = socket.recv_from_with_tos(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"on");
+    assert_eq!(received, 0xb8);
+}
+
+pub fn test_net_socket_addr_from() {
+    fn generic<T: Into<SocketAddr>>(t: T) -> SocketAddr {
+        t.into()
+    }
+
+    let v4 = SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 80);
+    let v6 = SocketAddrV6::new(Ipv6Addr::LOCALHOST, 443, 0, 0);
+
+    assert_eq!(SocketAddr::from(v4), SocketAddr::V4(v4));
+    assert_eq!(SocketAddr::from(v6), SocketAddr::V6(v6));
+    assert_eq!(SocketAddr::from((IpAddr::V4(*v4.ip()), 80)), SocketAddr::V4(v4));
+    assert_eq!(SocketAddr::from((IpAddr::V6(*v6.ip()), 443)), SocketAddr::V6(v6));
+    assert_eq!(SocketAddr::from((Ipv4Addr::new(10, 0, 0, 1), 80)), SocketAddr::V4(v4));
+    assert_eq!(SocketAddr::from((Ipv6Addr::LOCALHOST, 443)), SocketAddr::V6(v6));
+    assert_eq!(SocketAddr::from(([10, 0, 0, 1], 80)), SocketAddr::V4(v4));
+
+    assert_eq!(generic(v4), SocketAddr::V4(v4));
+    assert_eq!(generic((Ipv6Addr::LOCALHOST, 443)), SocketAddr::V6(v6));
+}
+
+static CLOSE_ERRORS: AtomicUsize = AtomicUsize::new(0);
+
+fn count_close_error(e: &io::Error) {
+    assert_eq!(e.raw_os_error(), Some(sgx_libc::EBADF));
+    CLOSE_ERRORS.fetch_add(1, Ordering::SeqCst);
+}
+
+pub fn test_net_close_error() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.close().unwrap();
+
+    // Close the descriptor behind the socket's back so that its own close
+    // fails with EBADF.
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    assert_eq!(unsafe { sgx_libc::ocall::close(socket.as_raw_fd()) }, 0);
+    let err = socket.close().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(sgx_libc::EBADF));
+
+    // Dropping stays silent unless a handler is installed.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    assert_eq!(unsafe { sgx_libc::ocall::close(listener.as_raw_fd()) }, 0);
+    drop(listener);
+    assert_eq!(CLOSE_ERRORS.load(Ordering::SeqCst), 0);
+
+    net::set_close_error_handler(count_close_error);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    assert_eq!(unsafe { sgx_libc::ocall::close(listener.as_raw_fd()) }, 0);
+    drop(listener);
+    assert_eq!(CLOSE_ERRORS.load(Ordering::SeqCst), 1);
+
+    assert!(net::take_close_error_handler().is_some());
+    assert!(net::take_close_error_handler().is_none());
+}
+
+static BROKER_PORT: AtomicUsize = AtomicUsize::new(0);
+
+fn broker_resolver(host_port: &str) -> io::Result<Vec<SocketAddr>> {
+    if host_port == "broker:9092" {
+        let port = BROKER_PORT.load(Ordering::SeqCst) as u16;
+        Ok(vec![SocketAddr::from(([127, 0, 0, 1], port))])
+    } else {
+        Err(io::Error::from(ErrorKind::NotFound))
+    }
+}
+
+pub fn test_net_test_resolver() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    BROKER_PORT.store(addr.port() as usize, Ordering::SeqCst);
+
+    net::set_test_resolver(broker_resolver);
+    let stream = TcpStream::connect("broker:9092").unwrap();
+    assert_eq!(stream.peer_addr().unwrap(), addr);
+    listener.accept().unwrap();
+
+    let addrs: Vec<SocketAddr> = ("broker", 9092).to_socket_addrs().unwrap().collect();
+    assert_eq!(addrs, vec![addr]);
+    // Literal addresses never reach the resolver.
+    let addrs: Vec<SocketAddr> = "10.0.0.1:1".to_socket_addrs().unwrap().collect();
+    assert_eq!(addrs, vec![SocketAddr::from(([10, 0, 0, 1], 1))]);
+
+    assert!(net::take_test_resolver().is_some());
+}
+
+pub fn test_net_udp_peer_and_local_addr() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+    assert_eq!(client.peer_addr().unwrap_err().kind(), ErrorKind::NotConnected);
+
+    let server_addr = server.local_addr().unwrap();
+    client.connect(server_addr).unwrap();
+    assert_eq!(client.peer_addr().unwrap(), server_addr);
+
+    let local = client.local_addr().unwrap();
+    assert_ne!(local.port(), 0);
+    for _ in 0..3 {
+        assert_eq!(client.local_addr().unwrap(), local);
+    }
+
+    // An unbound socket reports port 0, and binding it refreshes the address.
+    let unbound = UdpSocket::new_v4().unwrap();
+    assert_eq!(unbound.local_addr().unwrap().port(), 0);
+    unbound.bind_socket("127.0.0.1:0").unwrap();
+    let bound = unbound.local_addr().unwrap();
+    assert_ne!(bound.port(), 0);
+    assert_eq!(bound.ip(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+}
+
+pub fn test_net_tcp_take_read_to_end() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (server, _) = listener.accept().unwrap();
+
+    let frame: Vec<u8> = (0..10240u32).map(|i| (i % 251) as u8).collect();
+    client.write_all(&frame).unwrap();
+    client.write_all(b"next").unwrap();
+
+    // Reaching the limit exactly leaves the rest of the stream untouched.
+    let mut buf = Vec::new();
+    let n = (&server).take(10240).read_to_end(&mut buf).unwrap();
+    assert_eq!(n, 10240);
+    assert_eq!(buf, frame);
+
+    // EOF before the limit returns what was read.
+    drop(client);
+    let mut rest = Vec::new();
+    assert_eq!((&server).take(10240).read_to_end(&mut rest).unwrap(), 4);
+    assert_eq!(rest, b"next");
+}
+
+pub fn test_net_ip_net_contains() {
+    let net: Ipv4Net = "10.0.0.0/8".parse().unwrap();
+    assert!(net.contains(&Ipv4Addr::new(10, 20, 30, 40)));
+    assert!(net.contains(&Ipv4Addr::new(10, 0, 0, 0)));
+    assert!(net.contains(&Ipv4Addr::new(10, 255, 255, 255)));
+    assert!(!net.contains(&Ipv4Addr::new(9, 255, 255, 255)));
+    assert!(!net.contains(&Ipv4Addr::new(11, 0, 0, 0)));
+    assert_eq!(net.to_string(), "10.0.0.0/8");
+
+    let host = Ipv4Net::new(Ipv4Addr::new(192, 168, 1, 7), 32).unwrap();
+    assert!(host.contains(&Ipv4Addr::new(192, 168, 1, 7)));
+    assert!(!host.contains(&Ipv4Addr::new(192, 168, 1, 8)));
+    let all = Ipv4Net::new(Ipv4Addr::new(1, 2, 3, 4), 0).unwrap();
+    assert!(all.contains(&Ipv4Addr::BROADCAST));
+    assert_eq!(all.network(), Ipv4Addr::UNSPECIFIED);
+
+    let net: Ipv6Net = "2001:db8::/32".parse().unwrap();
+    assert!(net.contains(&Ipv6Addr::new(0x2001, 0xdb8, 0xffff, 0, 0, 0, 0, 1)));
+    assert!(!net.contains(&Ipv6Addr::new(0x2001, 0xdb9, 0, 0, 0, 0, 0, 0)));
+    assert_eq!(net.netmask(), Ipv6Addr::new(0xffff, 0xffff, 0, 0, 0, 0, 0, 0));
+
+    // Cross-family queries never match.
+    let net: IpNet = "0.0.0.0/0".parse().unwrap();
+    assert!(net.contains(&IpAddr::V4(Ipv4Addr::LOCALHOST)));
+    assert!(!net.contains(&IpAddr::V6(Ipv6Addr::LOCALHOST)));
+    let net: IpNet = "::/0".parse().unwrap();
+    assert!(!net.contains(&IpAddr::V4(Ipv4Addr::LOCALHOST)));
+
+    assert!(Ipv4Net::new(Ipv4Addr::LOCALHOST, 33).is_none());
+    assert!(Ipv6Net::new(Ipv6Addr::LOCALHOST, 129).is_none());
+    assert!("10.0.0.0/33".parse::<IpNet>().is_err());
+    assert!("::1/129".parse::<IpNet>().is_err());
+    assert!("10.0.0.0".parse::<IpNet>().is_err());
+    assert!("::1/64".parse::<Ipv4Net>().is_err());
+}
+
+pub fn test_net_tcp_write_all_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (mut server, _) = listener.accept().unwrap();
+
+    // The server doesn't read, so the socket buffers fill up long before
+    // the whole payload is sent.
+    let payload: Vec<u8> = (0..8 * 1024 * 1024u32).map(|i| i as u8).collect();
+    let start = Instant::now();
+    let n = client.write_all_timeout(&payload, Duration::from_millis(200)).unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(200));
+    assert!(n > 0 && n < payload.len());
+
+    // Exactly the reported prefix reached the peer.
+    let mut received = vec![0; n];
+    server.read_exact(&mut received).unwrap();
+    assert_eq!(&received[..], &payload[..n]);
+    server.set_nonblocking(true).unwrap();
+    let mut extra = [0u8; 1];
+    assert_eq!(server.read(&mut extra).unwrap_err().kind(), ErrorKind::WouldBlock);
+
+    // A reader that keeps up lets the whole buffer through.
+    let n = client.write_all_timeout(b"tail", Duration::from_secs(1)).unwrap();
+    assert_eq!(n, 4);
+}
+
+pub fn test_net_shutdown_raw() {
+    use sgx_libc::{SHUT_RD, SHUT_RDWR, SHUT_WR};
+
+    for &(how, raw) in &[(Shutdown::Read, SHUT_RD), (Shutdown::Write, SHUT_WR), (Shutdown::Both, SHUT_RDWR)] {
+        assert_eq!(how.as_raw(), raw);
+        assert_eq!(Shutdown::from_raw(raw), Some(how));
+    }
+    assert_eq!(Shutdown::from_raw(3), None);
+    assert_eq!(Shutdown::from_raw(-1), None);
+}
+
+pub fn test_net_tcp_wait_readable() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (mut server, _) = listener.accept().unwrap();
+
+    // An idle stream times out.
+    let start = Instant::now();
+    let err = server.wait_readable(Some(Duration::from_millis(50))).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    client.wait_writable(Some(Duration::from_secs(1))).unwrap();
+
+    // Queued data makes it ready, and waiting doesn't consume it.
+    client.write_all(b"ping").unwrap();
+    server.wait_readable(Some(Duration::from_secs(5))).unwrap();
+    server.wait_readable(None).unwrap();
+    let mut buf = [0u8; 4];
+    server.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"ping");
+}
+
+pub fn test_net_addr_parse_error_position() {
+    let err = "192.168.300.1".parse::<Ipv4Addr>().unwrap_err();
+    assert_eq!(err.position(), Some(8));
+    assert_eq!(err.expected(), "an IPv4 address");
+    assert_eq!("1.2.3".parse::<Ipv4Addr>().unwrap_err().position(), Some(5));
+    assert_eq!("1.2.3.4 ".parse::<Ipv4Addr>().unwrap_err().position(), Some(7));
+
+    let err = "fe80::zz".parse::<Ipv6Addr>().unwrap_err();
+    assert_eq!(err.position(), Some(6));
+    assert_eq!(err.expected(), "an IPv6 address");
+    assert_eq!("1:2:3:4:5:6:7:8:9".parse::<Ipv6Addr>().unwrap_err().position(), Some(15));
+
+    let err = "127.0.0.1:99999".parse::<SocketAddr>().unwrap_err();
+    assert_eq!(err.position(), Some(10));
+    assert_eq!(err.expected(), "a socket address");
+    assert_eq!("[::1]:".parse::<SocketAddr>().unwrap_err().position(), Some(6));
+    assert_eq!("".parse::<IpAddr>().unwrap_err().position(), Some(0));
+
+    // The message is unchanged.
+    assert_eq!(err.to_string(), "invalid IP address syntax");
+}
+
+static TCP_READ: AtomicUsize = AtomicUsize::new(0);
+static TCP_WRITTEN: AtomicUsize = AtomicUsize::new(0);
+static UDP_RECEIVED: AtomicUsize = AtomicUsize::new(0);
+static UDP_SENT: AtomicUsize = AtomicUsize::new(0);
+
+fn counting_sink(kind: SocketKind, op: SocketOp, n: usize) {
+    let counter = match (kind, op) {
+        (SocketKind::Tcp, SocketOp::Read) => &TCP_READ,
+        (SocketKind::Tcp, SocketOp::Write) => &TCP_WRITTEN,
+        (SocketKind::Udp, SocketOp::RecvFrom) => &UDP_RECEIVED,
+        (SocketKind::Udp, SocketOp::SendTo) => &UDP_SENT,
+        _ => return,
+    };
+    counter.fetch_add(n, Ordering::SeqCst);
+}
+
+pub fn test_net_metrics_sink() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (mut server, _) = listener.accept().unwrap();
+    let a = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let b = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    net::install_metrics(counting_sink);
+    client.write_all(b"hello").unwrap();
+    let mut buf = [0u8; 5];
+    server.read_exact(&mut buf).unwrap();
+    a.send_to(b"abc", b.local_addr().unwrap()).unwrap();
+    let mut buf = [0u8; 16];
+    assert_eq!(b.recv_from(&mut buf).unwrap().0, 3);
+    assert!(net::uninstall_metrics().is_some());
+
+    // Nothing is recorded once the sink is gone.
+    client.write_all(b"!").unwrap();
+    server.read_exact(&mut buf[..1]).unwrap();
+    assert!(net::uninstall_metrics().is_none());
+
+    assert_eq!(TCP_WRITTEN.load(Ordering::SeqCst), 5);
+    assert_eq!(TCP_READ.load(Ordering::SeqCst), 5);
+    assert_eq!(UDP_SENT.load(Ordering::SeqCst), 3);
+    assert_eq!(UDP_RECEIVED.load(Ordering::SeqCst), 3);
+}
+
+pub fn test_net_udp_send_to_vectored() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server_addr = server.local_addr().unwrap();
+
+    let bufs = [IoSlice::new(b"head:"), IoSlice::new(b"payload")];
+    assert_eq!(client.send_to_vectored(&bufs, server_addr).unwrap(), 12);
+
+    let mut buf = [0_u8; 32];
+    let (n, from) = server.recv_from(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"head:payload");
+    assert_eq!(from, client.local_addr().unwrap());
+
+    client.connect(server_addr).unwrap();
+    let bufs = [IoSlice::new(b"ab"), IoSlice::new(b""), IoSlice::new(b"cd")];
+    assert_eq!(client.send_vectored(&bufs).unwrap(), 4);
+    let n = server.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"abcd");
+}
+
+pub fn test_net_ipv4_predicates() {
+    // All of the predicates can be evaluated at compile time.
+    const LINK_LOCAL: bool = Ipv4Addr::new(169, 254, 1, 1).is_link_local();
+    const DOCUMENTATION: bool = Ipv4Addr::new(192, 0, 2, 1).is_documentation();
+    const PROTOCOL: bool = Ipv4Addr::new(192, 0, 0, 1).is_ietf_protocol_assignment();
+    const GLOBAL: bool = Ipv4Addr::new(1, 1, 1, 1).is_global();
+    assert!(LINK_LOCAL && DOCUMENTATION && PROTOCOL && GLOBAL);
+
+    assert!(!Ipv4Addr::new(169, 253, 255, 255).is_link_local());
+    assert!(Ipv4Addr::new(169, 254, 0, 0).is_link_local());
+    assert!(Ipv4Addr::new(169, 254, 255, 255).is_link_local());
+    assert!(!Ipv4Addr::new(169, 255, 0, 0).is_link_local());
+
+    for &(a, b, c) in &[(192, 0, 2), (198, 51, 100), (203, 0, 113)] {
+        assert!(!Ipv4Addr::new(a, b, c - 1, 255).is_documentation());
+        assert!(Ipv4Addr::new(a, b, c, 0).is_documentation());
+        assert!(Ipv4Addr::new(a, b, c, 255).is_documentation());
+        assert!(!Ipv4Addr::new(a, b, c + 1, 0).is_documentation());
+        assert!(!Ipv4Addr::new(a, b, c, 1).is_global());
+    }
+
+    assert!(!Ipv4Addr::new(191, 255, 255, 255).is_ietf_protocol_assignment());
+    assert!(Ipv4Addr::new(192, 0, 0, 0).is_ietf_protocol_assignment());
+    assert!(Ipv4Addr::new(192, 0, 0, 255).is_ietf_protocol_assignment());
+    assert!(!Ipv4Addr::new(192, 0, 1, 0).is_ietf_protocol_assignment());
+    assert!(!Ipv4Addr::new(192, 0, 0, 8).is_global());
+    assert!(Ipv4Addr::new(192, 0, 0, 9).is_global());
+    assert!(Ipv4Addr::new(192, 0, 0, 10).is_global());
+    assert!(!Ipv4Addr::new(192, 0, 0, 11).is_global());
+
+    assert!(!Ipv4Addr::new(223, 255, 255, 255).is_multicast());
+    assert!(Ipv4Addr::new(224, 0, 0, 0).is_multicast());
+    assert!(Ipv4Addr::new(239, 255, 255, 255).is_multicast());
+    assert!(!Ipv4Addr::new(240, 0, 0, 0).is_multicast());
+}
+
+pub fn test_net_tcp_read_into_pooled() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (server, _) = listener.accept().unwrap();
+    let pool = BufferPool::new(64, 1);
+
+    client.write_all(b"first message").unwrap();
+    let buf = server.read_into_pooled(&pool).unwrap();
+    assert_eq!(&buf[..], b"first message");
+    assert_eq!(buf.capacity(), 64);
+    let ptr = buf.as_ptr();
+    drop(buf);
+    assert_eq!(pool.idle(), 1);
+
+    // The same allocation comes back, and only the new bytes are visible.
+    client.write_all(b"two").unwrap();
+    let buf = server.read_into_pooled(&pool).unwrap();
+    assert_eq!(buf.as_ptr(), ptr);
+    assert_eq!(&buf[..], b"two");
+    assert_eq!(pool.idle(), 0);
+
+    let mut fresh = pool.get();
+    assert!(fresh.is_empty());
+    assert_ne!(fresh.as_ptr(), ptr);
+    fresh.fill_with(|b| {
+        assert!(b.iter().all(|&x| x == 0));
+        Ok(0)
+    })
+    .unwrap();
+    assert!(fresh.is_empty());
+
+    // Only `max_idle` buffers are kept.
+    drop(buf);
+    drop(fresh);
+    assert_eq!(pool.idle(), 1);
+
+    drop(client);
+    assert!(server.read_into_pooled(&pool).unwrap().is_empty());
+}
+
+pub fn test_net_tcp_configure() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let _server = listener.accept().unwrap();
+
+    let opts = SocketOptions::new()
+        .nodelay(true)
+        .ttl(42)
+        .read_timeout(Some(Duration::from_millis(1500)))
+        .write_timeout(Some(Duration::from_secs(3)))
+        .recv_buffer_size(32 * 1024)
+        .send_buffer_size(16 * 1024);
+    stream.configure(&opts).unwrap();
+
+    assert!(stream.nodelay().unwrap());
+    assert_eq!(stream.ttl().unwrap(), 42);
+    assert_eq!(stream.read_timeout().unwrap(), Some(Duration::from_millis(1500)));
+    assert_eq!(stream.write_timeout().unwrap(), Some(Duration::from_secs(3)));
+    assert!(stream.recv_buffer_size().unwrap() >= 32 * 1024);
+    assert!(stream.send_buffer_size().unwrap() >= 16 * 1024);
+
+    // Unset options are left alone, and an empty set is a no-op.
+    stream.configure(&SocketOptions::new().nodelay(false)).unwrap();
+    assert!(!stream.nodelay().unwrap());
+    assert_eq!(stream.ttl().unwrap(), 42);
+    stream.configure(&SocketOptions::new()).unwrap();
+
+    // The failing option is named in the error.
+    let err = stream.configure(&SocketOptions::new().nodelay(true).ttl(1000)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert!(err.to_string().contains("IP_TTL"));
+    assert!(stream.nodelay().unwrap());
+
+    let err = stream.configure(&SocketOptions::new().read_timeout(Some(Duration::new(0, 0))));
+    assert_eq!(err.unwrap_err().kind(), ErrorKind::InvalidInput);
+}
+
+pub fn test_net_host_port_parse() {
+    let hp: HostPort = "example.com:443".parse().unwrap();
+    assert_eq!((hp.host(), hp.port()), ("example.com", 443));
+    assert_eq!(hp.to_string(), "example.com:443");
+
+    let hp: HostPort = "1.2.3.4:8080".parse().unwrap();
+    assert_eq!(hp.host(), "1.2.3.4");
+    assert_eq!(hp.port(), 8080);
+
+    let hp: HostPort = "[::1]:65535".parse().unwrap();
+    assert_eq!(hp.host(), "::1");
+    assert_eq!(hp.port(), 65535);
+    assert_eq!(hp.to_string(), "[::1]:65535");
+    assert_eq!(hp.to_string().parse::<HostPort>().unwrap(), hp);
+
+    let hp: HostPort = "[fe80::1%2]:0".parse().unwrap();
+    assert_eq!(hp.into_parts(), ("fe80::1%2".to_string(), 0));
+
+    // Nothing is resolved, but the pair can be resolved later.
+    let hp: HostPort = "127.0.0.1:80".parse().unwrap();
+    let addrs: Vec<SocketAddr> = hp.to_socket_addrs().unwrap().collect();
+    assert_eq!(addrs, vec![SocketAddr::from(([127, 0, 0, 1], 80))]);
+
+    for bad in &[
+        "",
+        "example.com",
+        "example.com:",
+        ":80",
+        "example.com:65536",
+        "example.com:-1",
+        "example.com:80x",
+        "::1:80",
+        "[::1]",
+        "[::1]80",
+        "[example.com]:80",
+        "exa mple.com:80",
+    ] {
+        let err = bad.parse::<HostPort>().unwrap_err();
+        assert_eq!(err.expected(), "a host and port");
+    }
+    assert_eq!("example.com".parse::<HostPort>().unwrap_err().position(), Some(11));
+    assert_eq!("example.com:65536".parse::<HostPort>().unwrap_err().position(), Some(12));
+}
+
+pub fn test_net_tcp_take_error_refused() {
+    // Find a port that nothing listens on.
+    let closed = {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        assert!(listener.take_error().unwrap().is_none());
+        listener.local_addr().unwrap()
+    };
+
+    let stream = TcpStream::new_v4().unwrap();
+    stream.set_nonblocking(true).unwrap();
+    match stream.connect_socket(closed) {
+        // Loopback connects may fail right away instead of asynchronously.
+        Err(ref e) if e.kind() == ErrorKind::ConnectionRefused => {}
+        _ => {
+            stream.wait_writable(Some(Duration::from_secs(5))).unwrap();
+            let err = stream.take_error().unwrap().expect("no pending error");
+            assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+        }
+    }
+    // Taking the error clears it.
+    assert!(stream.take_error().unwrap().is_none());
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    assert!(socket.take_error().unwrap().is_none());
+}
+
+pub fn test_net_nonblocking_would_block() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    assert_eq!(listener.accept().unwrap_err().kind(), ErrorKind::WouldBlock);
+
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (mut server, _) = loop {
+        match listener.accept() {
+            Ok(pair) => break pair,
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
+                listener.set_nonblocking(false).unwrap();
+            }
+            Err(e) => panic!("accept failed: {}", e),
+        }
+    };
+
+    let mut buf = [0u8; 8];
+    server.set_nonblocking(true).unwrap();
+    assert_eq!(server.read(&mut buf).unwrap_err().kind(), ErrorKind::WouldBlock);
+    client.write_all(b"data").unwrap();
+    server.set_nonblocking(false).unwrap();
+    assert_eq!(server.read(&mut buf).unwrap(), 4);
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.set_nonblocking(true).unwrap();
+    assert_eq!(socket.recv_from(&mut buf).unwrap_err().kind(), ErrorKind::WouldBlock);
+    socket.send_to(b"dgram", socket.local_addr().unwrap()).unwrap();
+    socket.set_nonblocking(false).unwrap();
+    assert_eq!(socket.recv_from(&mut buf).unwrap().0, 5);
+}
+
+pub fn test_net_unix_listener_and_datagram() {
+    use std::path::Path;
+    use std::untrusted::fs::remove_file;
+
+    let stream_path = Path::new("/tmp/sgx_unit_test_unix_stream.sock");
+    let _ = remove_file(stream_path);
+    let listener = UnixListener::bind(stream_path).unwrap();
+    assert_eq!(listener.local_addr().unwrap().as_pathname(), Some(stream_path));
+
+    let mut client = UnixStream::connect(stream_path).unwrap();
+    let (mut server, _) = listener.accept().unwrap();
+    client.write_all(b"hello").unwrap();
+    let mut buf = [0u8; 5];
+    server.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hello");
+    drop(listener);
+    remove_file(stream_path).unwrap();
+
+    let a_path = Path::new("/tmp/sgx_unit_test_unix_dgram_a.sock");
+    let b_path = Path::new("/tmp/sgx_unit_test_unix_dgram_b.sock");
+    let _ = remove_file(a_path);
+    let _ = remove_file(b_path);
+    let a = UnixDatagram::bind(a_path).unwrap();
+    let b = UnixDatagram::bind(b_path).unwrap();
+    assert_eq!(a.send_to(b"dgram", b_path).unwrap(), 5);
+    let (n, from) = b.recv_from(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"dgram");
+    assert_eq!(from.as_pathname(), Some(a_path));
+    remove_file(a_path).unwrap();
+    remove_file(b_path).unwrap();
+}
+
+static RESOLVER_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+fn counting_resolver(host: &str) -> io::Result<net::Resolution> {
+    RESOLVER_CALLS.fetch_add(1, Ordering::SeqCst);
+    match host {
+        "cached.internal" => Ok(net::Resolution::new(
+            vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), IpAddr::V6(Ipv6Addr::LOCALHOST)],
+            Duration::from_secs(300),
+        )),
+        "uncached.internal" => Ok(net::Resolution::new(
+            vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))],
+            Duration::from_secs(0),
+        )),
+        _ => Err(io::Error::new(ErrorKind::NotFound, "unknown host")),
+    }
+}
+
+pub fn test_net_resolver_cache() {
+    net::set_resolver(counting_resolver);
+    RESOLVER_CALLS.store(0, Ordering::SeqCst);
+
+    let addrs: Vec<SocketAddr> = "cached.internal:80".to_socket_addrs().unwrap().collect();
+    assert_eq!(
+        addrs,
+        vec![SocketAddr::from(([10, 0, 0, 1], 80)), SocketAddr::new(Ipv6Addr::LOCALHOST.into(), 80)]
+    );
+    // The port is not part of the cache key.
+    let addrs: Vec<SocketAddr> = ("cached.internal", 443).to_socket_addrs().unwrap().collect();
+    assert_eq!(addrs[0], SocketAddr::from(([10, 0, 0, 1], 443)));
+    assert_eq!(RESOLVER_CALLS.load(Ordering::SeqCst), 1);
+
+    // A zero TTL is never cached, and errors are passed on.
+    "uncached.internal:80".to_socket_addrs().unwrap();
+    "uncached.internal:80".to_socket_addrs().unwrap();
+    assert_eq!(RESOLVER_CALLS.load(Ordering::SeqCst), 3);
+    let err = "missing.internal:80".to_socket_addrs().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert_eq!(RESOLVER_CALLS.load(Ordering::SeqCst), 4);
+
+    // Literal addresses never reach the resolver.
+    "10.1.2.3:80".to_socket_addrs().unwrap();
+    ("::1", 80).to_socket_addrs().unwrap();
+    assert_eq!(RESOLVER_CALLS.load(Ordering::SeqCst), 4);
+
+    net::clear_resolver_cache();
+    "cached.internal:80".to_socket_addrs().unwrap();
+    assert_eq!(RESOLVER_CALLS.load(Ordering::SeqCst), 5);
+
+    net::set_resolver_cache_capacity(0);
+    "cached.internal:80".to_socket_addrs().unwrap();
+    "cached.internal:80".to_socket_addrs().unwrap();
+    assert_eq!(RESOLVER_CALLS.load(Ordering::SeqCst), 7);
+    net::set_resolver_cache_capacity(net::DEFAULT_RESOLVER_CACHE_CAPACITY);
+
+    assert!(net::take_resolver().is_some());
+    assert!(net::take_resolver().is_none());
+    // Back to the host resolver.
+    let addrs: Vec<SocketAddr> = "localhost:80".to_socket_addrs().unwrap().collect();
+    assert!(addrs.iter().all(|addr| addr.ip().is_loopback()));
+}
+
+static SLOW_RESOLVER: AtomicUsize = AtomicUsize::new(0);
+
+// Answers only once the test lets it, after signalling that it was called.
+fn slow_resolver(_host: &str) -> io::Result<net::Resolution> {
+    SLOW_RESOLVER.store(1, Ordering::SeqCst);
+    while SLOW_RESOLVER.load(Ordering::SeqCst) != 2 {
+        thread::yield_now();
+    }
+    Ok(net::Resolution::new(vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3))], Duration::from_secs(300)))
+}
+
+fn fresh_resolver(_host: &str) -> io::Result<net::Resolution> {
+    Ok(net::Resolution::new(vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 4))], Duration::from_secs(300)))
+}
+
+pub fn test_net_resolver_cache_replaced() {
+    net::set_resolver(slow_resolver);
+    SLOW_RESOLVER.store(0, Ordering::SeqCst);
+
+    let lookup = thread::spawn(|| "race.internal:80".to_socket_addrs().unwrap().next().unwrap());
+    while SLOW_RESOLVER.load(Ordering::SeqCst) != 1 {
+        thread::yield_now();
+    }
+    // Replaced while the old resolver is still answering: its answer goes
+    // back to the caller that asked, but not into the cache.
+    net::set_resolver(fresh_resolver);
+    SLOW_RESOLVER.store(2, Ordering::SeqCst);
+    assert_eq!(lookup.join().unwrap(), SocketAddr::from(([10, 0, 0, 3], 80)));
+
+    let addr = "race.internal:80".to_socket_addrs().unwrap().next().unwrap();
+    assert_eq!(addr, SocketAddr::from(([10, 0, 0, 4], 80)));
+    assert!(net::take_resolver().is_some());
+}
+
+pub fn test_net_epoll() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let poll = Poll::new().unwrap();
+    let mut events = Events::with_capacity(4);
+    assert_eq!(events.capacity(), 4);
+
+    poll.register(&listener, 7, Interest::READABLE).unwrap();
+    assert_eq!(
+        poll.register(&listener, 7, Interest::READABLE).unwrap_err().kind(),
+        io::ErrorKind::AlreadyExists
+    );
+
+    // Nothing pending yet, so the wait times out.
+    let n = poll.poll(&mut events, Some(Duration::from_millis(20))).unwrap();
+    assert_eq!(n, 0);
+    assert!(events.is_empty());
+
+    let _client = TcpStream::connect(addr).unwrap();
+    let n = poll.poll(&mut events, Some(Duration::from_secs(5))).unwrap();
+    assert_eq!(n, 1);
+    let event = events.iter().next().unwrap();
+    assert_eq!(event.token(), 7);
+    assert!(event.is_readable());
+    assert!(!event.is_writable());
+
+    poll.reregister(&listener, 9, Interest::READABLE).unwrap();
+    poll.poll(&mut events, Some(Duration::from_secs(5))).unwrap();
+    assert!(events.iter().all(|event| event.token() == 9));
+
+    poll.deregister(&listener).unwrap();
+    assert_eq!(poll.deregister(&listener).unwrap_err().kind(), io::ErrorKind::NotFound);
+    assert_eq!(poll.poll(&mut events, Some(Duration::ZERO)).unwrap(), 0);
+}
+
+// Wakes itself once before completing, to exercise the executor's ready queue.
+struct YieldOnce {
+    yielded: bool,
+    done: Rc<Cell<bool>>,
+}
+
+impl Future for YieldOnce {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> task::Poll<()> {
+        if self.yielded {
+            self.done.set(true);
+            task::Poll::Ready(())
+        } else {
+            self.yielded = true;
+            cx.waker().wake_by_ref();
+            task::Poll::Pending
+        }
+    }
+}
+
+pub fn test_net_local_executor() {
+    let executor = LocalExecutor::new().unwrap();
+    let listener = AsyncTcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut client = TcpStream::connect(addr).unwrap();
+    let (server, peer) = executor.block_on(listener.accept()).unwrap().unwrap();
+    assert_eq!(peer, client.local_addr().unwrap());
+
+    let done = Rc::new(Cell::new(false));
+    executor.spawn(YieldOnce { yielded: false, done: done.clone() });
+
+    // The data only arrives after the read has blocked on the reactor.
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        client.write_all(b"ping").unwrap();
+        let mut buf = [0; 4];
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"pong");
+    });
+    let mut buf = [0; 4];
+    executor.block_on(server.read_exact(&mut buf)).unwrap().unwrap();
+    assert_eq!(&buf, b"ping");
+    executor.block_on(server.write_all(b"pong")).unwrap().unwrap();
+    t.join().unwrap();
+    assert!(done.get());
+
+    // Peer shut down.
+    assert_eq!(executor.block_on(server.read(&mut buf)).unwrap().unwrap(), 0);
+
+    let std_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let stream = executor
+        .block_on(AsyncTcpStream::connect(std_listener.local_addr().unwrap()))
+        .unwrap()
+        .unwrap();
+    let (_accepted, peer) = std_listener.accept().unwrap();
+    assert_eq!(peer, stream.local_addr().unwrap());
+
+    // Nothing can ever wake this future.
+    let err = executor.block_on(future::pending::<()>()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Other);
+}
+
+pub fn test_net_tcp_extended_sockopts() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    // `bind` already sets SO_REUSEADDR.
+    assert!(listener.reuse_address().unwrap());
+    listener.set_linger(Some(Duration::from_secs(3))).unwrap();
+    assert_eq!(listener.linger().unwrap(), Some(Duration::from_secs(3)));
+
+    let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+    stream.set_keepalive(true).unwrap();
+    assert!(stream.keepalive().unwrap());
+    stream.set_keepalive_idle(Duration::from_secs(60)).unwrap();
+    assert_eq!(stream.keepalive_idle().unwrap(), Duration::from_secs(60));
+    // Sub-second remainders round up.
+    stream.set_keepalive_interval(Duration::from_millis(1500)).unwrap();
+    assert_eq!(stream.keepalive_interval().unwrap(), Duration::from_secs(2));
+    stream.set_keepalive_count(4).unwrap();
+    assert_eq!(stream.keepalive_count().unwrap(), 4);
+    stream.set_keepalive(false).unwrap();
+    assert!(!stream.keepalive().unwrap());
+
+    stream.set_reuse_address(true).unwrap();
+    assert!(stream.reuse_address().unwrap());
+    stream.set_tos(0x10).unwrap();
+    assert_eq!(stream.tos().unwrap(), 0x10);
+
+    let invalid = |res: io::Result<()>| res.unwrap_err().kind() == ErrorKind::InvalidInput;
+    assert!(invalid(stream.set_keepalive_idle(Duration::ZERO)));
+    assert!(invalid(stream.set_keepalive_interval(Duration::from_secs(40_000))));
+    assert!(invalid(stream.set_keepalive_count(0)));
+    assert!(invalid(stream.set_keepalive_count(128)));
+    assert!(invalid(stream.set_tos(256)));
+    assert!(invalid(stream.set_linger(Some(Duration::from_secs(u64::MAX)))));
+}
+
+pub fn test_net_udp_multicast_options() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.set_broadcast(true).unwrap();
+    assert!(socket.broadcast().unwrap());
+    socket.set_multicast_ttl_v4(8).unwrap();
+    assert_eq!(socket.multicast_ttl_v4().unwrap(), 8);
+    socket.set_multicast_loop_v4(false).unwrap();
+    assert!(!socket.multicast_loop_v4().unwrap());
+    socket.set_multicast_if_v4(&Ipv4Addr::LOCALHOST).unwrap();
+    assert_eq!(socket.multicast_if_v4().unwrap(), Ipv4Addr::LOCALHOST);
+
+    let socket = UdpSocket::bind("[::1]:0").unwrap();
+    socket.set_multicast_hops_v6(16).unwrap();
+    assert_eq!(socket.multicast_hops_v6().unwrap(), 16);
+    assert_eq!(socket.set_multicast_hops_v6(256).unwrap_err().kind(), ErrorKind::InvalidInput);
+    socket.set_multicast_loop_v6(false).unwrap();
+    assert!(!socket.multicast_loop_v6().unwrap());
+    socket.set_multicast_if_v6(0).unwrap();
+    assert_eq!(socket.multicast_if_v6().unwrap(), 0);
+}
+
+pub fn test_net_raw_socket_ping() {
+    // Ping sockets are only handed to groups within net.ipv4.ping_group_range.
+    let socket = match RawSocket::new_datagram(RawDomain::Ipv4, IPPROTO_ICMP) {
+        Ok(socket) => socket,
+        Err(ref e) if e.kind() == ErrorKind::PermissionDenied => return,
+        Err(e) => panic!("unexpected error: {}", e),
+    };
+    socket.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+    socket.set_ttl(32).unwrap();
+    assert_eq!(socket.ttl().unwrap(), 32);
+
+    let request = [8, 0, 0, 0, 0, 0, 0, 7, b'e', b'n', b'c', b'l'];
+    assert_eq!(socket.send_to(&request, "127.0.0.1:0").unwrap(), request.len());
+
+    let mut reply = [0; 64];
+    let (len, from) = socket.recv_from(&mut reply).unwrap();
+    assert_eq!(from.ip(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+    assert_eq!(len, request.len());
+    assert_eq!(reply[0], 0);
+    assert_eq!(&reply[6..len], &request[6..]);
+}
+
+pub fn test_net_vsock() {
+    let mut addr = VsockAddr::new(VsockAddr::CID_HOST, 5000);
+    assert_eq!((addr.cid(), addr.port()), (2, 5000));
+    addr.set_cid(VsockAddr::CID_LOCAL);
+    addr.set_port(VsockAddr::PORT_ANY);
+    assert_eq!(addr.to_string(), "1:4294967295");
+
+    // Loopback needs the host's vsock_loopback module; skip where vsock is absent.
+    let listener = match VsockListener::bind(&addr) {
+        Ok(listener) => listener,
+        Err(_) => return,
+    };
+    let local = listener.local_addr().unwrap();
+    assert_eq!(local.cid(), VsockAddr::CID_LOCAL);
+    assert_ne!(local.port(), VsockAddr::PORT_ANY);
+
+    let sender = thread::spawn(move || {
+        let mut stream = VsockStream::connect(&local).unwrap();
+        stream.write_all(b"vsock").unwrap();
+    });
+    let (mut stream, peer) = listener.accept().unwrap();
+    assert_eq!(peer.cid(), VsockAddr::CID_LOCAL);
+    assert_eq!(stream.peer_addr().unwrap(), peer);
+    let mut buf = Vec::new();
+    stream.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, b"vsock");
+    sender.join().unwrap();
+}
+
+pub fn test_net_tcp_vectored() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (mut server, _) = listener.accept().unwrap();
+    assert!(client.is_write_vectored());
+    assert!(server.is_read_vectored());
+
+    // Empty buffers anywhere in the list are skipped, and a list with nothing
+    // to transfer completes at once.
+    let bufs = [IoSlice::new(b"head:"), IoSlice::new(b""), IoSlice::new(b"body")];
+    assert_eq!(client.write_vectored(&bufs).unwrap(), 9);
+    assert_eq!(client.write_vectored(&[IoSlice::new(b"")]).unwrap(), 0);
+    client.shutdown(Shutdown::Write).unwrap();
+
+    let (mut head, mut empty, mut body) = ([0_u8; 5], [0_u8; 0], [0_u8; 8]);
+    let mut read = 0;
+    while read < 9 {
+        let mut bufs = [
+            IoSliceMut::new(&mut head[read.min(5)..]),
+            IoSliceMut::new(&mut empty),
+            IoSliceMut::new(&mut body[read.saturating_sub(5)..]),
+        ];
+        let n = server.read_vectored(&mut bufs).unwrap();
+        assert_ne!(n, 0);
+        read += n;
+    }
+    assert_eq!(&head, b"head:");
+    assert_eq!(&body[..4], b"body");
+
+    // An empty datagram goes through sendmsg with no buffers at all.
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    assert_eq!(sender.send_to_vectored(&[], receiver.local_addr().unwrap()).unwrap(), 0);
+    let mut buf = [0_u8; 8];
+    let (n, from) = receiver.recv_from(&mut buf).unwrap();
+    assert_eq!((n, from), (0, sender.local_addr().unwrap()));
+
+    // recvmsg skips empty buffers too, and takes a list of nothing but them.
+    let to = receiver.local_addr().unwrap();
+    sender.send_to(b"dgram", to).unwrap();
+    let mut empty = [0_u8; 0];
+    let mut bufs = [IoSliceMut::new(&mut empty), IoSliceMut::new(&mut buf)];
+    let (n, truncated, from) = receiver.recv_from_vectored(&mut bufs).unwrap();
+    assert_eq!((n, truncated, from), (5, false, sender.local_addr().unwrap()));
+    assert_eq!(&buf[..5], b"dgram");
+    sender.send_to(b"dgram", to).unwrap();
+    let (n, truncated, _) =
+        receiver.recv_from_vectored(&mut [IoSliceMut::new(&mut empty)]).unwrap();
+    assert_eq!((n, truncated), (0, true));
+}
+
+pub fn test_net_tcp_send_file() {
+    let data: Vec<u8> = (0..200_000_u32).map(|i| (i % 251) as u8).collect();
+    {
+        let mut file = SgxFile::create("sgx_send_file").unwrap();
+        file.write_all(&data).unwrap();
+    }
+    let file = SgxFile::open("sgx_send_file").unwrap();
+    (&file).seek(SeekFrom::Start(7)).unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (server, _) = listener.accept().unwrap();
+    let receiver = thread::spawn(move || {
+        let mut received = Vec::new();
+        (&server).read_to_end(&mut received).unwrap();
+        received
+    });
+
+    // Several chunks, then a range that runs past the end of the file.
+    assert_eq!(client.send_file(&file, 1000..150_000).unwrap(), 149_000);
+    assert_eq!(client.send_file(&file, 199_990..).unwrap(), 10);
+    assert_eq!(client.send_file(&file, 10..10).unwrap(), 0);
+    let (start, end) = (10, 5);
+    let err = client.send_file(&file, start..end).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    client.shutdown(Shutdown::Write).unwrap();
+    // The caller's cursor is where it was before.
+    assert_eq!((&file).seek(SeekFrom::Current(0)).unwrap(), 7);
+
+    let received = receiver.join().unwrap();
+    assert_eq!(received.len(), 149_010);
+    assert_eq!(&received[..149_000], &data[1000..150_000]);
+    assert_eq!(&received[149_000..], &data[199_990..]);
+
+    drop(file);
+    sgxfs::remove("sgx_send_file").unwrap();
+}
+
+pub fn test_net_tcp_happy_eyeballs() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let good = listener.local_addr().unwrap();
+    let refused = {
+        let closed = TcpListener::bind("[::1]:0").unwrap();
+        closed.local_addr().unwrap()
+    };
+
+    // A refused first attempt hands over to the next address at once.
+    let start = Instant::now();
+    let addrs = [refused, good];
+    let stream = TcpStream::connect_happy_eyeballs(&addrs[..], Duration::from_secs(30)).unwrap();
+    assert_eq!(stream.peer_addr().unwrap(), good);
+    assert!(start.elapsed() < Duration::from_secs(10));
+    drop(listener.accept().unwrap());
+
+    // An address that never answers is raced instead of waited out.
+    let start = Instant::now();
+    let blackhole: SocketAddr = "192.0.2.1:9".parse().unwrap();
+    let addrs = [blackhole, good];
+    let stream = TcpStream::connect_happy_eyeballs(&addrs[..], CONNECTION_ATTEMPT_DELAY).unwrap();
+    assert_eq!(stream.peer_addr().unwrap(), good);
+    assert!(start.elapsed() < Duration::from_secs(10));
+    drop(listener.accept().unwrap());
+
+    let err = TcpStream::connect_happy_eyeballs(refused, CONNECTION_ATTEMPT_DELAY).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+    let none: &[SocketAddr] = &[];
+    let err = TcpStream::connect_happy_eyeballs(none, CONNECTION_ATTEMPT_DELAY).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+pub fn test_net_tcp_accept_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let start = Instant::now();
+    let err = listener.accept_timeout(Duration::from_millis(100)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    let err = listener.accept_timeout(Duration::from_secs(0)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    let client = TcpStream::connect(addr).unwrap();
+    let (_stream, peer) = listener.accept_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(peer, client.local_addr().unwrap());
+
+    // The stored timeout applies to accept and carries over to clones.
+    assert_eq!(
+        listener.set_accept_timeout(Some(Duration::from_secs(0))).unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+    listener.set_accept_timeout(Some(Duration::from_millis(50))).unwrap();
+    assert_eq!(listener.accept().unwrap_err().kind(), ErrorKind::TimedOut);
+    let clone = listener.try_clone().unwrap();
+    assert_eq!(clone.incoming().next().unwrap().unwrap_err().kind(), ErrorKind::TimedOut);
+    listener.set_accept_timeout(None).unwrap();
+    let _client = TcpStream::connect(addr).unwrap();
+    listener.accept().unwrap();
+}
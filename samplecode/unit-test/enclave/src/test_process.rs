@@ -0,0 +1,73 @@
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::process::ExitStatusExt;
+use std::process::{self, Command, Stdio};
+use std::string::String;
+
+pub fn test_process_output() {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg("echo $SGX_PROCESS_VAR; echo err >&2; exit 3")
+        .env("SGX_PROCESS_VAR", "hello")
+        .output()
+        .unwrap();
+    assert_eq!(output.stdout, b"hello\n");
+    assert_eq!(output.stderr, b"err\n");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(!output.status.success());
+
+    let output = Command::new("pwd").current_dir("/").output().unwrap();
+    assert_eq!(output.stdout, b"/\n");
+    assert!(output.status.success());
+
+    let output =
+        Command::new("sh").arg("-c").arg("echo ${HOME-unset}").env_clear().output().unwrap();
+    assert_eq!(output.stdout, b"unset\n");
+
+    let err = Command::new("/nonexistent/program").spawn().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    let err = Command::new("sh").arg("a\0b").spawn().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+pub fn test_process_spawn_pipes() {
+    let mut child =
+        Command::new("cat").stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+    assert!(child.id() > 0);
+    child.stdin.take().unwrap().write_all(b"through the host").unwrap();
+    let mut out = String::new();
+    child.stdout.take().unwrap().read_to_string(&mut out).unwrap();
+    assert_eq!(out, "through the host");
+    assert!(child.wait().unwrap().success());
+
+    // One child's output can feed another.
+    let mut first = Command::new("echo").arg("piped").stdout(Stdio::piped()).spawn().unwrap();
+    let second = Command::new("cat")
+        .stdin(first.stdout.take().unwrap())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    assert_eq!(second.wait_with_output().unwrap().stdout, b"piped\n");
+    first.wait().unwrap();
+
+    let mut child = Command::new("sleep").arg("100").spawn().unwrap();
+    assert!(child.try_wait().unwrap().is_none());
+    child.kill().unwrap();
+    let status = child.wait().unwrap();
+    assert_eq!(status.signal(), Some(9));
+    assert_eq!(status.code(), None);
+    assert_eq!(child.kill().unwrap_err().kind(), ErrorKind::InvalidInput);
+}
+
+pub fn test_process_register_secret() {
+    let mut key = [0x5au8; 32];
+    let guard = unsafe { process::register_secret(key.as_mut_ptr(), key.len()) };
+    drop(guard);
+
+    // Host memory is rejected, it could not be scrubbed in any useful way.
+    unsafe {
+        let host = sgx_libc::ocall::malloc(32) as *mut u8;
+        assert!(!host.is_null());
+        should_panic!(process::register_secret(host, 32));
+        sgx_libc::ocall::free(host as *mut sgx_libc::c_void);
+    }
+}
@@ -375,3 +375,311 @@ pub fn test_cstr() {
         Cow::Owned(String::from("Hello �World")) as Cow<str>
     );
 }
+
+pub fn test_cstr_hash() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashMap;
+    use std::hash::{Hash, Hasher};
+
+    fn hash_of<T: Hash + ?Sized>(t: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        t.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    let c_string = CString::new("foo").unwrap();
+    let c_str = CStr::from_bytes_with_nul(b"foo\0").unwrap();
+    assert_eq!(&*c_string, c_str);
+    assert_eq!(hash_of(&c_string), hash_of(c_str));
+
+    let mut map = HashMap::new();
+    map.insert(c_string, 1);
+    assert_eq!(map.get(c_str), Some(&1));
+    assert_eq!(map.get(CStr::from_bytes_with_nul(b"bar\0").unwrap()), None);
+}
+
+pub fn test_cstr_truncate() {
+    let mut c_string = CString::new("foobar").unwrap();
+    c_string.truncate(3);
+    assert_eq!(c_string.as_bytes_with_nul(), b"foo\0");
+
+    let mut c_string = CString::new("foo").unwrap();
+    c_string.truncate(3);
+    assert_eq!(c_string.as_bytes_with_nul(), b"foo\0");
+
+    let mut c_string = CString::new("foo").unwrap();
+    c_string.truncate(10);
+    assert_eq!(c_string.as_bytes_with_nul(), b"foo\0");
+
+    let mut c_string = CString::new("foo").unwrap();
+    c_string.truncate(0);
+    assert_eq!(c_string.as_bytes_with_nul(), b"\0");
+    assert_eq!(c_string, CString::default());
+}
+
+pub fn test_cstr_as_ptr_range() {
+    let c_str = CStr::from_bytes_with_nul(b"foo\0").unwrap();
+    let range = c_str.as_ptr_range();
+    assert_eq!(range.start, c_str.as_ptr());
+    assert_eq!(range.end as usize - range.start as usize, 3);
+    assert_eq!(unsafe { *range.end }, 0);
+
+    let c_str = CStr::from_bytes_with_nul(b"\0").unwrap();
+    let range = c_str.as_ptr_range();
+    assert_eq!(range.start, range.end);
+}
+
+pub fn test_cstr_join() {
+    let c_string = CString::join(b", ", &["a", "bc", "def"]).unwrap();
+    assert_eq!(c_string.as_bytes_with_nul(), b"a, bc, def\0");
+
+    let c_string = CString::join(b"/", vec![b"only".to_vec()]).unwrap();
+    assert_eq!(c_string.as_bytes_with_nul(), b"only\0");
+
+    let parts: [&[u8]; 0] = [];
+    let c_string = CString::join(b"/", &parts).unwrap();
+    assert_eq!(c_string, CString::default());
+
+    let err = CString::join(b"/", &[&b"ab"[..], &b"c\0d"[..]]).unwrap_err();
+    assert_eq!(err.nul_position(), 4);
+    assert_eq!(err.into_vec(), b"ab/c\0d".to_vec());
+}
+
+pub fn test_cstr_debug_alternate() {
+    let c_str = CStr::from_bytes_with_nul("h\u{e9}llo \"w\"\0".as_bytes()).unwrap();
+    assert_eq!(format!("{:?}", c_str), "\"h\\xc3\\xa9llo \\\"w\\\"\"");
+    assert_eq!(format!("{:#?}", c_str), "\"h\u{e9}llo \\\"w\\\"\"");
+
+    let c_string = CString::new(&b"\x01\xff\xc3a\n"[..]).unwrap();
+    assert_eq!(format!("{:?}", c_string), "\"\\x01\\xff\\xc3a\\n\"");
+    assert_eq!(format!("{:#?}", c_string), "\"\\u{1}\\xff\\xc3a\\n\"");
+}
+
+pub fn test_cstr_from_fmt() {
+    let c_string = CString::from_fmt(format_args!("{}", 42)).unwrap();
+    assert_eq!(c_string.as_bytes_with_nul(), b"42\0");
+
+    let c_string = sgx_trts::cstr_format!("{}-{:04}", "id", 7).unwrap();
+    assert_eq!(c_string.as_bytes_with_nul(), b"id-0007\0");
+
+    let user_input = "ab\0cd";
+    let err = sgx_trts::cstr_format!("name={}", user_input).unwrap_err();
+    assert_eq!(err.nul_position(), 7);
+    assert_eq!(err.into_vec(), b"name=ab\0cd".to_vec());
+}
+
+pub fn test_cstr_to_string_lossy_into() {
+    let inputs: [&[u8]; 4] = [b"Hello\0", b" \xF0\x90\x80World\0", b"\0", b" \xff\xfe!\xc3\0"];
+    let mut buf = String::with_capacity(64);
+    let capacity = buf.capacity();
+    for bytes in inputs.iter() {
+        let c_str = CStr::from_bytes_with_nul(bytes).unwrap();
+        let before = buf.len();
+        c_str.to_string_lossy_into(&mut buf);
+        assert_eq!(&buf[before..], c_str.to_string_lossy());
+    }
+    assert_eq!(buf, "Hello \u{FFFD}World \u{FFFD}\u{FFFD}!\u{FFFD}");
+    assert_eq!(buf.capacity(), capacity);
+
+    buf.clear();
+    CStr::from_bytes_with_nul(b"plain\0").unwrap().to_string_lossy_into(&mut buf);
+    assert_eq!(buf, "plain");
+}
+
+pub fn test_cstr_eq() {
+    let a = CString::new("hello world").unwrap();
+    let b = CString::new("hello world").unwrap();
+    assert_eq!(a.as_c_str(), b.as_c_str());
+    assert_eq!(a, b);
+
+    let prefix = CString::new("hello").unwrap();
+    assert_ne!(a.as_c_str(), prefix.as_c_str());
+    assert_ne!(prefix.as_c_str(), a.as_c_str());
+
+    let same_len = CString::new("hello worle").unwrap();
+    assert_ne!(a.as_c_str(), same_len.as_c_str());
+
+    let empty = CString::default();
+    assert_eq!(empty.as_c_str(), CStr::from_bytes_with_nul(b"\0").unwrap());
+    assert_ne!(empty.as_c_str(), a.as_c_str());
+}
+
+pub fn test_cstr_new_lossy() {
+    let c_string = CString::new_lossy(&b"\0a\0\0b\0"[..]);
+    assert_eq!(c_string.as_bytes_with_nul(), b"?a??b?\0");
+    assert_eq!(c_string.as_bytes_with_nul().iter().filter(|&&b| b == 0).count(), 1);
+
+    let c_string = CString::new_lossy_with(vec![b'x', 0, b'y'], b'_');
+    assert_eq!(c_string.as_bytes_with_nul(), b"x_y\0");
+
+    let c_string = CString::new_lossy("clean");
+    assert_eq!(c_string, CString::new("clean").unwrap());
+
+    assert_eq!(CString::new_lossy(Vec::new()), CString::default());
+}
+
+pub fn test_cstr_to_bytes_escaped() {
+    let plain = CStr::from_bytes_with_nul(b"hello\0").unwrap();
+    assert_eq!(plain.to_bytes_escaped(), b"hello");
+    assert_eq!(plain.display_len(), 5);
+
+    let control = CStr::from_bytes_with_nul(b"a\tb\nc\0").unwrap();
+    assert_eq!(control.to_bytes_escaped(), b"a\\tb\\nc");
+    assert_eq!(control.display_len(), 7);
+
+    let high = CStr::from_bytes_with_nul(b"\x7f\xff\0").unwrap();
+    assert_eq!(high.to_bytes_escaped(), b"\\x7f\\xff");
+    assert_eq!(high.display_len(), 8);
+
+    for cstr in &[plain, control, high] {
+        let debug = format!("{:?}", cstr);
+        assert_eq!(&debug.as_bytes()[1..debug.len() - 1], &cstr.to_bytes_escaped()[..]);
+    }
+}
+
+pub fn test_cstring_revalidate() {
+    // A C fill that writes a shorter string than the buffer.
+    let mut c_string = CString::new(vec![b'x'; 15]).unwrap();
+    let buf = unsafe { c_string.as_bytes_with_nul_mut() };
+    assert_eq!(buf.len(), 16);
+    buf[..6].copy_from_slice(b"hello\0");
+    c_string.revalidate().unwrap();
+    assert_eq!(c_string.as_bytes_with_nul(), b"hello\0");
+
+    // A C fill that overwrites the terminator.
+    let mut c_string = CString::new("abc").unwrap();
+    unsafe { c_string.as_bytes_with_nul_mut() }.copy_from_slice(b"wxyz");
+    assert!(c_string.revalidate().is_err());
+    assert_eq!(c_string.as_bytes_with_nul(), b"wxy\0");
+
+    // An untouched buffer is already valid.
+    let mut c_string = CString::new("same").unwrap();
+    c_string.revalidate().unwrap();
+    assert_eq!(c_string.as_bytes(), b"same");
+}
+
+pub fn test_cstr_interner() {
+    use std::ffi::CStrInterner;
+    use std::sync::Arc;
+
+    let interner = CStrInterner::new(2);
+    let a1 = interner.intern("alpha").unwrap();
+    let a2 = interner.intern(b"alpha").unwrap();
+    assert!(Arc::ptr_eq(&a1, &a2));
+    assert_eq!(a1.to_bytes_with_nul(), b"alpha\0");
+
+    let b1 = interner.intern("beta").unwrap();
+    assert!(!Arc::ptr_eq(&a1, &b1));
+    assert_eq!(b1.to_bytes(), b"beta");
+    assert_eq!(interner.len(), 2);
+
+    // "alpha" was used last, so adding a third key evicts "beta".
+    interner.intern("alpha").unwrap();
+    let c1 = interner.intern("gamma").unwrap();
+    assert_eq!(interner.len(), 2);
+    assert!(Arc::ptr_eq(&a1, &interner.intern("alpha").unwrap()));
+    let b2 = interner.intern("beta").unwrap();
+    assert!(!Arc::ptr_eq(&b1, &b2));
+    assert_eq!(b1, b2);
+    assert_eq!(c1.to_bytes(), b"gamma");
+
+    assert!(interner.intern("bad\0key").is_err());
+    interner.clear();
+    assert!(interner.is_empty());
+
+    let uncached = CStrInterner::new(0);
+    let x1 = uncached.intern("x").unwrap();
+    let x2 = uncached.intern("x").unwrap();
+    assert!(!Arc::ptr_eq(&x1, &x2));
+    assert!(uncached.is_empty());
+}
+
+pub fn test_cstr_ascii_case() {
+    let upper = CStr::from_bytes_with_nul(b"Content-Type\0").unwrap();
+    let lower = CStr::from_bytes_with_nul(b"content-type\0").unwrap();
+    assert!(upper.eq_ignore_ascii_case(lower));
+    assert!(!upper.eq_ignore_ascii_case(CStr::from_bytes_with_nul(b"Content-Length\0").unwrap()));
+    assert!(!upper.eq_ignore_ascii_case(CStr::from_bytes_with_nul(b"Content-Typ\0").unwrap()));
+    assert_eq!(upper.to_ascii_lowercase().as_c_str(), lower);
+    assert_eq!(lower.to_ascii_uppercase().as_bytes(), b"CONTENT-TYPE");
+
+    // Non-ASCII bytes are left untouched and only match exactly.
+    let mixed = CStr::from_bytes_with_nul(b"Caf\xc3\xa9-\xff\0").unwrap();
+    assert_eq!(mixed.to_ascii_lowercase().as_bytes(), b"caf\xc3\xa9-\xff");
+    assert_eq!(mixed.to_ascii_uppercase().as_bytes(), b"CAF\xc3\xa9-\xff");
+    assert!(mixed.eq_ignore_ascii_case(CStr::from_bytes_with_nul(b"CAF\xc3\xa9-\xff\0").unwrap()));
+    assert!(!mixed.eq_ignore_ascii_case(CStr::from_bytes_with_nul(b"CAF\xc3\x89-\xff\0").unwrap()));
+}
+
+pub fn test_cstr_unchecked_debug_asserts() {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    // Missing terminator.
+    should_panic!({
+        let c_string = unsafe { CString::from_vec_with_nul_unchecked(b"abc".to_vec()) };
+        c_string.as_c_str().to_bytes().len()
+    });
+    // Interior nul.
+    should_panic!({
+        let c_string = unsafe { CString::from_vec_unchecked(b"a\0b".to_vec()) };
+        c_string.as_c_str().to_bytes().len()
+    });
+    // Valid data passes.
+    let c_string = unsafe { CString::from_vec_with_nul_unchecked(b"abc\0".to_vec()) };
+    assert_eq!(c_string.as_c_str().to_bytes(), b"abc");
+}
+
+pub fn test_cstring_leak() {
+    let leaked: &'static mut CStr = CString::new("callback_name").unwrap().leak();
+    let name: &'static CStr = leaked;
+    assert_eq!(name.to_bytes(), b"callback_name");
+    assert_eq!(name.to_bytes_with_nul().len(), 14);
+
+    let ptr = name.as_ptr();
+    let reclaimed = unsafe { CString::from_raw(ptr as *mut _) };
+    assert_eq!(reclaimed.as_ptr(), ptr);
+    assert_eq!(reclaimed.as_bytes(), b"callback_name");
+    drop(reclaimed);
+}
+
+pub fn test_cstring_encode_decode() {
+    let strings = [
+        CString::new("").unwrap(),
+        CString::new("sealed").unwrap(),
+        CString::new(&b"\x01\xff non-utf8"[..]).unwrap(),
+    ];
+    let mut buf = Vec::new();
+    for s in strings.iter() {
+        let encoded = s.encode();
+        assert_eq!(encoded.len(), 8 + s.as_bytes().len());
+        assert_eq!(CString::decode(&encoded).unwrap(), (s.clone(), encoded.len()));
+        buf.extend(encoded);
+    }
+    buf.extend_from_slice(b"trailing");
+
+    let mut rest = &buf[..];
+    for s in strings.iter() {
+        let (decoded, used) = CString::decode(rest).unwrap();
+        assert_eq!(&decoded, s);
+        rest = &rest[used..];
+    }
+    assert_eq!(rest, b"trailing");
+
+    // Truncated length prefix.
+    assert!(CString::decode(&[]).is_err());
+    assert!(CString::decode(&[0; 7]).is_err());
+    // Truncated data.
+    let encoded = CString::new("sealed").unwrap().encode();
+    let err = CString::decode(&encoded[..encoded.len() - 1]).unwrap_err();
+    assert_eq!(err.nul_position(), None);
+    // A length that cannot fit in memory.
+    let mut huge = u64::MAX.to_le_bytes().to_vec();
+    huge.extend_from_slice(b"abc");
+    assert!(CString::decode(&huge).is_err());
+    // Interior nul.
+    let mut nul = 3_u64.to_le_bytes().to_vec();
+    nul.extend_from_slice(b"a\0b");
+    let err = CString::decode(&nul).unwrap_err();
+    assert_eq!(err.nul_position(), Some(1));
+}
@@ -241,3 +241,313 @@ pub fn test_thread_id_not_equal() {
     let spawned_id = thread::spawn(|| thread::current().id()).join().unwrap();
     assert!(thread::current().id() != spawned_id);
 }
+
+pub fn test_thread_stack_size() {
+    let max = sgx_trts::enclave::rsgx_get_stack_max_size();
+    assert!(max > 0);
+
+    let handle = Builder::new().stack_size(max).spawn(|| 1).unwrap();
+    assert_eq!(handle.join().unwrap(), 1);
+
+    let err = Builder::new().stack_size(max + 1).spawn(|| 1).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+pub fn test_thread_adaptive_park() {
+    use std::sync::adaptive::{self, SpinPrimitive};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let old = adaptive::spin_limit(SpinPrimitive::Parker);
+    adaptive::set_spin_limit(SpinPrimitive::Parker, u32::MAX);
+    assert_eq!(adaptive::spin_limit(SpinPrimitive::Parker), u32::MAX);
+    adaptive::reset_stats(SpinPrimitive::Parker);
+
+    let ready = Arc::new(AtomicBool::new(false));
+    let ready2 = ready.clone();
+    let handle = thread::spawn(move || {
+        while !ready2.load(Ordering::SeqCst) {
+            thread::park();
+        }
+    });
+    ready.store(true, Ordering::SeqCst);
+    handle.thread().unpark();
+    handle.join().unwrap();
+
+    // The unpark always arrives while the parked thread is still spinning,
+    // so neither side should have left the enclave.
+    let stats = adaptive::stats(SpinPrimitive::Parker);
+    assert_eq!(stats.ocall_waits, 0);
+    assert_eq!(stats.ocall_wakes, 0);
+
+    adaptive::set_spin_limit(SpinPrimitive::Parker, old);
+}
+
+pub fn test_thread_dtor_order() {
+    use std::sync::{Arc, SgxMutex};
+    use std::vec::Vec;
+
+    struct Record(Arc<SgxMutex<Vec<&'static str>>>, &'static str);
+
+    impl Drop for Record {
+        fn drop(&mut self) {
+            self.0.lock().unwrap().push(self.1);
+        }
+    }
+
+    thread_local!(static TLS: std::cell::RefCell<Option<Record>> = std::cell::RefCell::new(None));
+
+    let log = Arc::new(SgxMutex::new(Vec::new()));
+    let log2 = log.clone();
+    thread::spawn(move || {
+        let first = log2.clone();
+        thread::at_thread_exit(move || first.lock().unwrap().push("first")).unwrap();
+        TLS.with(|v| *v.borrow_mut() = Some(Record(log2.clone(), "tls")));
+        let last = log2.clone();
+        thread::at_thread_exit(move || {
+            last.lock().unwrap().push("last");
+            // Registered while the destructors run, so it runs after them.
+            let late = last.clone();
+            thread::at_thread_exit(move || late.lock().unwrap().push("late")).unwrap();
+        })
+        .unwrap();
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(*log.lock().unwrap(), ["last", "tls", "first", "late"]);
+}
+
+pub fn test_thread_pool() {
+    use std::thread::pool;
+    use std::vec::Vec;
+
+    let pool = pool::Builder::new().name("pool".into()).num_threads(2).build().unwrap();
+    assert_eq!(pool.max_threads(), 2);
+
+    let jobs: Vec<_> = (0..8).map(|i| pool.spawn(move || i * 2)).collect();
+    let bad = pool.spawn(|| panic!("job panicked"));
+    let name = pool.spawn(|| thread::current().name().map(|n| n.starts_with("pool-")));
+    pool.join();
+    assert!(pool.num_threads() <= 2);
+    assert_eq!(pool.queued_jobs(), 0);
+
+    let sum: i32 = jobs.into_iter().map(|job| job.join().unwrap()).sum();
+    assert_eq!(sum, 56);
+    assert!(bad.join().is_err());
+    // The panic didn't take the worker down.
+    assert_eq!(name.join().unwrap(), Some(true));
+    assert_eq!(pool.spawn(|| 1).join().unwrap(), 1);
+
+    let err = pool::Builder::new().num_threads(usize::max_value()).build().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+pub fn test_thread_fast_rwlock() {
+    use std::sync::fast::RwLock;
+    use std::sync::Arc;
+    use std::vec::Vec;
+
+    let lock = RwLock::new(0);
+    {
+        let r = lock.read();
+        assert!(lock.try_read().is_some());
+        assert!(lock.try_write().is_none());
+        drop(r);
+        let w = lock.write();
+        assert!(lock.try_read().is_none());
+        drop(w);
+    }
+
+    let lock = Arc::new(RwLock::new(0usize));
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            let lock = lock.clone();
+            thread::spawn(move || {
+                for _ in 0..1000 {
+                    if i % 2 == 0 {
+                        *lock.write() += 1;
+                    } else {
+                        assert!(*lock.read() <= 2000);
+                    }
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(*lock.read(), 2000);
+}
+
+pub fn test_thread_once_lock() {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{LazyLock, OnceLock};
+    use std::vec::Vec;
+
+    static CELL: OnceLock<String> = OnceLock::new();
+    static INITS: AtomicUsize = AtomicUsize::new(0);
+    static MAP: LazyLock<HashMap<u32, &'static str>> = LazyLock::new(|| {
+        INITS.fetch_add(1, Ordering::SeqCst);
+        let mut m = HashMap::new();
+        m.insert(13, "Spica");
+        m
+    });
+
+    assert!(CELL.get().is_none());
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            thread::spawn(move || {
+                CELL.get_or_init(|| i.to_string());
+                assert_eq!(MAP.get(&13), Some(&"Spica"));
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert!(CELL.get().is_some());
+    assert!(CELL.set(String::from("late")).is_err());
+    assert_eq!(INITS.load(Ordering::SeqCst), 1);
+
+    let mut cell = OnceLock::new();
+    assert_eq!(cell.get_or_try_init(|| Err(())), Err(()));
+    assert_eq!(cell.get_or_try_init(|| Ok::<_, ()>(1)), Ok(&1));
+    assert_eq!(cell.take(), Some(1));
+    assert_eq!(cell.into_inner(), None);
+}
+
+pub fn test_thread_barrier_more_threads_than_n() {
+    use std::sync::{Arc, Barrier};
+    use std::vec::Vec;
+
+    // Four threads share a barrier for two, so every generation has to be
+    // released with exactly two threads and one leader, also when a thread of
+    // the next generation arrives while the previous one is being released.
+    const ROUNDS: usize = 1000;
+    let barrier = Arc::new(Barrier::new(2));
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let barrier = barrier.clone();
+            thread::spawn(move || (0..ROUNDS).filter(|_| barrier.wait().is_leader()).count())
+        })
+        .collect();
+    let leaders: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+    assert_eq!(leaders, 4 * ROUNDS / 2);
+}
+
+pub fn test_thread_barrier_wait_group() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier, WaitGroup};
+    use std::vec::Vec;
+
+    const N: usize = 4;
+    let barrier = Arc::new(Barrier::new(N));
+    let arrived = Arc::new(AtomicUsize::new(0));
+    let handles: Vec<_> = (0..N)
+        .map(|_| {
+            let barrier = barrier.clone();
+            let arrived = arrived.clone();
+            thread::spawn(move || {
+                let mut leaders = 0;
+                for phase in 1..=3 {
+                    arrived.fetch_add(1, Ordering::SeqCst);
+                    if barrier.wait().is_leader() {
+                        leaders += 1;
+                    }
+                    assert!(arrived.load(Ordering::SeqCst) >= phase * N);
+                    barrier.wait();
+                }
+                leaders
+            })
+        })
+        .collect();
+    let leaders: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+    assert_eq!(leaders, 3);
+
+    let wg = Arc::new(WaitGroup::new());
+    wg.wait();
+    let done = Arc::new(AtomicUsize::new(0));
+    for _ in 0..N {
+        wg.add(1);
+        let wg = wg.clone();
+        let done = done.clone();
+        thread::spawn(move || {
+            sleep(Duration::from_millis(10));
+            done.fetch_add(1, Ordering::SeqCst);
+            wg.done();
+        });
+    }
+    wg.wait();
+    assert_eq!(done.load(Ordering::SeqCst), N);
+    assert_eq!(wg.count(), 0);
+    assert!(panic::catch_unwind(|| WaitGroup::new().done()).is_err());
+}
+
+pub fn test_thread_switchless_sync() {
+    use std::sync::switchless::{Condvar, Mutex};
+    use std::sync::Arc;
+    use std::vec::Vec;
+
+    let lock = Arc::new(Mutex::new(0usize));
+    assert!(lock.try_lock().is_some());
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let lock = lock.clone();
+            thread::spawn(move || {
+                for _ in 0..1000 {
+                    *lock.lock() += 1;
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(*lock.lock(), 4000);
+
+    let pair = Arc::new((Mutex::new(false), Condvar::new()));
+    let pair2 = pair.clone();
+    let handle = thread::spawn(move || {
+        let (lock, cvar) = &*pair2;
+        sleep(Duration::from_millis(10));
+        *lock.lock() = true;
+        cvar.notify_all();
+    });
+    let (lock, cvar) = &*pair;
+    let ready = cvar.wait_while(lock.lock(), |ready| !*ready);
+    assert!(*ready);
+    drop(ready);
+    handle.join().unwrap();
+
+    let (guard, result) = cvar.wait_timeout_while(lock.lock(), Duration::from_millis(10), |_| true);
+    assert!(result.timed_out());
+    drop(guard);
+}
+
+pub fn test_thread_sleep_batched() {
+    use std::time::Instant;
+    use std::vec::Vec;
+
+    // Sleepers with interleaved deadlines share the host timer, and each
+    // still sleeps at least as long as asked.
+    let handles: Vec<_> = (0..6u64)
+        .map(|i| {
+            thread::spawn(move || {
+                let dur = Duration::from_millis(5 + (i * 7) % 30);
+                let start = Instant::now();
+                sleep(dur);
+                assert!(start.elapsed() >= dur);
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let start = Instant::now();
+    sleep(Duration::from_millis(0));
+    sleep(Duration::from_millis(1));
+    assert!(start.elapsed() >= Duration::from_millis(1));
+}
@@ -108,3 +108,218 @@ pub fn test_std_time() {
         assert!(a < hundred_twenty_years);
     }
 }
+
+pub fn test_time_condvar_wait_timeout_source() {
+    use std::io::ErrorKind;
+    use std::sync::{SgxCondvar, SgxMutex, TimeSource};
+
+    let lock = SgxMutex::new(());
+    let cvar = SgxCondvar::new();
+
+    let (guard, result) =
+        cvar.wait_timeout(lock.lock().unwrap(), Duration::from_millis(10)).unwrap();
+    assert!(result.timed_out());
+    assert_eq!(result.time_source(), TimeSource::Host);
+    drop(guard);
+
+    // No TSC runs faster than 10 GHz, so this can only lengthen timeouts.
+    match set_tsc_frequency(10_000_000_000) {
+        Ok(()) => {
+            assert_eq!(set_tsc_frequency(0).unwrap_err().kind(), ErrorKind::InvalidInput);
+            let start = Instant::now();
+            let dur = Duration::from_millis(10);
+            let (_guard, result) = cvar.wait_timeout(lock.lock().unwrap(), dur).unwrap();
+            assert!(result.timed_out());
+            assert_eq!(result.time_source(), TimeSource::Enclave);
+            assert!(start.elapsed() >= dur);
+        }
+        Err(e) => {
+            assert_eq!(e.kind(), ErrorKind::Unsupported);
+            assert_eq!(set_tsc_frequency(0).unwrap_err().kind(), ErrorKind::Unsupported);
+        }
+    }
+}
+
+pub fn test_time_instant_tsc() {
+    use std::thread;
+
+    let start = Instant::now();
+    thread::sleep(Duration::from_millis(20));
+    let elapsed = start.elapsed();
+    // The TSC clock may be calibrated against the host clock, so allow for
+    // a small error.
+    assert!(elapsed >= Duration::from_millis(19), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_secs(5), "{:?}", elapsed);
+
+    let mut prev = Instant::now();
+    for _ in 0..1000 {
+        let now = Instant::now();
+        assert!(now >= prev);
+        prev = now;
+    }
+}
+
+pub fn test_time_attested_system_time() {
+    use std::io::{self, ErrorKind};
+    use std::vec::Vec;
+
+    const SIGNED: u8 = 0xa5;
+
+    // Stands in for a Roughtime verifier: the "signature" is the first byte,
+    // followed by the seconds since the epoch.
+    struct FakeVerifier;
+
+    impl TimestampVerifier for FakeVerifier {
+        fn verify(&self, response: &[u8]) -> io::Result<(SystemTime, Duration)> {
+            if response.len() != 9 || response[0] != SIGNED {
+                return Err(io::Error::new(ErrorKind::InvalidData, "bad signature"));
+            }
+            let mut secs = [0u8; 8];
+            secs.copy_from_slice(&response[1..]);
+            Ok((UNIX_EPOCH + Duration::from_secs(u64::from_le_bytes(secs)), Duration::from_secs(1)))
+        }
+    }
+
+    fn response(time: SystemTime) -> Vec<u8> {
+        let secs = time.duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let mut r = vec![SIGNED];
+        r.extend_from_slice(&secs.to_le_bytes());
+        r
+    }
+
+    let host = <SystemTime as SystemTimeEx>::now();
+    let clock = AttestedTime::new(FakeVerifier, Duration::from_secs(2), Duration::from_secs(3600));
+    assert_eq!(clock.now().unwrap_err().kind(), ErrorKind::NotFound);
+
+    let mut forged = response(host);
+    forged[0] = 0;
+    assert_eq!(clock.update(&forged).unwrap_err().kind(), ErrorKind::InvalidData);
+
+    clock.update(&response(host)).unwrap();
+    let now = clock.now().unwrap();
+    assert!(now >= host);
+
+    // A replayed, older response is refused.
+    let old = response(host - Duration::from_secs(3600));
+    assert_eq!(clock.update(&old).unwrap_err().kind(), ErrorKind::InvalidData);
+
+    // The host clock is refused when it disagrees with the signed time.
+    let ahead = AttestedTime::new(FakeVerifier, Duration::from_secs(2), Duration::from_secs(3600));
+    ahead.update(&response(host + Duration::from_secs(86400))).unwrap();
+    assert_eq!(ahead.now().unwrap_err().kind(), ErrorKind::InvalidData);
+
+    set_trusted_time(clock).unwrap();
+    assert_eq!(set_trusted_time(ahead).unwrap_err().kind(), ErrorKind::AlreadyExists);
+    assert!(trusted_now().unwrap() >= now);
+}
+
+pub fn test_time_cached_now() {
+    let host = <SystemTime as SystemTimeEx>::now();
+    let first = cached_now(Duration::from_secs(60));
+    assert!(first >= host);
+
+    // Fresh enough: carried forward inside the enclave, never backwards.
+    let mut prev = first;
+    for _ in 0..1000 {
+        let now = cached_now(Duration::from_secs(60));
+        assert!(now >= prev);
+        prev = now;
+    }
+    assert!(prev.duration_since(first).unwrap() < Duration::from_secs(5));
+
+    // A zero staleness asks the host again, and stays monotonic.
+    std::thread::sleep(Duration::from_millis(10));
+    let refreshed = cached_now(Duration::from_secs(0));
+    assert!(refreshed >= prev);
+    assert!(refreshed.duration_since(host).unwrap() >= Duration::from_millis(10));
+}
+
+pub fn test_time_timer_wheel() {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{mpsc, Arc};
+    use std::task::{Context, LocalExecutor, Poll};
+    use std::thread;
+
+    // Driven by hand: timers fire in deadline order, across levels.
+    let wheel = TimerWheel::new();
+    let start = Instant::now();
+    let fired = Arc::new(std::sync::SgxMutex::new(Vec::new()));
+    let mut ids = Vec::new();
+    for (i, ms) in [5u64, 70, 4_100, 300_000, 86_400_000, 1].iter().enumerate() {
+        let fired = fired.clone();
+        let id = wheel
+            .register(start + Duration::from_millis(*ms), move || fired.lock().unwrap().push(i));
+        ids.push(id);
+    }
+    assert_eq!(wheel.len(), 6);
+    assert!(wheel.cancel(ids[2]));
+    assert!(!wheel.cancel(ids[2]));
+    assert!(wheel.next_deadline().unwrap() <= start + Duration::from_millis(1));
+
+    assert_eq!(wheel.advance(start + Duration::from_millis(4)), 1);
+    assert_eq!(wheel.advance(start + Duration::from_millis(100)), 2);
+    assert_eq!(wheel.advance(start + Duration::from_secs(299)), 0);
+    assert_eq!(wheel.advance(start + Duration::from_secs(300)), 1);
+    assert_eq!(wheel.advance(start + Duration::from_secs(86_400)), 1);
+    assert!(wheel.is_empty() && wheel.next_deadline().is_none());
+    assert_eq!(*fired.lock().unwrap(), [5, 0, 1, 3, 4]);
+    // A stale id does not cancel the timer now using its storage.
+    let id = wheel.register(start, || {});
+    assert!(!wheel.cancel(ids[4]));
+    assert!(wheel.cancel(id));
+
+    // Many timers, one driver thread.
+    let wheel = Arc::new(TimerWheel::new());
+    let driver = {
+        let wheel = wheel.clone();
+        thread::spawn(move || wheel.run())
+    };
+    let count = Arc::new(AtomicUsize::new(0));
+    let (tx, rx) = mpsc::channel();
+    let now = Instant::now();
+    for i in 0..10_000u64 {
+        let count = count.clone();
+        let tx = tx.clone();
+        let id = wheel.register(now + Duration::from_millis(10 + i % 50), move || {
+            if count.fetch_add(1, Ordering::SeqCst) + 1 == 5_000 {
+                tx.send(()).unwrap();
+            }
+        });
+        if i % 2 == 1 {
+            assert!(wheel.cancel(id));
+        }
+    }
+    rx.recv().unwrap();
+    assert!(now.elapsed() >= Duration::from_millis(10));
+    assert!(wheel.is_empty());
+    wheel.shutdown();
+    driver.join().unwrap();
+    assert_eq!(count.load(Ordering::SeqCst), 5_000);
+
+    // Driven by the executor: a task sleeps without any socket to wait on.
+    struct Sleep {
+        wheel: Arc<TimerWheel>,
+        deadline: Instant,
+    }
+
+    impl Future for Sleep {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if Instant::now() >= self.deadline {
+                return Poll::Ready(());
+            }
+            self.wheel.register_waker(self.deadline, cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    let executor = LocalExecutor::new().unwrap();
+    let now = Instant::now();
+    let sleep =
+        Sleep { wheel: executor.timers().clone(), deadline: now + Duration::from_millis(20) };
+    executor.block_on(sleep).unwrap();
+    assert!(now.elapsed() >= Duration::from_millis(20));
+}
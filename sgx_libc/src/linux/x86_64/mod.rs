@@ -286,6 +286,21 @@ s! {
         pub sun_path: [c_char; 108],
     }
 
+    pub struct inotify_event {
+        pub wd: c_int,
+        pub mask: u32,
+        pub cookie: u32,
+        pub len: u32,
+    }
+
+    pub struct sockaddr_vm {
+        pub svm_family: sa_family_t,
+        pub svm_reserved1: c_ushort,
+        pub svm_port: c_uint,
+        pub svm_cid: c_uint,
+        pub svm_zero: [u8; 4],
+    }
+
     pub struct sockaddr_storage {
         pub ss_family: sa_family_t,
         __ss_align: size_t,
@@ -615,6 +630,9 @@ pub const SIGPIPE: c_int = 13;
 pub const SIGALRM: c_int = 14;
 pub const SIGTERM: c_int = 15;
 
+pub const WNOHANG: c_int = 0x00000001;
+pub const WUNTRACED: c_int = 0x00000002;
+
 pub const PROT_NONE: c_int = 0;
 pub const PROT_READ: c_int = 1;
 pub const PROT_WRITE: c_int = 2;
@@ -850,6 +868,13 @@ pub const AF_PHONET: c_int = 35;
 pub const AF_IEEE802154: c_int = 36;
 pub const AF_CAIF: c_int = 37;
 pub const AF_ALG: c_int = 38;
+pub const AF_VSOCK: c_int = 40;
+
+pub const VMADDR_CID_ANY: c_uint = 0xFFFFFFFF;
+pub const VMADDR_CID_HYPERVISOR: c_uint = 0;
+pub const VMADDR_CID_LOCAL: c_uint = 1;
+pub const VMADDR_CID_HOST: c_uint = 2;
+pub const VMADDR_PORT_ANY: c_uint = 0xFFFFFFFF;
 
 pub const PF_UNSPEC: c_int = AF_UNSPEC;
 pub const PF_UNIX: c_int = AF_UNIX;
@@ -890,6 +915,7 @@ pub const PF_PHONET: c_int = AF_PHONET;
 pub const PF_IEEE802154: c_int = AF_IEEE802154;
 pub const PF_CAIF: c_int = AF_CAIF;
 pub const PF_ALG: c_int = AF_ALG;
+pub const PF_VSOCK: c_int = AF_VSOCK;
 
 pub const SOMAXCONN: c_int = 128;
 
@@ -919,9 +945,11 @@ pub const SOCK_RDM: c_int = 4;
 pub const IP_MULTICAST_IF: c_int = 32;
 pub const IP_MULTICAST_TTL: c_int = 33;
 pub const IP_MULTICAST_LOOP: c_int = 34;
+pub const IP_TOS: c_int = 1;
 pub const IP_TTL: c_int = 2;
 pub const IP_HDRINCL: c_int = 3;
 pub const IP_PKTINFO: c_int = 8;
+pub const IP_RECVTOS: c_int = 13;
 pub const IP_ADD_MEMBERSHIP: c_int = 35;
 pub const IP_DROP_MEMBERSHIP: c_int = 36;
 pub const IP_TRANSPARENT: c_int = 19;
@@ -934,6 +962,8 @@ pub const IPV6_DROP_MEMBERSHIP: c_int = 21;
 pub const IPV6_V6ONLY: c_int = 26;
 pub const IPV6_RECVPKTINFO: c_int = 49;
 pub const IPV6_PKTINFO: c_int = 50;
+pub const IPV6_RECVTCLASS: c_int = 66;
+pub const IPV6_TCLASS: c_int = 67;
 
 pub const TCP_NODELAY: c_int = 1;
 pub const TCP_MAXSEG: c_int = 2;
@@ -960,6 +990,33 @@ pub const LOCK_EX: c_int = 2;
 pub const LOCK_NB: c_int = 4;
 pub const LOCK_UN: c_int = 8;
 
+pub const IN_ACCESS: u32 = 0x0000_0001;
+pub const IN_MODIFY: u32 = 0x0000_0002;
+pub const IN_ATTRIB: u32 = 0x0000_0004;
+pub const IN_CLOSE_WRITE: u32 = 0x0000_0008;
+pub const IN_CLOSE_NOWRITE: u32 = 0x0000_0010;
+pub const IN_CLOSE: u32 = IN_CLOSE_WRITE | IN_CLOSE_NOWRITE;
+pub const IN_OPEN: u32 = 0x0000_0020;
+pub const IN_MOVED_FROM: u32 = 0x0000_0040;
+pub const IN_MOVED_TO: u32 = 0x0000_0080;
+pub const IN_MOVE: u32 = IN_MOVED_FROM | IN_MOVED_TO;
+pub const IN_CREATE: u32 = 0x0000_0100;
+pub const IN_DELETE: u32 = 0x0000_0200;
+pub const IN_DELETE_SELF: u32 = 0x0000_0400;
+pub const IN_MOVE_SELF: u32 = 0x0000_0800;
+pub const IN_ALL_EVENTS: u32 = 0x0000_0fff;
+pub const IN_UNMOUNT: u32 = 0x0000_2000;
+pub const IN_Q_OVERFLOW: u32 = 0x0000_4000;
+pub const IN_IGNORED: u32 = 0x0000_8000;
+pub const IN_ONLYDIR: u32 = 0x0100_0000;
+pub const IN_DONT_FOLLOW: u32 = 0x0200_0000;
+pub const IN_EXCL_UNLINK: u32 = 0x0400_0000;
+pub const IN_MASK_ADD: u32 = 0x2000_0000;
+pub const IN_ISDIR: u32 = 0x4000_0000;
+pub const IN_ONESHOT: u32 = 0x8000_0000;
+pub const IN_CLOEXEC: c_int = O_CLOEXEC;
+pub const IN_NONBLOCK: c_int = O_NONBLOCK;
+
 pub const SS_ONSTACK: c_int = 1;
 pub const SS_DISABLE: c_int = 2;
 
@@ -1479,6 +1536,46 @@ pub unsafe fn FD_ZERO(set: *mut fd_set) {
     }
 }
 
+#[inline]
+pub const fn WIFSTOPPED(status: c_int) -> bool {
+    (status & 0xff) == 0x7f
+}
+
+#[inline]
+pub const fn WSTOPSIG(status: c_int) -> c_int {
+    (status >> 8) & 0xff
+}
+
+#[inline]
+pub const fn WIFCONTINUED(status: c_int) -> bool {
+    status == 0xffff
+}
+
+#[inline]
+pub const fn WIFSIGNALED(status: c_int) -> bool {
+    ((status & 0x7f) + 1) as i8 >= 2
+}
+
+#[inline]
+pub const fn WTERMSIG(status: c_int) -> c_int {
+    status & 0x7f
+}
+
+#[inline]
+pub const fn WIFEXITED(status: c_int) -> bool {
+    (status & 0x7f) == 0
+}
+
+#[inline]
+pub const fn WEXITSTATUS(status: c_int) -> c_int {
+    (status >> 8) & 0xff
+}
+
+#[inline]
+pub const fn WCOREDUMP(status: c_int) -> bool {
+    (status & 0x80) != 0
+}
+
 #[inline]
 pub unsafe fn CPU_ALLOC_SIZE(count: c_int) -> size_t {
     let _dummy: cpu_set_t = mem::zeroed();
@@ -187,6 +187,12 @@ extern "C" {
     ) -> sgx_status_t;
     pub fn u_fsync_ocall(result: *mut c_int, error: *mut c_int, fd: c_int) -> sgx_status_t;
     pub fn u_fdatasync_ocall(result: *mut c_int, error: *mut c_int, fd: c_int) -> sgx_status_t;
+    pub fn u_flock_ocall(
+        result: *mut c_int,
+        error: *mut c_int,
+        fd: c_int,
+        operation: c_int,
+    ) -> sgx_status_t;
     pub fn u_fchmod_ocall(
         result: *mut c_int,
         error: *mut c_int,
@@ -275,6 +281,24 @@ extern "C" {
         buf: *mut stat64,
         flags: c_int,
     ) -> sgx_status_t;
+    pub fn u_inotify_init1_ocall(
+        result: *mut c_int,
+        error: *mut c_int,
+        flags: c_int,
+    ) -> sgx_status_t;
+    pub fn u_inotify_add_watch_ocall(
+        result: *mut c_int,
+        error: *mut c_int,
+        fd: c_int,
+        pathname: *const c_char,
+        mask: u32,
+    ) -> sgx_status_t;
+    pub fn u_inotify_rm_watch_ocall(
+        result: *mut c_int,
+        error: *mut c_int,
+        fd: c_int,
+        wd: c_int,
+    ) -> sgx_status_t;
     // fd
     pub fn u_read_ocall(
         result: *mut ssize_t,
@@ -496,6 +520,18 @@ extern "C" {
         optval: *const c_void,
         optlen: socklen_t,
     ) -> sgx_status_t;
+    pub fn u_setsockopts_ocall(
+        result: *mut c_int,
+        errno: *mut c_int,
+        failed: *mut size_t,
+        sockfd: c_int,
+        levels: *const c_int,
+        optnames: *const c_int,
+        optlens: *const socklen_t,
+        optvals: *const c_void,
+        optvals_len: size_t,
+        count: size_t,
+    ) -> sgx_status_t;
     pub fn u_getsockopt_ocall(
         result: *mut c_int,
         errno: *mut c_int,
@@ -568,6 +604,26 @@ extern "C" {
         maxevents: c_int,
         timeout: c_int,
     ) -> sgx_status_t;
+    pub fn u_aio_setup_ocall(
+        result: *mut *mut c_void,
+        error: *mut c_int,
+        entries: size_t,
+        threads: size_t,
+        notify_fd: *mut c_int,
+        ring: *mut *mut c_void,
+    ) -> sgx_status_t;
+    pub fn u_aio_submit_ocall(
+        result: *mut c_int,
+        error: *mut c_int,
+        ctx: *mut c_void,
+        user_data: u64,
+        opcode: c_int,
+        fd: c_int,
+        buf: *mut c_void,
+        len: size_t,
+        offset: off64_t,
+    ) -> sgx_status_t;
+    pub fn u_aio_destroy_ocall(ctx: *mut c_void) -> sgx_status_t;
     // sys
     pub fn u_sysconf_ocall(result: *mut c_long, error: *mut c_int, name: c_int) -> sgx_status_t;
     pub fn u_prctl_ocall(
@@ -628,6 +684,31 @@ extern "C" {
     pub fn u_raise_ocall(result: *mut c_int, signum: c_int) -> sgx_status_t;
     //process
     pub fn u_getpid_ocall(result: *mut pid_t) -> sgx_status_t;
+    pub fn u_exit_ocall(eid: uint64_t, code: c_int, aborted: c_int) -> sgx_status_t;
+    pub fn u_spawn_ocall(
+        result: *mut pid_t,
+        error: *mut c_int,
+        args: *const c_char,
+        args_len: size_t,
+        env: *const c_char,
+        env_len: size_t,
+        env_clear: c_int,
+        cwd: *const c_char,
+        stdio: *const c_int,
+    ) -> sgx_status_t;
+    pub fn u_waitpid_ocall(
+        result: *mut pid_t,
+        error: *mut c_int,
+        pid: pid_t,
+        status: *mut c_int,
+        options: c_int,
+    ) -> sgx_status_t;
+    pub fn u_kill_ocall(
+        result: *mut c_int,
+        error: *mut c_int,
+        pid: pid_t,
+        sig: c_int,
+    ) -> sgx_status_t;
 }
 
 pub unsafe fn malloc(size: size_t) -> *mut c_void {
@@ -1284,6 +1365,27 @@ pub unsafe fn fdatasync(fd: c_int) -> c_int {
     result
 }
 
+pub unsafe fn flock(fd: c_int, operation: c_int) -> c_int {
+    let mut result: c_int = 0;
+    let mut error: c_int = 0;
+    let status = u_flock_ocall(
+        &mut result as *mut c_int,
+        &mut error as *mut c_int,
+        fd,
+        operation,
+    );
+
+    if status == sgx_status_t::SGX_SUCCESS {
+        if result == -1 {
+            set_errno(error);
+        }
+    } else {
+        set_errno(ESGX);
+        result = -1;
+    }
+    result
+}
+
 pub unsafe fn fchmod(fd: c_int, mode: mode_t) -> c_int {
     let mut result: c_int = 0;
     let mut error: c_int = 0;
@@ -1432,6 +1534,9 @@ pub unsafe fn readlink(path: *const c_char, buf: *mut c_char, bufsz: size_t) ->
     if status == sgx_status_t::SGX_SUCCESS {
         if result == -1 {
             set_errno(error);
+        } else if result < -1 || result as size_t > bufsz {
+            set_errno(ESGX);
+            result = -1;
         }
     } else {
         set_errno(ESGX);
@@ -1472,8 +1577,22 @@ pub unsafe fn realpath(pathname: *const c_char) -> *mut c_char {
 
     if status == sgx_status_t::SGX_SUCCESS {
         if !result.is_null() {
-            let len: usize = strlen(result) + 1;
-            if sgx_is_outside_enclave(result as *const c_void, len) == 0 {
+            // Scan for the terminator one byte at a time, checking each byte
+            // before reading it, so that a string running into the enclave
+            // is caught without reading enclave memory.
+            let mut terminated = false;
+            for i in 0..PATH_MAX as usize {
+                let p = result.add(i);
+                if sgx_is_outside_enclave(p as *const c_void, 1) == 0 {
+                    break;
+                }
+                if *p == 0 {
+                    terminated = true;
+                    break;
+                }
+            }
+            if !terminated {
+                let _ = u_free_ocall(result as *mut c_void);
                 set_errno(ESGX);
                 result = ptr::null_mut();
             }
@@ -1559,7 +1678,20 @@ pub unsafe fn readdir64_r(
     if status == sgx_status_t::SGX_SUCCESS && result == 0 {
         let dir_ret = *dirresult;
         if !dir_ret.is_null() {
-            *dirresult = entry;
+            // The name comes from the host. It must be terminated within
+            // d_name and name a single entry, or callers joining it onto
+            // the directory path could be sent elsewhere.
+            let name = &(*entry).d_name;
+            let valid = match name.iter().position(|&c| c == 0) {
+                Some(len) => len > 0 && !name[..len].contains(&(b'/' as c_char)),
+                None => false,
+            };
+            if valid {
+                *dirresult = entry;
+            } else {
+                *dirresult = ptr::null_mut();
+                result = EINVAL;
+            }
         }
     } else {
         *dirresult = ptr::null_mut();
@@ -1630,6 +1762,65 @@ pub unsafe fn fstatat64(
     result
 }
 
+pub unsafe fn inotify_init1(flags: c_int) -> c_int {
+    let mut result: c_int = 0;
+    let mut error: c_int = 0;
+    let status = u_inotify_init1_ocall(&mut result as *mut c_int, &mut error as *mut c_int, flags);
+
+    if status == sgx_status_t::SGX_SUCCESS {
+        if result == -1 {
+            set_errno(error);
+        }
+    } else {
+        set_errno(ESGX);
+        result = -1;
+    }
+    result
+}
+
+pub unsafe fn inotify_add_watch(fd: c_int, pathname: *const c_char, mask: u32) -> c_int {
+    let mut result: c_int = 0;
+    let mut error: c_int = 0;
+    let status = u_inotify_add_watch_ocall(
+        &mut result as *mut c_int,
+        &mut error as *mut c_int,
+        fd,
+        pathname,
+        mask,
+    );
+
+    if status == sgx_status_t::SGX_SUCCESS {
+        if result == -1 {
+            set_errno(error);
+        }
+    } else {
+        set_errno(ESGX);
+        result = -1;
+    }
+    result
+}
+
+pub unsafe fn inotify_rm_watch(fd: c_int, wd: c_int) -> c_int {
+    let mut result: c_int = 0;
+    let mut error: c_int = 0;
+    let status = u_inotify_rm_watch_ocall(
+        &mut result as *mut c_int,
+        &mut error as *mut c_int,
+        fd,
+        wd,
+    );
+
+    if status == sgx_status_t::SGX_SUCCESS {
+        if result == -1 {
+            set_errno(error);
+        }
+    } else {
+        set_errno(ESGX);
+        result = -1;
+    }
+    result
+}
+
 pub unsafe fn read(fd: c_int, buf: *mut c_void, count: size_t) -> ssize_t {
     let mut result: ssize_t = 0;
     let mut error: c_int = 0;
@@ -1749,23 +1940,25 @@ pub unsafe fn readv(fd: c_int, iov: *const iovec, iovcnt: c_int) -> ssize_t {
     let mut ptr: *mut u8 = ptr::null_mut();
     let mut total_size: usize = 0;
 
-    if iov.is_null()
-        || iovcnt <= 0
-        || sgx_is_within_enclave(
-            iov as *const c_void,
-            iovcnt as usize * mem::size_of::<iovec>(),
-        ) == 0
+    if iovcnt < 0
+        || (iovcnt > 0
+            && (iov.is_null()
+                || sgx_is_within_enclave(
+                    iov as *const c_void,
+                    iovcnt as usize * mem::size_of::<iovec>(),
+                ) == 0))
     {
         set_errno(EINVAL);
         return -1;
     }
 
-    let v = slice::from_raw_parts(iov, iovcnt as usize);
+    let v: &[iovec] = if iovcnt > 0 { slice::from_raw_parts(iov, iovcnt as usize) } else { &[] };
     for io in v {
-        if !io.iov_base.is_null()
-            && io.iov_len > 0
-            && sgx_is_within_enclave(io.iov_base, io.iov_len) != 0
-        {
+        // Empty buffers may appear anywhere in the list, as on the host.
+        if io.iov_len == 0 {
+            continue;
+        }
+        if !io.iov_base.is_null() && sgx_is_within_enclave(io.iov_base, io.iov_len) != 0 {
             if let Some(io_size) = total_size.checked_add(io.iov_len) {
                 total_size = io_size;
             } else {
@@ -1778,6 +1971,11 @@ pub unsafe fn readv(fd: c_int, iov: *const iovec, iovcnt: c_int) -> ssize_t {
         }
     }
 
+    // Nothing to transfer, so there is no need to ask the host.
+    if total_size == 0 {
+        return 0;
+    }
+
     let iobase = if total_size <= MAX_OCALL_ALLOC_SIZE {
         sgx_ocalloc(total_size)
     } else {
@@ -1823,6 +2021,9 @@ pub unsafe fn readv(fd: c_int, iov: *const iovec, iovcnt: c_int) -> ssize_t {
             if remaining_bytes == 0 {
                 break;
             }
+            if v[i].iov_len == 0 {
+                continue;
+            }
             // Here, we only copy the remaining bytes if there are less than the iov_len.
             // Otherwise, the default 0s are copied into the buffer and overwrite data that should not be overwritten.
             ptr::copy_nonoverlapping(
@@ -2045,23 +2246,25 @@ pub unsafe fn writev(fd: c_int, iov: *const iovec, iovcnt: c_int) -> ssize_t {
     let mut ptr: *mut u8 = ptr::null_mut();
     let mut total_size: usize = 0;
 
-    if iov.is_null()
-        || iovcnt <= 0
-        || sgx_is_within_enclave(
-            iov as *const c_void,
-            iovcnt as usize * mem::size_of::<iovec>(),
-        ) == 0
+    if iovcnt < 0
+        || (iovcnt > 0
+            && (iov.is_null()
+                || sgx_is_within_enclave(
+                    iov as *const c_void,
+                    iovcnt as usize * mem::size_of::<iovec>(),
+                ) == 0))
     {
         set_errno(EINVAL);
         return -1;
     }
 
-    let v = slice::from_raw_parts(iov, iovcnt as usize);
+    let v: &[iovec] = if iovcnt > 0 { slice::from_raw_parts(iov, iovcnt as usize) } else { &[] };
     for io in v {
-        if !io.iov_base.is_null()
-            && io.iov_len > 0
-            && sgx_is_within_enclave(io.iov_base, io.iov_len) != 0
-        {
+        // Empty buffers may appear anywhere in the list, as on the host.
+        if io.iov_len == 0 {
+            continue;
+        }
+        if !io.iov_base.is_null() && sgx_is_within_enclave(io.iov_base, io.iov_len) != 0 {
             if let Some(io_size) = total_size.checked_add(io.iov_len) {
                 total_size = io_size;
             } else {
@@ -2074,6 +2277,11 @@ pub unsafe fn writev(fd: c_int, iov: *const iovec, iovcnt: c_int) -> ssize_t {
         }
     }
 
+    // Nothing to transfer, so there is no need to ask the host.
+    if total_size == 0 {
+        return 0;
+    }
+
     let iobase = if total_size <= MAX_OCALL_ALLOC_SIZE {
         sgx_ocalloc(total_size)
     } else {
@@ -2092,11 +2300,13 @@ pub unsafe fn writev(fd: c_int, iov: *const iovec, iovcnt: c_int) -> ssize_t {
             iov_base: ptr as *mut c_void,
             iov_len: io.iov_len,
         };
-        ptr::copy_nonoverlapping(
-            io.iov_base as *const u8,
-            tmpiov.iov_base as *mut u8,
-            io.iov_len as usize,
-        );
+        if io.iov_len > 0 {
+            ptr::copy_nonoverlapping(
+                io.iov_base as *const u8,
+                tmpiov.iov_base as *mut u8,
+                io.iov_len as usize,
+            );
+        }
         tmpiovec.push(tmpiov);
         ptr = ptr.add(io.iov_len);
     }
@@ -2557,6 +2767,42 @@ pub unsafe fn send(sockfd: c_int, buf: *const c_void, len: size_t, flags: c_int)
     result
 }
 
+// Like `send`, but `buf` already lives in untrusted memory, so it is handed
+// to the host as is rather than copied into a temporary allocation first.
+pub unsafe fn send_untrusted(
+    sockfd: c_int,
+    buf: *const c_void,
+    len: size_t,
+    flags: c_int,
+) -> ssize_t {
+    let mut result: ssize_t = 0;
+    let mut error: c_int = 0;
+
+    if buf.is_null() || sgx_is_outside_enclave(buf, len) == 0 {
+        set_errno(EINVAL);
+        return -1;
+    }
+
+    let status = u_send_ocall(
+        &mut result as *mut ssize_t,
+        &mut error as *mut c_int,
+        sockfd,
+        buf,
+        len,
+        flags,
+    );
+
+    if status == sgx_status_t::SGX_SUCCESS {
+        if result == -1 {
+            set_errno(error);
+        }
+    } else {
+        set_errno(ESGX);
+        result = -1;
+    }
+    result
+}
+
 pub unsafe fn sendto(
     sockfd: c_int,
     buf: *const c_void,
@@ -2634,8 +2880,11 @@ pub unsafe fn sendmsg(sockfd: c_int, msg: *const msghdr, flags: c_int) -> ssize_
         (ptr::null_mut(), 0)
     };
 
-    let iovecs = if !mhdr.msg_iov.is_null()
-        && mhdr.msg_iovlen > 0
+    // An empty datagram is valid, so `msg_iov` may be empty, as may any of
+    // its buffers.
+    let iovecs: &[iovec] = if mhdr.msg_iovlen == 0 {
+        &[]
+    } else if !mhdr.msg_iov.is_null()
         && sgx_is_within_enclave(
             mhdr.msg_iov as *const c_void,
             mhdr.msg_iovlen * mem::size_of::<iovec>(),
@@ -2643,10 +2892,10 @@ pub unsafe fn sendmsg(sockfd: c_int, msg: *const msghdr, flags: c_int) -> ssize_
     {
         let iovs = slice::from_raw_parts(mhdr.msg_iov, mhdr.msg_iovlen);
         for io in iovs.iter() {
-            if !io.iov_base.is_null()
-                && io.iov_len > 0
-                && sgx_is_within_enclave(io.iov_base, io.iov_len) != 0
-            {
+            if io.iov_len == 0 {
+                continue;
+            }
+            if !io.iov_base.is_null() && sgx_is_within_enclave(io.iov_base, io.iov_len) != 0 {
                 if let Some(io_size) = total_size.checked_add(io.iov_len) {
                     total_size = io_size;
                 } else {
@@ -2670,12 +2919,17 @@ pub unsafe fn sendmsg(sockfd: c_int, msg: *const msghdr, flags: c_int) -> ssize_
         (ptr::null_mut(), 0)
     };
 
-    let io_base = malloc(total_size) as *mut u8;
-    if io_base.is_null() {
-        set_errno(ENOMEM);
-        return -1;
-    }
-    io_base.write_bytes(0_u8, total_size);
+    let io_base = if total_size > 0 {
+        let io_base = malloc(total_size) as *mut u8;
+        if io_base.is_null() {
+            set_errno(ENOMEM);
+            return -1;
+        }
+        io_base.write_bytes(0_u8, total_size);
+        io_base
+    } else {
+        ptr::null_mut()
+    };
 
     ptr = io_base;
     let io_data: Vec<iovec> = iovecs
@@ -2685,8 +2939,14 @@ pub unsafe fn sendmsg(sockfd: c_int, msg: *const msghdr, flags: c_int) -> ssize_
                 iov_base: ptr as *mut c_void,
                 iov_len: v.iov_len,
             };
-            ptr::copy_nonoverlapping(v.iov_base as *const u8, iov.iov_base as *mut u8, v.iov_len);
-            ptr = ptr.add(v.iov_len);
+            if v.iov_len > 0 {
+                ptr::copy_nonoverlapping(
+                    v.iov_base as *const u8,
+                    iov.iov_base as *mut u8,
+                    v.iov_len,
+                );
+                ptr = ptr.add(v.iov_len);
+            }
             iov
         })
         .collect();
@@ -2713,7 +2973,9 @@ pub unsafe fn sendmsg(sockfd: c_int, msg: *const msghdr, flags: c_int) -> ssize_
         result = -1;
     }
 
-    free(io_base as *mut c_void);
+    if !io_base.is_null() {
+        free(io_base as *mut c_void);
+    }
     result
 }
 
@@ -2857,8 +3119,10 @@ pub unsafe fn recvmsg(sockfd: c_int, msg: *mut msghdr, flags: c_int) -> ssize_t
         (ptr::null_mut(), 0)
     };
 
-    let iovecs = if !mhdr.msg_iov.is_null()
-        && mhdr.msg_iovlen > 0
+    // As with sendmsg, `msg_iov` may be empty, as may any of its buffers.
+    let iovecs: &mut [iovec] = if mhdr.msg_iovlen == 0 {
+        &mut []
+    } else if !mhdr.msg_iov.is_null()
         && sgx_is_within_enclave(
             mhdr.msg_iov as *const c_void,
             mhdr.msg_iovlen * mem::size_of::<iovec>(),
@@ -2866,10 +3130,10 @@ pub unsafe fn recvmsg(sockfd: c_int, msg: *mut msghdr, flags: c_int) -> ssize_t
     {
         let iovs = slice::from_raw_parts_mut(mhdr.msg_iov, mhdr.msg_iovlen);
         for io in iovs.iter() {
-            if !io.iov_base.is_null()
-                && io.iov_len > 0
-                && sgx_is_within_enclave(io.iov_base, io.iov_len) != 0
-            {
+            if io.iov_len == 0 {
+                continue;
+            }
+            if !io.iov_base.is_null() && sgx_is_within_enclave(io.iov_base, io.iov_len) != 0 {
                 if let Some(io_size) = total_size.checked_add(io.iov_len) {
                     total_size = io_size;
                 } else {
@@ -2893,12 +3157,17 @@ pub unsafe fn recvmsg(sockfd: c_int, msg: *mut msghdr, flags: c_int) -> ssize_t
         (ptr::null_mut(), 0)
     };
 
-    let io_base = malloc(total_size) as *mut u8;
-    if io_base.is_null() {
-        set_errno(ENOMEM);
-        return -1;
-    }
-    io_base.write_bytes(0_u8, total_size);
+    let io_base = if total_size > 0 {
+        let io_base = malloc(total_size) as *mut u8;
+        if io_base.is_null() {
+            set_errno(ENOMEM);
+            return -1;
+        }
+        io_base.write_bytes(0_u8, total_size);
+        io_base
+    } else {
+        ptr::null_mut()
+    };
 
     let mut msg_namelen_out = 0_u32;
     let mut msg_controllen_out = 0_usize;
@@ -2912,7 +3181,9 @@ pub unsafe fn recvmsg(sockfd: c_int, msg: *mut msghdr, flags: c_int) -> ssize_t
                 iov_base: ptr as *mut c_void,
                 iov_len: v.iov_len,
             };
-            ptr = ptr.add(v.iov_len);
+            if v.iov_len > 0 {
+                ptr = ptr.add(v.iov_len);
+            }
             iov
         })
         .collect();
@@ -2952,11 +3223,13 @@ pub unsafe fn recvmsg(sockfd: c_int, msg: *mut msghdr, flags: c_int) -> ssize_t
         let mut remaining_bytes = cmp::min(nrecv, total_size);
         for i in 0..iovecs.len() {
             let copy_len = cmp::min(iovecs[i].iov_len, remaining_bytes);
-            ptr::copy_nonoverlapping(
-                io_data[i].iov_base as *const u8,
-                iovecs[i].iov_base as *mut u8,
-                copy_len,
-            );
+            if copy_len > 0 {
+                ptr::copy_nonoverlapping(
+                    io_data[i].iov_base as *const u8,
+                    iovecs[i].iov_base as *mut u8,
+                    copy_len,
+                );
+            }
             remaining_bytes -= copy_len;
             if remaining_bytes == 0 {
                 break;
@@ -2968,7 +3241,9 @@ pub unsafe fn recvmsg(sockfd: c_int, msg: *mut msghdr, flags: c_int) -> ssize_t
         mhdr.msg_flags = msg_flags;
     }
 
-    free(io_base as *mut c_void);
+    if !io_base.is_null() {
+        free(io_base as *mut c_void);
+    }
     result
 }
 
@@ -3002,6 +3277,67 @@ pub unsafe fn setsockopt(
     result
 }
 
+// Sets `count` socket options with a single OCALL.
+//
+// Option `i` is `optnames[i]` at `levels[i]`, and its value is the next
+// `optlens[i]` bytes of `optvals`. The options are applied in order and the
+// first failure stops the batch. On return `failed` holds the index of the
+// option that failed, or `count` if all of them were applied or the OCALL
+// itself failed.
+pub unsafe fn setsockopts(
+    sockfd: c_int,
+    levels: *const c_int,
+    optnames: *const c_int,
+    optlens: *const socklen_t,
+    optvals: *const c_void,
+    optvals_len: size_t,
+    count: size_t,
+    failed: *mut size_t,
+) -> c_int {
+    let mut result: c_int = 0;
+    let mut error: c_int = 0;
+    let mut index: size_t = count;
+
+    if failed.is_null() {
+        set_errno(EINVAL);
+        return -1;
+    }
+    *failed = count;
+    if count == 0 {
+        return 0;
+    }
+    if levels.is_null() || optnames.is_null() || optlens.is_null() || optvals.is_null() {
+        set_errno(EINVAL);
+        return -1;
+    }
+
+    let status = u_setsockopts_ocall(
+        &mut result as *mut c_int,
+        &mut error as *mut c_int,
+        &mut index as *mut size_t,
+        sockfd,
+        levels,
+        optnames,
+        optlens,
+        optvals,
+        optvals_len,
+        count,
+    );
+
+    if status == sgx_status_t::SGX_SUCCESS {
+        if result == -1 {
+            set_errno(error);
+            if index < count {
+                *failed = index;
+            }
+        }
+    } else {
+        set_errno(ESGX);
+        result = -1;
+    }
+    result
+}
+
 pub unsafe fn getsockopt(
     sockfd: c_int,
     level: c_int,
@@ -3382,6 +3718,71 @@ pub unsafe fn epoll_wait(
     result
 }
 
+// The caller checks that the returned ring lies outside the enclave.
+pub unsafe fn aio_setup(
+    entries: size_t,
+    threads: size_t,
+    notify_fd: *mut c_int,
+    ring: *mut *mut c_void,
+) -> *mut c_void {
+    let mut result: *mut c_void = ptr::null_mut();
+    let mut error: c_int = 0;
+    let status = u_aio_setup_ocall(
+        &mut result as *mut *mut c_void,
+        &mut error as *mut c_int,
+        entries,
+        threads,
+        notify_fd,
+        ring,
+    );
+    if status == sgx_status_t::SGX_SUCCESS {
+        if result.is_null() {
+            set_errno(error);
+        }
+    } else {
+        set_errno(ESGX);
+        result = ptr::null_mut();
+    }
+    result
+}
+
+pub unsafe fn aio_submit(
+    ctx: *mut c_void,
+    user_data: u64,
+    opcode: c_int,
+    fd: c_int,
+    buf: *mut c_void,
+    len: size_t,
+    offset: off64_t,
+) -> c_int {
+    let mut result: c_int = 0;
+    let mut error: c_int = 0;
+    let status = u_aio_submit_ocall(
+        &mut result as *mut c_int,
+        &mut error as *mut c_int,
+        ctx,
+        user_data,
+        opcode,
+        fd,
+        buf,
+        len,
+        offset,
+    );
+    if status == sgx_status_t::SGX_SUCCESS {
+        if result == -1 {
+            set_errno(error);
+        }
+    } else {
+        set_errno(ESGX);
+        result = -1;
+    }
+    result
+}
+
+pub unsafe fn aio_destroy(ctx: *mut c_void) {
+    let _ = u_aio_destroy_ocall(ctx);
+}
+
 pub unsafe fn sysconf(name: c_int) -> c_long {
     let mut result: c_long = 0;
     let mut error: c_int = 0;
@@ -3607,3 +4008,87 @@ pub unsafe fn getpid() -> pid_t {
     }
     result
 }
+
+pub unsafe fn enclave_exit(eid: uint64_t, code: c_int, aborted: c_int) {
+    let _ = u_exit_ocall(eid, code, aborted);
+}
+
+pub unsafe fn spawn(
+    args: *const c_char,
+    args_len: size_t,
+    env: *const c_char,
+    env_len: size_t,
+    env_clear: c_int,
+    cwd: *const c_char,
+    stdio: *const c_int,
+) -> pid_t {
+    let mut result: pid_t = 0;
+    let mut error: c_int = 0;
+    let status = u_spawn_ocall(
+        &mut result as *mut pid_t,
+        &mut error as *mut c_int,
+        args,
+        args_len,
+        env,
+        env_len,
+        env_clear,
+        cwd,
+        stdio,
+    );
+    if status == sgx_status_t::SGX_SUCCESS {
+        if result == -1 {
+            set_errno(error);
+        } else if result <= 0 {
+            set_errno(ESGX);
+            result = -1;
+        }
+    } else {
+        set_errno(ESGX);
+        result = -1;
+    }
+    result
+}
+
+pub unsafe fn waitpid(pid: pid_t, status: *mut c_int, options: c_int) -> pid_t {
+    let mut result: pid_t = 0;
+    let mut error: c_int = 0;
+    let ret = u_waitpid_ocall(
+        &mut result as *mut pid_t,
+        &mut error as *mut c_int,
+        pid,
+        status,
+        options,
+    );
+    if ret == sgx_status_t::SGX_SUCCESS {
+        if result == -1 {
+            set_errno(error);
+        } else if result < 0 || (pid > 0 && result != 0 && result != pid) {
+            set_errno(ESGX);
+            result = -1;
+        }
+    } else {
+        set_errno(ESGX);
+        result = -1;
+    }
+    result
+}
+
+pub unsafe fn kill(pid: pid_t, sig: c_int) -> c_int {
+    let mut result: c_int = 0;
+    let mut error: c_int = 0;
+    let status = u_kill_ocall(
+        &mut result as *mut c_int,
+        &mut error as *mut c_int,
+        pid,
+        sig,
+    );
+    if status == sgx_status_t::SGX_SUCCESS {
+        if result == -1 {
+            set_errno(error);
+        }
+    } else {
+        set_errno(ESGX);
+        result = -1;
+    }
+    result
+}
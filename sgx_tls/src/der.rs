@@ -0,0 +1,108 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! The little DER needed to build and pick apart RA-TLS certificates.
+
+use std::vec::Vec;
+
+pub const TAG_INTEGER: u8 = 0x02;
+pub const TAG_BIT_STRING: u8 = 0x03;
+pub const TAG_OCTET_STRING: u8 = 0x04;
+pub const TAG_OID: u8 = 0x06;
+pub const TAG_UTF8_STRING: u8 = 0x0c;
+pub const TAG_UTC_TIME: u8 = 0x17;
+pub const TAG_GENERALIZED_TIME: u8 = 0x18;
+pub const TAG_SEQUENCE: u8 = 0x30;
+pub const TAG_SET: u8 = 0x31;
+
+pub fn context(n: u8) -> u8 {
+    0xa0 | n
+}
+
+pub fn tlv(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(value.len() + 6);
+    out.push(tag);
+    if value.len() < 0x80 {
+        out.push(value.len() as u8);
+    } else {
+        let len = (value.len() as u64).to_be_bytes();
+        let skip = len.iter().take_while(|&&b| b == 0).count();
+        out.push(0x80 | (len.len() - skip) as u8);
+        out.extend_from_slice(&len[skip..]);
+    }
+    out.extend_from_slice(value);
+    out
+}
+
+pub fn sequence(parts: &[&[u8]]) -> Vec<u8> {
+    tlv(TAG_SEQUENCE, &parts.concat())
+}
+
+/// Encodes a big-endian unsigned integer.
+pub fn unsigned(be: &[u8]) -> Vec<u8> {
+    let skip = be.iter().take_while(|&&b| b == 0).count();
+    let digits = &be[skip..];
+    let mut value = Vec::with_capacity(digits.len() + 1);
+    if digits.first().map_or(true, |&b| b & 0x80 != 0) {
+        value.push(0);
+    }
+    value.extend_from_slice(digits);
+    tlv(TAG_INTEGER, &value)
+}
+
+pub fn bit_string(bytes: &[u8]) -> Vec<u8> {
+    let mut value = Vec::with_capacity(bytes.len() + 1);
+    value.push(0);
+    value.extend_from_slice(bytes);
+    tlv(TAG_BIT_STRING, &value)
+}
+
+/// Splits the first element off `input`, returning its tag, its value and
+/// what follows it.
+pub fn split(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 || rest.len() < n {
+            return None;
+        }
+        let len = rest[..n].iter().fold(0usize, |len, &b| (len << 8) | b as usize);
+        (len, &rest[n..])
+    };
+    if rest.len() < len {
+        return None;
+    }
+    Some((tag, &rest[..len], &rest[len..]))
+}
+
+/// Like [`split`], but also returns the whole encoding of the element.
+pub fn split_raw(input: &[u8]) -> Option<(u8, &[u8], &[u8], &[u8])> {
+    let (tag, value, rest) = split(input)?;
+    let raw = &input[..input.len() - rest.len()];
+    Some((tag, value, raw, rest))
+}
+
+/// Returns the value of the only element in `input` if it has tag `tag`.
+pub fn expect(input: &[u8], tag: u8) -> Option<&[u8]> {
+    match split(input)? {
+        (t, value, rest) if t == tag && rest.is_empty() => Some(value),
+        _ => None,
+    }
+}
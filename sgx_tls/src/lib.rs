@@ -0,0 +1,51 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! TLS termination inside the enclave, backed by rustls.
+//!
+//! [`TlsStream`] runs the TLS handshake and record protection on top of any
+//! byte stream, typically a [`TcpStream`](std::net::TcpStream) whose
+//! descriptor lives in the untrusted host. The host only ever sees
+//! ciphertext, so it no longer has to be trusted to terminate TLS on the
+//! enclave's behalf.
+//!
+//! Certificates, private keys and trust anchors can be kept in files sealed
+//! with [`SgxFile`](std::sgxfs::SgxFile), see [`load_root_store`],
+//! [`load_certs`] and [`load_private_key`].
+//!
+//! With the `ratls` feature, [`ratls`] issues certificates that embed a DCAP
+//! quote for their own key and verifies them during the handshake.
+
+#![cfg_attr(not(target_env = "sgx"), no_std)]
+#![cfg_attr(target_env = "sgx", feature(rustc_private))]
+
+#[cfg(not(target_env = "sgx"))]
+#[macro_use]
+extern crate sgx_tstd as std;
+
+pub use rustls;
+pub use webpki;
+
+#[cfg(feature = "ratls")]
+mod der;
+#[cfg(feature = "ratls")]
+pub mod ratls;
+mod store;
+mod stream;
+
+pub use self::store::{load_certs, load_private_key, load_root_store, store_sealed};
+pub use self::stream::TlsStream;
@@ -0,0 +1,437 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Attestation-bound TLS certificates (RA-TLS).
+//!
+//! The enclave generates a fresh ECDSA P-256 key pair and embeds a DCAP quote
+//! in a self-signed certificate for it. The quote's report data carries the
+//! SHA-256 hash of the certificate's `SubjectPublicKeyInfo`, binding the key
+//! to the enclave identity. A peer accepting the certificate through
+//! [`RaTlsVerifier`] checks the quote and that binding during the handshake,
+//! and the handshake itself proves possession of the private key.
+
+use std::fmt;
+use std::io;
+use std::mem;
+use std::ptr;
+use std::string::String;
+use std::sync::Arc;
+use std::vec::Vec;
+
+use rustls::{
+    Certificate, ClientCertVerified, ClientCertVerifier, DistinguishedNames, PrivateKey,
+    RootCertStore, ServerCertVerified, ServerCertVerifier, TLSError,
+};
+use sgx_tcrypto::{rsgx_sha256_slice, SgxEccHandle};
+use sgx_tse::rsgx_create_report;
+use sgx_types::{
+    sgx_measurement_t, sgx_quote_header_t, sgx_report_body_t, sgx_report_data_t, sgx_report_t,
+    sgx_target_info_t, SGX_FLAGS_DEBUG,
+};
+
+use crate::der;
+
+// 1.2.840.10045.4.3.2, ecdsa-with-SHA256.
+const OID_ECDSA_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+// 1.2.840.10045.2.1, id-ecPublicKey.
+const OID_EC_PUBLIC_KEY: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+// 1.2.840.10045.3.1.7, prime256v1.
+const OID_PRIME256V1: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+// 2.5.4.3, commonName.
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+
+/// The extension OID the DCAP quote is stored under, 1.2.840.113741.1337.6,
+/// as used by other RA-TLS implementations.
+pub const QUOTE_EXTENSION_OID: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf8, 0x4d, 0x8a, 0x39, 0x06];
+
+// The only quote layout understood by the verifier.
+const QUOTE_VERSION: u16 = 3;
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, String::from(msg))
+}
+
+/// Obtains DCAP quotes from the untrusted quoting enclave.
+///
+/// Quote generation runs outside of the enclave, typically through
+/// `sgx_qe_get_target_info` and `sgx_qe_get_quote` behind an OCALL, so the
+/// application supplies the glue.
+pub trait QuoteProvider {
+    /// Returns the target info of the quoting enclave.
+    fn qe_target_info(&self) -> io::Result<sgx_target_info_t>;
+
+    /// Returns a quote for `report`, which targets the quoting enclave.
+    fn quote(&self, report: &sgx_report_t) -> io::Result<Vec<u8>>;
+}
+
+/// Checks the signature and TCB status of a DCAP quote.
+///
+/// This is where quote verification collateral comes in, whether through the
+/// quote verification enclave or a remote appraisal service. [`RaTlsVerifier`]
+/// calls it after checking the quote is bound to the presented key and
+/// matches its identity policy.
+pub trait QuoteVerifier: Send + Sync {
+    /// Returns `Ok` if `quote` was produced by a genuine, up-to-date platform.
+    fn verify_quote(&self, quote: &[u8]) -> io::Result<()>;
+}
+
+/// A self-signed certificate carrying a DCAP quote for its own key, and that
+/// key.
+pub struct AttestedCert {
+    cert_der: Vec<u8>,
+    key_der: Vec<u8>,
+}
+
+impl AttestedCert {
+    /// Generates a key pair and a certificate for it with common name
+    /// `subject`, embedding a quote obtained through `provider`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn provider() -> &'static dyn sgx_tls::ratls::QuoteProvider { unimplemented!() }
+    /// use rustls::{NoClientAuth, ServerConfig};
+    /// use sgx_tls::ratls::AttestedCert;
+    ///
+    /// let cert = AttestedCert::generate(provider(), "enclave").unwrap();
+    /// let mut config = ServerConfig::new(NoClientAuth::new());
+    /// config.set_single_cert(vec![cert.certificate()], cert.private_key()).unwrap();
+    /// ```
+    pub fn generate<Q: QuoteProvider + ?Sized>(
+        provider: &Q,
+        subject: &str,
+    ) -> io::Result<AttestedCert> {
+        let ecc = SgxEccHandle::new();
+        ecc.open().map_err(io::Error::from_sgx_error)?;
+        let (private, public) = ecc.create_key_pair().map_err(io::Error::from_sgx_error)?;
+
+        // Keys and signatures come back as little-endian integers.
+        let mut point = Vec::with_capacity(65);
+        point.push(0x04);
+        point.extend(public.gx.iter().rev());
+        point.extend(public.gy.iter().rev());
+        let mut scalar = private.r;
+        scalar.reverse();
+
+        let algorithm = der::sequence(&[
+            &der::tlv(der::TAG_OID, OID_EC_PUBLIC_KEY),
+            &der::tlv(der::TAG_OID, OID_PRIME256V1),
+        ]);
+        let spki = der::sequence(&[&algorithm, &der::bit_string(&point)]);
+
+        let hash = rsgx_sha256_slice(&spki[..]).map_err(io::Error::from_sgx_error)?;
+        let mut report_data = sgx_report_data_t { d: [0; 64] };
+        report_data.d[..hash.len()].copy_from_slice(&hash);
+        let target_info = provider.qe_target_info()?;
+        let report =
+            rsgx_create_report(&target_info, &report_data).map_err(io::Error::from_sgx_error)?;
+        let quote = provider.quote(&report)?;
+
+        let signature_algorithm = der::sequence(&[&der::tlv(der::TAG_OID, OID_ECDSA_SHA256)]);
+        let name = der::sequence(&[&der::tlv(
+            der::TAG_SET,
+            &der::sequence(&[
+                &der::tlv(der::TAG_OID, OID_COMMON_NAME),
+                &der::tlv(der::TAG_UTF8_STRING, subject.as_bytes()),
+            ]),
+        )]);
+        // The quote, not the validity period, is what makes the certificate
+        // trustworthy, so it never expires.
+        let validity = der::sequence(&[
+            &der::tlv(der::TAG_UTC_TIME, b"000101000000Z"),
+            &der::tlv(der::TAG_GENERALIZED_TIME, b"99991231235959Z"),
+        ]);
+        let extensions = der::tlv(
+            der::context(3),
+            &der::sequence(&[&der::sequence(&[
+                &der::tlv(der::TAG_OID, QUOTE_EXTENSION_OID),
+                &der::tlv(der::TAG_OCTET_STRING, &quote),
+            ])]),
+        );
+        let tbs = der::sequence(&[
+            &der::tlv(der::context(0), &der::unsigned(&[2])),
+            &der::unsigned(&hash[..16]),
+            &signature_algorithm,
+            &name,
+            &validity,
+            &name,
+            &spki,
+            &extensions,
+        ]);
+
+        let signature =
+            ecc.ecdsa_sign_slice(&tbs[..], &private).map_err(io::Error::from_sgx_error)?;
+        let signature = der::sequence(&[
+            &der::unsigned(&words_to_be(&signature.x)),
+            &der::unsigned(&words_to_be(&signature.y)),
+        ]);
+        let cert_der = der::sequence(&[&tbs, &signature_algorithm, &der::bit_string(&signature)]);
+
+        // PKCS #8 wrapping an RFC 5915 ECPrivateKey.
+        let ec_private_key = der::sequence(&[
+            &der::unsigned(&[1]),
+            &der::tlv(der::TAG_OCTET_STRING, &scalar),
+            &der::tlv(der::context(1), &der::bit_string(&point)),
+        ]);
+        let key_der = der::sequence(&[
+            &der::unsigned(&[0]),
+            &algorithm,
+            &der::tlv(der::TAG_OCTET_STRING, &ec_private_key),
+        ]);
+
+        Ok(AttestedCert { cert_der, key_der })
+    }
+
+    /// Returns the certificate, for use in a rustls configuration.
+    pub fn certificate(&self) -> Certificate {
+        Certificate(self.cert_der.clone())
+    }
+
+    /// Returns the PKCS #8 encoded private key.
+    pub fn private_key(&self) -> PrivateKey {
+        PrivateKey(self.key_der.clone())
+    }
+
+    /// Returns the DER encoding of the certificate.
+    pub fn cert_der(&self) -> &[u8] {
+        &self.cert_der
+    }
+}
+
+impl fmt::Debug for AttestedCert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AttestedCert").field("cert_der_len", &self.cert_der.len()).finish()
+    }
+}
+
+fn words_to_be(words: &[u32; 8]) -> [u8; 32] {
+    let mut be = [0u8; 32];
+    for (chunk, word) in be.chunks_mut(4).zip(words.iter()) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    be.reverse();
+    be
+}
+
+/// The parts of an RA-TLS certificate the verifier looks at.
+struct Parsed<'a> {
+    spki: &'a [u8],
+    quote: &'a [u8],
+}
+
+fn parse_cert(cert_der: &[u8]) -> Option<Parsed<'_>> {
+    let cert = der::expect(cert_der, der::TAG_SEQUENCE)?;
+    let (tag, tbs, _) = der::split(cert)?;
+    if tag != der::TAG_SEQUENCE {
+        return None;
+    }
+
+    let mut fields = Vec::new();
+    let mut rest = tbs;
+    while !rest.is_empty() {
+        let (tag, value, raw, next) = der::split_raw(rest)?;
+        fields.push((tag, value, raw));
+        rest = next;
+    }
+    // version, serial, signature, issuer, validity and subject precede the key.
+    let skip = if fields.first()?.0 == der::context(0) { 6 } else { 5 };
+    let (tag, _, spki) = *fields.get(skip)?;
+    if tag != der::TAG_SEQUENCE {
+        return None;
+    }
+
+    let (_, extensions, _) = *fields.iter().find(|(tag, ..)| *tag == der::context(3))?;
+    let mut rest = der::expect(extensions, der::TAG_SEQUENCE)?;
+    while !rest.is_empty() {
+        let (tag, extension, next) = der::split(rest)?;
+        rest = next;
+        if tag != der::TAG_SEQUENCE {
+            return None;
+        }
+        let (tag, oid, mut value) = der::split(extension)?;
+        if tag != der::TAG_OID || oid != QUOTE_EXTENSION_OID {
+            continue;
+        }
+        // Skip the optional `critical` flag.
+        if value.first() == Some(&0x01) {
+            value = der::split(value)?.2;
+        }
+        let quote = der::expect(value, der::TAG_OCTET_STRING)?;
+        return Some(Parsed { spki, quote });
+    }
+    None
+}
+
+fn report_body(quote: &[u8]) -> io::Result<sgx_report_body_t> {
+    let header_len = mem::size_of::<sgx_quote_header_t>();
+    if quote.len() < header_len + mem::size_of::<sgx_report_body_t>() {
+        return Err(invalid_data("quote is truncated"));
+    }
+    if u16::from_le_bytes([quote[0], quote[1]]) != QUOTE_VERSION {
+        return Err(invalid_data("unsupported quote version"));
+    }
+    // SAFETY: the length was checked above and the body is plain old data.
+    Ok(unsafe { ptr::read_unaligned(quote[header_len..].as_ptr() as *const sgx_report_body_t) })
+}
+
+/// Accepts RA-TLS certificates whose quote verifies and matches a policy.
+///
+/// It can be installed as either side's certificate verifier, through
+/// `ClientConfig::dangerous().set_certificate_verifier` or
+/// `ServerConfig::new`. Regular PKI checks such as names and expiry are not
+/// performed, the quote takes their place.
+#[derive(Clone)]
+pub struct RaTlsVerifier {
+    quote_verifier: Arc<dyn QuoteVerifier>,
+    mr_enclave: Option<sgx_measurement_t>,
+    mr_signer: Option<sgx_measurement_t>,
+    isv_prod_id: Option<u16>,
+    min_isv_svn: u16,
+    allow_debug: bool,
+}
+
+impl RaTlsVerifier {
+    /// Creates a verifier that accepts any non-debug enclave whose quote
+    /// passes `quote_verifier`.
+    pub fn new(quote_verifier: Arc<dyn QuoteVerifier>) -> RaTlsVerifier {
+        RaTlsVerifier {
+            quote_verifier,
+            mr_enclave: None,
+            mr_signer: None,
+            isv_prod_id: None,
+            min_isv_svn: 0,
+            allow_debug: false,
+        }
+    }
+
+    /// Requires the peer enclave to have this `MRENCLAVE`.
+    pub fn mr_enclave(&mut self, mr_enclave: sgx_measurement_t) -> &mut RaTlsVerifier {
+        self.mr_enclave = Some(mr_enclave);
+        self
+    }
+
+    /// Requires the peer enclave to be signed by this `MRSIGNER`.
+    pub fn mr_signer(&mut self, mr_signer: sgx_measurement_t) -> &mut RaTlsVerifier {
+        self.mr_signer = Some(mr_signer);
+        self
+    }
+
+    /// Requires the peer enclave to have this product ID.
+    pub fn isv_prod_id(&mut self, isv_prod_id: u16) -> &mut RaTlsVerifier {
+        self.isv_prod_id = Some(isv_prod_id);
+        self
+    }
+
+    /// Requires the peer enclave's security version to be at least
+    /// `min_isv_svn`.
+    pub fn min_isv_svn(&mut self, min_isv_svn: u16) -> &mut RaTlsVerifier {
+        self.min_isv_svn = min_isv_svn;
+        self
+    }
+
+    /// Sets whether enclaves running in debug mode are accepted, which they
+    /// are not by default.
+    pub fn allow_debug(&mut self, allow_debug: bool) -> &mut RaTlsVerifier {
+        self.allow_debug = allow_debug;
+        self
+    }
+
+    /// Verifies an RA-TLS certificate, returning the peer's report body.
+    pub fn verify_cert(&self, cert_der: &[u8]) -> io::Result<sgx_report_body_t> {
+        let parsed = parse_cert(cert_der).ok_or_else(|| invalid_data("not an RA-TLS certificate"))?;
+        let body = report_body(parsed.quote)?;
+
+        let hash = rsgx_sha256_slice(parsed.spki).map_err(io::Error::from_sgx_error)?;
+        let (bound, padding) = body.report_data.d.split_at(hash.len());
+        if bound != &hash[..] || padding.iter().any(|&b| b != 0) {
+            return Err(invalid_data("quote is not bound to the certificate key"));
+        }
+
+        if !self.allow_debug && body.attributes.flags & SGX_FLAGS_DEBUG != 0 {
+            return Err(invalid_data("peer is a debug enclave"));
+        }
+        if let Some(ref mr_enclave) = self.mr_enclave {
+            if body.mr_enclave.m != mr_enclave.m {
+                return Err(invalid_data("MRENCLAVE mismatch"));
+            }
+        }
+        if let Some(ref mr_signer) = self.mr_signer {
+            if body.mr_signer.m != mr_signer.m {
+                return Err(invalid_data("MRSIGNER mismatch"));
+            }
+        }
+        if let Some(isv_prod_id) = self.isv_prod_id {
+            if body.isv_prod_id != isv_prod_id {
+                return Err(invalid_data("ISV product ID mismatch"));
+            }
+        }
+        if body.isv_svn < self.min_isv_svn {
+            return Err(invalid_data("ISV security version is too low"));
+        }
+
+        self.quote_verifier.verify_quote(parsed.quote)?;
+        Ok(body)
+    }
+
+    fn verify_chain(&self, presented_certs: &[Certificate]) -> Result<(), TLSError> {
+        let leaf = presented_certs.first().ok_or(TLSError::NoCertificatesPresented)?;
+        self.verify_cert(&leaf.0)
+            .map(drop)
+            .map_err(|e| TLSError::General(format!("RA-TLS verification failed: {}", e)))
+    }
+}
+
+impl fmt::Debug for RaTlsVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RaTlsVerifier")
+            .field("mr_enclave", &self.mr_enclave.map(|m| m.m))
+            .field("mr_signer", &self.mr_signer.map(|m| m.m))
+            .field("isv_prod_id", &self.isv_prod_id)
+            .field("min_isv_svn", &self.min_isv_svn)
+            .field("allow_debug", &self.allow_debug)
+            .finish()
+    }
+}
+
+impl ServerCertVerifier for RaTlsVerifier {
+    fn verify_server_cert(
+        &self,
+        _roots: &RootCertStore,
+        presented_certs: &[Certificate],
+        _dns_name: webpki::DNSNameRef<'_>,
+        _ocsp_response: &[u8],
+    ) -> Result<ServerCertVerified, TLSError> {
+        self.verify_chain(presented_certs).map(|()| ServerCertVerified::assertion())
+    }
+}
+
+impl ClientCertVerifier for RaTlsVerifier {
+    fn client_auth_root_subjects(
+        &self,
+        _sni: Option<&webpki::DNSName>,
+    ) -> Option<DistinguishedNames> {
+        Some(DistinguishedNames::new())
+    }
+
+    fn verify_client_cert(
+        &self,
+        presented_certs: &[Certificate],
+        _sni: Option<&webpki::DNSName>,
+    ) -> Result<ClientCertVerified, TLSError> {
+        self.verify_chain(presented_certs).map(|()| ClientCertVerified::assertion())
+    }
+}
@@ -0,0 +1,80 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sgxfs::SgxFile;
+use std::vec::Vec;
+
+use rustls::internal::pemfile;
+use rustls::{Certificate, PrivateKey, RootCertStore};
+
+fn read_sealed<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
+    let mut pem = Vec::new();
+    SgxFile::open(path)?.read_to_end(&mut pem)?;
+    Ok(pem)
+}
+
+fn invalid_pem(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("no valid {} found", what))
+}
+
+/// Seals `pem` into the file at `path`, replacing any previous content.
+///
+/// This is meant for provisioning the files read by [`load_root_store`],
+/// [`load_certs`] and [`load_private_key`]; the data is only readable by
+/// enclaves that can derive the same sealing key.
+pub fn store_sealed<P: AsRef<Path>>(path: P, pem: &[u8]) -> io::Result<()> {
+    let mut file = SgxFile::create(path)?;
+    file.write_all(pem)?;
+    file.flush()
+}
+
+/// Builds a store of trust anchors from the PEM certificates sealed at
+/// `path`.
+///
+/// Certificates that fail to parse are skipped, but it is an error if none
+/// is usable.
+pub fn load_root_store<P: AsRef<Path>>(path: P) -> io::Result<RootCertStore> {
+    let pem = read_sealed(path)?;
+    let mut store = RootCertStore::empty();
+    match store.add_pem_file(&mut &pem[..]) {
+        Ok((valid, _)) if valid > 0 => Ok(store),
+        _ => Err(invalid_pem("root certificates")),
+    }
+}
+
+/// Reads the PEM certificate chain sealed at `path`, leaf first.
+pub fn load_certs<P: AsRef<Path>>(path: P) -> io::Result<Vec<Certificate>> {
+    let pem = read_sealed(path)?;
+    match pemfile::certs(&mut &pem[..]) {
+        Ok(certs) if !certs.is_empty() => Ok(certs),
+        _ => Err(invalid_pem("certificates")),
+    }
+}
+
+/// Reads the first private key sealed at `path`.
+///
+/// Both PKCS #8 and PKCS #1 RSA keys are accepted.
+pub fn load_private_key<P: AsRef<Path>>(path: P) -> io::Result<PrivateKey> {
+    let pem = read_sealed(path)?;
+    let mut keys = pemfile::pkcs8_private_keys(&mut &pem[..]).unwrap_or_default();
+    if keys.is_empty() {
+        keys = pemfile::rsa_private_keys(&mut &pem[..]).unwrap_or_default();
+    }
+    keys.into_iter().next().ok_or_else(|| invalid_pem("private key"))
+}
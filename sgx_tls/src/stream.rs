@@ -0,0 +1,182 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::vec::Vec;
+
+use rustls::{
+    Certificate, ClientConfig, ClientSession, ProtocolVersion, ServerConfig, ServerSession,
+    Session,
+};
+
+enum Conn {
+    Client(ClientSession),
+    Server(ServerSession),
+}
+
+// Runs `$body` with `$s` bound to whichever session `$conn` holds.
+macro_rules! with_session {
+    ($conn:expr, $s:ident => $body:expr) => {
+        match $conn {
+            Conn::Client($s) => $body,
+            Conn::Server($s) => $body,
+        }
+    };
+}
+
+/// A TLS connection over a byte stream, terminated inside the enclave.
+///
+/// The handshake completes before [`connect`] or [`accept`] return, after
+/// which the stream is read and written like the plaintext stream it wraps.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io::Write;
+/// use std::net::TcpStream;
+/// use std::sync::Arc;
+/// use sgx_tls::{load_root_store, rustls::ClientConfig, TlsStream};
+///
+/// let mut config = ClientConfig::new();
+/// config.root_store = load_root_store("roots.pem.sealed").unwrap();
+///
+/// let sock = TcpStream::connect("example.com:443").unwrap();
+/// let mut tls = TlsStream::connect(Arc::new(config), "example.com", sock).unwrap();
+/// tls.write_all(b"GET / HTTP/1.0\r\nHost: example.com\r\n\r\n").unwrap();
+/// ```
+///
+/// [`connect`]: TlsStream::connect
+/// [`accept`]: TlsStream::accept
+pub struct TlsStream<S: Read + Write = TcpStream> {
+    conn: Conn,
+    sock: S,
+}
+
+impl<S: Read + Write> TlsStream<S> {
+    /// Performs a client handshake with `server_name` over `sock`.
+    ///
+    /// The server's certificate is verified against `config` and must be
+    /// valid for `server_name`.
+    pub fn connect(
+        config: Arc<ClientConfig>,
+        server_name: &str,
+        sock: S,
+    ) -> io::Result<TlsStream<S>> {
+        let name = webpki::DNSNameRef::try_from_ascii_str(server_name).map_err(|_| {
+            let msg = format!("invalid server name: {}", server_name);
+            io::Error::new(io::ErrorKind::InvalidInput, msg)
+        })?;
+        TlsStream::handshake(Conn::Client(ClientSession::new(&config, name)), sock)
+    }
+
+    /// Performs a server handshake over `sock`.
+    pub fn accept(config: Arc<ServerConfig>, sock: S) -> io::Result<TlsStream<S>> {
+        TlsStream::handshake(Conn::Server(ServerSession::new(&config)), sock)
+    }
+
+    fn handshake(mut conn: Conn, mut sock: S) -> io::Result<TlsStream<S>> {
+        with_session!(&mut conn, s => {
+            while s.is_handshaking() {
+                s.complete_io(&mut sock)?;
+            }
+            // Flush what the last round left behind, such as the client's
+            // Finished message.
+            while s.wants_write() {
+                s.write_tls(&mut sock)?;
+            }
+        });
+        Ok(TlsStream { conn, sock })
+    }
+
+    /// Returns the certificate chain presented by the peer, if any.
+    pub fn peer_certificates(&self) -> Option<Vec<Certificate>> {
+        with_session!(&self.conn, s => s.get_peer_certificates())
+    }
+
+    /// Returns the protocol agreed through ALPN, if any.
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        with_session!(&self.conn, s => s.get_alpn_protocol())
+    }
+
+    /// Returns the negotiated TLS version.
+    pub fn protocol_version(&self) -> Option<ProtocolVersion> {
+        with_session!(&self.conn, s => s.get_protocol_version())
+    }
+
+    /// Sends a `close_notify` alert, telling the peer no more data follows.
+    ///
+    /// The underlying stream is left open.
+    pub fn shutdown(&mut self) -> io::Result<()> {
+        let sock = &mut self.sock;
+        with_session!(&mut self.conn, s => {
+            s.send_close_notify();
+            while s.wants_write() {
+                s.write_tls(sock)?;
+            }
+        });
+        sock.flush()
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.sock
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    ///
+    /// Reading from or writing to it directly corrupts the TLS connection.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.sock
+    }
+
+    /// Unwraps the underlying stream, discarding the TLS state.
+    pub fn into_inner(self) -> S {
+        self.sock
+    }
+}
+
+impl<S: Read + Write> Read for TlsStream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let sock = &mut self.sock;
+        with_session!(&mut self.conn, s => rustls::Stream::new(s, sock).read(buf))
+    }
+}
+
+impl<S: Read + Write> Write for TlsStream<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let sock = &mut self.sock;
+        with_session!(&mut self.conn, s => rustls::Stream::new(s, sock).write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let sock = &mut self.sock;
+        with_session!(&mut self.conn, s => rustls::Stream::new(s, sock).flush())
+    }
+}
+
+impl<S: Read + Write + fmt::Debug> fmt::Debug for TlsStream<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = match self.conn {
+            Conn::Client(_) => "client",
+            Conn::Server(_) => "server",
+        };
+        f.debug_struct("TlsStream").field("side", &side).field("sock", &self.sock).finish()
+    }
+}
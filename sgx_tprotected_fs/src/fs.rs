@@ -48,6 +48,16 @@ unsafe fn rsgx_fopen_auto_key(filename: &CStr, mode: &CStr) -> SysResult<SGX_FIL
     }
 }
 
+#[cfg(feature = "integrity_only")]
+unsafe fn rsgx_fopen_integrity_only(filename: &CStr, mode: &CStr) -> SysResult<SGX_FILE> {
+    let file = sgx_fopen_integrity_only(filename.as_ptr(), mode.as_ptr());
+    if file.is_null() {
+        Err(errno())
+    } else {
+        Ok(file)
+    }
+}
+
 unsafe fn rsgx_fwrite(stream: SGX_FILE, buf: &[u8]) -> SysResult<usize> {
     if stream.is_null() {
         return Err(libc::EINVAL);
@@ -275,6 +285,43 @@ impl SgxFileStream {
         unsafe { rsgx_fopen_auto_key(filename, mode).map(|f| SgxFileStream { stream: f }) }
     }
 
+    ///
+    /// The open_integrity_only function creates or opens a protected file whose content is not encrypted.
+    ///
+    /// # Description
+    ///
+    /// open_integrity_only is similar to open_auto_key, except that the file's data nodes are only
+    /// authenticated with a MAC and stored in plaintext. It suits large public data sets whose integrity
+    /// must be verified but whose confidentiality does not matter. Files created with this API can only be
+    /// opened with open_integrity_only.
+    ///
+    /// # Parameters
+    ///
+    /// **filename**
+    ///
+    /// The name of the file to be created or opened.
+    ///
+    /// **mode**
+    ///
+    /// The file open mode string. Allowed values are any combination of ‘r’, ‘w’ or ‘a’, with possible ‘+’
+    /// and possible ‘b’ (since string functions are currently not sup- ported, ‘b’ is meaningless).
+    ///
+    /// # Requirements
+    ///
+    /// Header: sgx_tprotected_fs.edl
+    ///
+    /// Library: libsgx_tprotected_fs.a, from an Intel SGX SDK that provides sgx_fopen_integrity_only
+    ///
+    /// # Return value
+    ///
+    /// If the function succeeds, it returns a valid file pointer, which can be used by all the other functions
+    /// in the Protected FS API, otherwise, error code is returned.
+    ///
+    #[cfg(feature = "integrity_only")]
+    pub fn open_integrity_only(filename: &CStr, mode: &CStr) -> SysResult<SgxFileStream> {
+        unsafe { rsgx_fopen_integrity_only(filename, mode).map(|f| SgxFileStream { stream: f }) }
+    }
+
     ///
     /// The read function reads the requested amount of data from the file, and extends the file pointer by that amount.
     ///
@@ -24,7 +24,9 @@ use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::cmp::Ordering;
+use core::convert::TryFrom;
 use core::fmt::{self, Write};
+use core::hash::{Hash, Hasher};
 use core::mem;
 use core::num::NonZeroU8;
 use core::ops;
@@ -120,7 +122,7 @@ use sgx_types::c_char;
 /// documentation of `CString` before use, as improper ownership management
 /// of `CString` instances can lead to invalid memory accesses, memory leaks,
 /// and other memory errors.
-#[derive(PartialEq, PartialOrd, Eq, Ord, Hash, Clone)]
+#[derive(PartialOrd, Eq, Ord, Clone)]
 #[cfg_attr(not(test), rustc_diagnostic_item = "cstring_type")]
 pub struct CString {
     // Invariant 1: the slice ends with a zero byte and has a length of at least one.
@@ -196,7 +198,6 @@ pub struct CString {
 /// ```
 ///
 /// [`&str`]: prim@str
-#[derive(Hash)]
 #[cfg_attr(not(test), rustc_diagnostic_item = "CStr")]
 // FIXME:
 // `fn from` in `impl From<&CStr> for Box<CStr>` current implementation relies
@@ -381,6 +382,63 @@ impl FromVecWithNulError {
     }
 }
 
+/// An error indicating that a buffer does not hold a C string encoded by
+/// [`CStr::encode`].
+///
+/// This error is created by the [`CString::decode`] method. See its
+/// documentation for more.
+///
+/// # Examples
+///
+/// ```
+/// use std::ffi::{CString, DecodeError};
+///
+/// let _: DecodeError = CString::decode(&[3, 0, 0]).unwrap_err();
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DecodeError {
+    kind: DecodeErrorKind,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum DecodeErrorKind {
+    TruncatedLength,
+    TruncatedData,
+    InteriorNul(usize),
+}
+
+impl DecodeError {
+    /// Returns the position of the nul byte in the decoded string, if that is
+    /// why decoding failed.
+    ///
+    /// The position is relative to the start of the string, not of the
+    /// buffer.
+    pub fn nul_position(&self) -> Option<usize> {
+        match self.kind {
+            DecodeErrorKind::InteriorNul(pos) => Some(pos),
+            _ => None,
+        }
+    }
+
+    pub fn __description(&self) -> &str {
+        match self.kind {
+            DecodeErrorKind::TruncatedLength => "buffer is too short to hold a length prefix",
+            DecodeErrorKind::TruncatedData => "buffer is shorter than the encoded length",
+            DecodeErrorKind::InteriorNul(..) => "encoded data contains a nul byte",
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.__description())?;
+        if let DecodeErrorKind::InteriorNul(pos) = self.kind {
+            write!(f, " at byte pos {}", pos)?;
+        }
+        Ok(())
+    }
+}
+
 /// An error indicating invalid UTF-8 when converting a [`CString`] into a [`String`].
 ///
 /// `CString` is just a wrapper over a buffer of bytes with a nul terminator;
@@ -476,6 +534,121 @@ impl CString {
         }
     }
 
+    /// Creates a new C-compatible string from a container of bytes, replacing
+    /// any interior 0 bytes with `b'?'`.
+    ///
+    /// Unlike [`CString::new`], this never fails, which suits best-effort
+    /// uses such as logging untrusted buffers. Note that the replacement
+    /// changes the data and cannot be undone: the original bytes can no
+    /// longer be told apart from a literal `?`. Use
+    /// [`CString::new_lossy_with`] to choose a different replacement byte.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ffi::CString;
+    ///
+    /// let c_string = CString::new_lossy(&b"a\0b\0c"[..]);
+    /// assert_eq!(c_string.as_bytes_with_nul(), b"a?b?c\0");
+    /// ```
+    pub fn new_lossy<T: Into<Vec<u8>>>(t: T) -> CString {
+        Self::new_lossy_with(t, b'?')
+    }
+
+    /// Creates a new C-compatible string from a container of bytes, replacing
+    /// any interior 0 bytes with `replacement`.
+    ///
+    /// See [`CString::new_lossy`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ffi::CString;
+    ///
+    /// let c_string = CString::new_lossy_with("a\0b", b' ');
+    /// assert_eq!(c_string.as_bytes_with_nul(), b"a b\0");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `replacement` is 0.
+    pub fn new_lossy_with<T: Into<Vec<u8>>>(t: T, replacement: u8) -> CString {
+        assert_ne!(replacement, 0, "the replacement for nul bytes must not be nul");
+        let mut bytes = t.into();
+        for b in bytes.iter_mut().filter(|b| **b == 0) {
+            *b = replacement;
+        }
+        unsafe { CString::from_vec_unchecked(bytes) }
+    }
+
+    /// Creates a new C-compatible string from formatting arguments.
+    ///
+    /// The arguments are formatted into a single buffer which is then checked
+    /// for interior 0 bytes once, so values that come from untrusted input
+    /// cannot silently truncate the string seen by C code. The
+    /// [`cstr_format!`] macro is usually more convenient.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ffi::CString;
+    ///
+    /// let c_string = CString::from_fmt(format_args!("fd={}", 3)).expect("CString::from_fmt failed");
+    /// assert_eq!(c_string.as_bytes(), b"fd=3");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the formatted output contains an
+    /// internal 0 byte. The [`NulError`] returned will contain the formatted
+    /// bytes as well as the position of the nul byte.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a formatting trait implementation returns an error, like
+    /// [`format!`] does.
+    ///
+    /// [`cstr_format!`]: crate::cstr_format
+    pub fn from_fmt(args: fmt::Arguments<'_>) -> Result<CString, NulError> {
+        Self::_new(alloc::fmt::format(args).into_bytes())
+    }
+
+    /// Creates a new C-compatible string by concatenating `parts`, placing
+    /// `sep` between each pair of adjacent parts.
+    ///
+    /// A single trailing 0 byte is appended to the result. An empty iterator
+    /// yields an empty `CString`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ffi::CString;
+    ///
+    /// let path = CString::join(b"/", &["usr", "local", "lib"]).expect("CString::join failed");
+    /// assert_eq!(path.as_bytes(), b"usr/local/lib");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the concatenated bytes, including
+    /// the separators, contain an internal 0 byte. The [`NulError`] returned
+    /// will contain the concatenated bytes as well as the position of the nul
+    /// byte within them.
+    pub fn join<I, P>(sep: &[u8], parts: I) -> Result<CString, NulError>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<[u8]>,
+    {
+        let mut bytes = Vec::new();
+        for (i, part) in parts.into_iter().enumerate() {
+            if i != 0 {
+                bytes.extend_from_slice(sep);
+            }
+            bytes.extend_from_slice(part.as_ref());
+        }
+        Self::_new(bytes)
+    }
+
     /// Creates a C-compatible string by consuming a byte vector,
     /// without checking for interior 0 bytes.
     ///
@@ -699,6 +872,96 @@ impl CString {
         &self.inner
     }
 
+    /// Shortens this `CString` to at most `new_len` bytes of content.
+    ///
+    /// The first `new_len` bytes are kept and a new nul terminator is
+    /// appended after them. If `new_len` is greater than or equal to the
+    /// current length (as returned by [`CString::as_bytes`]), this has no
+    /// effect. Since the existing content contains no nul bytes, the result
+    /// never contains an interior nul.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ffi::CString;
+    ///
+    /// let mut c_string = CString::new("foobar").expect("CString::new failed");
+    /// c_string.truncate(3);
+    /// assert_eq!(c_string.as_bytes_with_nul(), b"foo\0");
+    ///
+    /// c_string.truncate(0);
+    /// assert_eq!(c_string.as_bytes_with_nul(), b"\0");
+    /// ```
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len >= self.as_bytes().len() {
+            return;
+        }
+        let mut v = mem::take(&mut self.inner).into_vec();
+        v.truncate(new_len);
+        v.push(0);
+        self.inner = v.into_boxed_slice();
+    }
+
+    /// Returns the underlying storage, including the trailing nul terminator,
+    /// as a mutable slice so that a C function can fill it in place.
+    ///
+    /// # Safety
+    ///
+    /// Writes through the returned slice may remove the terminator or add
+    /// interior nul bytes. The caller must call [`CString::revalidate`]
+    /// after the writes and before using this `CString` in any other way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ffi::CString;
+    ///
+    /// let mut c_string = CString::new(vec![b' '; 15]).expect("CString::new failed");
+    /// let buf = unsafe { c_string.as_bytes_with_nul_mut() };
+    /// buf[..6].copy_from_slice(b"hello\0");
+    /// c_string.revalidate().expect("no nul terminator");
+    /// assert_eq!(c_string.as_bytes(), b"hello");
+    /// ```
+    #[inline]
+    pub unsafe fn as_bytes_with_nul_mut(&mut self) -> &mut [u8] {
+        &mut self.inner
+    }
+
+    /// Restores the `CString` invariants after its storage was written through
+    /// [`CString::as_bytes_with_nul_mut`].
+    ///
+    /// The string is truncated just after its first nul byte. If the storage
+    /// contains no nul byte at all, an error is returned and the last byte is
+    /// overwritten with a nul so that the `CString` is still well-formed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ffi::CString;
+    ///
+    /// let mut c_string = CString::new("abc").expect("CString::new failed");
+    /// unsafe { c_string.as_bytes_with_nul_mut()[3] = b'd' };
+    /// assert!(c_string.revalidate().is_err());
+    /// assert_eq!(c_string.as_bytes_with_nul(), b"abc\0");
+    /// ```
+    pub fn revalidate(&mut self) -> Result<(), FromBytesWithNulError> {
+        match memchr::memchr(0, &self.inner) {
+            Some(nul_pos) => {
+                if nul_pos + 1 < self.inner.len() {
+                    let mut v = mem::take(&mut self.inner).into_vec();
+                    v.truncate(nul_pos + 1);
+                    self.inner = v.into_boxed_slice();
+                }
+                Ok(())
+            }
+            None => {
+                let last = self.inner.len() - 1;
+                self.inner[last] = 0;
+                Err(FromBytesWithNulError::not_nul_terminated())
+            }
+        }
+    }
+
     /// Extracts a [`CStr`] slice containing the entire string.
     ///
     /// # Examples
@@ -732,6 +995,35 @@ impl CString {
         unsafe { Box::from_raw(Box::into_raw(self.into_inner()) as *mut CStr) }
     }
 
+    /// Consumes the `CString` and leaks its allocation, returning a reference
+    /// that lives for the rest of the program.
+    ///
+    /// This mirrors [`Box::leak`], and is meant for strings that are
+    /// handed to C code which keeps them around, such as names registered
+    /// once at startup.
+    ///
+    /// The memory is not freed unless it is reclaimed: passing the leaked
+    /// pointer to [`CString::from_raw`] turns it back into an owned
+    /// `CString`. After that the `&'static` reference, and any copy of it,
+    /// must no longer be used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ffi::{CStr, CString};
+    ///
+    /// let name: &'static mut CStr = CString::new("callback").expect("CString::new failed").leak();
+    /// assert_eq!(name.to_bytes(), b"callback");
+    ///
+    /// // Reclaim the allocation once the name is no longer needed.
+    /// let owned = unsafe { CString::from_raw(name.as_ptr() as *mut _) };
+    /// drop(owned);
+    /// ```
+    #[inline]
+    pub fn leak(self) -> &'static mut CStr {
+        Box::leak(self.into_boxed_c_str())
+    }
+
     /// Bypass "move out of struct which implements [`Drop`] trait" restriction.
     #[inline]
     fn into_inner(self) -> Box<[u8]> {
@@ -820,6 +1112,53 @@ impl CString {
             }),
         }
     }
+
+    /// Decodes a C string written by [`CStr::encode`] at the start of `buf`.
+    ///
+    /// Returns the string together with the number of bytes of `buf` it
+    /// took up, so that several encoded strings can be read back one after
+    /// the other. Any bytes after the encoded string are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `buf` ends before the length prefix or before the
+    /// bytes it announces, or if those bytes contain a nul byte. Decoding
+    /// never allocates more than the bytes present in `buf`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ffi::CString;
+    ///
+    /// let mut buf = CString::new("first").expect("CString::new failed").encode();
+    /// buf.extend(CString::new("second").expect("CString::new failed").encode());
+    ///
+    /// let (first, used) = CString::decode(&buf).expect("CString::decode failed");
+    /// let (second, _) = CString::decode(&buf[used..]).expect("CString::decode failed");
+    /// assert_eq!(first.as_bytes(), b"first");
+    /// assert_eq!(second.as_bytes(), b"second");
+    /// ```
+    pub fn decode(buf: &[u8]) -> Result<(CString, usize), DecodeError> {
+        const PREFIX_LEN: usize = mem::size_of::<u64>();
+
+        if buf.len() < PREFIX_LEN {
+            return Err(DecodeError { kind: DecodeErrorKind::TruncatedLength });
+        }
+        let (prefix, rest) = buf.split_at(PREFIX_LEN);
+        let mut len_bytes = [0_u8; PREFIX_LEN];
+        len_bytes.copy_from_slice(prefix);
+        let len = u64::from_le_bytes(len_bytes);
+        let data = match usize::try_from(len) {
+            Ok(len) if len <= rest.len() => &rest[..len],
+            _ => return Err(DecodeError { kind: DecodeErrorKind::TruncatedData }),
+        };
+        if let Some(pos) = memchr::memchr(0, data) {
+            return Err(DecodeError { kind: DecodeErrorKind::InteriorNul(pos) });
+        }
+        // SAFETY: `data` was just checked to contain no nul byte.
+        let cstring = unsafe { CString::from_vec_unchecked(data.to_vec()) };
+        Ok((cstring, PREFIX_LEN + data.len()))
+    }
 }
 
 // Turns this `CString` into an empty string to prevent
@@ -839,7 +1178,7 @@ impl ops::Deref for CString {
 
     #[inline]
     fn deref(&self) -> &CStr {
-        unsafe { CStr::from_bytes_with_nul_unchecked(self.as_bytes_with_nul()) }
+        unsafe { CStr::from_bytes_with_nul_debug_checked(self.as_bytes_with_nul()) }
     }
 }
 
@@ -859,20 +1198,65 @@ impl From<CString> for Vec<u8> {
     }
 }
 
+/// The default form escapes every non-ASCII byte with `\xNN`. The alternate
+/// form (`{:#?}`) prints valid UTF-8 verbatim, escaping only non-printable
+/// characters and bytes that are not part of a valid UTF-8 sequence.
 impl fmt::Debug for CStr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return self.fmt_utf8_lossless(f);
+        }
         write!(f, "\"")?;
-        for byte in self
-            .to_bytes()
-            .iter()
-            .flat_map(|&b| ascii::escape_default(b))
-        {
+        for byte in self.escaped_bytes() {
             f.write_char(byte as char)?;
         }
         write!(f, "\"")
     }
 }
 
+impl CStr {
+    // The escaping used by the default `Debug` output, without the quotes.
+    fn escaped_bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        self.to_bytes().iter().flat_map(|&b| ascii::escape_default(b))
+    }
+
+    fn fmt_utf8_lossless(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_str_escaped(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+            for c in s.chars() {
+                if c == '\'' {
+                    f.write_char(c)?;
+                } else {
+                    for e in c.escape_debug() {
+                        f.write_char(e)?;
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        f.write_char('"')?;
+        let mut bytes = self.to_bytes();
+        loop {
+            match str::from_utf8(bytes) {
+                Ok(s) => {
+                    write_str_escaped(f, s)?;
+                    break;
+                }
+                Err(e) => {
+                    let (valid, rest) = bytes.split_at(e.valid_up_to());
+                    write_str_escaped(f, unsafe { str::from_utf8_unchecked(valid) })?;
+                    let invalid_len = e.error_len().unwrap_or(rest.len());
+                    for b in &rest[..invalid_len] {
+                        write!(f, "\\x{:02x}", b)?;
+                    }
+                    bytes = &rest[invalid_len..];
+                }
+            }
+        }
+        f.write_char('"')
+    }
+}
+
 impl Default for &CStr {
     fn default() -> Self {
         const SLICE: &[c_char] = &[0];
@@ -1177,6 +1561,12 @@ impl CStr {
     /// performing any sanity checks. The provided slice **must** be nul-terminated
     /// and not contain any interior nul bytes.
     ///
+    /// Being `const`, this function cannot check these requirements even in
+    /// debug builds. The runtime conversions built on top of it, such as
+    /// dereferencing a [`CString`] or indexing a `CStr`, do check them with
+    /// `debug_assert!`, so a [`CString`] created from invalid data by
+    /// [`CString::from_vec_unchecked`] is caught on first use in tests.
+    ///
     /// # Examples
     ///
     /// ```
@@ -1198,6 +1588,20 @@ impl CStr {
         &*(bytes as *const [u8] as *const CStr)
     }
 
+    // `from_bytes_with_nul_unchecked` for runtime call sites, which checks its
+    // safety requirements in debug builds. The public function is `const` and
+    // cannot run these checks itself.
+    #[inline]
+    unsafe fn from_bytes_with_nul_debug_checked(bytes: &[u8]) -> &CStr {
+        debug_assert!(!bytes.is_empty(), "C string must not be empty");
+        debug_assert!(bytes[bytes.len() - 1] == 0, "C string is not nul-terminated");
+        debug_assert!(
+            memchr::memchr(0, bytes) == Some(bytes.len() - 1),
+            "C string contains an interior nul byte"
+        );
+        CStr::from_bytes_with_nul_unchecked(bytes)
+    }
+
     /// Returns the inner pointer to this C string.
     ///
     /// The returned pointer will be valid for as long as `self` is, and points
@@ -1249,6 +1653,35 @@ impl CStr {
         self.inner.as_ptr()
     }
 
+    /// Returns the two raw pointers spanning the contents of this C string.
+    ///
+    /// The returned range is half-open: `start` points to the first byte and
+    /// `end` points one past the last content byte, i.e. at the nul
+    /// terminator. `end - start` is therefore equal to the length returned by
+    /// [`CStr::to_bytes`], which is what C functions taking a begin/end pointer
+    /// pair expect.
+    ///
+    /// The same lifetime caveats as for [`CStr::as_ptr`] apply to both
+    /// pointers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ffi::CStr;
+    ///
+    /// let cstr = CStr::from_bytes_with_nul(b"foo\0").expect("CStr::from_bytes_with_nul failed");
+    /// let range = cstr.as_ptr_range();
+    /// assert_eq!(range.end as usize - range.start as usize, 3);
+    /// ```
+    #[inline]
+    pub fn as_ptr_range(&self) -> ops::Range<*const c_char> {
+        let start = self.as_ptr();
+        // SAFETY: the contents are always followed by the nul terminator, so
+        // `start + len` stays within the same allocation.
+        let end = unsafe { start.add(self.to_bytes().len()) };
+        start..end
+    }
+
     /// Converts this C string to a byte slice.
     ///
     /// The returned slice will **not** contain the trailing nul terminator that this C
@@ -1364,6 +1797,168 @@ impl CStr {
         String::from_utf8_lossy(self.to_bytes())
     }
 
+    /// Returns the contents of this `CStr` with every byte that is not
+    /// printable ASCII escaped, as done by its [`Debug`] output.
+    ///
+    /// Tabs, newlines, quotes and backslashes are escaped with a backslash and
+    /// other bytes outside the printable ASCII range as `\xNN`. The result does
+    /// not include the quotes that `Debug` adds around it.
+    ///
+    /// [`Debug`]: fmt::Debug
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ffi::CStr;
+    ///
+    /// let cstr = CStr::from_bytes_with_nul(b"a\tb\xff\0").expect("CStr::from_bytes_with_nul failed");
+    /// assert_eq!(cstr.to_bytes_escaped(), b"a\\tb\\xff");
+    /// ```
+    pub fn to_bytes_escaped(&self) -> Vec<u8> {
+        let mut escaped = Vec::with_capacity(self.display_len());
+        escaped.extend(self.escaped_bytes());
+        escaped
+    }
+
+    /// Returns the length of [`CStr::to_bytes_escaped`] without allocating.
+    ///
+    /// Since the escaped form is printable ASCII, this is also its display
+    /// width, which is useful for padding columns of C strings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ffi::CStr;
+    ///
+    /// let cstr = CStr::from_bytes_with_nul(b"a\tb\xff\0").expect("CStr::from_bytes_with_nul failed");
+    /// assert_eq!(cstr.display_len(), 8);
+    /// ```
+    pub fn display_len(&self) -> usize {
+        self.to_bytes().iter().map(|&b| ascii::escape_default(b).len()).sum()
+    }
+
+    /// Encodes this C string into a self-delimiting byte buffer, for example
+    /// to persist it to sealed storage.
+    ///
+    /// The encoding is the length of the string as a little-endian `u64`,
+    /// followed by the bytes of the string without the nul terminator. It
+    /// does not depend on the platform, and [`CString::decode`] reads it
+    /// back.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ffi::CStr;
+    ///
+    /// let cstr = CStr::from_bytes_with_nul(b"hi\0").expect("CStr::from_bytes_with_nul failed");
+    /// assert_eq!(cstr.encode(), [2, 0, 0, 0, 0, 0, 0, 0, b'h', b'i']);
+    /// ```
+    pub fn encode(&self) -> Vec<u8> {
+        let bytes = self.to_bytes();
+        let mut buf = Vec::with_capacity(mem::size_of::<u64>() + bytes.len());
+        buf.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+        buf.extend_from_slice(bytes);
+        buf
+    }
+
+    /// Appends the contents of this `CStr` to `buf`, replacing any invalid
+    /// UTF-8 sequences with [U+FFFD REPLACEMENT CHARACTER][U+FFFD].
+    ///
+    /// This decodes exactly like [`CStr::to_string_lossy`], but writes into an
+    /// existing [`String`] so that its allocation can be reused across many
+    /// calls. Valid UTF-8 is copied without any replacement characters.
+    ///
+    /// [U+FFFD]: crate::char::REPLACEMENT_CHARACTER
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ffi::CStr;
+    ///
+    /// let mut buf = String::new();
+    /// for bytes in [&b"Hello \0"[..], &b"\xF0\x90\x80World\0"[..]] {
+    ///     let cstr = CStr::from_bytes_with_nul(bytes).expect("CStr::from_bytes_with_nul failed");
+    ///     cstr.to_string_lossy_into(&mut buf);
+    /// }
+    /// assert_eq!(buf, "Hello \u{FFFD}World");
+    /// ```
+    pub fn to_string_lossy_into(&self, buf: &mut String) {
+        let mut bytes = self.to_bytes();
+        buf.reserve(bytes.len());
+        loop {
+            match str::from_utf8(bytes) {
+                Ok(s) => {
+                    buf.push_str(s);
+                    break;
+                }
+                Err(e) => {
+                    let (valid, rest) = bytes.split_at(e.valid_up_to());
+                    buf.push_str(unsafe { str::from_utf8_unchecked(valid) });
+                    buf.push(char::REPLACEMENT_CHARACTER);
+                    match e.error_len() {
+                        Some(len) => bytes = &rest[len..],
+                        None => break,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks that two C strings are an ASCII case-insensitive match.
+    ///
+    /// Same as comparing `to_ascii_lowercase(a) == to_ascii_lowercase(b)`,
+    /// but without allocating and copying temporaries. Bytes outside the
+    /// ASCII range must match exactly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ffi::CStr;
+    ///
+    /// let a = CStr::from_bytes_with_nul(b"Content-Type\0").expect("CStr::from_bytes_with_nul failed");
+    /// let b = CStr::from_bytes_with_nul(b"content-type\0").expect("CStr::from_bytes_with_nul failed");
+    /// assert!(a.eq_ignore_ascii_case(b));
+    /// ```
+    pub fn eq_ignore_ascii_case(&self, other: &CStr) -> bool {
+        self.to_bytes().eq_ignore_ascii_case(other.to_bytes())
+    }
+
+    /// Returns a copy of this C string where each ASCII uppercase letter is
+    /// mapped to its lowercase equivalent.
+    ///
+    /// Bytes outside the ASCII range are left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ffi::{CStr, CString};
+    ///
+    /// let cstr = CStr::from_bytes_with_nul(b"Content-Type\0").expect("CStr::from_bytes_with_nul failed");
+    /// assert_eq!(cstr.to_ascii_lowercase(), CString::new("content-type").expect("CString::new failed"));
+    /// ```
+    pub fn to_ascii_lowercase(&self) -> CString {
+        // SAFETY: case mapping never turns a non-nul byte into a nul byte.
+        unsafe { CString::from_vec_unchecked(self.to_bytes().to_ascii_lowercase()) }
+    }
+
+    /// Returns a copy of this C string where each ASCII lowercase letter is
+    /// mapped to its uppercase equivalent.
+    ///
+    /// Bytes outside the ASCII range are left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::ffi::{CStr, CString};
+    ///
+    /// let cstr = CStr::from_bytes_with_nul(b"Content-Type\0").expect("CStr::from_bytes_with_nul failed");
+    /// assert_eq!(cstr.to_ascii_uppercase(), CString::new("CONTENT-TYPE").expect("CString::new failed"));
+    /// ```
+    pub fn to_ascii_uppercase(&self) -> CString {
+        // SAFETY: case mapping never turns a non-nul byte into a nul byte.
+        unsafe { CString::from_vec_unchecked(self.to_bytes().to_ascii_uppercase()) }
+    }
+
     /// Converts a [`Box`]`<CStr>` into a [`CString`] without copying or allocating.
     ///
     /// # Examples
@@ -1384,8 +1979,11 @@ impl CStr {
 }
 
 impl PartialEq for CStr {
+    #[inline]
     fn eq(&self, other: &CStr) -> bool {
-        self.to_bytes().eq(other.to_bytes())
+        // Slice equality compares the lengths first, so strings of different
+        // lengths are rejected without touching their contents.
+        self.to_bytes() == other.to_bytes()
     }
 }
 
@@ -1403,6 +2001,32 @@ impl Ord for CStr {
     }
 }
 
+// `CString` borrows as `CStr`, so both must hash identically whenever they
+// compare equal; otherwise a `HashMap<CString, _>` could not be queried with a
+// `&CStr`. Both impls feed the bytes (including the nul terminator) to the
+// hasher as a `[u8]` slice, independent of the signedness of `c_char`.
+impl Hash for CStr {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_bytes_with_nul().hash(state);
+    }
+}
+
+impl Hash for CString {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_c_str().hash(state);
+    }
+}
+
+// Spelled out next to `Hash`, which it has to agree with.
+impl PartialEq for CString {
+    #[inline]
+    fn eq(&self, other: &CString) -> bool {
+        self.as_c_str() == other.as_c_str()
+    }
+}
+
 impl ToOwned for CStr {
     type Owned = CString;
 
@@ -1443,7 +2067,7 @@ impl ops::Index<ops::RangeFrom<usize>> for CStr {
         // byte, since otherwise we could get an empty string that doesn't end
         // in a null.
         if index.start < bytes.len() {
-            unsafe { CStr::from_bytes_with_nul_unchecked(&bytes[index.start..]) }
+            unsafe { CStr::from_bytes_with_nul_debug_checked(&bytes[index.start..]) }
         } else {
             panic!(
                 "index out of bounds: the len is {} but the index is {}",
@@ -1467,3 +2091,41 @@ impl AsRef<CStr> for CString {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate test;
+
+    use self::test::{black_box, Bencher};
+    use super::CStr;
+    use alloc::vec::Vec;
+
+    const LEN: usize = 64 * 1024;
+
+    fn c_string(len: usize) -> Vec<u8> {
+        let mut bytes = alloc::vec![b'a'; len];
+        bytes.push(0);
+        bytes
+    }
+
+    // Strings that differ only in length are told apart without reading
+    // their contents, so this stays flat as `LEN` grows.
+    #[bench]
+    fn eq_different_length_bench(b: &mut Bencher) {
+        let long = c_string(LEN);
+        let short = c_string(LEN - 1);
+        let long = CStr::from_bytes_with_nul(&long).unwrap();
+        let short = CStr::from_bytes_with_nul(&short).unwrap();
+        b.iter(|| black_box(long) == black_box(short));
+    }
+
+    // For comparison: equal strings of the same length are read in full.
+    #[bench]
+    fn eq_same_length_bench(b: &mut Bencher) {
+        let x = c_string(LEN);
+        let y = c_string(LEN);
+        let x = CStr::from_bytes_with_nul(&x).unwrap();
+        let y = CStr::from_bytes_with_nul(&y).unwrap();
+        b.iter(|| black_box(x) == black_box(y));
+    }
+}
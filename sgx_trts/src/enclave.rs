@@ -562,6 +562,25 @@ pub fn rsgx_get_tcs_max_num() -> u32 {
     unsafe { g_global_data.tcs_max_num as u32 }
 }
 
+///
+/// rsgx_get_stack_max_size is to get the stack size of each thread, as set by
+/// `StackMaxSize` in the enclave configuration.
+///
+/// With EDMM, only part of the stack is committed up front and the rest is
+/// committed as the stack grows, up to this size.
+///
+/// **Note**
+///
+/// This API is only an experimental funtion.
+///
+#[inline]
+pub fn rsgx_get_stack_max_size() -> usize {
+    unsafe {
+        let td = &g_global_data.td_template;
+        td.stack_base_addr.wrapping_sub(td.stack_limit_addr)
+    }
+}
+
 pub fn rsgx_get_tcs_num() -> (u32, u32, u32) {
     let gd = unsafe {
         let p = rsgx_get_global_data();
@@ -64,6 +64,7 @@
 
 #![no_std]
 #![cfg_attr(target_env = "sgx", feature(rustc_private))]
+#![cfg_attr(test, feature(test))]
 
 #![allow(incomplete_features)]
 #![feature(allocator_api)]
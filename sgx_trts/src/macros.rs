@@ -15,7 +15,7 @@
 // specific language governing permissions and limitations
 // under the License..
 
-//! Global constructor/destructor support
+//! Global constructor/destructor support and helper macros
 
 /// global_ctors_object is the base macro of implementing constructors.
 ///
@@ -76,3 +76,26 @@ macro_rules! global_dtors_object {
         }
     };
 }
+
+/// Creates a [`CString`] using interpolation of runtime expressions.
+///
+/// This takes the same arguments as `format!` and evaluates to
+/// `Result<CString, NulError>`; see [`CString::from_fmt`] for details.
+///
+/// # Examples
+///
+/// ```
+/// let c_string = cstr_format!("{}:{}", "localhost", 8080).unwrap();
+/// assert_eq!(c_string.as_bytes(), b"localhost:8080");
+///
+/// assert!(cstr_format!("{}", "a\0b").is_err());
+/// ```
+///
+/// [`CString`]: crate::c_str::CString
+/// [`CString::from_fmt`]: crate::c_str::CString::from_fmt
+#[macro_export]
+macro_rules! cstr_format {
+    ($($arg:tt)*) => {
+        $crate::c_str::CString::from_fmt(format_args!($($arg)*))
+    };
+}
@@ -24,6 +24,15 @@
 //! There are several functions and structs in this module that have a
 //! counterpart ending in `os`. Those ending in `os` will return an [`OsString`]
 //! and those without will return a [`String`].
+//!
+//! # Environment variables
+//!
+//! By default the environment variable functions read and write the host
+//! process's environment through OCALLs, so the host sees every variable the
+//! enclave sets and can change them at any time. With the `trusted_env`
+//! feature, the host environment is copied into the enclave once, on first
+//! use or when the runtime is initialized, and all later reads and writes only
+//! touch that copy.
 
 #![allow(clippy::needless_doctest_main)]
 use crate::error::Error;
@@ -31,8 +40,13 @@ use crate::ffi::{OsStr, OsString};
 use crate::fmt;
 use crate::io;
 use crate::path::{Path, PathBuf};
+use crate::sys;
 use crate::sys::os as os_imp;
 
+pub use self::verified::{import_verified, EnvVerifier};
+
+mod verified;
+
 /// Returns the current working directory as a [`PathBuf`].
 ///
 /// # Errors
@@ -130,6 +144,8 @@ pub fn vars() -> Vars {
 /// The returned iterator contains a snapshot of the process's environment
 /// variables at the time of this invocation. Modifications to environment
 /// variables afterwards will not be reflected in the returned iterator.
+/// Since the snapshot is taken up front, the iterator knows its exact
+/// length, see [`ExactSizeIterator::len`].
 ///
 /// Note that the returned iterator will not check if the environment variables
 /// are valid Unicode. If you want to panic on invalid UTF-8,
@@ -150,6 +166,28 @@ pub fn vars_os() -> VarsOs {
     VarsOs { inner: os_imp::env() }
 }
 
+/// Returns an iterator of (variable, value) pairs of OS strings, for the
+/// environment variables of the current process whose name starts with
+/// `prefix`.
+///
+/// The prefix is compared byte by byte, so it works the same whether or not
+/// the names are valid Unicode. Like [`vars_os`], the returned iterator is a
+/// snapshot taken at the time of this invocation, but only the matching
+/// variables are copied into it.
+///
+/// # Examples
+///
+/// ```
+/// use std::env;
+///
+/// for (key, value) in env::vars_os_with_prefix("ENCLAVE_") {
+///     println!("{:?}: {:?}", key, value);
+/// }
+/// ```
+pub fn vars_os_with_prefix<P: AsRef<OsStr>>(prefix: P) -> VarsOs {
+    VarsOs { inner: os_imp::env_with_prefix(prefix.as_ref()) }
+}
+
 impl Iterator for Vars {
     type Item = (String, String);
     fn next(&mut self) -> Option<(String, String)> {
@@ -160,6 +198,12 @@ impl Iterator for Vars {
     }
 }
 
+impl ExactSizeIterator for Vars {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
 impl fmt::Debug for Vars {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Vars").finish_non_exhaustive()
@@ -176,6 +220,12 @@ impl Iterator for VarsOs {
     }
 }
 
+impl ExactSizeIterator for VarsOs {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
 impl fmt::Debug for VarsOs {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("VarOs").finish_non_exhaustive()
@@ -269,6 +319,31 @@ pub enum VarError {
     NotUnicode(OsString),
 }
 
+impl VarError {
+    /// Consumes this error, returning the raw value of the variable if it was
+    /// present but not valid unicode.
+    ///
+    /// Returns [`None`] for [`VarError::NotPresent`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::env;
+    /// use std::ffi::OsStr;
+    /// use std::os::unix::ffi::OsStrExt;
+    ///
+    /// env::set_var("KEY", OsStr::from_bytes(b"fo\xffo"));
+    /// let raw = env::var("KEY").unwrap_err().into_os_string();
+    /// assert_eq!(raw.as_deref(), Some(OsStr::from_bytes(b"fo\xffo")));
+    /// ```
+    pub fn into_os_string(self) -> Option<OsString> {
+        match self {
+            VarError::NotPresent => None,
+            VarError::NotUnicode(s) => Some(s),
+        }
+    }
+}
+
 impl fmt::Display for VarError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
@@ -368,6 +443,143 @@ fn _remove_var(key: &OsStr) {
         .unwrap_or_else(|e| panic!("failed to remove environment variable `{:?}`: {}", key, e))
 }
 
+/// Removes an environment variable from the environment of the currently
+/// running process, returning its previous value.
+///
+/// Returns [`None`] if the variable was not set. The lookup and the removal
+/// happen under the same lock, so no concurrent [`set_var`] can slip in
+/// between them.
+///
+/// # Panics
+///
+/// This function may panic if `key` is empty, contains an ASCII equals sign
+/// `'='` or the NUL character `'\0'`.
+///
+/// # Examples
+///
+/// ```
+/// use std::env;
+///
+/// let key = "KEY";
+/// env::set_var(key, "VALUE");
+/// assert_eq!(env::take_var(key), Some("VALUE".into()));
+/// assert_eq!(env::take_var(key), None);
+/// ```
+pub fn take_var<K: AsRef<OsStr>>(key: K) -> Option<OsString> {
+    _take_var(key.as_ref())
+}
+
+fn _take_var(key: &OsStr) -> Option<OsString> {
+    os_imp::takeenv(key)
+        .unwrap_or_else(|e| panic!("failed to remove environment variable `{:?}`: {}", key, e))
+}
+
+/// Returns every environment variable of the current process as a list of
+/// (variable, value) pairs.
+///
+/// The list is copied under the environment lock, so it is a consistent
+/// view even while other threads change variables. Together with
+/// [`import_snapshot`] it can be used to move the environment of one
+/// enclave into another.
+///
+/// # Examples
+///
+/// ```
+/// use std::env;
+///
+/// env::set_var("KEY", "VALUE");
+/// let snapshot = env::export_snapshot();
+/// assert!(snapshot.iter().any(|(k, v)| k == "KEY" && v == "VALUE"));
+/// ```
+pub fn export_snapshot() -> Vec<(OsString, OsString)> {
+    os_imp::export_env()
+}
+
+/// Replaces the whole environment of the current process with `snapshot`.
+///
+/// Variables that are not in `snapshot` are removed. If a name appears more
+/// than once, the last value wins. The replacement happens under a single
+/// write lock, so other threads see either the old environment or the new
+/// one. Watchers registered with [`watch_var`] are notified, after the lock
+/// is released, for every variable whose value changed.
+///
+/// # Errors
+///
+/// Returns an [`io::ErrorKind::InvalidInput`] error naming the first entry
+/// whose name is empty or contains an ASCII equals sign `'='` or the NUL
+/// character `'\0'`, or whose value contains the NUL character. The
+/// environment is left untouched in that case.
+///
+/// # Examples
+///
+/// ```
+/// use std::env;
+///
+/// let snapshot = env::export_snapshot();
+/// env::set_var("KEY", "VALUE");
+/// env::import_snapshot(snapshot).unwrap();
+/// ```
+pub fn import_snapshot<I, K, V>(snapshot: I) -> io::Result<()>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: Into<OsString>,
+    V: Into<OsString>,
+{
+    let snapshot: Vec<(OsString, OsString)> =
+        snapshot.into_iter().map(|(k, v)| (k.into(), v.into())).collect();
+    os_imp::import_env(&snapshot)
+}
+
+/// Registers `f` to be called whenever the environment variable `key` is
+/// changed through [`set_var`], [`remove_var`] or [`take_var`].
+///
+/// The callback receives the new value, or [`None`] if the variable was
+/// removed. It runs on the thread that made the change, after the change is
+/// visible and with no environment lock held, so it may itself call into
+/// this module. Changes made concurrently from several threads may be
+/// reported in a different order than they were applied.
+///
+/// The watcher stays registered until the returned [`VarWatcher`] is
+/// dropped.
+///
+/// # Examples
+///
+/// ```
+/// use std::env;
+/// use std::ffi::OsStr;
+///
+/// fn on_change(value: Option<&OsStr>) {
+///     println!("LOG_LEVEL is now {:?}", value);
+/// }
+///
+/// let watcher = env::watch_var("LOG_LEVEL", on_change);
+/// env::set_var("LOG_LEVEL", "debug");
+/// drop(watcher);
+/// ```
+pub fn watch_var<K: AsRef<OsStr>>(key: K, f: fn(Option<&OsStr>)) -> VarWatcher {
+    VarWatcher { id: os_imp::watch_env(key.as_ref(), f) }
+}
+
+/// A registration created by [`watch_var`].
+///
+/// Dropping it unregisters the callback.
+#[must_use = "the callback is unregistered as soon as the watcher is dropped"]
+pub struct VarWatcher {
+    id: usize,
+}
+
+impl Drop for VarWatcher {
+    fn drop(&mut self) {
+        os_imp::unwatch_env(self.id);
+    }
+}
+
+impl fmt::Debug for VarWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VarWatcher").finish_non_exhaustive()
+    }
+}
+
 /// An iterator that splits an environment variable into paths according to
 /// platform-specific conventions.
 ///
@@ -497,7 +709,37 @@ where
     I: IntoIterator<Item = T>,
     T: AsRef<OsStr>,
 {
-    os_imp::join_paths(paths.into_iter()).map_err(|e| JoinPathsError { inner: e })
+    os_imp::join_paths(paths.into_iter(), false).map_err(|e| JoinPathsError { inner: e })
+}
+
+/// Joins a collection of [`Path`]s like [`join_paths`], but also rejects
+/// empty paths.
+///
+/// An empty segment in `PATH` (two adjacent separators, or a leading or
+/// trailing one) is treated as the current directory by many programs,
+/// which is rarely what an enclave intends when building a search path.
+///
+/// # Errors
+///
+/// Returns an [`Err`] if one of the input [`Path`]s is empty or contains an
+/// invalid character for constructing the `PATH` variable.
+///
+/// # Examples
+///
+/// ```
+/// use std::env;
+/// use std::path::Path;
+///
+/// let paths = [Path::new("/bin"), Path::new(""), Path::new("/usr/bin")];
+/// assert!(env::join_paths(paths.iter()).is_ok());
+/// assert!(env::join_paths_strict(paths.iter()).is_err());
+/// ```
+pub fn join_paths_strict<I, T>(paths: I) -> Result<OsString, JoinPathsError>
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<OsStr>,
+{
+    os_imp::join_paths(paths.into_iter(), true).map_err(|e| JoinPathsError { inner: e })
 }
 
 impl fmt::Display for JoinPathsError {
@@ -548,6 +790,11 @@ pub fn home_dir() -> Option<PathBuf> {
     os_imp::home_dir()
 }
 
+// Checks the home directory in a passwd entry returned by the host. Only
+// public for the unit tests.
+#[doc(hidden)]
+pub use crate::sys::os::passwd_dir;
+
 /// Returns the path of a temporary directory.
 ///
 /// The temporary directory may be shared among users, or between processes
@@ -560,9 +807,8 @@ pub fn home_dir() -> Option<PathBuf> {
 /// # Unix
 ///
 /// Returns the value of the `TMPDIR` environment variable if it is
-/// set, otherwise for non-Android it returns `/tmp`. If Android, since there
-/// is no global temporary folder (it is usually allocated per-app), it returns
-/// `/data/local/tmp`.
+/// set and not empty, otherwise it returns the default set by
+/// [`set_temp_dir_default`], or `/tmp` if no default has been set.
 ///
 /// # Windows
 ///
@@ -586,6 +832,24 @@ pub fn temp_dir() -> PathBuf {
     os_imp::temp_dir()
 }
 
+/// Sets the directory returned by [`temp_dir`] when the `TMPDIR` environment
+/// variable is unset or empty.
+///
+/// This lets an enclave pick its scratch location without relying on the
+/// host-provided environment. The initial default is `/tmp`.
+///
+/// # Examples
+///
+/// ```
+/// use std::env;
+/// use std::path::PathBuf;
+///
+/// env::set_temp_dir_default(PathBuf::from("/var/tmp/enclave"));
+/// ```
+pub fn set_temp_dir_default(dir: PathBuf) {
+    os_imp::set_temp_dir_default(dir)
+}
+
 /// Returns the full filesystem path of the current running executable.
 ///
 /// # Platform-specific behavior
@@ -656,6 +920,151 @@ pub fn current_exe() -> io::Result<PathBuf> {
     os_imp::current_exe()
 }
 
+/// An iterator over the arguments of a process, yielding a [`String`] value for
+/// each argument.
+///
+/// This struct is created by [`env::args()`]. See its documentation
+/// for more.
+///
+/// The first element is traditionally the path of the executable, but it can be
+/// set to arbitrary text, and might not even exist. This means this property
+/// should not be relied upon for security purposes.
+///
+/// [`env::args()`]: args
+pub struct Args {
+    inner: ArgsOs,
+}
+
+/// An iterator over the arguments of a process, yielding an [`OsString`] value
+/// for each argument.
+///
+/// This struct is created by [`env::args_os()`]. See its documentation
+/// for more.
+///
+/// The first element is traditionally the path of the executable, but it can be
+/// set to arbitrary text, and might not even exist. This means this property
+/// should not be relied upon for security purposes.
+///
+/// [`env::args_os()`]: args_os
+pub struct ArgsOs {
+    inner: sys::args::Args,
+}
+
+/// Returns the arguments that this program was started with (normally passed
+/// via the command line).
+///
+/// An enclave has no command line of its own. The arguments are the ones the
+/// host passed in through the `t_global_args_ecall` ECALL, typically with
+/// `SgxEnclave::set_args` right after the enclave was created. Only the first
+/// call of that ECALL has an effect, so the arguments cannot change once the
+/// enclave has read them, but they come from the untrusted host and must be
+/// validated like any other input. If the host never set them, the iterator
+/// is empty.
+///
+/// # Panics
+///
+/// The returned iterator will panic during iteration if any argument to the
+/// process is not valid Unicode. If this is not desired,
+/// use the [`args_os`] function instead.
+///
+/// # Examples
+///
+/// ```
+/// use std::env;
+///
+/// // Prints each argument on a separate line
+/// for argument in env::args() {
+///     println!("{}", argument);
+/// }
+/// ```
+pub fn args() -> Args {
+    Args { inner: args_os() }
+}
+
+/// Returns the arguments that this program was started with (normally passed
+/// via the command line).
+///
+/// See [`args`] for where the arguments come from. Unlike it, this function
+/// does not panic on arguments that are not valid Unicode.
+///
+/// # Examples
+///
+/// ```
+/// use std::env;
+///
+/// // Prints each argument on a separate line
+/// for argument in env::args_os() {
+///     println!("{:?}", argument);
+/// }
+/// ```
+pub fn args_os() -> ArgsOs {
+    ArgsOs { inner: sys::args::args() }
+}
+
+impl !Send for Args {}
+
+impl !Sync for Args {}
+
+impl Iterator for Args {
+    type Item = String;
+    fn next(&mut self) -> Option<String> {
+        self.inner.next().map(|s| s.into_string().unwrap())
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Args {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl DoubleEndedIterator for Args {
+    fn next_back(&mut self) -> Option<String> {
+        self.inner.next_back().map(|s| s.into_string().unwrap())
+    }
+}
+
+impl fmt::Debug for Args {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Args").field("inner", &self.inner.inner).finish()
+    }
+}
+
+impl !Send for ArgsOs {}
+
+impl !Sync for ArgsOs {}
+
+impl Iterator for ArgsOs {
+    type Item = OsString;
+    fn next(&mut self) -> Option<OsString> {
+        self.inner.next()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl ExactSizeIterator for ArgsOs {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl DoubleEndedIterator for ArgsOs {
+    fn next_back(&mut self) -> Option<OsString> {
+        self.inner.next_back()
+    }
+}
+
+impl fmt::Debug for ArgsOs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArgsOs").field("inner", &self.inner).finish()
+    }
+}
+
 /// Constants associated with the current target
 pub mod consts {
     use crate::sys::env::os;
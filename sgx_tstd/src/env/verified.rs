@@ -0,0 +1,188 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use crate::ffi::{CString, OsStr, OsString};
+use crate::io;
+use crate::os::unix::ffi::{OsStrExt, OsStringExt};
+use crate::ptr;
+use crate::sys::os as os_imp;
+use sgx_types::*;
+
+// Prefixed to the encoded variables, so that a signature made for this
+// purpose cannot be replayed as a signature over anything else.
+const DOMAIN: &[u8] = b"sgx_tstd env import v1\0";
+
+/// How [`import_verified`] authenticates the variables it reads.
+///
+/// Both forms cover the same byte string, built from the allowlist in order.
+/// For each name it holds the name's length as a 4-byte little-endian
+/// integer followed by the name, then either a single `0` byte if the host
+/// does not set the variable, or a `1` byte, the value's length as a 4-byte
+/// little-endian integer and the value. The whole is prefixed with the 23
+/// bytes `b"sgx_tstd env import v1\0"`.
+///
+/// Because unset variables are covered too, the host can neither add nor
+/// remove a variable without the check failing.
+#[derive(Clone, Copy)]
+pub enum EnvVerifier<'a> {
+    /// The SHA-256 hash of the encoded variables, typically pinned when the
+    /// enclave is built.
+    Sha256(&'a sgx_sha256_hash_t),
+    /// An ECDSA signature over the encoded variables, made with the P-256
+    /// key of the operator and SHA-256.
+    EcdsaP256 {
+        /// The operator's public key.
+        public_key: &'a sgx_ec256_public_t,
+        /// The signature supplied alongside the configuration.
+        signature: &'a sgx_ec256_signature_t,
+    },
+}
+
+/// Imports the variables named in `allowlist` from the host environment,
+/// after checking them with `verifier`.
+///
+/// Only the named variables are read from the host. If they match, each is
+/// set to the host's value, or removed if the host does not set it, and is
+/// then visible through [`var`](super::var) and the other functions of this
+/// module. If they do not match, nothing is changed.
+///
+/// Without the `trusted_env` feature the imported variables stay in the
+/// host environment, where the host can change them again later. Enable it
+/// so that the verified values are the ones the enclave keeps.
+///
+/// # Errors
+///
+/// Returns an [`io::ErrorKind::InvalidInput`] error if a name in
+/// `allowlist` is empty, appears twice, or contains an ASCII equals sign
+/// `'='` or the NUL character `'\0'`, and an [`io::ErrorKind::InvalidData`]
+/// error if the variables do not match `verifier`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::env::{self, EnvVerifier};
+///
+/// // Computed over the expected configuration when the enclave is built.
+/// const CONFIG_HASH: [u8; 32] = [0; 32];
+///
+/// env::import_verified(&["LOG_LEVEL", "DB_URL"], EnvVerifier::Sha256(&CONFIG_HASH))
+///     .expect("host configuration was tampered with");
+/// let db = env::var("DB_URL").unwrap();
+/// ```
+pub fn import_verified<K: AsRef<OsStr>>(
+    allowlist: &[K],
+    verifier: EnvVerifier<'_>,
+) -> io::Result<()> {
+    let mut names: Vec<CString> = Vec::with_capacity(allowlist.len());
+    for k in allowlist {
+        let k = k.as_ref().as_bytes();
+        if k.is_empty() || k.contains(&b'=') || k.contains(&0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid environment variable name {:?}", OsStr::from_bytes(k)),
+            ));
+        }
+        if names.iter().any(|name| name.as_bytes() == k) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("environment variable {:?} is listed twice", OsStr::from_bytes(k)),
+            ));
+        }
+        names.push(CString::new(k)?);
+    }
+
+    let values = os_imp::host_env(&names);
+    let message = encode(&names, &values);
+    if !verify(&message, verifier)? {
+        return Err(io::Error::new_const(
+            io::ErrorKind::InvalidData,
+            &"environment variables do not match the expected configuration",
+        ));
+    }
+
+    let mut vars = Vec::with_capacity(names.len());
+    for (k, v) in names.into_iter().zip(values) {
+        // Values read back through `CStr` cannot contain a NUL byte.
+        let v = match v {
+            Some(v) => Some(CString::new(v.into_vec())?),
+            None => None,
+        };
+        vars.push((k, v));
+    }
+    os_imp::update_env(&vars)
+}
+
+fn encode(names: &[CString], values: &[Option<OsString>]) -> Vec<u8> {
+    let mut out = DOMAIN.to_vec();
+    for (k, v) in names.iter().zip(values) {
+        let k = k.as_bytes();
+        out.extend_from_slice(&(k.len() as u32).to_le_bytes());
+        out.extend_from_slice(k);
+        match v {
+            None => out.push(0),
+            Some(v) => {
+                let v = v.as_bytes();
+                out.push(1);
+                out.extend_from_slice(&(v.len() as u32).to_le_bytes());
+                out.extend_from_slice(v);
+            }
+        }
+    }
+    out
+}
+
+fn verify(message: &[u8], verifier: EnvVerifier<'_>) -> io::Result<bool> {
+    if message.len() > u32::MAX as usize {
+        return Ok(false);
+    }
+    match verifier {
+        EnvVerifier::Sha256(expected) => {
+            let mut hash = sgx_sha256_hash_t::default();
+            let status =
+                unsafe { sgx_sha256_msg(message.as_ptr(), message.len() as u32, &mut hash) };
+            if status != sgx_status_t::SGX_SUCCESS {
+                return Err(io::Error::from_sgx_error(status));
+            }
+            // The hash is public, so there is nothing to hide in the timing
+            // of the comparison.
+            Ok(&hash == expected)
+        }
+        EnvVerifier::EcdsaP256 { public_key, signature } => {
+            let mut handle: sgx_ecc_state_handle_t = ptr::null_mut();
+            let status = unsafe { sgx_ecc256_open_context(&mut handle) };
+            if status != sgx_status_t::SGX_SUCCESS {
+                return Err(io::Error::from_sgx_error(status));
+            }
+            let mut result = sgx_generic_ecresult_t::SGX_EC_INVALID_SIGNATURE as u8;
+            let status = unsafe {
+                sgx_ecdsa_verify(
+                    message.as_ptr(),
+                    message.len() as u32,
+                    public_key,
+                    signature,
+                    &mut result,
+                    handle,
+                )
+            };
+            unsafe { sgx_ecc256_close_context(handle) };
+            if status != sgx_status_t::SGX_SUCCESS {
+                return Err(io::Error::from_sgx_error(status));
+            }
+            Ok(result == sgx_generic_ecresult_t::SGX_EC_VALID as u8)
+        }
+    }
+}
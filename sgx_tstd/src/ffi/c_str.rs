@@ -17,8 +17,11 @@
 
 pub use sgx_trts::c_str::*;
 
+use crate::collections::BTreeMap;
 use crate::error::Error;
+use crate::fmt;
 use crate::io;
+use crate::sync::{Arc, PoisonError, SgxMutex};
 
 impl Error for NulError {
     fn description(&self) -> &str {
@@ -41,6 +44,12 @@ impl Error for FromBytesWithNulError {
 
 impl Error for FromVecWithNulError {}
 
+impl Error for DecodeError {
+    fn description(&self) -> &str {
+        self.__description()
+    }
+}
+
 impl Error for IntoStringError {
     fn description(&self) -> &str {
         self.__description()
@@ -50,3 +59,118 @@ impl Error for IntoStringError {
         Some(self.__source())
     }
 }
+
+/// A thread-safe, bounded cache of C strings keyed by their contents.
+///
+/// Converting the same Rust string to a [`CString`] for every call into a C
+/// library allocates each time. An interner hands out shared [`Arc<CStr>`]
+/// handles instead, so a key is only converted once while it stays cached.
+///
+/// At most `capacity` strings are kept. When a new key would exceed that,
+/// the least recently interned or looked-up key is evicted. Evicting a key
+/// never invalidates handles that were already returned for it; interning
+/// it again simply allocates a new one.
+///
+/// # Examples
+///
+/// ```
+/// use std::ffi::CStrInterner;
+/// use std::sync::Arc;
+///
+/// let interner = CStrInterner::new(64);
+/// let a = interner.intern("log_level").expect("key contains a nul byte");
+/// let b = interner.intern("log_level").expect("key contains a nul byte");
+/// assert!(Arc::ptr_eq(&a, &b));
+/// assert_eq!(a.to_bytes(), b"log_level");
+/// ```
+pub struct CStrInterner {
+    capacity: usize,
+    entries: SgxMutex<InternerEntries>,
+}
+
+struct InternerEntries {
+    // Each entry remembers the tick of its last use, for LRU eviction.
+    map: BTreeMap<Box<[u8]>, (Arc<CStr>, u64)>,
+    tick: u64,
+}
+
+impl CStrInterner {
+    /// Creates an empty interner that keeps at most `capacity` strings.
+    ///
+    /// With a capacity of zero nothing is cached and every call to
+    /// [`intern`] allocates.
+    ///
+    /// [`intern`]: CStrInterner::intern
+    pub fn new(capacity: usize) -> CStrInterner {
+        CStrInterner {
+            capacity,
+            entries: SgxMutex::new(InternerEntries { map: BTreeMap::new(), tick: 0 }),
+        }
+    }
+
+    /// Returns a shared C string with the contents of `key`.
+    ///
+    /// Repeated calls with equal keys return handles to the same allocation
+    /// for as long as the key stays cached.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`NulError`] if `key` contains a nul byte, like
+    /// [`CString::new`].
+    pub fn intern<T: AsRef<[u8]>>(&self, key: T) -> Result<Arc<CStr>, NulError> {
+        let key = key.as_ref();
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.tick += 1;
+        let tick = entries.tick;
+        if let Some((cstr, last_used)) = entries.map.get_mut(key) {
+            *last_used = tick;
+            return Ok(cstr.clone());
+        }
+
+        let cstr: Arc<CStr> = CString::new(key)?.into();
+        if self.capacity == 0 {
+            return Ok(cstr);
+        }
+        if entries.map.len() >= self.capacity {
+            let oldest = entries
+                .map
+                .iter()
+                .min_by_key(|(_, entry)| entry.1)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.map.remove(&oldest);
+            }
+        }
+        entries.map.insert(key.into(), (cstr.clone(), tick));
+        Ok(cstr)
+    }
+
+    /// Returns the maximum number of strings kept by this interner.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of strings currently cached.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner).map.len()
+    }
+
+    /// Returns `true` if no strings are cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Evicts every cached string.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner).map.clear();
+    }
+}
+
+impl fmt::Debug for CStrInterner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CStrInterner")
+            .field("capacity", &self.capacity)
+            .field("len", &self.len())
+            .finish()
+    }
+}
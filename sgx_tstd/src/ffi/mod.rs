@@ -160,7 +160,7 @@
 
 pub use self::c_str::FromBytesWithNulError;
 pub use self::c_str::FromVecWithNulError;
-pub use self::c_str::{CStr, CString, IntoStringError, NulError};
+pub use self::c_str::{CStr, CStrInterner, CString, DecodeError, IntoStringError, NulError};
 pub use self::os_str::{OsStr, OsString};
 
 pub use core::ffi::c_void;
@@ -28,6 +28,10 @@ use crate::time::SystemTime;
 #[cfg(not(feature = "untrusted_fs"))]
 use crate::untrusted::path::PathEx;
 
+pub use self::tempfile::{tempfile, tempfile_in, TempDir};
+
+mod tempfile;
+
 /// A reference to an open file on the filesystem.
 ///
 /// An instance of a `File` can be read and/or written depending on what options
@@ -478,6 +482,95 @@ impl File {
         self.inner.truncate(size)
     }
 
+    /// Acquires a shared advisory lock on the file, blocking until it can be
+    /// taken.
+    ///
+    /// Any number of handles may hold a shared lock at once, but not while
+    /// another holds an exclusive one. The locks are `flock(2)` locks held
+    /// by the host on behalf of the open file description, so they are
+    /// seen by every process and enclave on the machine that uses them,
+    /// including other enclave instances sharing a sealed state directory.
+    /// They are only advisory: a host or peer that does not lock the file
+    /// can still modify it.
+    ///
+    /// The lock is released by [`unlock`] or when every handle to the open
+    /// file description is closed. Calling this on a handle that already
+    /// holds an exclusive lock converts it to a shared one.
+    ///
+    /// [`unlock`]: File::unlock
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use std::io::prelude::*;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let mut f = File::open("state/manifest")?;
+    ///     f.lock_shared()?;
+    ///     let mut manifest = String::new();
+    ///     f.read_to_string(&mut manifest)?;
+    ///     f.unlock()?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn lock_shared(&self) -> io::Result<()> {
+        self.inner.lock_shared()
+    }
+
+    /// Acquires an exclusive advisory lock on the file, blocking until it can
+    /// be taken.
+    ///
+    /// No other handle may hold a lock on the file while an exclusive lock
+    /// is held. See [`lock_shared`] for how these locks behave.
+    ///
+    /// [`lock_shared`]: File::lock_shared
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs::OpenOptions;
+    /// use std::io::prelude::*;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let mut f = OpenOptions::new().append(true).open("state/journal")?;
+    ///     f.lock_exclusive()?;
+    ///     f.write_all(b"entry\n")?;
+    ///     f.sync_data()?;
+    ///     f.unlock()?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn lock_exclusive(&self) -> io::Result<()> {
+        self.inner.lock_exclusive()
+    }
+
+    /// Tries to acquire a shared advisory lock on the file without blocking.
+    ///
+    /// Returns `Ok(false)` if another handle holds an exclusive lock. See
+    /// [`lock_shared`] for how these locks behave.
+    ///
+    /// [`lock_shared`]: File::lock_shared
+    pub fn try_lock_shared(&self) -> io::Result<bool> {
+        self.inner.try_lock_shared()
+    }
+
+    /// Tries to acquire an exclusive advisory lock on the file without
+    /// blocking.
+    ///
+    /// Returns `Ok(false)` if another handle holds any lock on the file. See
+    /// [`lock_shared`] for how these locks behave.
+    ///
+    /// [`lock_shared`]: File::lock_shared
+    pub fn try_lock_exclusive(&self) -> io::Result<bool> {
+        self.inner.try_lock_exclusive()
+    }
+
+    /// Releases the lock held through this handle, if any.
+    pub fn unlock(&self) -> io::Result<()> {
+        self.inner.unlock()
+    }
+
     /// Queries metadata about the underlying file.
     ///
     /// # Examples
@@ -1772,6 +1865,8 @@ pub fn soft_link<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> io::Re
 ///
 /// * `path` is not a symbolic link.
 /// * `path` does not exist.
+/// * The host returns a link target that is empty or contains a NUL byte,
+///   in which case the error is of kind [`io::ErrorKind::InvalidData`].
 ///
 /// # Examples
 ///
@@ -1812,6 +1907,13 @@ pub fn read_link<P: AsRef<Path>>(path: P) -> io::Result<PathBuf> {
 ///
 /// * `path` does not exist.
 /// * A non-final component in path is not a directory.
+/// * The host returns a path that is not absolute or still contains `.`,
+///   `..` or empty components, in which case the error is of kind
+///   [`io::ErrorKind::InvalidData`].
+///
+/// The path is resolved by the host. The checks above make sure the result
+/// is in canonical form, so that access policies inside the enclave can be
+/// applied to it, but not that it names the file the host will open later.
 ///
 /// # Examples
 ///
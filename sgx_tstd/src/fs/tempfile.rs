@@ -0,0 +1,176 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use super::{remove_dir_all, remove_file, DirBuilder, File, OpenOptions};
+use crate::env;
+use crate::ffi::OsString;
+use crate::fmt;
+use crate::io;
+use crate::mem;
+use crate::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use crate::path::{Path, PathBuf};
+use crate::sys::rand;
+
+// 128 random bits make a collision with an existing or attacker-chosen name
+// practically impossible; the retries only cover the astronomically rare
+// case, and a host that answers `EEXIST` to everything.
+const NUM_RAND_BYTES: usize = 16;
+const NUM_RETRIES: u32 = 8;
+
+/// Creates a new temporary file in [`env::temp_dir`].
+///
+/// The file is created with a name drawn from the enclave's hardware random
+/// number generator, with `O_EXCL` so that an existing file or symlink is
+/// never opened in its place, and with permissions `0o600`. It is then
+/// unlinked straight away, so it is deleted as soon as the returned handle
+/// is closed, even if the enclave is torn down.
+///
+/// The contents are stored in plaintext on the host. Use [`sgxfs`] for
+/// scratch data that must stay confidential.
+///
+/// [`sgxfs`]: crate::sgxfs
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::fs;
+/// use std::io::prelude::*;
+///
+/// fn main() -> std::io::Result<()> {
+///     let mut scratch = fs::tempfile()?;
+///     scratch.write_all(b"intermediate results")?;
+///     Ok(())
+/// }
+/// ```
+pub fn tempfile() -> io::Result<File> {
+    tempfile_in(env::temp_dir())
+}
+
+/// Creates a new temporary file in `dir`.
+///
+/// See [`tempfile`] for how the file is created and deleted.
+pub fn tempfile_in<P: AsRef<Path>>(dir: P) -> io::Result<File> {
+    create_unique(dir.as_ref(), |path| {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(path)?;
+        remove_file(path)?;
+        Ok(file)
+    })
+}
+
+/// A directory in the file system that is removed, with its contents, when
+/// dropped.
+///
+/// The directory is created with a name drawn from the enclave's hardware
+/// random number generator and with permissions `0o700`, so that other
+/// users on the host cannot predict or enter it.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::fs::{self, TempDir};
+///
+/// fn main() -> std::io::Result<()> {
+///     let dir = TempDir::new()?;
+///     fs::write(dir.path().join("blob"), b"scratch")?;
+///     dir.close()?;
+///     Ok(())
+/// }
+/// ```
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Creates a new temporary directory in [`env::temp_dir`].
+    pub fn new() -> io::Result<TempDir> {
+        TempDir::new_in(env::temp_dir())
+    }
+
+    /// Creates a new temporary directory in `dir`.
+    pub fn new_in<P: AsRef<Path>>(dir: P) -> io::Result<TempDir> {
+        create_unique(dir.as_ref(), |path| {
+            DirBuilder::new().mode(0o700).create(path)?;
+            Ok(TempDir { path: path.to_path_buf() })
+        })
+    }
+
+    /// Returns the path of the directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Keeps the directory, returning its path.
+    pub fn into_path(mut self) -> PathBuf {
+        mem::take(&mut self.path)
+    }
+
+    /// Removes the directory and its contents, reporting any error that
+    /// dropping the `TempDir` would ignore.
+    pub fn close(self) -> io::Result<()> {
+        remove_dir_all(self.into_path())
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if !self.path.as_os_str().is_empty() {
+            let _ = remove_dir_all(&self.path);
+        }
+    }
+}
+
+impl fmt::Debug for TempDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TempDir").field("path", &self.path).finish()
+    }
+}
+
+fn create_unique<T, F>(dir: &Path, mut create: F) -> io::Result<T>
+where
+    F: FnMut(&Path) -> io::Result<T>,
+{
+    for _ in 0..NUM_RETRIES {
+        let path = dir.join(random_name());
+        match create(&path) {
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            result => return result,
+        }
+    }
+    Err(io::Error::new_const(
+        io::ErrorKind::AlreadyExists,
+        &"too many temporary files exist",
+    ))
+}
+
+fn random_name() -> OsString {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+
+    let mut bytes = [0u8; NUM_RAND_BYTES];
+    rand::fill_bytes(&mut bytes);
+    let mut name = String::with_capacity(4 + 2 * NUM_RAND_BYTES);
+    name.push_str(".tmp");
+    for b in &bytes {
+        name.push(HEX[(b >> 4) as usize] as char);
+        name.push(HEX[(b & 0xf) as usize] as char);
+    }
+    OsString::from(name)
+}
@@ -2513,7 +2513,22 @@ impl<T: Read> Read for Take<T> {
         // Pass in a reservation_size closure that respects the current value
         // of limit for each read. If we hit the read limit, this prevents the
         // final zero-byte read from allocating again.
-        read_to_end_with_reservation(self, buf, |self_| cmp::min(self_.limit, 32) as usize)
+        read_to_end_with_reservation(self, buf, |self_| self_.inner.take_reservation(self_.limit))
+    }
+}
+
+/// How much `Take::read_to_end` reserves before each read of the inner reader.
+///
+/// Readers whose every `read` leaves the enclave specialize this so that a
+/// bounded read needs as few OCALLs as possible.
+pub(crate) trait TakeReservation {
+    fn take_reservation(&self, limit: u64) -> usize;
+}
+
+impl<T> TakeReservation for T {
+    #[inline]
+    default fn take_reservation(&self, limit: u64) -> usize {
+        cmp::min(limit, 32) as usize
     }
 }
 
@@ -152,6 +152,7 @@ pub use sgx_types::cfg_if;
 #[macro_use]
 extern crate sgx_trts;
 pub use sgx_trts::{
+    cstr_format,
     global_ctors_object,
     global_dtors_object,
     is_x86_feature_detected,
@@ -231,6 +232,7 @@ pub mod num;
 pub mod os;
 pub mod panic;
 pub mod path;
+pub mod process;
 pub mod sync;
 pub mod time;
 pub mod enclave;
@@ -238,15 +240,7 @@ pub mod untrusted;
 
 pub mod lazy;
 
-pub mod task {
-    //! Types and Traits for working with asynchronous tasks.
-
-    #[doc(inline)]
-    pub use core::task::*;
-
-    #[doc(inline)]
-    pub use alloc_crate::task::*;
-}
+pub mod task;
 
 // Platform-abstraction modules
 #[macro_use]
@@ -16,8 +16,6 @@
 // under the License..
 
 use crate::cmp::Ordering;
-#[cfg(feature = "net")]
-use crate::convert::TryInto;
 use crate::fmt;
 use crate::hash;
 use crate::io::{self, Write};
@@ -26,8 +24,8 @@ use crate::mem;
 use crate::net::{htons, ntohs, IpAddr, Ipv4Addr, Ipv6Addr};
 use crate::option;
 use crate::slice;
-#[cfg(feature = "net")]
-use crate::sys_common::net::LookupHost;
+#[cfg(feature = "test_resolver")]
+use crate::sync::atomic::{self, AtomicPtr};
 use crate::sys_common::{AsInner, FromInner, IntoInner};
 use crate::vec;
 
@@ -168,14 +166,24 @@ impl SocketAddr {
 
     /// Changes the IP address associated with this socket address.
     ///
+    /// If `new_ip` is of the same family as the current address, only the IP
+    /// is replaced and, for IPv6, the flow info and scope ID are kept. If the
+    /// families differ, the address switches to the family of `new_ip`: the
+    /// port is kept, while the IPv6 flow info and scope ID are dropped when
+    /// switching to IPv4 and set to 0 when switching to IPv6.
+    ///
     /// # Examples
     ///
     /// ```
-    /// use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    /// use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
     ///
     /// let mut socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
     /// socket.set_ip(IpAddr::V4(Ipv4Addr::new(10, 10, 0, 1)));
     /// assert_eq!(socket.ip(), IpAddr::V4(Ipv4Addr::new(10, 10, 0, 1)));
+    ///
+    /// socket.set_ip(IpAddr::V6(Ipv6Addr::LOCALHOST));
+    /// assert!(socket.is_ipv6());
+    /// assert_eq!(socket.port(), 8080);
     /// ```
     pub fn set_ip(&mut self, new_ip: IpAddr) {
         // `match (*self, new_ip)` would have us mutate a copy of self only to throw it away.
@@ -735,6 +743,76 @@ impl hash::Hash for SocketAddrV6 {
     }
 }
 
+/// A host and a port, as written in `host:port` form, that has not been
+/// resolved.
+///
+/// The host is kept as a string: it may be a host name, an IPv4 address, or
+/// an IPv6 address, which must be written in brackets. For IPv6 hosts the
+/// brackets are not part of [`host`].
+///
+/// [`host`]: HostPort::host
+///
+/// # Examples
+///
+/// ```
+/// use std::net::HostPort;
+///
+/// let hp: HostPort = "example.com:443".parse().unwrap();
+/// assert_eq!(hp.host(), "example.com");
+/// assert_eq!(hp.port(), 443);
+///
+/// let hp: HostPort = "[::1]:8080".parse().unwrap();
+/// assert_eq!(hp.host(), "::1");
+/// assert_eq!(hp.to_string(), "[::1]:8080");
+///
+/// assert!("example.com".parse::<HostPort>().is_err());
+/// ```
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct HostPort {
+    host: String,
+    port: u16,
+}
+
+impl HostPort {
+    /// Creates a new `HostPort` from a host and a port.
+    ///
+    /// The host is not checked or resolved.
+    pub fn new(host: String, port: u16) -> HostPort {
+        HostPort { host, port }
+    }
+
+    /// Returns the host, without brackets if it is an IPv6 address.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Returns the port.
+    pub const fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Consumes the `HostPort`, returning the host and the port.
+    pub fn into_parts(self) -> (String, u16) {
+        (self.host, self.port)
+    }
+}
+
+impl From<HostPort> for (String, u16) {
+    fn from(hp: HostPort) -> (String, u16) {
+        hp.into_parts()
+    }
+}
+
+impl fmt::Display for HostPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
 /// A trait for objects which can be converted or resolved to one or more
 /// [`SocketAddr`] values.
 ///
@@ -755,6 +833,12 @@ impl hash::Hash for SocketAddrV6 {
 ///    [`SocketAddr`] as expected by its [`FromStr`] implementation or a string like
 ///    `<host_name>:<port>` pair where `<port>` is a [`u16`] value.
 ///
+/// Host names are resolved with the `getaddrinfo` OCALL, or with the resolver
+/// installed by [`set_resolver`], and answers are cached in the enclave for
+/// as long as their TTL allows.
+///
+/// [`set_resolver`]: crate::net::set_resolver
+///
 /// This trait allows constructing network objects like [`TcpStream`] or
 /// [`UdpSocket`] easily with values of various types for the bind/connection
 /// address. It is needed because sometimes one type is more appropriate than
@@ -907,17 +991,44 @@ impl ToSocketAddrs for (Ipv6Addr, u16) {
 }
 
 #[cfg(feature = "net")]
-#[allow(clippy::unnecessary_wraps)]
-#[allow(clippy::needless_collect)]
-fn resolve_socket_addr(lh: LookupHost) -> io::Result<vec::IntoIter<SocketAddr>> {
-    let p = lh.port();
-    let v: Vec<_> = lh
-        .map(|mut a| {
-            a.set_port(p);
-            a
-        })
-        .collect();
-    Ok(v.into_iter())
+#[cfg(feature = "test_resolver")]
+static TEST_RESOLVER: AtomicPtr<()> = AtomicPtr::new(crate::ptr::null_mut());
+
+/// Installs a resolver that host names are looked up with before the host
+/// resolver is asked, replacing any previously installed one.
+///
+/// The resolver receives the `"host:port"` string being resolved by
+/// [`ToSocketAddrs`] and returns the addresses it maps to, so that tests can
+/// resolve names such as `"broker:9092"` to a loopback listener without a
+/// `getaddrinfo` OCALL. Strings that already are socket addresses are never
+/// passed to it. Returning an error with kind
+/// [`io::ErrorKind::NotFound`] falls through to the regular lookup.
+///
+/// This is only available with the `test_resolver` feature, which should not
+/// be enabled in production builds.
+#[cfg(feature = "test_resolver")]
+pub fn set_test_resolver(resolver: fn(&str) -> io::Result<Vec<SocketAddr>>) {
+    TEST_RESOLVER.store(resolver as *mut (), atomic::Ordering::SeqCst);
+}
+
+/// Removes the resolver installed with [`set_test_resolver`], returning it.
+#[cfg(feature = "test_resolver")]
+pub fn take_test_resolver() -> Option<fn(&str) -> io::Result<Vec<SocketAddr>>> {
+    let resolver = TEST_RESOLVER.swap(crate::ptr::null_mut(), atomic::Ordering::SeqCst);
+    if resolver.is_null() { None } else { Some(unsafe { mem::transmute(resolver) }) }
+}
+
+#[cfg(feature = "test_resolver")]
+fn test_resolve(host_port: &str) -> Option<io::Result<vec::IntoIter<SocketAddr>>> {
+    let resolver = TEST_RESOLVER.load(atomic::Ordering::SeqCst);
+    if resolver.is_null() {
+        return None;
+    }
+    let resolver: fn(&str) -> io::Result<Vec<SocketAddr>> = unsafe { mem::transmute(resolver) };
+    match resolver(host_port) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => None,
+        r => Some(r.map(Vec::into_iter)),
+    }
 }
 
 impl ToSocketAddrs for (&str, u16) {
@@ -935,10 +1046,15 @@ impl ToSocketAddrs for (&str, u16) {
             return Ok(vec![SocketAddr::V6(addr)].into_iter());
         }
 
+        #[cfg(feature = "test_resolver")]
+        if let Some(r) = test_resolve(&format!("{}:{}", host, port)) {
+            return r;
+        }
+
         #[cfg(not(feature = "net"))]
         let r = Err(io::Error::new_const(io::ErrorKind::InvalidInput, &"invalid socket address"));
         #[cfg(feature = "net")]
-        let r = resolve_socket_addr((host, port).try_into()?);
+        let r = super::resolver::resolve_host(host, port);
         r
     }
 }
@@ -950,6 +1066,13 @@ impl ToSocketAddrs for (String, u16) {
     }
 }
 
+impl ToSocketAddrs for HostPort {
+    type Iter = vec::IntoIter<SocketAddr>;
+    fn to_socket_addrs(&self) -> io::Result<vec::IntoIter<SocketAddr>> {
+        (self.host(), self.port).to_socket_addrs()
+    }
+}
+
 // accepts strings like 'localhost:12345'
 impl ToSocketAddrs for str {
     type Iter = vec::IntoIter<SocketAddr>;
@@ -959,10 +1082,24 @@ impl ToSocketAddrs for str {
             return Ok(vec![addr].into_iter());
         }
 
+        #[cfg(feature = "test_resolver")]
+        if let Some(r) = test_resolve(self) {
+            return r;
+        }
+
         #[cfg(not(feature = "net"))]
         let r = Err(io::Error::new_const(io::ErrorKind::InvalidInput, &"invalid socket address"));
         #[cfg(feature = "net")]
-        let r = resolve_socket_addr(self.try_into()?);
+        let r = {
+            // split the string by ':' and convert the second part to u16
+            let (host, port_str) = self.rsplit_once(':').ok_or_else(|| {
+                io::Error::new_const(io::ErrorKind::InvalidInput, &"invalid socket address")
+            })?;
+            let port: u16 = port_str.parse().map_err(|_| {
+                io::Error::new_const(io::ErrorKind::InvalidInput, &"invalid port value")
+            })?;
+            super::resolver::resolve_host(host, port)
+        };
         r
     }
 }
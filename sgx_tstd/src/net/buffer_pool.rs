@@ -0,0 +1,166 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use crate::fmt;
+use crate::io;
+use crate::mem;
+use crate::ops::{Deref, DerefMut};
+use crate::sync::{PoisonError, SgxMutex};
+use crate::vec::Vec;
+
+/// A bounded pool of fixed-size byte buffers.
+///
+/// Taking a buffer with [`get`] reuses one that was returned earlier when
+/// possible, so a server that reads one request at a time does not allocate
+/// on the enclave heap for every read. A [`PooledBuf`] goes back to the pool
+/// when it is dropped, unless the pool already holds `max_idle` buffers, in
+/// which case it is freed.
+///
+/// A buffer only ever exposes the bytes written into it since it was taken
+/// from the pool, so the contents of a previous use are never visible.
+///
+/// [`get`]: BufferPool::get
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::net::{BufferPool, TcpStream};
+///
+/// let pool = BufferPool::new(4096, 8);
+/// let stream = TcpStream::connect("127.0.0.1:8080").unwrap();
+/// let buf = stream.read_into_pooled(&pool).unwrap();
+/// println!("read {} bytes", buf.len());
+/// ```
+pub struct BufferPool {
+    buf_size: usize,
+    max_idle: usize,
+    idle: SgxMutex<Vec<Vec<u8>>>,
+}
+
+impl BufferPool {
+    /// Creates an empty pool of buffers of `buf_size` bytes, keeping at most
+    /// `max_idle` of them around once they are returned.
+    ///
+    /// No buffer is allocated until the first call to [`BufferPool::get`].
+    pub fn new(buf_size: usize, max_idle: usize) -> BufferPool {
+        BufferPool { buf_size, max_idle, idle: SgxMutex::new(Vec::new()) }
+    }
+
+    /// Returns the size of the buffers handed out by this pool.
+    pub fn buf_size(&self) -> usize {
+        self.buf_size
+    }
+
+    /// Returns the number of buffers currently waiting in the pool.
+    pub fn idle(&self) -> usize {
+        self.idle.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    /// Takes an empty buffer out of the pool, allocating a new one if none
+    /// is idle.
+    pub fn get(&self) -> PooledBuf<'_> {
+        let buf = self.idle.lock().unwrap_or_else(PoisonError::into_inner).pop();
+        let buf = buf.unwrap_or_else(|| Vec::with_capacity(self.buf_size));
+        PooledBuf { buf, pool: self }
+    }
+
+    fn put(&self, mut buf: Vec<u8>) {
+        let mut idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+        if idle.len() < self.max_idle {
+            buf.clear();
+            idle.push(buf);
+        }
+    }
+}
+
+impl fmt::Debug for BufferPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufferPool")
+            .field("buf_size", &self.buf_size)
+            .field("max_idle", &self.max_idle)
+            .field("idle", &self.idle())
+            .finish()
+    }
+}
+
+/// A buffer borrowed from a [`BufferPool`].
+///
+/// It dereferences to the bytes filled in so far, which start out empty.
+/// Dropping it hands the allocation back to the pool.
+pub struct PooledBuf<'a> {
+    buf: Vec<u8>,
+    pool: &'a BufferPool,
+}
+
+impl PooledBuf<'_> {
+    /// Returns the number of bytes this buffer can hold.
+    pub fn capacity(&self) -> usize {
+        self.pool.buf_size
+    }
+
+    /// Replaces the contents of this buffer with the bytes written by `f`.
+    ///
+    /// `f` is given the whole zeroed buffer and returns how many bytes it
+    /// filled, as [`Read::read`] does. On error the buffer is left empty.
+    ///
+    /// [`Read::read`]: crate::io::Read::read
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` reports more bytes than the buffer holds.
+    pub fn fill_with<F>(&mut self, f: F) -> io::Result<usize>
+    where
+        F: FnOnce(&mut [u8]) -> io::Result<usize>,
+    {
+        self.buf.clear();
+        self.buf.resize(self.pool.buf_size, 0);
+        let ret = f(&mut self.buf);
+        let n = ret.as_ref().map_or(0, |&n| n);
+        assert!(n <= self.pool.buf_size, "fill_with reported more bytes than the buffer holds");
+        self.buf.truncate(n);
+        ret
+    }
+}
+
+impl Deref for PooledBuf<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl DerefMut for PooledBuf<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.buf
+    }
+}
+
+impl Drop for PooledBuf<'_> {
+    fn drop(&mut self) {
+        self.pool.put(mem::take(&mut self.buf));
+    }
+}
+
+impl fmt::Debug for PooledBuf<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PooledBuf")
+            .field("len", &self.buf.len())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
@@ -574,7 +574,8 @@ impl Ipv4Addr {
     /// - addresses used for documentation (see [`Ipv4Addr::is_documentation()`])
     /// - the unspecified address (see [`Ipv4Addr::is_unspecified()`]), and the whole
     ///   `0.0.0.0/8` block
-    /// - addresses reserved for future protocols, except
+    /// - addresses reserved for future protocols (see
+    /// [`Ipv4Addr::is_ietf_protocol_assignment()`]), except
     /// `192.0.0.9/32` and `192.0.0.10/32` which are globally routable
     /// - addresses reserved for future use (see [`Ipv4Addr::is_reserved()`]
     /// - addresses reserved for networking devices benchmarking (see
@@ -645,8 +646,7 @@ impl Ipv4Addr {
             && !self.is_broadcast()
             && !self.is_documentation()
             && !self.is_shared()
-            // addresses reserved for future protocols (`192.0.0.0/24`)
-            && !(self.octets()[0] == 192 && self.octets()[1] == 0 && self.octets()[2] == 0)
+            && !self.is_ietf_protocol_assignment()
             && !self.is_reserved()
             && !self.is_benchmarking()
             // Make sure the address is not in 0.0.0.0/8
@@ -673,6 +673,37 @@ impl Ipv4Addr {
         self.octets()[0] == 100 && (self.octets()[1] & 0b1100_0000 == 0b0100_0000)
     }
 
+    /// Returns [`true`] if this address is part of `192.0.0.0/24`, which is reserved to
+    /// IANA for IETF protocol assignments, as documented in [IETF RFC 6890].
+    ///
+    /// Note that parts of this block are in use:
+    ///
+    /// - `192.0.0.8/32` is the "IPv4 dummy address" (see [IETF RFC 7600 section 4.2][rfc_7600])
+    /// - `192.0.0.9/32` is the "Port Control Protocol Anycast" (see [IETF RFC 7723][rfc_7723])
+    /// - `192.0.0.10/32` is used for NAT traversal (see [IETF RFC 8155][rfc_8155])
+    ///
+    /// [IETF RFC 6890]: https://tools.ietf.org/html/rfc6890#section-2.1
+    /// [rfc_7600]: https://tools.ietf.org/html/rfc7600#section-4.2
+    /// [rfc_7723]: https://tools.ietf.org/html/rfc7723
+    /// [rfc_8155]: https://tools.ietf.org/html/rfc8155
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![feature(ip)]
+    /// use std::net::Ipv4Addr;
+    ///
+    /// assert_eq!(Ipv4Addr::new(192, 0, 0, 0).is_ietf_protocol_assignment(), true);
+    /// assert_eq!(Ipv4Addr::new(192, 0, 0, 8).is_ietf_protocol_assignment(), true);
+    /// assert_eq!(Ipv4Addr::new(192, 0, 0, 255).is_ietf_protocol_assignment(), true);
+    /// assert_eq!(Ipv4Addr::new(192, 0, 1, 0).is_ietf_protocol_assignment(), false);
+    /// assert_eq!(Ipv4Addr::new(191, 255, 255, 255).is_ietf_protocol_assignment(), false);
+    /// ```
+    #[inline]
+    pub const fn is_ietf_protocol_assignment(&self) -> bool {
+        self.octets()[0] == 192 && self.octets()[1] == 0 && self.octets()[2] == 0
+    }
+
     /// Returns [`true`] if this address part of the `198.18.0.0/15` range, which is reserved for
     /// network devices benchmarking. This range is defined in [IETF RFC 2544] as `192.18.0.0`
     /// through `198.19.255.255` but [errata 423] corrects it to `198.18.0.0/15`.
@@ -1077,6 +1108,23 @@ impl From<[u8; 4]> for Ipv4Addr {
     }
 }
 
+impl From<Ipv4Addr> for [u8; 4] {
+    /// Converts an `Ipv4Addr` into a four element byte array.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::net::Ipv4Addr;
+    ///
+    /// let addr = Ipv4Addr::new(13, 12, 11, 10);
+    /// assert_eq!([13u8, 12u8, 11u8, 10u8], <[u8; 4]>::from(addr));
+    /// ```
+    #[inline]
+    fn from(ip: Ipv4Addr) -> [u8; 4] {
+        ip.octets()
+    }
+}
+
 impl From<[u8; 4]> for IpAddr {
     /// Creates an `IpAddr::V4` from a four element byte array.
     ///
@@ -1858,6 +1906,49 @@ impl From<[u16; 8]> for Ipv6Addr {
     }
 }
 
+impl From<Ipv6Addr> for [u8; 16] {
+    /// Converts an `Ipv6Addr` into a sixteen element byte array.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::net::Ipv6Addr;
+    ///
+    /// let addr = Ipv6Addr::new(0x1918, 0x1716, 0x1514, 0x1312, 0x1110, 0x0f0e, 0x0d0c, 0x0b0a);
+    /// assert_eq!(
+    ///     [
+    ///         25u8, 24u8, 23u8, 22u8, 21u8, 20u8, 19u8, 18u8,
+    ///         17u8, 16u8, 15u8, 14u8, 13u8, 12u8, 11u8, 10u8,
+    ///     ],
+    ///     <[u8; 16]>::from(addr)
+    /// );
+    /// ```
+    #[inline]
+    fn from(ip: Ipv6Addr) -> [u8; 16] {
+        ip.octets()
+    }
+}
+
+impl From<Ipv6Addr> for [u16; 8] {
+    /// Converts an `Ipv6Addr` into an eight element 16-bit array.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::net::Ipv6Addr;
+    ///
+    /// let addr = Ipv6Addr::new(0x20d, 0x20c, 0x20b, 0x20a, 0x209, 0x208, 0x207, 0x206);
+    /// assert_eq!(
+    ///     [525u16, 524u16, 523u16, 522u16, 521u16, 520u16, 519u16, 518u16],
+    ///     <[u16; 8]>::from(addr)
+    /// );
+    /// ```
+    #[inline]
+    fn from(ip: Ipv6Addr) -> [u16; 8] {
+        ip.segments()
+    }
+}
+
 
 impl From<[u8; 16]> for IpAddr {
     /// Creates an `IpAddr::V6` from a sixteen element byte array.
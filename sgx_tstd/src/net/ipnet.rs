@@ -0,0 +1,268 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! IP network (CIDR block) types.
+
+use crate::fmt;
+use crate::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// An IP network, either IPv4 or IPv6, written in CIDR notation such as
+/// `10.0.0.0/8` or `fe80::/10`.
+///
+/// # Examples
+///
+/// ```
+/// use std::net::{IpAddr, IpNet, Ipv4Addr, Ipv6Addr};
+///
+/// let net: IpNet = "10.0.0.0/8".parse().unwrap();
+/// assert!(net.contains(&IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3))));
+/// assert!(!net.contains(&IpAddr::V4(Ipv4Addr::new(11, 0, 0, 0))));
+/// // Addresses of the other family are never contained.
+/// assert!(!net.contains(&IpAddr::V6(Ipv6Addr::LOCALHOST)));
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub enum IpNet {
+    /// An IPv4 network.
+    V4(Ipv4Net),
+    /// An IPv6 network.
+    V6(Ipv6Net),
+}
+
+/// An IPv4 network: an address and a prefix length of at most 32.
+///
+/// The address may have host bits set; they are ignored by
+/// [`Ipv4Net::contains`] and cleared by [`Ipv4Net::network`].
+///
+/// # Examples
+///
+/// ```
+/// use std::net::{Ipv4Addr, Ipv4Net};
+///
+/// let net = Ipv4Net::new(Ipv4Addr::new(192, 168, 1, 7), 24).unwrap();
+/// assert_eq!(net.network(), Ipv4Addr::new(192, 168, 1, 0));
+/// assert_eq!(net.netmask(), Ipv4Addr::new(255, 255, 255, 0));
+/// assert!(net.contains(&Ipv4Addr::new(192, 168, 1, 255)));
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Ipv4Net {
+    addr: Ipv4Addr,
+    prefix_len: u8,
+}
+
+/// An IPv6 network: an address and a prefix length of at most 128.
+///
+/// The address may have host bits set; they are ignored by
+/// [`Ipv6Net::contains`] and cleared by [`Ipv6Net::network`].
+///
+/// # Examples
+///
+/// ```
+/// use std::net::{Ipv6Addr, Ipv6Net};
+///
+/// let net: Ipv6Net = "fd00::/8".parse().unwrap();
+/// assert!(net.contains(&Ipv6Addr::new(0xfd12, 0, 0, 0, 0, 0, 0, 1)));
+/// assert!(!net.contains(&Ipv6Addr::LOCALHOST));
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Ipv6Net {
+    addr: Ipv6Addr,
+    prefix_len: u8,
+}
+
+impl IpNet {
+    /// Creates a network from an address and a prefix length.
+    ///
+    /// Returns [`None`] if `prefix_len` is larger than 32 for an IPv4
+    /// address or 128 for an IPv6 address.
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Option<IpNet> {
+        match addr {
+            IpAddr::V4(addr) => Ipv4Net::new(addr, prefix_len).map(IpNet::V4),
+            IpAddr::V6(addr) => Ipv6Net::new(addr, prefix_len).map(IpNet::V6),
+        }
+    }
+
+    /// Returns the address this network was created with.
+    pub fn addr(&self) -> IpAddr {
+        match *self {
+            IpNet::V4(ref net) => IpAddr::V4(net.addr()),
+            IpNet::V6(ref net) => IpAddr::V6(net.addr()),
+        }
+    }
+
+    /// Returns the prefix length.
+    pub fn prefix_len(&self) -> u8 {
+        match *self {
+            IpNet::V4(ref net) => net.prefix_len(),
+            IpNet::V6(ref net) => net.prefix_len(),
+        }
+    }
+
+    /// Returns the address with all host bits cleared.
+    pub fn network(&self) -> IpAddr {
+        match *self {
+            IpNet::V4(ref net) => IpAddr::V4(net.network()),
+            IpNet::V6(ref net) => IpAddr::V6(net.network()),
+        }
+    }
+
+    /// Returns `true` if `addr` is in this network.
+    ///
+    /// An address of a different family than the network is never
+    /// contained; IPv4-mapped IPv6 addresses are not converted.
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        match (self, addr) {
+            (IpNet::V4(net), IpAddr::V4(addr)) => net.contains(addr),
+            (IpNet::V6(net), IpAddr::V6(addr)) => net.contains(addr),
+            _ => false,
+        }
+    }
+}
+
+impl Ipv4Net {
+    /// Creates a network from an address and a prefix length.
+    ///
+    /// Returns [`None`] if `prefix_len` is larger than 32.
+    pub const fn new(addr: Ipv4Addr, prefix_len: u8) -> Option<Ipv4Net> {
+        if prefix_len > 32 {
+            None
+        } else {
+            Some(Ipv4Net { addr, prefix_len })
+        }
+    }
+
+    /// Returns the address this network was created with.
+    pub const fn addr(&self) -> Ipv4Addr {
+        self.addr
+    }
+
+    /// Returns the prefix length.
+    pub const fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Returns the netmask, e.g. `255.255.255.0` for a `/24`.
+    pub fn netmask(&self) -> Ipv4Addr {
+        Ipv4Addr::from(self.mask())
+    }
+
+    /// Returns the address with all host bits cleared.
+    pub fn network(&self) -> Ipv4Addr {
+        Ipv4Addr::from(u32::from(self.addr) & self.mask())
+    }
+
+    /// Returns `true` if `addr` is in this network.
+    pub fn contains(&self, addr: &Ipv4Addr) -> bool {
+        (u32::from(*addr) ^ u32::from(self.addr)) & self.mask() == 0
+    }
+
+    fn mask(&self) -> u32 {
+        u32::MAX.checked_shl(32 - u32::from(self.prefix_len)).unwrap_or(0)
+    }
+}
+
+impl Ipv6Net {
+    /// Creates a network from an address and a prefix length.
+    ///
+    /// Returns [`None`] if `prefix_len` is larger than 128.
+    pub const fn new(addr: Ipv6Addr, prefix_len: u8) -> Option<Ipv6Net> {
+        if prefix_len > 128 {
+            None
+        } else {
+            Some(Ipv6Net { addr, prefix_len })
+        }
+    }
+
+    /// Returns the address this network was created with.
+    pub const fn addr(&self) -> Ipv6Addr {
+        self.addr
+    }
+
+    /// Returns the prefix length.
+    pub const fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Returns the netmask, e.g. `ffff:ffff::` for a `/32`.
+    pub fn netmask(&self) -> Ipv6Addr {
+        Ipv6Addr::from(self.mask())
+    }
+
+    /// Returns the address with all host bits cleared.
+    pub fn network(&self) -> Ipv6Addr {
+        Ipv6Addr::from(u128::from(self.addr) & self.mask())
+    }
+
+    /// Returns `true` if `addr` is in this network.
+    pub fn contains(&self, addr: &Ipv6Addr) -> bool {
+        (u128::from(*addr) ^ u128::from(self.addr)) & self.mask() == 0
+    }
+
+    fn mask(&self) -> u128 {
+        u128::MAX.checked_shl(128 - u32::from(self.prefix_len)).unwrap_or(0)
+    }
+}
+
+impl From<Ipv4Net> for IpNet {
+    fn from(net: Ipv4Net) -> IpNet {
+        IpNet::V4(net)
+    }
+}
+
+impl From<Ipv6Net> for IpNet {
+    fn from(net: Ipv6Net) -> IpNet {
+        IpNet::V6(net)
+    }
+}
+
+impl fmt::Display for IpNet {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            IpNet::V4(ref net) => net.fmt(fmt),
+            IpNet::V6(ref net) => net.fmt(fmt),
+        }
+    }
+}
+
+impl fmt::Display for Ipv4Net {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+impl fmt::Display for Ipv6Net {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+impl fmt::Debug for IpNet {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, fmt)
+    }
+}
+
+impl fmt::Debug for Ipv4Net {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, fmt)
+    }
+}
+
+impl fmt::Debug for Ipv6Net {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, fmt)
+    }
+}
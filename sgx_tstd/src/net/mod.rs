@@ -24,31 +24,74 @@
 //!
 //! * [`TcpListener`] and [`TcpStream`] provide functionality for communication over TCP
 //! * [`UdpSocket`] provides functionality for communication over UDP
+//! * [`VsockListener`] and [`VsockStream`] connect virtual machine guests and their host
+//!   over `AF_VSOCK`, addressed by [`VsockAddr`]
+//! * [`RawSocket`] sends and receives datagrams of any IP protocol, such as ICMP
+//!   echo requests, when the `raw_socket` feature is enabled
+//! * [`poll`] and [`PollFd`] wait for readiness on a handful of sockets at once
+//! * [`Poll`] and [`Events`] wait for readiness on many registered sockets through `epoll`
+//! * [`BufferPool`] recycles read buffers instead of allocating one per read
 //! * [`IpAddr`] represents IP addresses of either IPv4 or IPv6; [`Ipv4Addr`] and
 //!   [`Ipv6Addr`] are respectively IPv4 and IPv6 addresses
+//! * [`IpNet`], [`Ipv4Net`] and [`Ipv6Net`] are networks in CIDR notation, for
+//!   testing whether an address falls in a given block
 //! * [`SocketAddr`] represents socket addresses of either IPv4 or IPv6; [`SocketAddrV4`]
 //!   and [`SocketAddrV6`] are respectively IPv4 and IPv6 socket addresses
+//! * [`HostPort`] is an unresolved `host:port` pair, as found in configuration
 //! * [`ToSocketAddrs`] is a trait that used for generic address resolution when interacting
 //!   with networking objects like [`TcpListener`], [`TcpStream`] or [`UdpSocket`]
+//! * [`set_resolver`] replaces the host's name resolution with the application's own,
+//!   and host names are cached in the enclave according to their TTL
 //! * Other types are return or parameter types for various methods in this module
 
 use crate::io::{self, Error, ErrorKind};
+#[cfg(feature = "net")]
+use crate::time::Duration;
+
+use sgx_libc::{self as c, c_int};
 
-pub use self::addr::{SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs};
+pub use self::addr::{HostPort, SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs};
+#[cfg(feature = "net")]
+pub use self::buffer_pool::{BufferPool, PooledBuf};
+#[cfg(feature = "test_resolver")]
+pub use self::addr::{set_test_resolver, take_test_resolver};
 pub use self::ip::{IpAddr, Ipv4Addr, Ipv6Addr, Ipv6MulticastScope};
+pub use self::ipnet::{IpNet, Ipv4Net, Ipv6Net};
 pub use self::parser::AddrParseError;
 #[cfg(feature = "net")]
+pub use self::poll::{poll, Event, Events, Interest, Poll, PollFd};
+#[cfg(feature = "raw_socket")]
+pub use self::raw::{RawDomain, RawSocket, IPPROTO_ICMP, IPPROTO_ICMPV6};
+#[cfg(feature = "net")]
+pub use self::resolver::{
+    clear_resolver_cache, set_host_resolver_ttl, set_resolver, set_resolver_cache_capacity,
+    take_resolver, Resolution, Resolver, DEFAULT_RESOLVER_CACHE_CAPACITY,
+};
+#[cfg(feature = "net")]
 pub use self::tcp::{Incoming, TcpListener, TcpStream};
 #[cfg(feature = "net")]
 pub use self::udp::UdpSocket;
+#[cfg(feature = "net")]
+pub use self::vsock::{VsockAddr, VsockIncoming, VsockListener, VsockStream};
 
 mod addr;
+#[cfg(feature = "net")]
+mod buffer_pool;
 mod ip;
+mod ipnet;
 mod parser;
 #[cfg(feature = "net")]
+mod poll;
+#[cfg(feature = "raw_socket")]
+mod raw;
+#[cfg(feature = "net")]
+mod resolver;
+#[cfg(feature = "net")]
 mod tcp;
 #[cfg(feature = "net")]
 mod udp;
+#[cfg(feature = "net")]
+mod vsock;
 
 /// Possible values which can be passed to the [`TcpStream::shutdown`] method.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -71,6 +114,298 @@ pub enum Shutdown {
     Both,
 }
 
+impl Shutdown {
+    /// Converts a C `how` argument (`SHUT_RD`, `SHUT_WR` or `SHUT_RDWR`) into
+    /// a `Shutdown`.
+    ///
+    /// Returns [`None`] for any other value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sgx_libc::SHUT_WR;
+    /// use std::net::Shutdown;
+    ///
+    /// assert_eq!(Shutdown::from_raw(SHUT_WR), Some(Shutdown::Write));
+    /// assert_eq!(Shutdown::from_raw(42), None);
+    /// ```
+    pub const fn from_raw(how: c_int) -> Option<Shutdown> {
+        match how {
+            c::SHUT_RD => Some(Shutdown::Read),
+            c::SHUT_WR => Some(Shutdown::Write),
+            c::SHUT_RDWR => Some(Shutdown::Both),
+            _ => None,
+        }
+    }
+
+    /// Returns the C `how` argument (`SHUT_RD`, `SHUT_WR` or `SHUT_RDWR`)
+    /// for this `Shutdown`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sgx_libc::SHUT_RDWR;
+    /// use std::net::Shutdown;
+    ///
+    /// assert_eq!(Shutdown::Both.as_raw(), SHUT_RDWR);
+    /// ```
+    pub const fn as_raw(self) -> c_int {
+        match self {
+            Shutdown::Read => c::SHUT_RD,
+            Shutdown::Write => c::SHUT_WR,
+            Shutdown::Both => c::SHUT_RDWR,
+        }
+    }
+}
+
+/// How [`TcpStream::connect_with_retry`] retries connection attempts.
+///
+/// Each attempt is a full sweep over the addresses the target resolves to,
+/// as done by [`TcpStream::connect`]. If a sweep fails with a transient
+/// error (the connection was refused, reset, aborted or timed out), the
+/// caller sleeps for the backoff duration and starts another sweep, up to
+/// the maximum number of attempts.
+///
+/// The default policy makes a single attempt, which matches
+/// [`TcpStream::connect`].
+#[cfg(feature = "net")]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff: Duration,
+}
+
+#[cfg(feature = "net")]
+impl RetryPolicy {
+    /// Creates a policy making at most `max_attempts` sweeps and sleeping
+    /// `backoff` between consecutive ones.
+    ///
+    /// A `max_attempts` of 0 is treated as 1.
+    pub const fn new(max_attempts: u32, backoff: Duration) -> RetryPolicy {
+        RetryPolicy { max_attempts: if max_attempts == 0 { 1 } else { max_attempts }, backoff }
+    }
+
+    /// Returns the maximum number of sweeps.
+    pub const fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Returns the time slept between consecutive sweeps.
+    pub const fn backoff(&self) -> Duration {
+        self.backoff
+    }
+
+    fn is_transient(err: &Error) -> bool {
+        matches!(
+            err.kind(),
+            ErrorKind::ConnectionRefused
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::TimedOut
+        )
+    }
+}
+
+#[cfg(feature = "net")]
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy::new(1, Duration::from_secs(0))
+    }
+}
+
+/// The delay between starting one connection attempt and the next that
+/// RFC 8305 recommends, for use with [`TcpStream::connect_happy_eyeballs`].
+#[cfg(feature = "net")]
+pub const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// A set of socket options for [`TcpStream::configure`] to apply at once.
+///
+/// Each option is left unchanged on the socket unless it is set here. Setting
+/// options one by one costs an OCALL each, while [`TcpStream::configure`]
+/// applies all of them with a single one.
+///
+/// # Examples
+///
+/// ```
+/// use std::net::SocketOptions;
+/// use std::time::Duration;
+///
+/// let opts = SocketOptions::new()
+///     .nodelay(true)
+///     .read_timeout(Some(Duration::from_secs(5)))
+///     .recv_buffer_size(64 * 1024);
+/// ```
+#[cfg(feature = "net")]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct SocketOptions {
+    pub(crate) nodelay: Option<bool>,
+    pub(crate) ttl: Option<u32>,
+    pub(crate) read_timeout: Option<Option<Duration>>,
+    pub(crate) write_timeout: Option<Option<Duration>>,
+    pub(crate) recv_buffer_size: Option<usize>,
+    pub(crate) send_buffer_size: Option<usize>,
+}
+
+#[cfg(feature = "net")]
+impl SocketOptions {
+    /// Creates a set with no options, which leaves the socket unchanged.
+    pub fn new() -> SocketOptions {
+        SocketOptions::default()
+    }
+
+    /// Sets `TCP_NODELAY`, as [`TcpStream::set_nodelay`] does.
+    pub fn nodelay(mut self, nodelay: bool) -> SocketOptions {
+        self.nodelay = Some(nodelay);
+        self
+    }
+
+    /// Sets `IP_TTL`, as [`TcpStream::set_ttl`] does.
+    pub fn ttl(mut self, ttl: u32) -> SocketOptions {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Sets the read timeout, as [`TcpStream::set_read_timeout`] does.
+    ///
+    /// A zero [`Duration`] makes [`TcpStream::configure`] fail before any
+    /// option is applied.
+    pub fn read_timeout(mut self, dur: Option<Duration>) -> SocketOptions {
+        self.read_timeout = Some(dur);
+        self
+    }
+
+    /// Sets the write timeout, as [`TcpStream::set_write_timeout`] does.
+    ///
+    /// A zero [`Duration`] makes [`TcpStream::configure`] fail before any
+    /// option is applied.
+    pub fn write_timeout(mut self, dur: Option<Duration>) -> SocketOptions {
+        self.write_timeout = Some(dur);
+        self
+    }
+
+    /// Sets `SO_RCVBUF`, the size of the kernel receive buffer.
+    ///
+    /// The kernel may adjust the value; see
+    /// [`TcpStream::recv_buffer_size`] for the size actually in use.
+    pub fn recv_buffer_size(mut self, size: usize) -> SocketOptions {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Sets `SO_SNDBUF`, the size of the kernel send buffer.
+    ///
+    /// The kernel may adjust the value; see
+    /// [`TcpStream::send_buffer_size`] for the size actually in use.
+    pub fn send_buffer_size(mut self, size: usize) -> SocketOptions {
+        self.send_buffer_size = Some(size);
+        self
+    }
+}
+
+/// Registers a handler for errors from closing a socket on drop, replacing
+/// any that was previously registered.
+///
+/// Dropping a [`TcpStream`], [`TcpListener`], [`UdpSocket`] or Unix domain
+/// socket closes its descriptor through an OCALL. By default an error from
+/// that close is silently ignored, as there is nothing a destructor can do
+/// about it. The handler is called with such an error instead, for example
+/// to log descriptor leaks or failed enclave boundary crossings. The close
+/// is never retried either way.
+///
+/// To observe close errors for a particular socket as a `Result`, use the
+/// `close` method of the socket type instead of dropping it.
+///
+/// The handler is a global resource and may be called from any thread.
+#[cfg(feature = "net")]
+pub fn set_close_error_handler(handler: fn(&io::Error)) {
+    crate::sys::net::set_close_error_handler(handler)
+}
+
+/// Unregisters the current close error handler, returning it.
+///
+/// *See also the function [`set_close_error_handler`].*
+///
+/// Returns [`None`] if no handler is registered, in which case close errors
+/// on drop are ignored.
+#[cfg(feature = "net")]
+pub fn take_close_error_handler() -> Option<fn(&io::Error)> {
+    crate::sys::net::take_close_error_handler()
+}
+
+/// The kind of socket an I/O operation reported to a [`MetricsSink`] was
+/// performed on.
+#[cfg(feature = "net")]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum SocketKind {
+    /// A [`TcpStream`].
+    Tcp,
+    /// A [`UdpSocket`].
+    Udp,
+}
+
+/// The I/O operation reported to a [`MetricsSink`].
+#[cfg(feature = "net")]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum SocketOp {
+    /// Data was received from a stream or a connected datagram socket.
+    Read,
+    /// Data was sent on a stream or a connected datagram socket.
+    Write,
+    /// A datagram was received along with its source address.
+    RecvFrom,
+    /// A datagram was sent to an explicit destination.
+    SendTo,
+}
+
+/// A callback receiving the socket kind, the operation and the number of
+/// bytes transferred, see [`install_metrics`].
+#[cfg(feature = "net")]
+pub type MetricsSink = fn(SocketKind, SocketOp, usize);
+
+/// Installs a sink that is called after every successful read or write on a
+/// [`TcpStream`] or [`UdpSocket`], replacing any previously installed sink.
+///
+/// This covers reads, writes, `recv`, `send`, `recv_from` and `send_to` in
+/// all their variants (vectored, with a deadline, ...). Peeking is not
+/// reported, as no data is consumed. A write that transfers a whole buffer in
+/// several OCALLs, such as [`TcpStream::write_all_timeout`], is reported once
+/// with the total count.
+///
+/// The sink is a global resource and may be called from any thread, so it
+/// should be cheap and must not block. While no sink is installed the cost
+/// of the hook is a single atomic load per operation.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::net::{self, SocketKind, SocketOp};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// static EGRESS: AtomicUsize = AtomicUsize::new(0);
+///
+/// fn count(_kind: SocketKind, op: SocketOp, n: usize) {
+///     if let SocketOp::Write | SocketOp::SendTo = op {
+///         EGRESS.fetch_add(n, Ordering::Relaxed);
+///     }
+/// }
+///
+/// net::install_metrics(count);
+/// ```
+#[cfg(feature = "net")]
+pub fn install_metrics(sink: MetricsSink) {
+    crate::sys_common::net::install_metrics(sink)
+}
+
+/// Uninstalls the current metrics sink, returning it.
+///
+/// *See also the function [`install_metrics`].*
+///
+/// Returns [`None`] if no sink is installed.
+#[cfg(feature = "net")]
+pub fn uninstall_metrics() -> Option<MetricsSink> {
+    crate::sys_common::net::uninstall_metrics()
+}
+
 #[inline]
 const fn htons(i: u16) -> u16 {
     i.to_be()
@@ -98,4 +433,46 @@ where
     Err(last_err.unwrap_or_else(|| {
         Error::new_const(ErrorKind::InvalidInput, &"could not resolve to any addresses")
     }))
-}
\ No newline at end of file
+}
+
+// Orders addresses for racing connection attempts as RFC 8305 suggests,
+// alternating between the address families and starting with the family of
+// the first address. Order within each family is kept.
+#[cfg(feature = "net")]
+fn interleave_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_v6 = match addrs.first() {
+        Some(addr) => addr.is_ipv6(),
+        None => return addrs,
+    };
+    let len = addrs.len();
+    let (preferred, other): (Vec<_>, Vec<_>) =
+        addrs.into_iter().partition(|addr| addr.is_ipv6() == first_v6);
+    let (mut preferred, mut other) = (preferred.into_iter(), other.into_iter());
+    let mut interleaved = Vec::with_capacity(len);
+    loop {
+        match (preferred.next(), other.next()) {
+            (None, None) => return interleaved,
+            (a, b) => {
+                interleaved.extend(a);
+                interleaved.extend(b);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "net")]
+fn each_addr_retry<A: ToSocketAddrs, F, T>(addr: A, policy: &RetryPolicy, mut f: F) -> io::Result<T>
+where
+    F: FnMut(io::Result<&SocketAddr>) -> io::Result<T>,
+{
+    let mut attempt = 1;
+    loop {
+        match each_addr(&addr, &mut f) {
+            Err(ref e) if attempt < policy.max_attempts && RetryPolicy::is_transient(e) => {
+                crate::sys::net::sleep(policy.backoff);
+                attempt += 1;
+            }
+            res => return res,
+        }
+    }
+}
@@ -20,11 +20,15 @@
 //! This module is "publicly exported" through the `FromStr` implementations
 //! below.
 
+use crate::cmp;
 use crate::convert::TryInto as _;
 use crate::error::Error;
 use crate::fmt;
-use crate::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
-use crate::str::FromStr;
+use crate::net::{
+    HostPort, IpAddr, IpNet, Ipv4Addr, Ipv4Net, Ipv6Addr, Ipv6Net, SocketAddr, SocketAddrV4,
+    SocketAddrV6,
+};
+use crate::str::{self, FromStr};
 
 trait ReadNumberHelper: crate::marker::Sized {
     const ZERO: Self;
@@ -51,11 +55,25 @@ impl_helper! { u8 u16 u32 }
 struct Parser<'a> {
     // Parsing as ASCII, so can use byte array.
     state: &'a [u8],
+    input_len: usize,
+    // Offset just past the last separator or number successfully read by any
+    // alternative, i.e. where the best attempt got stuck.
+    furthest: usize,
 }
 
 impl<'a> Parser<'a> {
     fn new(input: &'a str) -> Parser<'a> {
-        Parser { state: input.as_bytes() }
+        Parser { state: input.as_bytes(), input_len: input.len(), furthest: 0 }
+    }
+
+    /// The number of bytes consumed so far.
+    fn offset(&self) -> usize {
+        self.input_len - self.state.len()
+    }
+
+    /// Record that the input up to the current offset parsed successfully.
+    fn mark_progress(&mut self) {
+        self.furthest = cmp::max(self.furthest, self.offset());
     }
 
     /// Run a parser, and restore the pre-parse state if it fails.
@@ -73,12 +91,15 @@ impl<'a> Parser<'a> {
 
     /// Run a parser, but fail if the entire input wasn't consumed.
     /// Doesn't run atomically.
-    fn parse_with<T, F>(&mut self, inner: F) -> Result<T, AddrParseError>
+    fn parse_with<T, F>(&mut self, inner: F, kind: AddrKind) -> Result<T, AddrParseError>
     where
         F: FnOnce(&mut Parser<'_>) -> Option<T>,
     {
         let result = inner(self);
-        if self.state.is_empty() { result } else { None }.ok_or(AddrParseError(()))
+        if self.state.is_empty() { result } else { None }.ok_or_else(|| AddrParseError {
+            kind,
+            position: Some(cmp::max(self.furthest, self.offset())),
+        })
     }
 
     /// Peek the next character from the input
@@ -97,9 +118,13 @@ impl<'a> Parser<'a> {
     #[must_use]
     /// Read the next character from the input if it matches the target.
     fn read_given_char(&mut self, target: char) -> Option<()> {
-        self.read_atomically(|p| {
+        let result = self.read_atomically(|p| {
             p.read_char().and_then(|c| if c == target { Some(()) } else { None })
-        })
+        });
+        if result.is_some() {
+            self.mark_progress();
+        }
+        result
     }
 
     /// Helper for reading separators in an indexed loop. Reads the separator
@@ -143,6 +168,10 @@ impl<'a> Parser<'a> {
 
             if digit_count == 0 { None } else { Some(result) }
         })
+        .map(|result| {
+            self.mark_progress();
+            result
+        })
     }
 
     /// Read an IPv4 address.
@@ -281,55 +310,152 @@ impl<'a> Parser<'a> {
             .map(SocketAddr::V4)
             .or_else(|| self.read_socket_addr_v6().map(SocketAddr::V6))
     }
+
+    /// Read a host name or IPv4 address, up to the next `:`.
+    fn read_host_name(&mut self) -> Option<&'a str> {
+        let len = self
+            .state
+            .iter()
+            .position(|&b| b == b':' || b == b'[' || b == b']' || b.is_ascii_whitespace())
+            .unwrap_or(self.state.len());
+        if len == 0 {
+            return None;
+        }
+        let (host, tail) = self.state.split_at(len);
+        // Split at an ASCII byte, so `host` is still valid UTF-8.
+        let host = str::from_utf8(host).ok()?;
+        self.state = tail;
+        self.mark_progress();
+        Some(host)
+    }
+
+    /// Read a host with a port, where an IPv6 host is written in brackets.
+    fn read_host_port(&mut self) -> Option<HostPort> {
+        self.read_atomically(|p| {
+            let host = if p.peek_char() == Some('[') {
+                p.read_given_char('[')?;
+                let bracketed = p.state;
+                p.read_ipv6_addr()?;
+                p.read_scope_id();
+                let len = bracketed.len() - p.state.len();
+                p.read_given_char(']')?;
+                str::from_utf8(&bracketed[..len]).ok()?
+            } else {
+                p.read_host_name()?
+            };
+            let port = p.read_port()?;
+            Some(HostPort::new(host.into(), port))
+        })
+    }
+
+    /// Read a `/` followed by a prefix length in base 10.
+    fn read_prefix_len(&mut self) -> Option<u8> {
+        self.read_atomically(|p| {
+            p.read_given_char('/')?;
+            p.read_number(10, Some(3))
+        })
+    }
+
+    /// Read an IPv4 network in CIDR notation.
+    fn read_ipv4_net(&mut self) -> Option<Ipv4Net> {
+        self.read_atomically(|p| {
+            let ip = p.read_ipv4_addr()?;
+            let prefix_len = p.read_prefix_len()?;
+            Ipv4Net::new(ip, prefix_len)
+        })
+    }
+
+    /// Read an IPv6 network in CIDR notation.
+    fn read_ipv6_net(&mut self) -> Option<Ipv6Net> {
+        self.read_atomically(|p| {
+            let ip = p.read_ipv6_addr()?;
+            let prefix_len = p.read_prefix_len()?;
+            Ipv6Net::new(ip, prefix_len)
+        })
+    }
+
+    /// Read an IP network, either IPv4 or IPv6.
+    fn read_ip_net(&mut self) -> Option<IpNet> {
+        self.read_ipv4_net().map(IpNet::V4).or_else(|| self.read_ipv6_net().map(IpNet::V6))
+    }
 }
 
 impl FromStr for IpAddr {
     type Err = AddrParseError;
     fn from_str(s: &str) -> Result<IpAddr, AddrParseError> {
-        Parser::new(s).parse_with(|p| p.read_ip_addr())
+        Parser::new(s).parse_with(|p| p.read_ip_addr(), AddrKind::Ip)
     }
 }
 
 impl FromStr for Ipv4Addr {
     type Err = AddrParseError;
     fn from_str(s: &str) -> Result<Ipv4Addr, AddrParseError> {
-        Parser::new(s).parse_with(|p| p.read_ipv4_addr())
+        Parser::new(s).parse_with(|p| p.read_ipv4_addr(), AddrKind::Ipv4)
     }
 }
 
 impl FromStr for Ipv6Addr {
     type Err = AddrParseError;
     fn from_str(s: &str) -> Result<Ipv6Addr, AddrParseError> {
-        Parser::new(s).parse_with(|p| p.read_ipv6_addr())
+        Parser::new(s).parse_with(|p| p.read_ipv6_addr(), AddrKind::Ipv6)
     }
 }
 
 impl FromStr for SocketAddrV4 {
     type Err = AddrParseError;
     fn from_str(s: &str) -> Result<SocketAddrV4, AddrParseError> {
-        Parser::new(s).parse_with(|p| p.read_socket_addr_v4())
+        Parser::new(s).parse_with(|p| p.read_socket_addr_v4(), AddrKind::SocketV4)
     }
 }
 
 impl FromStr for SocketAddrV6 {
     type Err = AddrParseError;
     fn from_str(s: &str) -> Result<SocketAddrV6, AddrParseError> {
-        Parser::new(s).parse_with(|p| p.read_socket_addr_v6())
+        Parser::new(s).parse_with(|p| p.read_socket_addr_v6(), AddrKind::SocketV6)
     }
 }
 
 impl FromStr for SocketAddr {
     type Err = AddrParseError;
     fn from_str(s: &str) -> Result<SocketAddr, AddrParseError> {
-        Parser::new(s).parse_with(|p| p.read_socket_addr())
+        Parser::new(s).parse_with(|p| p.read_socket_addr(), AddrKind::Socket)
+    }
+}
+
+impl FromStr for HostPort {
+    type Err = AddrParseError;
+    fn from_str(s: &str) -> Result<HostPort, AddrParseError> {
+        Parser::new(s).parse_with(|p| p.read_host_port(), AddrKind::HostPort)
+    }
+}
+
+impl FromStr for IpNet {
+    type Err = AddrParseError;
+    fn from_str(s: &str) -> Result<IpNet, AddrParseError> {
+        Parser::new(s).parse_with(|p| p.read_ip_net(), AddrKind::IpNet)
+    }
+}
+
+impl FromStr for Ipv4Net {
+    type Err = AddrParseError;
+    fn from_str(s: &str) -> Result<Ipv4Net, AddrParseError> {
+        Parser::new(s).parse_with(|p| p.read_ipv4_net(), AddrKind::Ipv4Net)
+    }
+}
+
+impl FromStr for Ipv6Net {
+    type Err = AddrParseError;
+    fn from_str(s: &str) -> Result<Ipv6Net, AddrParseError> {
+        Parser::new(s).parse_with(|p| p.read_ipv6_net(), AddrKind::Ipv6Net)
     }
 }
 
-/// An error which can be returned when parsing an IP address or a socket address.
+/// An error which can be returned when parsing an IP address, a socket address,
+/// an IP network or a host and port.
 ///
 /// This error is used as the error type for the [`FromStr`] implementation for
-/// [`IpAddr`], [`Ipv4Addr`], [`Ipv6Addr`], [`SocketAddr`], [`SocketAddrV4`], and
-/// [`SocketAddrV6`].
+/// [`IpAddr`], [`Ipv4Addr`], [`Ipv6Addr`], [`SocketAddr`], [`SocketAddrV4`],
+/// [`SocketAddrV6`], [`IpNet`], [`Ipv4Net`], [`Ipv6Net`] and [`HostPort`].
 ///
 /// # Potential causes
 ///
@@ -349,8 +475,64 @@ impl FromStr for SocketAddr {
 /// // No problem, the `panic!` message has disappeared.
 /// let _foo: SocketAddr = "127.0.0.1:8080".parse().expect("unreachable panic");
 /// ```
+///
+/// The error also tells what was being parsed and where parsing failed:
+///
+/// ```
+/// use std::net::Ipv4Addr;
+///
+/// let err = "192.168.300.1".parse::<Ipv4Addr>().unwrap_err();
+/// assert_eq!(err.expected(), "an IPv4 address");
+/// assert_eq!(err.position(), Some(8));
+/// ```
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct AddrParseError(());
+pub struct AddrParseError {
+    kind: AddrKind,
+    position: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AddrKind {
+    Ip,
+    Ipv4,
+    Ipv6,
+    Socket,
+    SocketV4,
+    SocketV6,
+    IpNet,
+    Ipv4Net,
+    Ipv6Net,
+    HostPort,
+}
+
+impl AddrParseError {
+    /// Returns the byte offset of the first part of the input that could not
+    /// be parsed, if known.
+    ///
+    /// This is the start of the first separator or number that did not fit,
+    /// or of unexpected trailing input. It is equal to the length of the
+    /// input if the input ended too early.
+    pub fn position(&self) -> Option<usize> {
+        self.position
+    }
+
+    /// Returns a short description of what the input was expected to be,
+    /// such as `"an IPv6 address"` or `"a socket address"`.
+    pub fn expected(&self) -> &'static str {
+        match self.kind {
+            AddrKind::Ip => "an IP address",
+            AddrKind::Ipv4 => "an IPv4 address",
+            AddrKind::Ipv6 => "an IPv6 address",
+            AddrKind::Socket => "a socket address",
+            AddrKind::SocketV4 => "an IPv4 socket address",
+            AddrKind::SocketV6 => "an IPv6 socket address",
+            AddrKind::IpNet => "an IP network",
+            AddrKind::Ipv4Net => "an IPv4 network",
+            AddrKind::Ipv6Net => "an IPv6 network",
+            AddrKind::HostPort => "a host and port",
+        }
+    }
+}
 
 impl fmt::Display for AddrParseError {
     #[allow(deprecated, deprecated_in_future)]
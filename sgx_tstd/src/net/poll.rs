@@ -0,0 +1,468 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use crate::collections::BTreeMap;
+use crate::fmt;
+use crate::io;
+use crate::marker::PhantomData;
+use crate::ops::{BitOr, BitOrAssign};
+use crate::os::unix::io::{AsRawFd, BorrowedFd, RawFd};
+use crate::slice;
+use crate::sync::{PoisonError, SgxMutex};
+use crate::sys::net as net_imp;
+use crate::time::{Duration, Instant};
+#[cfg(not(feature = "untrusted_time"))]
+use crate::untrusted::time::InstantEx;
+use crate::vec::Vec;
+
+use sgx_libc as libc;
+
+/// The readiness events a [`PollFd`] is interested in.
+///
+/// Values can be combined with `|`.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Interest(libc::c_short);
+
+impl Interest {
+    /// Interest in read readiness (`POLLIN`).
+    pub const READABLE: Interest = Interest(libc::POLLIN);
+    /// Interest in write readiness (`POLLOUT`).
+    pub const WRITABLE: Interest = Interest(libc::POLLOUT);
+
+    /// Returns `true` if this includes [`Interest::READABLE`].
+    pub fn is_readable(self) -> bool {
+        self.0 & libc::POLLIN != 0
+    }
+
+    /// Returns `true` if this includes [`Interest::WRITABLE`].
+    pub fn is_writable(self) -> bool {
+        self.0 & libc::POLLOUT != 0
+    }
+}
+
+impl BitOr for Interest {
+    type Output = Interest;
+
+    fn bitor(self, rhs: Interest) -> Interest {
+        Interest(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Interest {
+    fn bitor_assign(&mut self, rhs: Interest) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl fmt::Debug for Interest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interest")
+            .field("readable", &self.is_readable())
+            .field("writable", &self.is_writable())
+            .finish()
+    }
+}
+
+/// A socket registered with [`poll`], together with the events it is
+/// interested in and the events reported by the last call.
+///
+/// A `PollFd` borrows the socket it was created from, so the descriptor
+/// cannot be closed while it is being polled.
+#[repr(transparent)]
+pub struct PollFd<'a> {
+    inner: libc::pollfd,
+    _marker: PhantomData<BorrowedFd<'a>>,
+}
+
+impl<'a> PollFd<'a> {
+    /// Creates a new `PollFd` for `source` with the given interest.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Interest, PollFd, TcpListener};
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:8080").unwrap();
+    /// let fd = PollFd::new(&listener, Interest::READABLE);
+    /// ```
+    pub fn new<S: AsRawFd + ?Sized>(source: &'a S, interest: Interest) -> PollFd<'a> {
+        PollFd {
+            inner: libc::pollfd { fd: source.as_raw_fd(), events: interest.0, revents: 0 },
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the raw file descriptor being polled.
+    pub fn as_raw_fd(&self) -> RawFd {
+        self.inner.fd
+    }
+
+    /// Returns the interest registered for this descriptor.
+    pub fn interest(&self) -> Interest {
+        Interest(self.inner.events)
+    }
+
+    /// Changes the interest registered for this descriptor.
+    pub fn set_interest(&mut self, interest: Interest) {
+        self.inner.events = interest.0;
+    }
+
+    /// Returns `true` if the last [`poll`] reported the socket readable.
+    pub fn is_readable(&self) -> bool {
+        self.inner.revents & libc::POLLIN != 0
+    }
+
+    /// Returns `true` if the last [`poll`] reported the socket writable.
+    pub fn is_writable(&self) -> bool {
+        self.inner.revents & libc::POLLOUT != 0
+    }
+
+    /// Returns `true` if the last [`poll`] reported an error condition
+    /// (`POLLERR` or `POLLNVAL`).
+    pub fn is_error(&self) -> bool {
+        self.inner.revents & (libc::POLLERR | libc::POLLNVAL) != 0
+    }
+
+    /// Returns `true` if the last [`poll`] reported that the peer hung up.
+    pub fn is_hangup(&self) -> bool {
+        self.inner.revents & libc::POLLHUP != 0
+    }
+}
+
+impl fmt::Debug for PollFd<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PollFd")
+            .field("fd", &self.inner.fd)
+            .field("interest", &self.interest())
+            .field("revents", &self.inner.revents)
+            .finish()
+    }
+}
+
+/// Waits until at least one of `fds` is ready or the timeout expires.
+///
+/// The events reported for each descriptor are written back into the
+/// corresponding [`PollFd`], replacing those of any earlier call. A `timeout`
+/// of [`None`] blocks indefinitely, while a zero duration returns
+/// immediately. Returns the number of descriptors with non-empty events, so
+/// `Ok(0)` means the timeout expired.
+///
+/// This is implemented over a single `poll` OCALL, so the enclave thread
+/// blocks in the untrusted host for the duration of the wait.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::net::{self, Interest, PollFd, TcpListener};
+/// use std::time::Duration;
+///
+/// let listener = TcpListener::bind("127.0.0.1:8080").unwrap();
+/// let mut fds = [PollFd::new(&listener, Interest::READABLE)];
+/// if net::poll(&mut fds, Some(Duration::from_secs(1))).unwrap() > 0 {
+///     assert!(fds[0].is_readable());
+/// }
+/// ```
+pub fn poll(fds: &mut [PollFd<'_>], timeout: Option<Duration>) -> io::Result<usize> {
+    for fd in fds.iter_mut() {
+        fd.inner.revents = 0;
+    }
+    // SAFETY: `PollFd` is a transparent wrapper around `pollfd`.
+    let raw = unsafe { slice::from_raw_parts_mut(fds.as_mut_ptr() as *mut libc::pollfd, fds.len()) };
+    let n = net_imp::poll(raw, timeout)?;
+    // Only keep events that poll(2) can legitimately report for our interest.
+    for fd in fds.iter_mut() {
+        let valid = fd.inner.events | libc::POLLERR | libc::POLLHUP | libc::POLLNVAL;
+        fd.inner.revents &= valid;
+    }
+    Ok(n)
+}
+
+/// A readiness selector backed by an untrusted `epoll` instance.
+///
+/// Sockets are registered once with a caller-chosen token and an
+/// [`Interest`], and [`Poll::poll`] then reports the tokens of those that
+/// became ready. Unlike [`poll`], the set of descriptors lives in the host
+/// kernel, so each wait costs one OCALL regardless of how many sockets are
+/// registered.
+///
+/// The host is not trusted to report events faithfully: events for
+/// descriptors that were never registered are dropped, readiness is masked to
+/// the registered interest, and an early return with no events is retried
+/// until the timeout has elapsed according to the enclave's clock.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::net::{Events, Interest, Poll, TcpListener};
+/// use std::time::Duration;
+///
+/// let listener = TcpListener::bind("127.0.0.1:8080").unwrap();
+/// let poll = Poll::new().unwrap();
+/// poll.register(&listener, 0, Interest::READABLE).unwrap();
+///
+/// let mut events = Events::with_capacity(16);
+/// poll.poll(&mut events, Some(Duration::from_secs(1))).unwrap();
+/// for event in events.iter() {
+///     assert_eq!(event.token(), 0);
+/// }
+/// ```
+pub struct Poll {
+    epoll: net_imp::Epoll,
+    registrations: SgxMutex<BTreeMap<RawFd, (usize, Interest)>>,
+}
+
+impl Poll {
+    /// Creates a new selector with no registered sockets.
+    pub fn new() -> io::Result<Poll> {
+        Ok(Poll { epoll: net_imp::Epoll::new()?, registrations: SgxMutex::new(BTreeMap::new()) })
+    }
+
+    /// Starts watching `source` for `interest`, reporting readiness under
+    /// `token`.
+    ///
+    /// Returns an error of kind [`io::ErrorKind::AlreadyExists`] if the
+    /// descriptor is already registered; use [`Poll::reregister`] to change
+    /// it.
+    pub fn register<S: AsRawFd + ?Sized>(
+        &self,
+        source: &S,
+        token: usize,
+        interest: Interest,
+    ) -> io::Result<()> {
+        let fd = source.as_raw_fd();
+        let mut registrations = self.registrations.lock().unwrap_or_else(PoisonError::into_inner);
+        if registrations.contains_key(&fd) {
+            return Err(io::Error::new_const(
+                io::ErrorKind::AlreadyExists,
+                &"descriptor is already registered",
+            ));
+        }
+        self.epoll.ctl(libc::EPOLL_CTL_ADD, fd, interest.to_epoll(), fd as u64)?;
+        registrations.insert(fd, (token, interest));
+        Ok(())
+    }
+
+    /// Changes the token and interest of an already registered `source`.
+    pub fn reregister<S: AsRawFd + ?Sized>(
+        &self,
+        source: &S,
+        token: usize,
+        interest: Interest,
+    ) -> io::Result<()> {
+        let fd = source.as_raw_fd();
+        let mut registrations = self.registrations.lock().unwrap_or_else(PoisonError::into_inner);
+        let registration = registrations.get_mut(&fd).ok_or_else(not_registered)?;
+        self.epoll.ctl(libc::EPOLL_CTL_MOD, fd, interest.to_epoll(), fd as u64)?;
+        *registration = (token, interest);
+        Ok(())
+    }
+
+    /// Stops watching `source`.
+    ///
+    /// This must be called before the socket is closed, otherwise readiness
+    /// for a later socket reusing the descriptor may be reported under the
+    /// old token.
+    pub fn deregister<S: AsRawFd + ?Sized>(&self, source: &S) -> io::Result<()> {
+        let fd = source.as_raw_fd();
+        let mut registrations = self.registrations.lock().unwrap_or_else(PoisonError::into_inner);
+        if !registrations.contains_key(&fd) {
+            return Err(not_registered());
+        }
+        self.epoll.ctl(libc::EPOLL_CTL_DEL, fd, 0, 0)?;
+        registrations.remove(&fd);
+        Ok(())
+    }
+
+    /// Waits until at least one registered socket is ready or the timeout
+    /// expires.
+    ///
+    /// `events` is cleared and filled with up to [`Events::capacity`] events.
+    /// A `timeout` of [`None`] blocks indefinitely, while a zero duration
+    /// returns immediately. Returns the number of events, so `Ok(0)` means
+    /// the timeout expired.
+    pub fn poll(&self, events: &mut Events, timeout: Option<Duration>) -> io::Result<usize> {
+        events.clear();
+        if events.raw.is_empty() {
+            return Err(io::Error::new_const(
+                io::ErrorKind::InvalidInput,
+                &"cannot poll with a zero-capacity Events",
+            ));
+        }
+        let deadline = timeout.and_then(|t| Instant::now().checked_add(t));
+        let mut remaining = timeout;
+        loop {
+            let n = self.epoll.wait(&mut events.raw, remaining)?;
+            self.collect(events, n);
+            if !events.is_empty() {
+                return Ok(events.len());
+            }
+            // Nothing valid was reported: only trust a timeout once our own
+            // clock agrees that it has expired.
+            match (timeout, deadline) {
+                (None, _) => {}
+                (Some(t), _) if t == Duration::ZERO => return Ok(0),
+                (Some(_), Some(deadline)) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Ok(0);
+                    }
+                    remaining = Some(deadline - now);
+                }
+                // The timeout overflows `Instant`, treat it as unbounded.
+                (Some(_), None) => remaining = None,
+            }
+        }
+    }
+
+    fn collect(&self, events: &mut Events, n: usize) {
+        let registrations = self.registrations.lock().unwrap_or_else(PoisonError::into_inner);
+        for raw in &events.raw[..n] {
+            let (data, ready) = (raw.u64, raw.events);
+            let registration = if data <= RawFd::MAX as u64 {
+                registrations.get(&(data as RawFd))
+            } else {
+                None
+            };
+            if let Some(&(token, interest)) = registration {
+                let valid = interest.to_epoll() | (libc::EPOLLERR | libc::EPOLLHUP) as u32;
+                let ready = ready & valid;
+                if ready != 0 {
+                    events.events.push(Event { token, events: ready });
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Poll {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Poll").field("epfd", &self.epoll.as_raw_fd()).finish_non_exhaustive()
+    }
+}
+
+fn not_registered() -> io::Error {
+    io::Error::new_const(io::ErrorKind::NotFound, &"descriptor is not registered")
+}
+
+impl Interest {
+    // `POLLIN`/`POLLOUT` share their values with `EPOLLIN`/`EPOLLOUT`.
+    fn to_epoll(self) -> u32 {
+        self.0 as u16 as u32
+    }
+}
+
+/// A buffer of readiness events filled in by [`Poll::poll`].
+pub struct Events {
+    raw: Vec<libc::epoll_event>,
+    events: Vec<Event>,
+}
+
+impl Events {
+    /// Creates a buffer that holds up to `capacity` events per wait.
+    pub fn with_capacity(capacity: usize) -> Events {
+        Events {
+            raw: vec![libc::epoll_event { events: 0, u64: 0 }; capacity],
+            events: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the maximum number of events reported by one wait.
+    pub fn capacity(&self) -> usize {
+        self.raw.len()
+    }
+
+    /// Returns the number of events reported by the last wait.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns `true` if the last wait reported no events.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Returns an iterator over the events reported by the last wait.
+    pub fn iter(&self) -> slice::Iter<'_, Event> {
+        self.events.iter()
+    }
+
+    /// Discards the reported events.
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+impl<'a> IntoIterator for &'a Events {
+    type Item = &'a Event;
+    type IntoIter = slice::Iter<'a, Event>;
+
+    fn into_iter(self) -> slice::Iter<'a, Event> {
+        self.iter()
+    }
+}
+
+impl fmt::Debug for Events {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.events.iter()).finish()
+    }
+}
+
+/// Readiness of one registered socket, as reported by [`Poll::poll`].
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Event {
+    token: usize,
+    events: u32,
+}
+
+impl Event {
+    /// Returns the token the socket was registered with.
+    pub fn token(&self) -> usize {
+        self.token
+    }
+
+    /// Returns `true` if the socket is readable.
+    pub fn is_readable(&self) -> bool {
+        self.events & libc::EPOLLIN as u32 != 0
+    }
+
+    /// Returns `true` if the socket is writable.
+    pub fn is_writable(&self) -> bool {
+        self.events & libc::EPOLLOUT as u32 != 0
+    }
+
+    /// Returns `true` if an error condition was reported (`EPOLLERR`).
+    pub fn is_error(&self) -> bool {
+        self.events & libc::EPOLLERR as u32 != 0
+    }
+
+    /// Returns `true` if the peer hung up (`EPOLLHUP`).
+    pub fn is_hangup(&self) -> bool {
+        self.events & libc::EPOLLHUP as u32 != 0
+    }
+}
+
+impl fmt::Debug for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Event")
+            .field("token", &self.token)
+            .field("readable", &self.is_readable())
+            .field("writable", &self.is_writable())
+            .field("error", &self.is_error())
+            .field("hangup", &self.is_hangup())
+            .finish()
+    }
+}
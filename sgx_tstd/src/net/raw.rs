@@ -0,0 +1,291 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use crate::fmt;
+use crate::io::{self, Error, ErrorKind};
+use crate::net::{SocketAddr, ToSocketAddrs};
+use crate::sys_common::net as net_imp;
+use crate::sys_common::{AsInner, FromInner, IntoInner};
+use crate::time::Duration;
+
+use sgx_libc as c;
+use sgx_libc::c_int;
+
+/// The address family a [`RawSocket`] is created in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RawDomain {
+    /// IPv4 (`AF_INET`).
+    Ipv4,
+    /// IPv6 (`AF_INET6`).
+    Ipv6,
+}
+
+impl RawDomain {
+    fn family(self) -> c_int {
+        match self {
+            RawDomain::Ipv4 => c::AF_INET,
+            RawDomain::Ipv6 => c::AF_INET6,
+        }
+    }
+}
+
+/// A socket that exchanges IP payloads of an arbitrary protocol.
+///
+/// Unlike [`UdpSocket`], a `RawSocket` is not tied to a transport: it is
+/// created for an address family and an IP protocol number, and every
+/// datagram sent or received carries that protocol's header and payload. This
+/// is what ICMP echo ("ping") health checks and custom layer 4 protocols need.
+///
+/// Two flavours are available:
+///
+/// * [`RawSocket::new`] opens a `SOCK_RAW` socket. The host only grants these
+///   to processes holding `CAP_NET_RAW`. Received IPv4 datagrams include the IP
+///   header, and with [`set_header_included`] the enclave supplies its own.
+/// * [`RawSocket::new_datagram`] opens a `SOCK_DGRAM` socket for the protocol.
+///   With [`IPPROTO_ICMP`] or [`IPPROTO_ICMPV6`] this is the unprivileged ping
+///   socket Linux offers to groups listed in `net.ipv4.ping_group_range`; the
+///   host fills in the identifier and checksum of echo requests.
+///
+/// Port numbers in addresses passed to or returned by a `RawSocket` carry no
+/// meaning and are zero on received datagrams.
+///
+/// Raw sockets are only compiled in with the `raw_socket` feature, so enclaves
+/// that do not need them do not expose the capability.
+///
+/// [`UdpSocket`]: crate::net::UdpSocket
+/// [`set_header_included`]: RawSocket::set_header_included
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::net::{RawDomain, RawSocket, IPPROTO_ICMP};
+///
+/// fn main() -> std::io::Result<()> {
+///     let socket = RawSocket::new_datagram(RawDomain::Ipv4, IPPROTO_ICMP)?;
+///
+///     // An ICMP echo request: type 8, code 0, checksum and identifier left to the host.
+///     let request = [8, 0, 0, 0, 0, 0, 0, 1, b'p', b'i', b'n', b'g'];
+///     socket.send_to(&request, "10.0.0.1:0")?;
+///
+///     let mut reply = [0; 64];
+///     let (len, from) = socket.recv_from(&mut reply)?;
+///     println!("{} bytes from {}", len, from.ip());
+///     Ok(())
+/// }
+/// ```
+pub struct RawSocket(net_imp::RawSocket);
+
+/// The IP protocol number of ICMP, for use with [`RawSocket`].
+pub const IPPROTO_ICMP: i32 = c::IPPROTO_ICMP;
+
+/// The IP protocol number of ICMPv6, for use with [`RawSocket`].
+pub const IPPROTO_ICMPV6: i32 = c::IPPROTO_ICMPV6;
+
+impl RawSocket {
+    /// Creates a `SOCK_RAW` socket for the given address family and IP
+    /// protocol number.
+    ///
+    /// The host refuses this with [`io::ErrorKind::PermissionDenied`] unless
+    /// the application runs with `CAP_NET_RAW`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{RawDomain, RawSocket};
+    ///
+    /// // IP protocol 253 is reserved for experimentation.
+    /// let socket = RawSocket::new(RawDomain::Ipv4, 253).expect("couldn't open raw socket");
+    /// ```
+    pub fn new(domain: RawDomain, protocol: i32) -> io::Result<RawSocket> {
+        net_imp::RawSocket::new(domain.family(), c::SOCK_RAW, protocol).map(RawSocket)
+    }
+
+    /// Creates a `SOCK_DGRAM` socket for the given address family and IP
+    /// protocol number.
+    ///
+    /// Linux supports this for [`IPPROTO_ICMP`] and [`IPPROTO_ICMPV6`] without
+    /// special privileges when the application's group is within
+    /// `net.ipv4.ping_group_range`. Datagrams are ICMP messages without the IP
+    /// header.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{RawDomain, RawSocket, IPPROTO_ICMPV6};
+    ///
+    /// let socket = RawSocket::new_datagram(RawDomain::Ipv6, IPPROTO_ICMPV6)
+    ///     .expect("couldn't open ping socket");
+    /// ```
+    pub fn new_datagram(domain: RawDomain, protocol: i32) -> io::Result<RawSocket> {
+        net_imp::RawSocket::new(domain.family(), c::SOCK_DGRAM, protocol).map(RawSocket)
+    }
+
+    /// Binds the socket to a local address, restricting the datagrams it
+    /// receives to those addressed to it.
+    ///
+    /// If `addr` yields multiple addresses, binding is attempted with each of
+    /// them until one succeeds.
+    pub fn bind<A: ToSocketAddrs>(&self, addr: A) -> io::Result<()> {
+        super::each_addr(addr, |addr| self.0.bind(addr))
+    }
+
+    /// Connects the socket to a remote address, so that [`send`] and
+    /// [`recv`] can be used and only datagrams from that address are received.
+    ///
+    /// [`send`]: RawSocket::send
+    /// [`recv`]: RawSocket::recv
+    pub fn connect<A: ToSocketAddrs>(&self, addr: A) -> io::Result<()> {
+        super::each_addr(addr, |addr| self.0.connect(addr))
+    }
+
+    /// Returns the remote address this socket was connected to.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.0.peer_addr()
+    }
+
+    /// Returns the local address this socket is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.0.socket_addr()
+    }
+
+    /// Sends a datagram to the given address, returning the number of bytes
+    /// written.
+    ///
+    /// Only the IP address of `addr` is used; the port is ignored.
+    pub fn send_to<A: ToSocketAddrs>(&self, buf: &[u8], addr: A) -> io::Result<usize> {
+        match addr.to_socket_addrs()?.next() {
+            Some(addr) => self.0.send_to(buf, &addr),
+            None => Err(Error::new_const(ErrorKind::InvalidInput, &"no addresses to send data to")),
+        }
+    }
+
+    /// Receives a single datagram, returning the number of bytes read and the
+    /// address it came from.
+    ///
+    /// If `buf` is too small to hold the datagram, it is truncated.
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.0.recv_from(buf)
+    }
+
+    /// Sends a datagram to the connected remote address.
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        self.0.send(buf)
+    }
+
+    /// Receives a single datagram from the connected remote address.
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.recv(buf)
+    }
+
+    /// Sets the read timeout to the timeout specified.
+    ///
+    /// If the value specified is [`None`], then [`read`](RawSocket::recv)
+    /// calls will block indefinitely. An [`Err`] is returned if the zero
+    /// [`Duration`] is passed to this method.
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.0.set_read_timeout(dur)
+    }
+
+    /// Sets the write timeout to the timeout specified.
+    ///
+    /// If the value specified is [`None`], then [`send`](RawSocket::send)
+    /// calls will block indefinitely. An [`Err`] is returned if the zero
+    /// [`Duration`] is passed to this method.
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.0.set_write_timeout(dur)
+    }
+
+    /// Returns the read timeout of this socket.
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        self.0.read_timeout()
+    }
+
+    /// Returns the write timeout of this socket.
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        self.0.write_timeout()
+    }
+
+    /// Sets the value for the `IP_TTL` option on an IPv4 socket.
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        self.0.set_ttl(ttl)
+    }
+
+    /// Gets the value of the `IP_TTL` option for an IPv4 socket.
+    pub fn ttl(&self) -> io::Result<u32> {
+        self.0.ttl()
+    }
+
+    /// Sets the value for the `IPV6_UNICAST_HOPS` option on an IPv6 socket.
+    pub fn set_unicast_hops_v6(&self, hops: u32) -> io::Result<()> {
+        self.0.set_unicast_hops_v6(hops)
+    }
+
+    /// Gets the value of the `IPV6_UNICAST_HOPS` option for an IPv6 socket.
+    pub fn unicast_hops_v6(&self) -> io::Result<u32> {
+        self.0.unicast_hops_v6()
+    }
+
+    /// Sets the value for the `IP_HDRINCL` option on an IPv4 `SOCK_RAW` socket.
+    ///
+    /// When enabled, datagrams passed to [`send`] and [`send_to`] must start
+    /// with an IPv4 header built by the caller.
+    ///
+    /// [`send`]: RawSocket::send
+    /// [`send_to`]: RawSocket::send_to
+    pub fn set_header_included(&self, included: bool) -> io::Result<()> {
+        self.0.set_header_included(included)
+    }
+
+    /// Gets the value of the `IP_HDRINCL` option for this socket.
+    pub fn header_included(&self) -> io::Result<bool> {
+        self.0.header_included()
+    }
+
+    /// Gets the value of the `SO_ERROR` option on this socket, clearing it.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.0.take_error()
+    }
+
+    /// Moves this socket into or out of nonblocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.0.set_nonblocking(nonblocking)
+    }
+}
+
+impl AsInner<net_imp::RawSocket> for RawSocket {
+    fn as_inner(&self) -> &net_imp::RawSocket {
+        &self.0
+    }
+}
+
+impl FromInner<net_imp::RawSocket> for RawSocket {
+    fn from_inner(inner: net_imp::RawSocket) -> RawSocket {
+        RawSocket(inner)
+    }
+}
+
+impl IntoInner<net_imp::RawSocket> for RawSocket {
+    fn into_inner(self) -> net_imp::RawSocket {
+        self.0
+    }
+}
+
+impl fmt::Debug for RawSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
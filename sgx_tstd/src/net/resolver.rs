@@ -0,0 +1,262 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use crate::convert::TryFrom;
+use crate::io;
+use crate::mem;
+use crate::net::{IpAddr, SocketAddr};
+use crate::ptr;
+use crate::string::String;
+use crate::sync::atomic::{AtomicPtr, Ordering};
+use crate::sync::SgxThreadSpinlock;
+use crate::sys_common::net::LookupHost;
+use crate::time::{Duration, Instant};
+#[cfg(not(feature = "untrusted_time"))]
+use crate::untrusted::time::InstantEx;
+use crate::vec::{self, Vec};
+
+/// The addresses a host name resolves to, and how long they may be cached.
+///
+/// This is what a resolver installed with [`set_resolver`] returns.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Resolution {
+    addrs: Vec<IpAddr>,
+    ttl: Duration,
+}
+
+impl Resolution {
+    /// Creates a resolution to `addrs`, valid for `ttl`.
+    ///
+    /// A zero `ttl` keeps the result out of the cache.
+    pub fn new(addrs: Vec<IpAddr>, ttl: Duration) -> Resolution {
+        Resolution { addrs, ttl }
+    }
+
+    /// Returns the resolved addresses, in order of preference.
+    pub fn addrs(&self) -> &[IpAddr] {
+        &self.addrs
+    }
+
+    /// Returns how long the addresses may be cached.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+}
+
+/// A function resolving a host name, such as `"example.com"`, to addresses.
+pub type Resolver = fn(&str) -> io::Result<Resolution>;
+
+/// The default capacity of the resolver cache, in host names.
+pub const DEFAULT_RESOLVER_CACHE_CAPACITY: usize = 64;
+
+static RESOLVER: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+static RESOLVER_CACHE_LOCK: SgxThreadSpinlock = SgxThreadSpinlock::new();
+static mut RESOLVER_CACHE: Vec<CacheEntry> = Vec::new();
+static mut RESOLVER_CACHE_CAPACITY: usize = DEFAULT_RESOLVER_CACHE_CAPACITY;
+// Bumped whenever the cache is cleared, so that lookups started before are
+// not cached afterwards.
+static mut RESOLVER_CACHE_GENERATION: u64 = 0;
+static mut HOST_RESOLVER_TTL: Duration = Duration::from_secs(0);
+
+struct CacheEntry {
+    host: String,
+    addrs: Vec<IpAddr>,
+    expires: Instant,
+}
+
+/// Installs `resolver` to look up host names for [`ToSocketAddrs`], replacing
+/// the `getaddrinfo` OCALL or any previously installed resolver.
+///
+/// Host names are otherwise resolved by the untrusted host, which the enclave
+/// has to trust. A resolver lets the application pick where answers come
+/// from, for example DNS over HTTPS to a server it authenticates, and audit
+/// every lookup. It is authoritative: an error it returns is passed on to
+/// the caller rather than falling back to the host.
+///
+/// Strings that already are IP or socket addresses are never passed to it.
+/// Installing a resolver clears the cache.
+///
+/// [`ToSocketAddrs`]: crate::net::ToSocketAddrs
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io;
+/// use std::net::{self, IpAddr, Ipv4Addr, Resolution};
+/// use std::time::Duration;
+///
+/// fn resolve(host: &str) -> io::Result<Resolution> {
+///     match host {
+///         "db.internal" => Ok(Resolution::new(
+///             vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5))],
+///             Duration::from_secs(300),
+///         )),
+///         _ => Err(io::Error::new(io::ErrorKind::NotFound, "unknown host")),
+///     }
+/// }
+///
+/// net::set_resolver(resolve);
+/// ```
+pub fn set_resolver(resolver: Resolver) {
+    RESOLVER.store(resolver as *mut (), Ordering::SeqCst);
+    clear_resolver_cache();
+}
+
+/// Removes the resolver installed with [`set_resolver`], returning it.
+///
+/// Host names are resolved with the `getaddrinfo` OCALL again afterwards,
+/// and the cache is cleared.
+pub fn take_resolver() -> Option<Resolver> {
+    let resolver = RESOLVER.swap(ptr::null_mut(), Ordering::SeqCst);
+    clear_resolver_cache();
+    if resolver.is_null() { None } else { Some(unsafe { mem::transmute(resolver) }) }
+}
+
+/// Sets how many host names the resolver cache holds, evicting entries if
+/// it holds more. A capacity of 0 disables caching.
+///
+/// The default is [`DEFAULT_RESOLVER_CACHE_CAPACITY`].
+pub fn set_resolver_cache_capacity(capacity: usize) {
+    unsafe {
+        RESOLVER_CACHE_LOCK.lock();
+        RESOLVER_CACHE_CAPACITY = capacity;
+        while RESOLVER_CACHE.len() > capacity {
+            evict_one();
+        }
+        RESOLVER_CACHE_LOCK.unlock();
+    }
+}
+
+/// Sets how long answers from the `getaddrinfo` OCALL are cached.
+///
+/// `getaddrinfo` does not report the TTL of its answers, so they are not
+/// cached unless this is set. Answers from a resolver installed with
+/// [`set_resolver`] carry their own TTL instead.
+pub fn set_host_resolver_ttl(ttl: Duration) {
+    unsafe {
+        RESOLVER_CACHE_LOCK.lock();
+        HOST_RESOLVER_TTL = ttl;
+        RESOLVER_CACHE_LOCK.unlock();
+    }
+}
+
+/// Drops every cached host name.
+pub fn clear_resolver_cache() {
+    unsafe {
+        RESOLVER_CACHE_LOCK.lock();
+        RESOLVER_CACHE.clear();
+        RESOLVER_CACHE_GENERATION = RESOLVER_CACHE_GENERATION.wrapping_add(1);
+        RESOLVER_CACHE_LOCK.unlock();
+    }
+}
+
+// Resolves `host` through the cache, then the installed resolver or the host.
+// No lock is held while resolving, so concurrent misses for the same name
+// may each resolve it, and the resolver may be replaced meanwhile. The answer
+// is then not cached, as it may come from the resolver that was replaced.
+pub(super) fn resolve_host(host: &str, port: u16) -> io::Result<vec::IntoIter<SocketAddr>> {
+    let now = Instant::now();
+    let addrs = match cache_lookup(host, now) {
+        Ok(addrs) => addrs,
+        Err(generation) => {
+            let resolver = RESOLVER.load(Ordering::SeqCst);
+            let resolution = if resolver.is_null() {
+                lookup_host(host)?
+            } else {
+                let resolver: Resolver = unsafe { mem::transmute(resolver) };
+                resolver(host)?
+            };
+            cache_insert(host, &resolution, now, generation);
+            resolution.addrs
+        }
+    };
+    let addrs: Vec<SocketAddr> = addrs.into_iter().map(|ip| SocketAddr::new(ip, port)).collect();
+    Ok(addrs.into_iter())
+}
+
+fn lookup_host(host: &str) -> io::Result<Resolution> {
+    let mut addrs: Vec<IpAddr> = Vec::new();
+    for addr in LookupHost::try_from((host, 0))? {
+        // `getaddrinfo` lists an address once per socket type.
+        if !addrs.contains(&addr.ip()) {
+            addrs.push(addr.ip());
+        }
+    }
+    let ttl = unsafe {
+        RESOLVER_CACHE_LOCK.lock();
+        let ttl = HOST_RESOLVER_TTL;
+        RESOLVER_CACHE_LOCK.unlock();
+        ttl
+    };
+    Ok(Resolution::new(addrs, ttl))
+}
+
+// On a miss, returns the generation of the cache to pass to `cache_insert`.
+fn cache_lookup(host: &str, now: Instant) -> Result<Vec<IpAddr>, u64> {
+    unsafe {
+        RESOLVER_CACHE_LOCK.lock();
+        RESOLVER_CACHE.retain(|entry| entry.expires > now);
+        let addrs = RESOLVER_CACHE
+            .iter()
+            .find(|entry| entry.host == host)
+            .map(|entry| entry.addrs.clone())
+            .ok_or(RESOLVER_CACHE_GENERATION);
+        RESOLVER_CACHE_LOCK.unlock();
+        addrs
+    }
+}
+
+fn cache_insert(host: &str, resolution: &Resolution, now: Instant, generation: u64) {
+    if resolution.ttl == Duration::from_secs(0) || resolution.addrs.is_empty() {
+        return;
+    }
+    let expires = match now.checked_add(resolution.ttl) {
+        Some(expires) => expires,
+        None => return,
+    };
+    unsafe {
+        RESOLVER_CACHE_LOCK.lock();
+        if generation != RESOLVER_CACHE_GENERATION {
+            RESOLVER_CACHE_LOCK.unlock();
+            return;
+        }
+        RESOLVER_CACHE.retain(|entry| entry.host != host);
+        if RESOLVER_CACHE_CAPACITY > 0 {
+            while RESOLVER_CACHE.len() >= RESOLVER_CACHE_CAPACITY {
+                evict_one();
+            }
+            RESOLVER_CACHE.push(CacheEntry {
+                host: host.into(),
+                addrs: resolution.addrs.clone(),
+                expires,
+            });
+        }
+        RESOLVER_CACHE_LOCK.unlock();
+    }
+}
+
+// Drops the entry closest to expiring. The caller must hold the cache lock.
+unsafe fn evict_one() {
+    let soonest = RESOLVER_CACHE
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, entry)| entry.expires)
+        .map(|(i, _)| i);
+    if let Some(i) = soonest {
+        RESOLVER_CACHE.swap_remove(i);
+    }
+}
@@ -17,9 +17,14 @@
 
 use crate::io::prelude::*;
 
+use crate::cmp;
 use crate::fmt;
-use crate::io::{self, Initializer, IoSlice, IoSliceMut};
-use crate::net::{Shutdown, SocketAddr, ToSocketAddrs};
+use crate::io::{self, Initializer, IoSlice, IoSliceMut, TakeReservation};
+use crate::net::{
+    BufferPool, PooledBuf, RetryPolicy, Shutdown, SocketAddr, SocketOptions, ToSocketAddrs,
+};
+use crate::ops::{Bound, RangeBounds};
+use crate::sgxfs::SgxFile;
 use crate::sys_common::net as net_imp;
 use crate::sys_common::{AsInner, FromInner, IntoInner};
 use crate::time::Duration;
@@ -165,6 +170,66 @@ impl TcpStream {
         super::each_addr(addr, net_imp::TcpStream::connect).map(TcpStream)
     }
 
+    /// Opens a TCP connection to a remote host, retrying according to
+    /// `policy`.
+    ///
+    /// Each attempt behaves like [`TcpStream::connect`]. When an attempt fails
+    /// with a transient error such as [`io::ErrorKind::ConnectionRefused`],
+    /// the calling thread sleeps in the host for the policy's backoff and then
+    /// tries again, until the maximum number of attempts is reached. This
+    /// helps clients that may start before the peer is listening. Other
+    /// errors are returned immediately.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{RetryPolicy, TcpStream};
+    /// use std::time::Duration;
+    ///
+    /// let policy = RetryPolicy::new(10, Duration::from_millis(200));
+    /// let stream = TcpStream::connect_with_retry("127.0.0.1:8080", &policy)
+    ///                        .expect("Couldn't connect to the server...");
+    /// ```
+    pub fn connect_with_retry<A: ToSocketAddrs>(
+        addr: A,
+        policy: &RetryPolicy,
+    ) -> io::Result<TcpStream> {
+        super::each_addr_retry(addr, policy, net_imp::TcpStream::connect).map(TcpStream)
+    }
+
+    /// Opens a TCP connection to a remote host, racing attempts to its
+    /// addresses in the manner of "Happy Eyeballs" (RFC 8305).
+    ///
+    /// [`TcpStream::connect`] tries the addresses one after another, so an
+    /// unreachable first address, such as an IPv6 address on a host without
+    /// IPv6 connectivity, stalls it for the full connect timeout of the OS.
+    /// This function instead alternates between IPv6 and IPv4 addresses,
+    /// starting with the family of the first resolved address, and starts the
+    /// next attempt whenever `stagger` passes without a connection or all
+    /// started attempts have failed. The first attempt to succeed is returned
+    /// and the others are abandoned.
+    ///
+    /// [`CONNECTION_ATTEMPT_DELAY`] is the stagger RFC 8305 recommends. If no
+    /// attempt succeeds, the error from the last one to fail is returned.
+    ///
+    /// [`CONNECTION_ATTEMPT_DELAY`]: crate::net::CONNECTION_ATTEMPT_DELAY
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{TcpStream, CONNECTION_ATTEMPT_DELAY};
+    ///
+    /// let stream = TcpStream::connect_happy_eyeballs("example.com:443", CONNECTION_ATTEMPT_DELAY)
+    ///                        .expect("Couldn't connect to the server...");
+    /// ```
+    pub fn connect_happy_eyeballs<A: ToSocketAddrs>(
+        addr: A,
+        stagger: Duration,
+    ) -> io::Result<TcpStream> {
+        let addrs = super::interleave_families(addr.to_socket_addrs()?.collect());
+        net_imp::TcpStream::connect_racing(&addrs, stagger).map(TcpStream)
+    }
+
     /// Opens a TCP connection to a remote host.
     ///
     /// `addr` is an address of the remote host. Anything which implements
@@ -423,6 +488,142 @@ impl TcpStream {
         self.0.write_timeout()
     }
 
+    /// Writes as much of `buf` as the peer accepts within `timeout`, returning
+    /// the number of bytes written.
+    ///
+    /// Unlike [`write_all`] with a write timeout set, running out of time is
+    /// not an error: the returned count is simply less than `buf.len()`, and
+    /// the caller knows exactly which part of the buffer is still unsent.
+    /// Interrupted writes are retried. Errors other than the timeout are
+    /// returned as usual, in which case an unknown prefix may have been
+    /// written.
+    ///
+    /// This method does not depend on [`set_write_timeout`] and leaves the
+    /// socket in blocking mode.
+    ///
+    /// [`write_all`]: Write::write_all
+    /// [`set_write_timeout`]: TcpStream::set_write_timeout
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpStream;
+    /// use std::time::Duration;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// let frame = [0u8; 4096];
+    /// let n = stream.write_all_timeout(&frame, Duration::from_secs(1))
+    ///               .expect("write failed");
+    /// if n < frame.len() {
+    ///     println!("peer is slow, {} bytes still pending", frame.len() - n);
+    /// }
+    /// ```
+    pub fn write_all_timeout(&self, buf: &[u8], timeout: Duration) -> io::Result<usize> {
+        self.0.write_all_timeout(buf, timeout)
+    }
+
+    /// Blocks until the stream is readable, or until `timeout` elapses.
+    ///
+    /// The stream is readable when data is queued, the peer has closed its
+    /// side of the connection, or an error is pending, so the next [`read`]
+    /// will not block. No data is consumed. With a timeout of [`None`] this
+    /// waits indefinitely.
+    ///
+    /// The wait is a single `poll` OCALL; if the timeout elapses first, an
+    /// error of kind [`TimedOut`] is returned.
+    ///
+    /// [`read`]: Read::read
+    /// [`TimedOut`]: io::ErrorKind::TimedOut
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::io::Read;
+    /// use std::net::TcpStream;
+    /// use std::time::Duration;
+    ///
+    /// let mut stream = TcpStream::connect("127.0.0.1:8080")
+    ///                            .expect("Couldn't connect to the server...");
+    /// stream.wait_readable(Some(Duration::from_secs(1))).expect("no data in time");
+    /// let mut buf = [0; 128];
+    /// stream.read(&mut buf).expect("read failed");
+    /// ```
+    pub fn wait_readable(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.0.wait_readable(timeout)
+    }
+
+    /// Blocks until the stream is writable, or until `timeout` elapses.
+    ///
+    /// See [`TcpStream::wait_readable`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpStream;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// stream.wait_writable(None).expect("wait_writable call failed");
+    /// ```
+    pub fn wait_writable(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.0.wait_writable(timeout)
+    }
+
+    /// Sends the bytes of a protected file that fall within `range`,
+    /// returning the number of bytes sent.
+    ///
+    /// The file is decrypted a chunk at a time and each chunk is copied once
+    /// into a buffer in untrusted memory that is reused for the whole transfer,
+    /// so every chunk costs a single send OCALL. Writing the file through
+    /// [`write_all`] instead allocates and frees a fresh untrusted buffer around
+    /// each large write.
+    ///
+    /// Fewer bytes than the range spans are sent if the file ends first. The
+    /// file's cursor is moved while reading and put back before returning,
+    /// also on error. The data leaves the enclave in the clear, as with any
+    /// other write to this stream.
+    ///
+    /// If an error is returned, an unspecified prefix of the range may already
+    /// have been sent.
+    ///
+    /// [`write_all`]: Write::write_all
+    ///
+    /// # Errors
+    ///
+    /// An error of kind [`io::ErrorKind::InvalidInput`] is returned if the
+    /// range ends before it starts or its bounds overflow a `u64`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpStream;
+    /// use std::sgxfs::SgxFile;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// let file = SgxFile::open("assets.bin").expect("open failed");
+    /// let sent = stream.send_file(&file, 4096..).expect("send_file call failed");
+    /// ```
+    pub fn send_file<R: RangeBounds<u64>>(&self, file: &SgxFile, range: R) -> io::Result<u64> {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => Some(start),
+            Bound::Excluded(&start) => start.checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.checked_add(1).map(Some),
+            Bound::Excluded(&end) => Some(Some(end)),
+            Bound::Unbounded => Some(None),
+        };
+        match (start, end) {
+            (Some(start), Some(end)) if end.map_or(true, |end| start <= end) => {
+                self.0.send_file(file, start, end)
+            }
+            _ => Err(io::Error::new_const(io::ErrorKind::InvalidInput, &"invalid file range")),
+        }
+    }
+
     /// Receives data on the socket from the remote address to which it is
     /// connected, without removing that data from the queue. On success,
     /// returns the number of bytes peeked.
@@ -444,6 +645,32 @@ impl TcpStream {
         self.0.peek(buf)
     }
 
+    /// Reads from the stream into a buffer taken from `pool`.
+    ///
+    /// This makes a single call to [`read`], and the returned buffer holds
+    /// exactly the bytes that were read. An empty buffer means the peer has
+    /// closed its end of the connection. The buffer goes back to `pool` when
+    /// it is dropped.
+    ///
+    /// [`read`]: Read::read
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{BufferPool, TcpStream};
+    ///
+    /// let pool = BufferPool::new(4096, 8);
+    /// let stream = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// let buf = stream.read_into_pooled(&pool).expect("read_into_pooled call failed");
+    /// println!("{:?}", &buf[..]);
+    /// ```
+    pub fn read_into_pooled<'a>(&self, pool: &'a BufferPool) -> io::Result<PooledBuf<'a>> {
+        let mut buf = pool.get();
+        buf.fill_with(|b| self.0.read(b))?;
+        Ok(buf)
+    }
+
     /// Sets the value of the `SO_LINGER` option on this socket.
     ///
     /// This value controls how the socket is closed when data remains
@@ -489,6 +716,168 @@ impl TcpStream {
         self.0.linger()
     }
 
+    /// Sets the value of the `SO_KEEPALIVE` option on this socket.
+    ///
+    /// If set, the host sends keepalive probes on an otherwise idle
+    /// connection and reports it broken if the peer stops answering. The
+    /// probes are tuned with [`TcpStream::set_keepalive_idle`],
+    /// [`TcpStream::set_keepalive_interval`] and [`TcpStream::set_keepalive_count`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpStream;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// stream.set_keepalive(true).expect("set_keepalive call failed");
+    /// ```
+    pub fn set_keepalive(&self, keepalive: bool) -> io::Result<()> {
+        self.0.set_keepalive(keepalive)
+    }
+
+    /// Gets the value of the `SO_KEEPALIVE` option on this socket.
+    ///
+    /// For more information about this option, see [`TcpStream::set_keepalive`].
+    pub fn keepalive(&self) -> io::Result<bool> {
+        self.0.keepalive()
+    }
+
+    /// Sets the value of the `TCP_KEEPIDLE` option on this socket.
+    ///
+    /// This is how long the connection must be idle before the first
+    /// keepalive probe is sent. The host counts whole seconds, so the
+    /// duration is rounded up; it must be between 1 and 32767 seconds,
+    /// otherwise an error of kind [`io::ErrorKind::InvalidInput`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpStream;
+    /// use std::time::Duration;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// stream.set_keepalive_idle(Duration::from_secs(60)).expect("set_keepalive_idle call failed");
+    /// ```
+    pub fn set_keepalive_idle(&self, idle: Duration) -> io::Result<()> {
+        self.0.set_keepalive_idle(idle)
+    }
+
+    /// Gets the value of the `TCP_KEEPIDLE` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`TcpStream::set_keepalive_idle`].
+    pub fn keepalive_idle(&self) -> io::Result<Duration> {
+        self.0.keepalive_idle()
+    }
+
+    /// Sets the value of the `TCP_KEEPINTVL` option on this socket.
+    ///
+    /// This is the time between two unanswered keepalive probes, with the
+    /// same rounding and range as [`TcpStream::set_keepalive_idle`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpStream;
+    /// use std::time::Duration;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// stream.set_keepalive_interval(Duration::from_secs(10)).unwrap();
+    /// ```
+    pub fn set_keepalive_interval(&self, interval: Duration) -> io::Result<()> {
+        self.0.set_keepalive_interval(interval)
+    }
+
+    /// Gets the value of the `TCP_KEEPINTVL` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`TcpStream::set_keepalive_interval`].
+    pub fn keepalive_interval(&self) -> io::Result<Duration> {
+        self.0.keepalive_interval()
+    }
+
+    /// Sets the value of the `TCP_KEEPCNT` option on this socket.
+    ///
+    /// This is how many unanswered keepalive probes are sent before the
+    /// connection is dropped. It must be between 1 and 127, otherwise an
+    /// error of kind [`io::ErrorKind::InvalidInput`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpStream;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// stream.set_keepalive_count(5).expect("set_keepalive_count call failed");
+    /// ```
+    pub fn set_keepalive_count(&self, count: u32) -> io::Result<()> {
+        self.0.set_keepalive_count(count)
+    }
+
+    /// Gets the value of the `TCP_KEEPCNT` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`TcpStream::set_keepalive_count`].
+    pub fn keepalive_count(&self) -> io::Result<u32> {
+        self.0.keepalive_count()
+    }
+
+    /// Sets the value of the `SO_REUSEADDR` option on this socket.
+    ///
+    /// This allows binding to an address that still has connections in the
+    /// `TIME_WAIT` state.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpStream;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// stream.set_reuse_address(true).expect("set_reuse_address call failed");
+    /// ```
+    pub fn set_reuse_address(&self, reuse: bool) -> io::Result<()> {
+        self.0.set_reuse_address(reuse)
+    }
+
+    /// Gets the value of the `SO_REUSEADDR` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`TcpStream::set_reuse_address`].
+    pub fn reuse_address(&self) -> io::Result<bool> {
+        self.0.reuse_address()
+    }
+
+    /// Sets the value of the `IP_TOS` option on this socket.
+    ///
+    /// This value sets the type-of-service field of every IPv4 packet sent
+    /// from this socket. It must fit in a byte, otherwise an error of kind
+    /// [`io::ErrorKind::InvalidInput`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpStream;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// stream.set_tos(0x10).expect("set_tos call failed");
+    /// ```
+    pub fn set_tos(&self, tos: u32) -> io::Result<()> {
+        self.0.set_tos(tos)
+    }
+
+    /// Gets the value of the `IP_TOS` option on this socket.
+    ///
+    /// For more information about this option, see [`TcpStream::set_tos`].
+    pub fn tos(&self) -> io::Result<u32> {
+        self.0.tos()
+    }
+
     /// Sets the value of the `TCP_NODELAY` option on this socket.
     ///
     /// If set, this option disables the Nagle algorithm. This means that
@@ -528,6 +917,144 @@ impl TcpStream {
         self.0.nodelay()
     }
 
+    /// Applies every option set in `opts` to this socket with a single
+    /// OCALL.
+    ///
+    /// The options are applied in the order they are declared in
+    /// [`SocketOptions`], and the first one rejected by the host stops the
+    /// others from being applied. The error then names that option, for
+    /// example `SO_RCVBUF`, and keeps the [`ErrorKind`] of the failure.
+    /// Options applied before it stay in effect.
+    ///
+    /// [`ErrorKind`]: io::ErrorKind
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{SocketOptions, TcpStream};
+    /// use std::time::Duration;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// let opts = SocketOptions::new()
+    ///     .nodelay(true)
+    ///     .ttl(64)
+    ///     .read_timeout(Some(Duration::from_secs(5)));
+    /// stream.configure(&opts).expect("configure call failed");
+    /// ```
+    pub fn configure(&self, opts: &SocketOptions) -> io::Result<()> {
+        self.0.configure(opts)
+    }
+
+    /// Gets the value of the `SO_RCVBUF` option on this socket.
+    ///
+    /// On Linux this is twice the value that was set, as the kernel reserves
+    /// room for its own bookkeeping.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpStream;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// println!("{}", stream.recv_buffer_size().expect("recv_buffer_size call failed"));
+    /// ```
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        self.0.recv_buffer_size()
+    }
+
+    /// Gets the value of the `SO_SNDBUF` option on this socket.
+    ///
+    /// On Linux this is twice the value that was set, as the kernel reserves
+    /// room for its own bookkeeping.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpStream;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// println!("{}", stream.send_buffer_size().expect("send_buffer_size call failed"));
+    /// ```
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        self.0.send_buffer_size()
+    }
+
+    /// Sets the value of the `TCP_QUICKACK` option on this socket.
+    ///
+    /// If set, ACKs are sent immediately rather than delayed, which lowers
+    /// latency for request/response workloads. The option is not permanent:
+    /// the kernel may fall back to delayed ACKs after subsequent operations,
+    /// so callers that depend on it may need to re-apply it, for example after
+    /// each read.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpStream;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// stream.set_quickack(true).expect("set_quickack call failed");
+    /// ```
+    pub fn set_quickack(&self, quickack: bool) -> io::Result<()> {
+        self.0.set_quickack(quickack)
+    }
+
+    /// Gets the value of the `TCP_QUICKACK` option on this socket.
+    ///
+    /// For more information about this option, see [`TcpStream::set_quickack`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpStream;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// stream.set_quickack(true).expect("set_quickack call failed");
+    /// assert_eq!(stream.quickack().unwrap_or(false), true);
+    /// ```
+    pub fn quickack(&self) -> io::Result<bool> {
+        self.0.quickack()
+    }
+
+    /// Makes reads report end-of-file instead of an error when the peer resets
+    /// the connection.
+    ///
+    /// When enabled, a read that fails with [`io::ErrorKind::ConnectionReset`]
+    /// returns `Ok(0)` instead, which suits ported protocols that expect a
+    /// plain EOF when the peer goes away. Writes are unaffected and still fail
+    /// on a reset connection. This is off by default.
+    ///
+    /// Note that this masks resets: an abortive close looks the same as an
+    /// orderly shutdown, so a truncated message can be mistaken for a complete
+    /// one. Only enable it when the protocol detects truncation on its own.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpStream;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// stream.set_eof_on_reset(true);
+    /// assert!(stream.eof_on_reset());
+    /// ```
+    pub fn set_eof_on_reset(&self, eof_on_reset: bool) {
+        self.0.set_eof_on_reset(eof_on_reset)
+    }
+
+    /// Returns whether reads report end-of-file when the peer resets the
+    /// connection.
+    ///
+    /// For more information, see [`TcpStream::set_eof_on_reset`].
+    pub fn eof_on_reset(&self) -> bool {
+        self.0.eof_on_reset()
+    }
+
     /// Sets the value for the `IP_TTL` option on this socket.
     ///
     /// This value sets the time-to-live field that is used in every packet sent
@@ -564,6 +1091,33 @@ impl TcpStream {
         self.0.ttl()
     }
 
+    /// Sets the value of the `SO_MARK` option on this socket.
+    ///
+    /// The mark is attached to every packet sent from this socket and can be
+    /// matched by host firewall rules or used for policy routing. Setting it
+    /// typically requires `CAP_NET_ADMIN` on the host; without it this
+    /// returns an error of kind [`io::ErrorKind::PermissionDenied`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpStream;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// stream.set_mark(42).expect("set_mark call failed");
+    /// ```
+    pub fn set_mark(&self, mark: u32) -> io::Result<()> {
+        self.0.set_mark(mark)
+    }
+
+    /// Gets the value of the `SO_MARK` option for this socket.
+    ///
+    /// For more information about this option, see [`TcpStream::set_mark`].
+    pub fn mark(&self) -> io::Result<u32> {
+        self.0.mark()
+    }
+
     /// Gets the value of the `SO_ERROR` option on this socket.
     ///
     /// This will retrieve the stored error in the underlying socket, clearing
@@ -583,6 +1137,27 @@ impl TcpStream {
         self.0.take_error()
     }
 
+    /// Closes the socket, returning any error reported by `close`.
+    ///
+    /// Dropping a socket also closes it but ignores errors, unless a handler
+    /// was installed with [`set_close_error_handler`]. Whether or not an error
+    /// is returned, the descriptor must be considered closed.
+    ///
+    /// [`set_close_error_handler`]: crate::net::set_close_error_handler
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpStream;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// stream.close().expect("close call failed");
+    /// ```
+    pub fn close(self) -> io::Result<()> {
+        self.0.close()
+    }
+
     /// Moves this TCP stream into or out of nonblocking mode.
     ///
     /// This will result in `read`, `write`, `recv` and `send` operations
@@ -714,6 +1289,25 @@ impl Write for &TcpStream {
     }
 }
 
+// Reserve the whole remaining limit (up to `MAX_TAKE_RESERVATION`) so that
+// `stream.take(n).read_to_end(..)` reads a frame in a single OCALL when the
+// data is already there, instead of growing the buffer from 32 bytes.
+const MAX_TAKE_RESERVATION: u64 = 64 * 1024;
+
+impl TakeReservation for TcpStream {
+    #[inline]
+    fn take_reservation(&self, limit: u64) -> usize {
+        cmp::min(limit, MAX_TAKE_RESERVATION) as usize
+    }
+}
+
+impl TakeReservation for &TcpStream {
+    #[inline]
+    fn take_reservation(&self, limit: u64) -> usize {
+        cmp::min(limit, MAX_TAKE_RESERVATION) as usize
+    }
+}
+
 impl AsInner<net_imp::TcpStream> for TcpStream {
     fn as_inner(&self) -> &net_imp::TcpStream {
         &self.0
@@ -794,6 +1388,26 @@ impl TcpListener {
         super::each_addr(addr, net_imp::TcpListener::bind).map(TcpListener)
     }
 
+    /// Creates a new `TcpListener` bound to the specified address, using
+    /// `backlog` as the maximum length of the queue of pending connections.
+    ///
+    /// [`TcpListener::bind`] uses a backlog of 128, which may be too small
+    /// for bursty services. Values below 1 are clamped to 1, and the host
+    /// kernel may silently cap large values (see `net.core.somaxconn`).
+    /// Address resolution behaves as for [`TcpListener::bind`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpListener;
+    ///
+    /// let listener = TcpListener::bind_with_backlog("127.0.0.1:80", 1024).unwrap();
+    /// ```
+    pub fn bind_with_backlog<A: ToSocketAddrs>(addr: A, backlog: i32) -> io::Result<TcpListener> {
+        super::each_addr(addr, |addr| net_imp::TcpListener::bind_with_backlog(addr, backlog))
+            .map(TcpListener)
+    }
+
     /// TcpListener will be bound to the specified address.
     ///
     /// The returned listener is ready for accepting connections.
@@ -853,6 +1467,9 @@ impl TcpListener {
     /// is established. When established, the corresponding [`TcpStream`] and the
     /// remote peer's address will be returned.
     ///
+    /// If a timeout was set with [`TcpListener::set_accept_timeout`], this
+    /// behaves like [`TcpListener::accept_timeout`] with that timeout.
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -868,6 +1485,80 @@ impl TcpListener {
         self.0.accept().map(|(a, b)| (TcpStream(a), b))
     }
 
+    /// Accepts a new incoming connection, waiting no longer than `timeout`
+    /// for one to arrive.
+    ///
+    /// The listener is polled for a pending connection, so a server can wake
+    /// up periodically, for example to rotate keys or check a shutdown flag,
+    /// instead of blocking in [`accept`] indefinitely. An error of kind
+    /// [`io::ErrorKind::TimedOut`] is returned if no connection arrives in
+    /// time.
+    ///
+    /// When several threads accept on the same listener, one of them may take
+    /// the connection another was woken for. Put the listener in nonblocking
+    /// mode so that the losing thread goes back to waiting rather than
+    /// blocking past its timeout.
+    ///
+    /// [`accept`]: TcpListener::accept
+    ///
+    /// # Errors
+    ///
+    /// An error of kind [`io::ErrorKind::InvalidInput`] is returned if
+    /// `timeout` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::io::ErrorKind;
+    /// use std::net::TcpListener;
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::time::Duration;
+    ///
+    /// static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:8080").unwrap();
+    /// while !SHUTDOWN.load(Ordering::Relaxed) {
+    ///     match listener.accept_timeout(Duration::from_secs(1)) {
+    ///         Ok((_socket, addr)) => println!("new client: {:?}", addr),
+    ///         Err(ref e) if e.kind() == ErrorKind::TimedOut => continue,
+    ///         Err(e) => println!("couldn't get client: {:?}", e),
+    ///     }
+    /// }
+    /// ```
+    pub fn accept_timeout(&self, timeout: Duration) -> io::Result<(TcpStream, SocketAddr)> {
+        self.0.accept_timeout(timeout).map(|(a, b)| (TcpStream(a), b))
+    }
+
+    /// Sets a timeout applied to every [`accept`], including those made by
+    /// [`incoming`].
+    ///
+    /// If the value specified is [`None`], then [`accept`] calls will block
+    /// indefinitely. Clones made with [`try_clone`] start with the timeout
+    /// this listener has at the time.
+    ///
+    /// [`accept`]: TcpListener::accept
+    /// [`incoming`]: TcpListener::incoming
+    /// [`try_clone`]: TcpListener::try_clone
+    ///
+    /// # Errors
+    ///
+    /// An error of kind [`io::ErrorKind::InvalidInput`] is returned if the
+    /// zero [`Duration`] is passed to this method.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpListener;
+    /// use std::time::Duration;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:8080").unwrap();
+    /// listener.set_accept_timeout(Some(Duration::from_secs(5)))
+    ///         .expect("set_accept_timeout call failed");
+    /// ```
+    pub fn set_accept_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.0.set_accept_timeout(timeout)
+    }
+
     /// Returns an iterator over the connections being received on this
     /// listener.
     ///
@@ -946,6 +1637,189 @@ impl TcpListener {
         self.0.only_v6()
     }
 
+    /// Sets the value of the `SO_LINGER` option on this socket.
+    ///
+    /// Connections accepted from this listener inherit the setting. For more
+    /// information about this option, see [`TcpStream::set_linger`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpListener;
+    /// use std::time::Duration;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:80").unwrap();
+    /// listener.set_linger(Some(Duration::from_secs(0))).expect("set_linger call failed");
+    /// ```
+    pub fn set_linger(&self, linger: Option<Duration>) -> io::Result<()> {
+        self.0.set_linger(linger)
+    }
+
+    /// Gets the value of the `SO_LINGER` option on this socket.
+    ///
+    /// For more information about this option, see [`TcpListener::set_linger`].
+    pub fn linger(&self) -> io::Result<Option<Duration>> {
+        self.0.linger()
+    }
+
+    /// Sets the value of the `SO_KEEPALIVE` option on this socket.
+    ///
+    /// If set, the host sends keepalive probes on an otherwise idle
+    /// connection and reports it broken if the peer stops answering. The
+    /// probes are tuned with [`TcpListener::set_keepalive_idle`],
+    /// [`TcpListener::set_keepalive_interval`] and [`TcpListener::set_keepalive_count`].
+    ///
+    /// Connections accepted from this listener inherit the setting.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpListener;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:80").unwrap();
+    /// listener.set_keepalive(true).expect("set_keepalive call failed");
+    /// ```
+    pub fn set_keepalive(&self, keepalive: bool) -> io::Result<()> {
+        self.0.set_keepalive(keepalive)
+    }
+
+    /// Gets the value of the `SO_KEEPALIVE` option on this socket.
+    ///
+    /// For more information about this option, see [`TcpListener::set_keepalive`].
+    pub fn keepalive(&self) -> io::Result<bool> {
+        self.0.keepalive()
+    }
+
+    /// Sets the value of the `TCP_KEEPIDLE` option on this socket.
+    ///
+    /// This is how long the connection must be idle before the first
+    /// keepalive probe is sent. The host counts whole seconds, so the
+    /// duration is rounded up; it must be between 1 and 32767 seconds,
+    /// otherwise an error of kind [`io::ErrorKind::InvalidInput`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpListener;
+    /// use std::time::Duration;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:80").unwrap();
+    /// listener.set_keepalive_idle(Duration::from_secs(60)).unwrap();
+    /// ```
+    pub fn set_keepalive_idle(&self, idle: Duration) -> io::Result<()> {
+        self.0.set_keepalive_idle(idle)
+    }
+
+    /// Gets the value of the `TCP_KEEPIDLE` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`TcpListener::set_keepalive_idle`].
+    pub fn keepalive_idle(&self) -> io::Result<Duration> {
+        self.0.keepalive_idle()
+    }
+
+    /// Sets the value of the `TCP_KEEPINTVL` option on this socket.
+    ///
+    /// This is the time between two unanswered keepalive probes, with the
+    /// same rounding and range as [`TcpListener::set_keepalive_idle`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpListener;
+    /// use std::time::Duration;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:80").unwrap();
+    /// listener.set_keepalive_interval(Duration::from_secs(10)).unwrap();
+    /// ```
+    pub fn set_keepalive_interval(&self, interval: Duration) -> io::Result<()> {
+        self.0.set_keepalive_interval(interval)
+    }
+
+    /// Gets the value of the `TCP_KEEPINTVL` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`TcpListener::set_keepalive_interval`].
+    pub fn keepalive_interval(&self) -> io::Result<Duration> {
+        self.0.keepalive_interval()
+    }
+
+    /// Sets the value of the `TCP_KEEPCNT` option on this socket.
+    ///
+    /// This is how many unanswered keepalive probes are sent before the
+    /// connection is dropped. It must be between 1 and 127, otherwise an
+    /// error of kind [`io::ErrorKind::InvalidInput`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpListener;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:80").unwrap();
+    /// listener.set_keepalive_count(5).expect("set_keepalive_count call failed");
+    /// ```
+    pub fn set_keepalive_count(&self, count: u32) -> io::Result<()> {
+        self.0.set_keepalive_count(count)
+    }
+
+    /// Gets the value of the `TCP_KEEPCNT` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`TcpListener::set_keepalive_count`].
+    pub fn keepalive_count(&self) -> io::Result<u32> {
+        self.0.keepalive_count()
+    }
+
+    /// Sets the value of the `SO_REUSEADDR` option on this socket.
+    ///
+    /// This allows binding to an address that still has connections in the
+    /// `TIME_WAIT` state.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpListener;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:80").unwrap();
+    /// listener.set_reuse_address(true).expect("set_reuse_address call failed");
+    /// ```
+    pub fn set_reuse_address(&self, reuse: bool) -> io::Result<()> {
+        self.0.set_reuse_address(reuse)
+    }
+
+    /// Gets the value of the `SO_REUSEADDR` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`TcpListener::set_reuse_address`].
+    pub fn reuse_address(&self) -> io::Result<bool> {
+        self.0.reuse_address()
+    }
+
+    /// Sets the value of the `IP_TOS` option on this socket.
+    ///
+    /// This value sets the type-of-service field of every IPv4 packet sent
+    /// from this socket. It must fit in a byte, otherwise an error of kind
+    /// [`io::ErrorKind::InvalidInput`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpListener;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:80").unwrap();
+    /// listener.set_tos(0x10).expect("set_tos call failed");
+    /// ```
+    pub fn set_tos(&self, tos: u32) -> io::Result<()> {
+        self.0.set_tos(tos)
+    }
+
+    /// Gets the value of the `IP_TOS` option on this socket.
+    ///
+    /// For more information about this option, see [`TcpListener::set_tos`].
+    pub fn tos(&self) -> io::Result<u32> {
+        self.0.tos()
+    }
+
     /// Gets the value of the `SO_ERROR` option on this socket.
     ///
     /// This will retrieve the stored error in the underlying socket, clearing
@@ -964,6 +1838,26 @@ impl TcpListener {
         self.0.take_error()
     }
 
+    /// Closes the socket, returning any error reported by `close`.
+    ///
+    /// Dropping a socket also closes it but ignores errors, unless a handler
+    /// was installed with [`set_close_error_handler`]. Whether or not an error
+    /// is returned, the descriptor must be considered closed.
+    ///
+    /// [`set_close_error_handler`]: crate::net::set_close_error_handler
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpListener;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:80").unwrap();
+    /// listener.close().expect("close call failed");
+    /// ```
+    pub fn close(self) -> io::Result<()> {
+        self.0.close()
+    }
+
     /// Moves this TCP stream into or out of nonblocking mode.
     ///
     /// This will result in the `accept` operation becoming nonblocking,
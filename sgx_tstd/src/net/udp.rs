@@ -16,11 +16,11 @@
 // under the License..
 
 use crate::fmt;
-use crate::io::{self, Error, ErrorKind};
+use crate::io::{self, Error, ErrorKind, IoSlice, IoSliceMut};
 use crate::net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
 use crate::sys_common::net as net_imp;
 use crate::sys_common::{AsInner, FromInner, IntoInner};
-use crate::time::Duration;
+use crate::time::{Duration, Instant};
 
 use sgx_libc::c_int;
 
@@ -148,6 +148,117 @@ impl UdpSocket {
         self.0.recv_from(buf)
     }
 
+    /// Receives a single datagram message on the socket, waiting no later than
+    /// `deadline` for one to arrive. On success, returns the number of bytes
+    /// read and the origin.
+    ///
+    /// This is a one-shot alternative to [`UdpSocket::set_read_timeout`]: the
+    /// socket is polled until `deadline` and the persistent `SO_RCVTIMEO`
+    /// setting is left untouched. An error of kind
+    /// [`io::ErrorKind::TimedOut`] is returned if nothing arrives in time.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::UdpSocket;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let socket = UdpSocket::bind("127.0.0.1:34254").expect("couldn't bind to address");
+    /// let mut buf = [0; 10];
+    /// let deadline = Instant::now() + Duration::from_secs(1);
+    /// match socket.recv_from_deadline(&mut buf, deadline) {
+    ///     Ok((n, src_addr)) => println!("received {} bytes from {}", n, src_addr),
+    ///     Err(e) => println!("recv_from_deadline function failed: {:?}", e),
+    /// }
+    /// ```
+    pub fn recv_from_deadline(
+        &self,
+        buf: &mut [u8],
+        deadline: Instant,
+    ) -> io::Result<(usize, SocketAddr)> {
+        self.0.recv_from_deadline(buf, deadline)
+    }
+
+    /// Receives a single datagram message on the socket, scattering it across
+    /// `bufs`. On success, returns the number of bytes read, whether the
+    /// datagram was truncated and the origin.
+    ///
+    /// The buffers are filled in order. If the datagram is larger than their
+    /// combined length, the excess bytes are discarded and the returned flag
+    /// is `true` (the kernel reported `MSG_TRUNC`).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::io::IoSliceMut;
+    /// use std::net::UdpSocket;
+    ///
+    /// let socket = UdpSocket::bind("127.0.0.1:34254").expect("couldn't bind to address");
+    /// let mut header = [0; 4];
+    /// let mut body = [0; 512];
+    /// let mut bufs = [IoSliceMut::new(&mut header), IoSliceMut::new(&mut body)];
+    /// let (n, truncated, src_addr) = socket.recv_from_vectored(&mut bufs)
+    ///                                      .expect("Didn't receive data");
+    /// ```
+    pub fn recv_from_vectored(
+        &self,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> io::Result<(usize, bool, SocketAddr)> {
+        self.0.recv_from_vectored(bufs)
+    }
+
+    /// Receives a single datagram message on the socket along with its TOS
+    /// byte. On success, returns the number of bytes read, the origin and the
+    /// TOS (IPv4) or traffic class (IPv6) value, whose low two bits carry the
+    /// ECN codepoint.
+    ///
+    /// The TOS is only delivered once enabled with
+    /// [`UdpSocket::set_recv_tos`]; otherwise it is reported as 0. As with
+    /// [`UdpSocket::recv_from`], excess bytes of a datagram that does not fit
+    /// in `buf` are discarded.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::UdpSocket;
+    ///
+    /// let socket = UdpSocket::bind("127.0.0.1:34254").expect("couldn't bind to address");
+    /// socket.set_recv_tos(true).expect("set_recv_tos call failed");
+    /// let mut buf = [0; 10];
+    /// let (n, src_addr, tos) = socket.recv_from_with_tos(&mut buf)
+    ///                                .expect("Didn't receive data");
+    /// let ecn = tos & 0b11;
+    /// ```
+    pub fn recv_from_with_tos(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr, u8)> {
+        self.0.recv_from_with_tos(buf)
+    }
+
+    /// Sets the value of the `IP_RECVTOS` option (or `IPV6_RECVTCLASS` for
+    /// IPv6 sockets) on this socket.
+    ///
+    /// When enabled, the TOS byte of incoming datagrams is delivered to
+    /// [`UdpSocket::recv_from_with_tos`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::UdpSocket;
+    ///
+    /// let socket = UdpSocket::bind("127.0.0.1:34254").expect("couldn't bind to address");
+    /// socket.set_recv_tos(true).expect("set_recv_tos call failed");
+    /// ```
+    pub fn set_recv_tos(&self, recv_tos: bool) -> io::Result<()> {
+        self.0.set_recv_tos(recv_tos)
+    }
+
+    /// Gets the value of the `IP_RECVTOS` option (or `IPV6_RECVTCLASS` for
+    /// IPv6 sockets) on this socket.
+    ///
+    /// For more information about this option, see [`UdpSocket::set_recv_tos`].
+    pub fn recv_tos(&self) -> io::Result<bool> {
+        self.0.recv_tos()
+    }
+
     /// Receives a single datagram message on the socket, without removing it from the
     /// queue. On success, returns the number of bytes read and the origin.
     ///
@@ -207,6 +318,41 @@ impl UdpSocket {
         }
     }
 
+    /// Like [`send_to`], except that it gathers the datagram from a slice of
+    /// buffers.
+    ///
+    /// The buffers are sent as a single datagram without being copied into
+    /// one, for example a header followed by a payload. Since a datagram is
+    /// sent whole or not at all, on success the returned count is the sum
+    /// of the buffer lengths.
+    ///
+    /// Like [`send_to`], only the first address yielded by `addr` is used.
+    ///
+    /// [`send_to`]: UdpSocket::send_to
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::io::IoSlice;
+    /// use std::net::UdpSocket;
+    ///
+    /// let socket = UdpSocket::bind("127.0.0.1:34254").expect("couldn't bind to address");
+    /// let header = [1, 0, 0, 4];
+    /// let payload = b"data";
+    /// let bufs = [IoSlice::new(&header), IoSlice::new(payload)];
+    /// socket.send_to_vectored(&bufs, "127.0.0.1:4242").expect("couldn't send data");
+    /// ```
+    pub fn send_to_vectored<A: ToSocketAddrs>(
+        &self,
+        bufs: &[IoSlice<'_>],
+        addr: A,
+    ) -> io::Result<usize> {
+        match addr.to_socket_addrs()?.next() {
+            Some(addr) => self.0.send_to_vectored(bufs, &addr),
+            None => Err(Error::new_const(ErrorKind::InvalidInput, &"no addresses to send data to")),
+        }
+    }
+
     /// Returns the socket address of the remote peer this socket was connected to.
     ///
     /// # Examples
@@ -237,6 +383,11 @@ impl UdpSocket {
 
     /// Returns the socket address that this socket was created from.
     ///
+    /// Once the socket has a port assigned, the address is cached so later
+    /// calls do not leave the enclave. Binding or connecting through this
+    /// handle refreshes it; handles obtained from [`UdpSocket::try_clone`]
+    /// keep their own cache.
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -529,6 +680,80 @@ impl UdpSocket {
         self.0.multicast_loop_v6()
     }
 
+    /// Sets the value of the `IPV6_MULTICAST_HOPS` option for this socket.
+    ///
+    /// This is the IPv6 counterpart of [`UdpSocket::set_multicast_ttl_v4`]:
+    /// it limits how many hops multicast packets sent from this socket may
+    /// travel. It must fit in a byte, otherwise an error of kind
+    /// [`io::ErrorKind::InvalidInput`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::UdpSocket;
+    ///
+    /// let socket = UdpSocket::bind("[::1]:34254").expect("couldn't bind to address");
+    /// socket.set_multicast_hops_v6(8).expect("set_multicast_hops_v6 call failed");
+    /// ```
+    pub fn set_multicast_hops_v6(&self, multicast_hops_v6: u32) -> io::Result<()> {
+        self.0.set_multicast_hops_v6(multicast_hops_v6)
+    }
+
+    /// Gets the value of the `IPV6_MULTICAST_HOPS` option for this socket.
+    ///
+    /// For more information about this option, see [`UdpSocket::set_multicast_hops_v6`].
+    pub fn multicast_hops_v6(&self) -> io::Result<u32> {
+        self.0.multicast_hops_v6()
+    }
+
+    /// Sets the value of the `IP_MULTICAST_IF` option for this socket.
+    ///
+    /// Selects the local interface, by its address, that IPv4 multicast
+    /// packets are sent from. [`Ipv4Addr::UNSPECIFIED`] lets the host choose.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::{Ipv4Addr, UdpSocket};
+    ///
+    /// let socket = UdpSocket::bind("127.0.0.1:34254").expect("couldn't bind to address");
+    /// socket.set_multicast_if_v4(&Ipv4Addr::LOCALHOST).expect("set_multicast_if_v4 call failed");
+    /// ```
+    pub fn set_multicast_if_v4(&self, interface: &Ipv4Addr) -> io::Result<()> {
+        self.0.set_multicast_if_v4(interface)
+    }
+
+    /// Gets the value of the `IP_MULTICAST_IF` option for this socket.
+    ///
+    /// For more information about this option, see [`UdpSocket::set_multicast_if_v4`].
+    pub fn multicast_if_v4(&self) -> io::Result<Ipv4Addr> {
+        self.0.multicast_if_v4()
+    }
+
+    /// Sets the value of the `IPV6_MULTICAST_IF` option for this socket.
+    ///
+    /// Selects the interface, by its index, that IPv6 multicast packets are
+    /// sent from. An index of 0 lets the host choose.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::UdpSocket;
+    ///
+    /// let socket = UdpSocket::bind("[::1]:34254").expect("couldn't bind to address");
+    /// socket.set_multicast_if_v6(0).expect("set_multicast_if_v6 call failed");
+    /// ```
+    pub fn set_multicast_if_v6(&self, interface: u32) -> io::Result<()> {
+        self.0.set_multicast_if_v6(interface)
+    }
+
+    /// Gets the value of the `IPV6_MULTICAST_IF` option for this socket.
+    ///
+    /// For more information about this option, see [`UdpSocket::set_multicast_if_v6`].
+    pub fn multicast_if_v6(&self) -> io::Result<u32> {
+        self.0.multicast_if_v6()
+    }
+
     /// Sets the value for the `IP_TTL` option on this socket.
     ///
     /// This value sets the time-to-live field that is used in every packet sent
@@ -563,6 +788,32 @@ impl UdpSocket {
         self.0.ttl()
     }
 
+    /// Sets the value of the `SO_MARK` option on this socket.
+    ///
+    /// The mark is attached to every packet sent from this socket and can be
+    /// matched by host firewall rules or used for policy routing. Setting it
+    /// typically requires `CAP_NET_ADMIN` on the host; without it this
+    /// returns an error of kind [`io::ErrorKind::PermissionDenied`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::UdpSocket;
+    ///
+    /// let socket = UdpSocket::bind("127.0.0.1:34254").expect("couldn't bind to address");
+    /// socket.set_mark(42).expect("set_mark call failed");
+    /// ```
+    pub fn set_mark(&self, mark: u32) -> io::Result<()> {
+        self.0.set_mark(mark)
+    }
+
+    /// Gets the value of the `SO_MARK` option for this socket.
+    ///
+    /// For more information about this option, see [`UdpSocket::set_mark`].
+    pub fn mark(&self) -> io::Result<u32> {
+        self.0.mark()
+    }
+
     /// Executes an operation of the `IP_ADD_MEMBERSHIP` type.
     ///
     /// This function specifies a new multicast group for this socket to join.
@@ -619,6 +870,26 @@ impl UdpSocket {
         self.0.take_error()
     }
 
+    /// Closes the socket, returning any error reported by `close`.
+    ///
+    /// Dropping a socket also closes it but ignores errors, unless a handler
+    /// was installed with [`set_close_error_handler`]. Whether or not an error
+    /// is returned, the descriptor must be considered closed.
+    ///
+    /// [`set_close_error_handler`]: crate::net::set_close_error_handler
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::UdpSocket;
+    ///
+    /// let socket = UdpSocket::bind("127.0.0.1:34254").expect("couldn't bind to address");
+    /// socket.close().expect("close call failed");
+    /// ```
+    pub fn close(self) -> io::Result<()> {
+        self.0.close()
+    }
+
     /// Connects this UDP socket to a remote address, allowing the `send` and
     /// `recv` syscalls to be used to send data and also applies filters to only
     /// receive data from the specified address.
@@ -669,6 +940,29 @@ impl UdpSocket {
         self.0.send(buf)
     }
 
+    /// Like [`send`], except that it gathers the datagram from a slice of
+    /// buffers.
+    ///
+    /// See [`UdpSocket::send_to_vectored`] for details. This method will fail
+    /// if the socket is not connected.
+    ///
+    /// [`send`]: UdpSocket::send
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::io::IoSlice;
+    /// use std::net::UdpSocket;
+    ///
+    /// let socket = UdpSocket::bind("127.0.0.1:34254").expect("couldn't bind to address");
+    /// socket.connect("127.0.0.1:8080").expect("connect function failed");
+    /// let bufs = [IoSlice::new(b"head"), IoSlice::new(b"body")];
+    /// socket.send_vectored(&bufs).expect("couldn't send message");
+    /// ```
+    pub fn send_vectored(&self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        self.0.send_vectored(bufs)
+    }
+
     /// Receives a single datagram message on the socket from the remote address to
     /// which it is connected. On success, returns the number of bytes read.
     ///
@@ -696,6 +990,57 @@ impl UdpSocket {
         self.0.recv(buf)
     }
 
+    /// Receives a single datagram message from the connected remote address,
+    /// waiting no later than `deadline` for one to arrive. On success, returns
+    /// the number of bytes read.
+    ///
+    /// As with [`UdpSocket::recv_from_deadline`], the socket's persistent read
+    /// timeout is left untouched and an error of kind
+    /// [`io::ErrorKind::TimedOut`] is returned if nothing arrives in time.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::UdpSocket;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let socket = UdpSocket::bind("127.0.0.1:34254").expect("couldn't bind to address");
+    /// socket.connect("127.0.0.1:8080").expect("connect function failed");
+    /// let mut buf = [0; 10];
+    /// let deadline = Instant::now() + Duration::from_secs(1);
+    /// match socket.recv_deadline(&mut buf, deadline) {
+    ///     Ok(received) => println!("received {} bytes {:?}", received, &buf[..received]),
+    ///     Err(e) => println!("recv_deadline function failed: {:?}", e),
+    /// }
+    /// ```
+    pub fn recv_deadline(&self, buf: &mut [u8], deadline: Instant) -> io::Result<usize> {
+        self.0.recv_deadline(buf, deadline)
+    }
+
+    /// Receives a single datagram message from the connected remote address,
+    /// scattering it across `bufs`. On success, returns the number of bytes
+    /// read and whether the datagram was truncated.
+    ///
+    /// See [`UdpSocket::recv_from_vectored`] for how truncation is reported.
+    /// This method will fail if the socket is not connected.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::io::IoSliceMut;
+    /// use std::net::UdpSocket;
+    ///
+    /// let socket = UdpSocket::bind("127.0.0.1:34254").expect("couldn't bind to address");
+    /// socket.connect("127.0.0.1:8080").expect("connect function failed");
+    /// let mut header = [0; 4];
+    /// let mut body = [0; 512];
+    /// let mut bufs = [IoSliceMut::new(&mut header), IoSliceMut::new(&mut body)];
+    /// let (n, truncated) = socket.recv_vectored(&mut bufs).expect("Didn't receive data");
+    /// ```
+    pub fn recv_vectored(&self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<(usize, bool)> {
+        self.0.recv_vectored(bufs)
+    }
+
     /// Receives single datagram on the socket from the remote address to which it is
     /// connected, without removing the message from input queue. On success, returns
     /// the number of bytes peeked.
@@ -0,0 +1,500 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use crate::fmt;
+use crate::io::{self, Initializer, IoSlice, IoSliceMut};
+use crate::mem;
+use crate::net::Shutdown;
+use crate::os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+use crate::sys::cvt;
+use crate::sys::net::Socket;
+use crate::sys_common::{AsInner, FromInner};
+use crate::time::Duration;
+
+/// The address of a vsock socket: a context identifier (CID) naming the
+/// virtual machine, and a port within it.
+///
+/// The host is always reachable at [`VsockAddr::CID_HOST`]; each guest is
+/// assigned its own CID by the hypervisor.
+///
+/// # Examples
+///
+/// ```
+/// use std::net::VsockAddr;
+///
+/// let addr = VsockAddr::new(VsockAddr::CID_HOST, 5000);
+/// assert_eq!(addr.cid(), 2);
+/// assert_eq!(addr.port(), 5000);
+/// assert_eq!(addr.to_string(), "2:5000");
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VsockAddr {
+    cid: u32,
+    port: u32,
+}
+
+impl VsockAddr {
+    /// Binds to any CID the local machine answers to.
+    pub const CID_ANY: u32 = libc::VMADDR_CID_ANY;
+    /// The hypervisor.
+    pub const CID_HYPERVISOR: u32 = libc::VMADDR_CID_HYPERVISOR;
+    /// The local machine, for loopback communication.
+    pub const CID_LOCAL: u32 = libc::VMADDR_CID_LOCAL;
+    /// The host of the virtual machine.
+    pub const CID_HOST: u32 = libc::VMADDR_CID_HOST;
+    /// Lets the host pick a free port when binding.
+    pub const PORT_ANY: u32 = libc::VMADDR_PORT_ANY;
+
+    /// Creates a new vsock address from a context identifier and port.
+    pub const fn new(cid: u32, port: u32) -> VsockAddr {
+        VsockAddr { cid, port }
+    }
+
+    /// Returns the context identifier of this address.
+    pub const fn cid(&self) -> u32 {
+        self.cid
+    }
+
+    /// Returns the port of this address.
+    pub const fn port(&self) -> u32 {
+        self.port
+    }
+
+    /// Changes the context identifier of this address.
+    pub fn set_cid(&mut self, cid: u32) {
+        self.cid = cid;
+    }
+
+    /// Changes the port of this address.
+    pub fn set_port(&mut self, port: u32) {
+        self.port = port;
+    }
+
+    fn to_sockaddr(&self) -> libc::sockaddr_vm {
+        let mut addr: libc::sockaddr_vm = unsafe { mem::zeroed() };
+        addr.svm_family = libc::AF_VSOCK as libc::sa_family_t;
+        addr.svm_cid = self.cid;
+        addr.svm_port = self.port;
+        addr
+    }
+
+    fn from_sockaddr(addr: &libc::sockaddr_vm, len: libc::socklen_t) -> io::Result<VsockAddr> {
+        if addr.svm_family as libc::c_int != libc::AF_VSOCK
+            || (len as usize) < mem::size_of::<libc::sockaddr_vm>()
+        {
+            return Err(io::Error::new_const(
+                io::ErrorKind::InvalidInput,
+                &"file descriptor did not correspond to a vsock socket",
+            ));
+        }
+        Ok(VsockAddr { cid: addr.svm_cid, port: addr.svm_port })
+    }
+
+    fn new_with<F>(f: F) -> io::Result<VsockAddr>
+    where
+        F: FnOnce(*mut libc::sockaddr, *mut libc::socklen_t) -> libc::c_int,
+    {
+        let mut addr: libc::sockaddr_vm = unsafe { mem::zeroed() };
+        let mut len = mem::size_of::<libc::sockaddr_vm>() as libc::socklen_t;
+        cvt(f(&mut addr as *mut _ as *mut _, &mut len))?;
+        VsockAddr::from_sockaddr(&addr, len)
+    }
+}
+
+impl fmt::Display for VsockAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.cid, self.port)
+    }
+}
+
+/// A stream between a local and a remote vsock socket.
+///
+/// vsock is the host-guest transport of virtual machines: when the enclave's
+/// application runs inside a VM, a `VsockStream` reaches services on the host
+/// (or other guests) without configuring a network interface. It behaves
+/// like a [`TcpStream`](crate::net::TcpStream) addressed by [`VsockAddr`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io::prelude::*;
+/// use std::net::{VsockAddr, VsockStream};
+///
+/// fn main() -> std::io::Result<()> {
+///     let mut stream = VsockStream::connect(&VsockAddr::new(VsockAddr::CID_HOST, 5000))?;
+///     stream.write_all(b"hello world")?;
+///     let mut response = String::new();
+///     stream.read_to_string(&mut response)?;
+///     println!("{}", response);
+///     Ok(())
+/// }
+/// ```
+pub struct VsockStream(Socket);
+
+impl fmt::Debug for VsockStream {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut builder = fmt.debug_struct("VsockStream");
+        builder.field("fd", self.0.as_inner());
+        if let Ok(addr) = self.local_addr() {
+            builder.field("local", &addr);
+        }
+        if let Ok(addr) = self.peer_addr() {
+            builder.field("peer", &addr);
+        }
+        builder.finish()
+    }
+}
+
+impl VsockStream {
+    /// Connects to the vsock socket listening at `addr`.
+    pub fn connect(addr: &VsockAddr) -> io::Result<VsockStream> {
+        let inner = Socket::new_raw(libc::AF_VSOCK, libc::SOCK_STREAM)?;
+        let addr = addr.to_sockaddr();
+        cvt(unsafe {
+            libc::connect(
+                inner.as_raw_fd(),
+                &addr as *const _ as *const _,
+                mem::size_of_val(&addr) as libc::socklen_t,
+            )
+        })?;
+        Ok(VsockStream(inner))
+    }
+
+    /// Creates a new independently owned handle to the underlying socket.
+    pub fn try_clone(&self) -> io::Result<VsockStream> {
+        self.0.duplicate().map(VsockStream)
+    }
+
+    /// Returns the address of the local half of this connection.
+    pub fn local_addr(&self) -> io::Result<VsockAddr> {
+        VsockAddr::new_with(|addr, len| unsafe { libc::getsockname(self.as_raw_fd(), addr, len) })
+    }
+
+    /// Returns the address of the remote half of this connection.
+    pub fn peer_addr(&self) -> io::Result<VsockAddr> {
+        VsockAddr::new_with(|addr, len| unsafe { libc::getpeername(self.as_raw_fd(), addr, len) })
+    }
+
+    /// Sets the read timeout for the socket.
+    ///
+    /// If the provided value is [`None`], then [`read`] calls will block
+    /// indefinitely. An [`Err`] is returned if the zero [`Duration`] is passed
+    /// to this method.
+    ///
+    /// [`read`]: io::Read::read
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.0.set_timeout(timeout, libc::SO_RCVTIMEO)
+    }
+
+    /// Sets the write timeout for the socket.
+    ///
+    /// If the provided value is [`None`], then [`write`] calls will block
+    /// indefinitely. An [`Err`] is returned if the zero [`Duration`] is passed
+    /// to this method.
+    ///
+    /// [`write`]: io::Write::write
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.0.set_timeout(timeout, libc::SO_SNDTIMEO)
+    }
+
+    /// Returns the read timeout of this socket.
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        self.0.timeout(libc::SO_RCVTIMEO)
+    }
+
+    /// Returns the write timeout of this socket.
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        self.0.timeout(libc::SO_SNDTIMEO)
+    }
+
+    /// Moves the socket into or out of nonblocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.0.set_nonblocking(nonblocking)
+    }
+
+    /// Returns the value of the `SO_ERROR` option.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.0.take_error()
+    }
+
+    /// Shuts down the read, write, or both halves of this connection.
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.0.shutdown(how)
+    }
+
+    /// Receives data on the socket from the remote address to which it is
+    /// connected, without removing that data from the queue.
+    pub fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.peek(buf)
+    }
+}
+
+impl io::Read for VsockStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        io::Read::read(&mut &*self, buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        io::Read::read_vectored(&mut &*self, bufs)
+    }
+
+    #[inline]
+    fn is_read_vectored(&self) -> bool {
+        io::Read::is_read_vectored(&&*self)
+    }
+
+    #[inline]
+    unsafe fn initializer(&self) -> Initializer {
+        Initializer::nop()
+    }
+}
+
+impl<'a> io::Read for &'a VsockStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        self.0.read_vectored(bufs)
+    }
+
+    #[inline]
+    fn is_read_vectored(&self) -> bool {
+        self.0.is_read_vectored()
+    }
+
+    #[inline]
+    unsafe fn initializer(&self) -> Initializer {
+        Initializer::nop()
+    }
+}
+
+impl io::Write for VsockStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::Write::write(&mut &*self, buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        io::Write::write_vectored(&mut &*self, bufs)
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        io::Write::is_write_vectored(&&*self)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::Write::flush(&mut &*self)
+    }
+}
+
+impl<'a> io::Write for &'a VsockStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        self.0.write_vectored(bufs)
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        self.0.is_write_vectored()
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl AsRawFd for VsockStream {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl FromRawFd for VsockStream {
+    #[inline]
+    unsafe fn from_raw_fd(fd: RawFd) -> VsockStream {
+        VsockStream(Socket::from_inner(FromInner::from_inner(OwnedFd::from_raw_fd(fd))))
+    }
+}
+
+impl IntoRawFd for VsockStream {
+    #[inline]
+    fn into_raw_fd(self) -> RawFd {
+        self.0.into_raw_fd()
+    }
+}
+
+impl AsFd for VsockStream {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+/// A vsock socket server, listening for connections.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::net::{VsockAddr, VsockListener};
+///
+/// fn main() -> std::io::Result<()> {
+///     let listener = VsockListener::bind(&VsockAddr::new(VsockAddr::CID_ANY, 5000))?;
+///
+///     for stream in listener.incoming() {
+///         let stream = stream?;
+///         println!("connection from {}", stream.peer_addr()?);
+///     }
+///     Ok(())
+/// }
+/// ```
+pub struct VsockListener(Socket);
+
+impl fmt::Debug for VsockListener {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut builder = fmt.debug_struct("VsockListener");
+        builder.field("fd", self.0.as_inner());
+        if let Ok(addr) = self.local_addr() {
+            builder.field("local", &addr);
+        }
+        builder.finish()
+    }
+}
+
+impl VsockListener {
+    /// Creates a new `VsockListener` bound to the specified address.
+    ///
+    /// Use [`VsockAddr::CID_ANY`] to accept connections addressed to any of the
+    /// machine's CIDs, and [`VsockAddr::PORT_ANY`] to let the host pick a port.
+    pub fn bind(addr: &VsockAddr) -> io::Result<VsockListener> {
+        let inner = Socket::new_raw(libc::AF_VSOCK, libc::SOCK_STREAM)?;
+        let addr = addr.to_sockaddr();
+        unsafe {
+            cvt(libc::bind(
+                inner.as_raw_fd(),
+                &addr as *const _ as *const _,
+                mem::size_of_val(&addr) as libc::socklen_t,
+            ))?;
+            cvt(libc::listen(inner.as_raw_fd(), 128))?;
+        }
+        Ok(VsockListener(inner))
+    }
+
+    /// Accepts a new incoming connection to this listener, returning the
+    /// stream and the address of the connecting peer.
+    pub fn accept(&self) -> io::Result<(VsockStream, VsockAddr)> {
+        let mut storage: libc::sockaddr_vm = unsafe { mem::zeroed() };
+        let mut len = mem::size_of_val(&storage) as libc::socklen_t;
+        let sock = self.0.accept(&mut storage as *mut _ as *mut _, &mut len)?;
+        let addr = VsockAddr::from_sockaddr(&storage, len)?;
+        Ok((VsockStream(sock), addr))
+    }
+
+    /// Creates a new independently owned handle to the underlying socket.
+    pub fn try_clone(&self) -> io::Result<VsockListener> {
+        self.0.duplicate().map(VsockListener)
+    }
+
+    /// Returns the local address of this listener.
+    pub fn local_addr(&self) -> io::Result<VsockAddr> {
+        VsockAddr::new_with(|addr, len| unsafe { libc::getsockname(self.as_raw_fd(), addr, len) })
+    }
+
+    /// Moves the socket into or out of nonblocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.0.set_nonblocking(nonblocking)
+    }
+
+    /// Returns the value of the `SO_ERROR` option.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.0.take_error()
+    }
+
+    /// Returns an iterator over incoming connections.
+    ///
+    /// The iterator will never return [`None`] and will also not yield the
+    /// peer's [`VsockAddr`] structure.
+    pub fn incoming(&self) -> VsockIncoming<'_> {
+        VsockIncoming { listener: self }
+    }
+}
+
+impl AsRawFd for VsockListener {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl FromRawFd for VsockListener {
+    #[inline]
+    unsafe fn from_raw_fd(fd: RawFd) -> VsockListener {
+        VsockListener(Socket::from_inner(FromInner::from_inner(OwnedFd::from_raw_fd(fd))))
+    }
+}
+
+impl IntoRawFd for VsockListener {
+    #[inline]
+    fn into_raw_fd(self) -> RawFd {
+        self.0.into_raw_fd()
+    }
+}
+
+impl AsFd for VsockListener {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+impl<'a> IntoIterator for &'a VsockListener {
+    type Item = io::Result<VsockStream>;
+    type IntoIter = VsockIncoming<'a>;
+
+    fn into_iter(self) -> VsockIncoming<'a> {
+        self.incoming()
+    }
+}
+
+/// An iterator over incoming connections to a [`VsockListener`].
+///
+/// It will never return [`None`].
+#[derive(Debug)]
+pub struct VsockIncoming<'a> {
+    listener: &'a VsockListener,
+}
+
+impl<'a> Iterator for VsockIncoming<'a> {
+    type Item = io::Result<VsockStream>;
+
+    fn next(&mut self) -> Option<io::Result<VsockStream>> {
+        Some(self.listener.accept().map(|s| s.0))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+mod libc {
+    pub use sgx_libc::ocall::{bind, connect, getpeername, getsockname, listen};
+    pub use sgx_libc::*;
+}
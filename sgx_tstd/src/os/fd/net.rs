@@ -31,6 +31,8 @@ macro_rules! impl_as_raw_fd {
     )*};
 }
 impl_as_raw_fd! { TcpStream TcpListener UdpSocket }
+#[cfg(feature = "raw_socket")]
+impl_as_raw_fd! { RawSocket }
 
 macro_rules! impl_from_raw_fd {
     ($($t:ident)*) => {$(
@@ -46,6 +48,8 @@ macro_rules! impl_from_raw_fd {
     )*};
 }
 impl_from_raw_fd! { TcpStream TcpListener UdpSocket }
+#[cfg(feature = "raw_socket")]
+impl_from_raw_fd! { RawSocket }
 
 macro_rules! impl_into_raw_fd {
     ($($t:ident)*) => {$(
@@ -58,3 +62,5 @@ macro_rules! impl_into_raw_fd {
     )*};
 }
 impl_into_raw_fd! { TcpStream TcpListener UdpSocket }
+#[cfg(feature = "raw_socket")]
+impl_into_raw_fd! { RawSocket }
@@ -47,6 +47,8 @@ pub mod fs;
 pub mod io;
 #[cfg(feature = "net")]
 pub mod net;
+#[cfg(feature = "process")]
+pub mod process;
 pub mod raw;
 #[cfg(feature = "thread")]
 pub mod thread;
@@ -68,6 +70,9 @@ pub mod prelude {
     #[doc(no_inline)]
     pub use super::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
     #[doc(no_inline)]
+    #[cfg(feature = "process")]
+    pub use super::process::ExitStatusExt;
+    #[doc(no_inline)]
     #[cfg(feature = "thread")]
     pub use super::thread::JoinHandleExt;
 }
@@ -710,6 +710,29 @@ impl UnixDatagram {
         self.0.take_error()
     }
 
+    /// Closes the socket, returning any error reported by `close`.
+    ///
+    /// Dropping a socket also closes it but ignores errors, unless a handler
+    /// was installed with [`set_close_error_handler`]. Whether or not an error
+    /// is returned, the descriptor must be considered closed.
+    ///
+    /// [`set_close_error_handler`]: crate::net::set_close_error_handler
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::os::unix::net::UnixDatagram;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let sock = UnixDatagram::unbound()?;
+    ///     sock.close()?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn close(self) -> io::Result<()> {
+        self.0.close()
+    }
+
     /// Shut down the read, write, or both halves of this connection.
     ///
     /// This function will cause all pending and future I/O calls on the
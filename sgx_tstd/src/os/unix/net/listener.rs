@@ -210,6 +210,29 @@ impl UnixListener {
         self.0.take_error()
     }
 
+    /// Closes the socket, returning any error reported by `close`.
+    ///
+    /// Dropping a socket also closes it but ignores errors, unless a handler
+    /// was installed with [`set_close_error_handler`]. Whether or not an error
+    /// is returned, the descriptor must be considered closed.
+    ///
+    /// [`set_close_error_handler`]: crate::net::set_close_error_handler
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::os::unix::net::UnixListener;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let listener = UnixListener::bind("/path/to/the/socket")?;
+    ///     listener.close()?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn close(self) -> io::Result<()> {
+        self.0.close()
+    }
+
     /// Returns an iterator over incoming connections.
     ///
     /// The iterator will never return [`None`] and will also not yield the
@@ -169,6 +169,12 @@ impl UnixStream {
 
     /// Gets the peer credentials for this Unix domain socket.
     ///
+    /// The credentials are read with the `SO_PEERCRED` socket option and
+    /// describe the process on the other end at the time the connection was
+    /// established. They can be used to refuse IPC from unexpected host
+    /// processes, keeping in mind that the values are reported by the
+    /// untrusted host kernel.
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -375,6 +381,29 @@ impl UnixStream {
         self.0.take_error()
     }
 
+    /// Closes the socket, returning any error reported by `close`.
+    ///
+    /// Dropping a socket also closes it but ignores errors, unless a handler
+    /// was installed with [`set_close_error_handler`]. Whether or not an error
+    /// is returned, the descriptor must be considered closed.
+    ///
+    /// [`set_close_error_handler`]: crate::net::set_close_error_handler
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::os::unix::net::UnixStream;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let socket = UnixStream::connect("/tmp/sock")?;
+    ///     socket.close()?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn close(self) -> io::Result<()> {
+        self.0.close()
+    }
+
     /// Shuts down the read, write, or both halves of this connection.
     ///
     /// This function will cause all pending and future I/O calls on the
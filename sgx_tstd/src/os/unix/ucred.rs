@@ -71,11 +71,18 @@ pub mod impl_linux {
                 &mut ucred_size,
             );
 
-            if ret == 0 && ucred_size as usize == mem::size_of::<ucred>() {
-                Ok(UCred { uid: ucred.uid, gid: ucred.gid, pid: Some(ucred.pid) })
-            } else {
-                Err(io::Error::last_os_error())
+            if ret != 0 {
+                return Err(io::Error::last_os_error());
             }
+            // The option is filled in by the untrusted host; a short or
+            // oversized answer must not be mistaken for valid credentials.
+            if ucred_size as usize != mem::size_of::<ucred>() {
+                return Err(io::Error::new_const(
+                    io::ErrorKind::InvalidData,
+                    &"unexpected SO_PEERCRED option length",
+                ));
+            }
+            Ok(UCred { uid: ucred.uid, gid: ucred.gid, pid: Some(ucred.pid) })
         }
     }
 }
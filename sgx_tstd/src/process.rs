@@ -0,0 +1,268 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! A module for working with processes.
+//!
+//! An enclave lives inside a host process and has no process of its own.
+//! This module ends the enclave in a controlled way with [`exit`] and
+//! [`abort`], and with the `process` feature, spawns processes on the host
+//! with [`Command`].
+//!
+//! # Ending the enclave
+//!
+//! [`exit`] runs the callbacks registered with [`rt::at_exit`], flushes
+//! stdout, overwrites the memory registered with [`register_secret`] with
+//! zeros and reports the exit code to the host. The enclave is then locked:
+//! the ECALL that called `exit` and every later ECALL return
+//! `SGX_ERROR_ENCLAVE_CRASHED`, and the host reads the code with
+//! `SgxEnclave::exit_status`. [`abort`] does the same without running the
+//! callbacks.
+//!
+//! The enclave id is only known inside the enclave after `SgxEnclave::init`
+//! has called `t_global_init_ecall`, so the host needs the `global_init`
+//! feature of `sgx_urts` to tell which enclave exited.
+//!
+//! # Spawning processes
+//!
+//! [`Command`] is only available with the `process` feature, and the enclave
+//! must import `sgx_process.edl`. It is modeled after `std::process`.
+//!
+//! An enclave cannot run a process of its own. [`Command`] asks the untrusted
+//! host to start one through an OCALL, and everything about it is under the
+//! host's control:
+//!
+//! * The program, its arguments, its environment and its working directory
+//!   are all visible to the host, which may run something else entirely.
+//! * Whatever the child writes to a pipe, and the [`ExitStatus`] it reports,
+//!   may be forged. Treat them like any other input from the host, and
+//!   authenticate anything the enclave relies on.
+//! * Anything the enclave writes to a child is disclosed to the host.
+//!
+//! The child starts from the host process's environment rather than the
+//! enclave's, which differ with the `trusted_env` feature. Use
+//! [`Command::env`] and [`Command::env_clear`] to give it what it needs.
+//!
+//! Use it for host tooling whose results the enclave can check or does not
+//! depend on for its security.
+//!
+//! ```no_run
+//! use std::process::Command;
+//!
+//! let output = Command::new("echo")
+//!                      .arg("Hello world")
+//!                      .output()
+//!                      .expect("Failed to execute command");
+//!
+//! assert_eq!(b"Hello world\n", output.stdout.as_slice());
+//! ```
+//!
+//! [`rt::at_exit`]: crate::rt::at_exit
+
+use crate::fmt;
+use crate::hint;
+use crate::ptr;
+use crate::sync::atomic::{self, AtomicBool, AtomicUsize, Ordering};
+use crate::sync::SgxThreadSpinlock;
+use crate::sys;
+use crate::thread;
+use sgx_trts::trts::rsgx_raw_is_within_enclave;
+
+#[cfg(feature = "process")]
+pub use self::command::{
+    Child, ChildStderr, ChildStdin, ChildStdout, Command, ExitStatus, Output, Stdio,
+};
+
+#[cfg(feature = "process")]
+mod command;
+
+/// Returns the OS-assigned process identifier associated with the host
+/// process of this enclave.
+#[cfg(feature = "process")]
+pub fn id() -> u32 {
+    sys::os::getpid()
+}
+
+// The exit status `abort` reports, as a shell would for SIGABRT.
+const ABORT_CODE: i32 = 134;
+
+/// Terminates the enclave with the specified exit code.
+///
+/// The callbacks registered with [`rt::at_exit`](crate::rt::at_exit) run
+/// first, on the calling thread, and stdout is flushed. The memory registered
+/// with [`register_secret`] is then overwritten with zeros, `code` is
+/// reported to the host, and the enclave is locked, so that this and every
+/// later ECALL return `SGX_ERROR_ENCLAVE_CRASHED` to the host. See the
+/// [module documentation](self) for how the host reads the code.
+///
+/// Destructors on the stacks of this or any other thread are not run.
+///
+/// A callback that calls `exit` again ends the enclave right away, with the
+/// new code and without running the remaining callbacks. Other threads that
+/// call `exit` or [`abort`] meanwhile wait for the first call to finish.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::process;
+///
+/// process::exit(0x0100);
+/// ```
+pub fn exit(code: i32) -> ! {
+    if begin_exit() {
+        crate::sys_common::rt::cleanup();
+        #[cfg(feature = "stdio")]
+        crate::io::cleanup();
+    }
+    finish_exit(code, false)
+}
+
+/// Terminates the enclave in an abnormal fashion.
+///
+/// Unlike [`exit`], this does not run the callbacks registered with
+/// [`rt::at_exit`](crate::rt::at_exit), since it is meant for when the
+/// enclave's state can no longer be trusted. It still flushes stdout,
+/// overwrites the memory registered with [`register_secret`] with zeros and
+/// reports to the host that the enclave aborted before locking it.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::process;
+///
+/// fn main() {
+///     println!("aborting");
+///
+///     process::abort();
+///
+///     // execution never gets here
+/// }
+/// ```
+pub fn abort() -> ! {
+    begin_exit();
+    #[cfg(feature = "stdio")]
+    crate::io::cleanup();
+    finish_exit(ABORT_CODE, true)
+}
+
+// The thread running `exit` or `abort`, or zero.
+static EXIT_OWNER: AtomicUsize = AtomicUsize::new(0);
+static EXIT_DONE: AtomicBool = AtomicBool::new(false);
+
+// Returns true for the first call. A nested call on the same thread returns
+// false, and any other thread waits here until the enclave is locked.
+fn begin_exit() -> bool {
+    let me = thread::rsgx_thread_self() as usize;
+    match EXIT_OWNER.compare_exchange(0, me, Ordering::SeqCst, Ordering::SeqCst) {
+        Ok(_) => true,
+        Err(owner) if owner == me => false,
+        Err(_) => {
+            while !EXIT_DONE.load(Ordering::SeqCst) {
+                hint::spin_loop();
+            }
+            sys::abort_internal()
+        }
+    }
+}
+
+fn finish_exit(code: i32, aborted: bool) -> ! {
+    zeroize_secrets();
+    sys::os::report_exit(code, aborted);
+    EXIT_DONE.store(true, Ordering::SeqCst);
+    sys::abort_internal()
+}
+
+struct Secret {
+    id: usize,
+    ptr: *mut u8,
+    len: usize,
+}
+
+static SECRETS_LOCK: SgxThreadSpinlock = SgxThreadSpinlock::new();
+static mut SECRETS: Vec<Secret> = Vec::new();
+static NEXT_SECRET: AtomicUsize = AtomicUsize::new(1);
+
+/// Registers `len` bytes at `ptr` to be overwritten with zeros when the
+/// enclave ends through [`exit`] or [`abort`].
+///
+/// The bytes stay registered until the returned [`SecretGuard`] is dropped,
+/// which must happen before the memory is freed or reused.
+///
+/// # Safety
+///
+/// The memory must stay valid for writes for as long as the guard exists.
+///
+/// # Panics
+///
+/// Panics if the memory is not entirely inside the enclave.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::process;
+///
+/// let mut key = [0u8; 32];
+/// let guard = unsafe { process::register_secret(key.as_mut_ptr(), key.len()) };
+/// // ... use `key` ...
+/// drop(guard);
+/// ```
+pub unsafe fn register_secret(ptr: *mut u8, len: usize) -> SecretGuard {
+    assert!(len == 0 || rsgx_raw_is_within_enclave(ptr, len), "secret is not inside the enclave");
+    let id = NEXT_SECRET.fetch_add(1, Ordering::Relaxed);
+    SECRETS_LOCK.lock();
+    SECRETS.push(Secret { id, ptr, len });
+    SECRETS_LOCK.unlock();
+    SecretGuard { id }
+}
+
+/// Keeps memory registered with [`register_secret`], and unregisters it when
+/// dropped.
+#[must_use = "the secret is unregistered as soon as the guard is dropped"]
+pub struct SecretGuard {
+    id: usize,
+}
+
+unsafe impl Send for SecretGuard {}
+unsafe impl Sync for SecretGuard {}
+
+impl Drop for SecretGuard {
+    fn drop(&mut self) {
+        unsafe {
+            SECRETS_LOCK.lock();
+            SECRETS.retain(|secret| secret.id != self.id);
+            SECRETS_LOCK.unlock();
+        }
+    }
+}
+
+impl fmt::Debug for SecretGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretGuard").finish_non_exhaustive()
+    }
+}
+
+fn zeroize_secrets() {
+    unsafe {
+        SECRETS_LOCK.lock();
+        for secret in SECRETS.iter() {
+            for i in 0..secret.len {
+                ptr::write_volatile(secret.ptr.add(i), 0);
+            }
+        }
+        atomic::compiler_fence(Ordering::SeqCst);
+        SECRETS_LOCK.unlock();
+    }
+}
@@ -66,9 +66,31 @@ pub extern "C" fn t_global_init_ecall(id: u64, path: *const u8, len: usize) {
             str::from_utf8_unchecked(str_slice)
         };
         enclave::set_enclave_path(s);
+        crate::sys::os::init_env();
     });
 }
 
+/// Receives the program arguments returned by [`env::args`], each terminated
+/// by a NUL byte. Only the first call has an effect, so the host cannot
+/// change the arguments once the enclave has started using them.
+///
+/// [`env::args`]: crate::env::args
+#[no_mangle]
+pub extern "C" fn t_global_args_ecall(args: *const u8, len: usize) {
+    // An empty buffer may arrive as a null pointer, and still counts as the
+    // one call that sets the arguments.
+    let blob = if args.is_null() { &[] } else { unsafe { slice::from_raw_parts(args, len) } };
+    crate::sys::args::init(blob);
+}
+
+#[no_mangle]
+pub extern "C" fn t_thread_exit_ecall() {
+    #[cfg(feature = "thread")]
+    unsafe {
+        crate::sys_common::thread_local_dtor::teardown()
+    };
+}
+
 global_dtors_object! {
     GLOBAL_DTORS, global_exit = { cleanup(); }
 }
@@ -17,10 +17,15 @@
 
 //! Filesystem manipulation operations.
 
+use crate::cmp;
+use crate::collections::HashMap;
+use crate::fmt;
 use crate::io::{self, SeekFrom, Seek, Read, Initializer, Write};
 use crate::path::Path;
+use crate::sync::{Arc, PoisonError, SgxMutex};
 use crate::sys::sgxfs as fs_imp;
 use crate::sys_common::{AsInner, AsInnerMut, FromInner, IntoInner};
+use crate::time::SystemTime;
 use sgx_types::{sgx_key_128bit_t, sgx_align_key_128bit_t};
 
 /// A reference to an open file on the filesystem.
@@ -126,6 +131,88 @@ impl SgxFile {
         OpenOptions::new().write(true).open_ex(path.as_ref(), key)
     }
 
+    /// Attempts to open an integrity-only protected file in read-only mode.
+    ///
+    /// See [`OpenOptions::open_integrity_only`] for what this mode protects.
+    #[cfg(feature = "sgxfs_integrity_only")]
+    pub fn open_integrity_only<P: AsRef<Path>>(path: P) -> io::Result<SgxFile> {
+        OpenOptions::new().read(true).open_integrity_only(path.as_ref())
+    }
+
+    /// Opens an integrity-only protected file in write-only mode.
+    ///
+    /// See [`OpenOptions::open_integrity_only`] for what this mode protects.
+    #[cfg(feature = "sgxfs_integrity_only")]
+    pub fn create_integrity_only<P: AsRef<Path>>(path: P) -> io::Result<SgxFile> {
+        OpenOptions::new().write(true).open_integrity_only(path.as_ref())
+    }
+
+    /// Returns the size of the file's contents, in bytes.
+    ///
+    /// The size is read from the file's authenticated metadata, so unlike
+    /// the size the host reports for the underlying file, it cannot be
+    /// tampered with. The cursor is left where it was.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::sgxfs::SgxFile;
+    ///
+    /// let file = SgxFile::open("foo.txt").unwrap();
+    /// println!("{} bytes", file.len().unwrap());
+    /// ```
+    pub fn len(&self) -> io::Result<u64> {
+        self.inner.len()
+    }
+
+    /// Extends the file to `size` bytes, filling the new space with zeros.
+    ///
+    /// Protected files cannot be shrunk in place, so an error of kind
+    /// [`io::ErrorKind::Unsupported`] is returned if `size` is less than the
+    /// current length. The cursor is left where it was.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file is not opened for
+    /// writing.
+    pub fn set_len(&self, size: u64) -> io::Result<()> {
+        self.inner.set_len(size)
+    }
+
+    /// Queries metadata about the file.
+    ///
+    /// See [`Metadata`] for which parts of it can be trusted.
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        self.inner.file_attr().map(Metadata)
+    }
+
+    /// Re-encrypts the file under a new key, replacing it on disk.
+    ///
+    /// The contents are streamed into a new protected file sealed according
+    /// to `policy`, which is then renamed over the original. A crash at any
+    /// point leaves a complete file at the original path, readable under
+    /// either the old or the new key; leftovers from an interrupted rotation
+    /// are cleaned up by the next one. Rotating to [`KeyPolicy::AutoKey`]
+    /// after an ISVSVN bump seals the file to the enclave's current SVN.
+    ///
+    /// The file must have been opened by path with read access. It is
+    /// closed afterwards and has to be reopened with the new key.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::sgxfs::{KeyPolicy, SgxFile};
+    ///
+    /// let file = SgxFile::open("foo.txt").unwrap();
+    /// file.rekey(KeyPolicy::AutoKey).unwrap();
+    /// ```
+    pub fn rekey(self, policy: KeyPolicy) -> io::Result<()> {
+        match policy {
+            KeyPolicy::AutoKey => self.inner.rekey(None),
+            KeyPolicy::Key(ref key) => self.inner.rekey(Some(key)),
+        }
+    }
+
     pub fn is_eof(&self) -> bool {
         self.inner.is_eof()
     }
@@ -139,6 +226,257 @@ impl SgxFile {
     }
 }
 
+/// The key a protected file is sealed under, used by [`SgxFile::rekey`].
+#[derive(Clone, Copy, Debug)]
+pub enum KeyPolicy {
+    /// A key derived from the enclave's seal key with the MRSIGNER policy at
+    /// its current SVN, as used by [`SgxFile::open`] and [`SgxFile::create`].
+    AutoKey,
+    /// A caller-supplied key, as used by [`SgxFile::open_ex`] and
+    /// [`SgxFile::create_ex`].
+    Key(sgx_key_128bit_t),
+}
+
+/// Metadata about a protected file, returned by [`SgxFile::metadata`].
+///
+/// The length comes from the file's authenticated metadata. The protected
+/// file format keeps no timestamps, so [`modified`] and [`accessed`] report
+/// what the host's file system says about the underlying file. Like anything
+/// else the host supplies, they are not protected against tampering.
+///
+/// [`modified`]: Metadata::modified
+/// [`accessed`]: Metadata::accessed
+pub struct Metadata(fs_imp::FileAttr);
+
+impl Metadata {
+    /// Returns the size of the file's contents, in bytes.
+    pub fn len(&self) -> u64 {
+        self.0.size()
+    }
+
+    /// Returns the last modification time the host reports for the file.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the host could not be asked, for instance
+    /// because the file was not opened by path.
+    pub fn modified(&self) -> io::Result<SystemTime> {
+        self.0.modified().map(FromInner::from_inner)
+    }
+
+    /// Returns the last access time the host reports for the file.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the host could not be asked, for instance
+    /// because the file was not opened by path.
+    pub fn accessed(&self) -> io::Result<SystemTime> {
+        self.0.accessed().map(FromInner::from_inner)
+    }
+}
+
+impl fmt::Debug for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metadata")
+            .field("len", &self.len())
+            .field("modified", &self.modified())
+            .field("accessed", &self.accessed())
+            .finish()
+    }
+}
+
+/// A read-only, paged view of a protected file.
+///
+/// Pages are decrypted on first access and kept in a least-recently-used
+/// cache inside enclave memory, so lookups into large files such as models
+/// or indexes only pay for the pages they touch instead of reading the
+/// whole file into the heap. The view can be shared between threads.
+///
+/// The length is fixed when the view is created. The underlying file must
+/// not be modified through another handle while the view is alive.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sgxfs::{SgxFile, SgxFileMap};
+///
+/// let map = SgxFileMap::new(SgxFile::open("model.bin").unwrap()).unwrap();
+/// let mut header = [0u8; 16];
+/// map.read_exact_at(&mut header, 0).unwrap();
+/// let page = map.page(3).unwrap();
+/// println!("{} bytes in page 3", page.len());
+/// ```
+pub struct SgxFileMap {
+    file: SgxFile,
+    len: u64,
+    cache: SgxMutex<PageCache>,
+}
+
+struct PageCache {
+    pages: HashMap<u64, (Arc<[u8]>, u64)>,
+    capacity: usize,
+    tick: u64,
+}
+
+impl SgxFileMap {
+    /// The size of a page, which matches the data node size of the
+    /// protected file format.
+    pub const PAGE_SIZE: usize = 4096;
+
+    /// The number of pages cached by [`SgxFileMap::new`].
+    pub const DEFAULT_CAPACITY: usize = 64;
+
+    /// Creates a view over `file` caching up to
+    /// [`DEFAULT_CAPACITY`](SgxFileMap::DEFAULT_CAPACITY) pages.
+    ///
+    /// The file must be open for reading.
+    pub fn new(file: SgxFile) -> io::Result<SgxFileMap> {
+        SgxFileMap::with_capacity(file, SgxFileMap::DEFAULT_CAPACITY)
+    }
+
+    /// Creates a view over `file` caching up to `capacity` pages.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidInput`] if
+    /// `capacity` is zero.
+    pub fn with_capacity(file: SgxFile, capacity: usize) -> io::Result<SgxFileMap> {
+        if capacity == 0 {
+            return Err(io::Error::new_const(
+                io::ErrorKind::InvalidInput,
+                &"cannot cache zero pages",
+            ));
+        }
+        let len = file.len()?;
+        Ok(SgxFileMap {
+            file,
+            len,
+            cache: SgxMutex::new(PageCache {
+                pages: HashMap::new(),
+                capacity,
+                tick: 0,
+            }),
+        })
+    }
+
+    /// Returns the length of the file in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of pages in the file. The last one may be shorter
+    /// than [`PAGE_SIZE`](SgxFileMap::PAGE_SIZE).
+    pub fn page_count(&self) -> u64 {
+        (self.len + SgxFileMap::PAGE_SIZE as u64 - 1) / SgxFileMap::PAGE_SIZE as u64
+    }
+
+    /// Returns the decrypted contents of the page at `index`.
+    ///
+    /// The page is shared with the cache, so no copy is made. It stays
+    /// valid after it has been evicted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidInput`] if `index`
+    /// is past the last page.
+    pub fn page(&self, index: u64) -> io::Result<Arc<[u8]>> {
+        if index >= self.page_count() {
+            return Err(io::Error::new_const(
+                io::ErrorKind::InvalidInput,
+                &"page index out of range",
+            ));
+        }
+
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        cache.tick += 1;
+        let tick = cache.tick;
+        if let Some(entry) = cache.pages.get_mut(&index) {
+            entry.1 = tick;
+            return Ok(entry.0.clone());
+        }
+
+        // The file's cursor is only moved while the cache is locked.
+        let start = index * SgxFileMap::PAGE_SIZE as u64;
+        let len = cmp::min(SgxFileMap::PAGE_SIZE as u64, self.len - start) as usize;
+        let mut data = vec![0u8; len];
+        (&self.file).seek(SeekFrom::Start(start))?;
+        (&self.file).read_exact(&mut data)?;
+        let page: Arc<[u8]> = Arc::from(data);
+
+        if cache.pages.len() >= cache.capacity {
+            let oldest = cache.pages.iter().min_by_key(|(_, entry)| entry.1).map(|(k, _)| *k);
+            if let Some(oldest) = oldest {
+                cache.pages.remove(&oldest);
+            }
+        }
+        cache.pages.insert(index, (page.clone(), tick));
+        Ok(page)
+    }
+
+    /// Reads bytes starting at `offset` into `buf`, returning how many were
+    /// read. Fewer bytes than requested are only returned at the end of the
+    /// file.
+    pub fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let mut read = 0;
+        while read < buf.len() {
+            let pos = offset.saturating_add(read as u64);
+            if pos >= self.len {
+                break;
+            }
+            let page_size = SgxFileMap::PAGE_SIZE as u64;
+            let page = self.page(pos / page_size)?;
+            let within = (pos % page_size) as usize;
+            let n = cmp::min(buf.len() - read, page.len() - within);
+            buf[read..read + n].copy_from_slice(&page[within..within + n]);
+            read += n;
+        }
+        Ok(read)
+    }
+
+    /// Reads exactly `buf.len()` bytes starting at `offset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`io::ErrorKind::UnexpectedEof`] if the file
+    /// ends before `buf` is filled.
+    pub fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        if self.read_at(buf, offset)? < buf.len() {
+            return Err(io::Error::new_const(
+                io::ErrorKind::UnexpectedEof,
+                &"failed to fill whole buffer",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Drops every cached page.
+    pub fn clear_cache(&self) {
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        cache.pages.clear();
+    }
+
+    /// Consumes the view, returning the underlying file.
+    pub fn into_inner(self) -> SgxFile {
+        self.file
+    }
+}
+
+impl fmt::Debug for SgxFileMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        f.debug_struct("SgxFileMap")
+            .field("len", &self.len)
+            .field("cached_pages", &cache.pages.len())
+            .field("capacity", &cache.capacity)
+            .finish()
+    }
+}
+
 impl AsInner<fs_imp::SgxFile> for SgxFile {
     fn as_inner(&self) -> &fs_imp::SgxFile { &self.inner }
 }
@@ -278,6 +616,24 @@ impl OpenOptions {
         Ok(SgxFile { inner })
     }
 
+    /// Opens a protected file whose content is authenticated but not encrypted.
+    ///
+    /// Every page is still checked against the file's MAC tree, so any
+    /// modification by the host is detected, but the data itself is stored
+    /// in plaintext. This avoids the cost of AES-GCM on large public data
+    /// sets that only need to be verified. Such files cannot be opened with
+    /// [`OpenOptions::open`] or [`OpenOptions::open_ex`], nor the other way
+    /// round.
+    ///
+    /// This requires a `libsgx_tprotected_fs` that provides
+    /// `sgx_fopen_integrity_only`, so it is only available with the
+    /// `sgxfs_integrity_only` feature.
+    #[cfg(feature = "sgxfs_integrity_only")]
+    pub fn open_integrity_only<P: AsRef<Path>>(&self, path: P) -> io::Result<SgxFile> {
+        let inner = fs_imp::SgxFile::open_integrity_only(path.as_ref(), &self.0)?;
+        Ok(SgxFile { inner })
+    }
+
     fn _open_ex(&self, path: &Path, key: &sgx_key_128bit_t) -> io::Result<SgxFile> {
         let inner = fs_imp::SgxFile::open_ex(path, &self.0, key)?;
         Ok(SgxFile { inner })
@@ -0,0 +1,138 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Tuning of the adaptive spin-then-OCALL strategy used by the blocking
+//! primitives.
+//!
+//! Every time a thread blocks on a contended `SgxThreadMutex` or
+//! `sync::fast::RwLock`, or parks itself with `thread::park`, the enclave
+//! has to leave through an OCALL (an EEXIT/EENTER pair plus a host futex
+//! call), and whoever wakes it up pays another OCALL. For short critical sections this round trip costs far
+//! more than the critical section itself.
+//!
+//! To avoid it, a thread first spins inside the enclave for a bounded number
+//! of iterations, watching the primitive it is waiting for. Only when the
+//! budget is exhausted does it register itself as a sleeper and leave the
+//! enclave. A thread that is still spinning is never registered as a sleeper,
+//! so releasing the primitive does not need an OCALL to wake it either.
+//!
+//! The budget can be tuned per primitive with [`set_spin_limit`]. A limit of
+//! `0` disables spinning and restores the old always-OCALL behavior, which
+//! may be preferable when the enclave has more TCS than available cores.
+//!
+//! The counters returned by [`stats`] are meant for benchmarking: they report
+//! how often a wait was satisfied by spinning and how many wait and wake
+//! OCALLs were actually issued.
+
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+/// The blocking primitives that support adaptive spinning.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SpinPrimitive {
+    /// `SgxThreadMutex` and everything built on it, such as `SgxMutex`.
+    Mutex,
+    /// The thread parker behind `thread::park` and `Thread::unpark`.
+    Parker,
+    /// `sync::fast::RwLock`, which backs off exponentially while spinning.
+    RwLock,
+}
+
+/// Default number of spin iterations before a waiter leaves the enclave.
+pub const DEFAULT_SPIN_LIMIT: u32 = 1000;
+
+/// Snapshot of the wait and wake counters of one primitive.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SpinStats {
+    /// Waits that were satisfied while spinning, without any OCALL.
+    pub spin_acquired: u64,
+    /// Wait OCALLs issued, i.e. times a waiter left the enclave to sleep.
+    pub ocall_waits: u64,
+    /// Wake OCALLs issued on behalf of a sleeping waiter.
+    pub ocall_wakes: u64,
+}
+
+struct Counters {
+    spin_limit: AtomicU32,
+    spin_acquired: AtomicU64,
+    ocall_waits: AtomicU64,
+    ocall_wakes: AtomicU64,
+}
+
+impl Counters {
+    const fn new() -> Counters {
+        Counters {
+            spin_limit: AtomicU32::new(DEFAULT_SPIN_LIMIT),
+            spin_acquired: AtomicU64::new(0),
+            ocall_waits: AtomicU64::new(0),
+            ocall_wakes: AtomicU64::new(0),
+        }
+    }
+}
+
+static MUTEX: Counters = Counters::new();
+static PARKER: Counters = Counters::new();
+static RWLOCK: Counters = Counters::new();
+
+fn counters(primitive: SpinPrimitive) -> &'static Counters {
+    match primitive {
+        SpinPrimitive::Mutex => &MUTEX,
+        SpinPrimitive::Parker => &PARKER,
+        SpinPrimitive::RwLock => &RWLOCK,
+    }
+}
+
+/// Sets how many spin iterations a waiter on `primitive` performs before it
+/// leaves the enclave to sleep. `0` disables spinning.
+pub fn set_spin_limit(primitive: SpinPrimitive, limit: u32) {
+    counters(primitive).spin_limit.store(limit, Ordering::Relaxed);
+}
+
+/// Returns the current spin limit of `primitive`.
+pub fn spin_limit(primitive: SpinPrimitive) -> u32 {
+    counters(primitive).spin_limit.load(Ordering::Relaxed)
+}
+
+/// Returns the wait and wake counters of `primitive` accumulated since the
+/// enclave was loaded, or since the last [`reset_stats`].
+pub fn stats(primitive: SpinPrimitive) -> SpinStats {
+    let c = counters(primitive);
+    SpinStats {
+        spin_acquired: c.spin_acquired.load(Ordering::Relaxed),
+        ocall_waits: c.ocall_waits.load(Ordering::Relaxed),
+        ocall_wakes: c.ocall_wakes.load(Ordering::Relaxed),
+    }
+}
+
+/// Resets the counters of `primitive` to zero.
+pub fn reset_stats(primitive: SpinPrimitive) {
+    let c = counters(primitive);
+    c.spin_acquired.store(0, Ordering::Relaxed);
+    c.ocall_waits.store(0, Ordering::Relaxed);
+    c.ocall_wakes.store(0, Ordering::Relaxed);
+}
+
+pub(crate) fn record_spin_acquired(primitive: SpinPrimitive) {
+    counters(primitive).spin_acquired.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn record_ocall_wait(primitive: SpinPrimitive) {
+    counters(primitive).ocall_waits.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn record_ocall_wake(primitive: SpinPrimitive) {
+    counters(primitive).ocall_wakes.fetch_add(1, Ordering::Relaxed);
+}
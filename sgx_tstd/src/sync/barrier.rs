@@ -16,11 +16,16 @@
 // under the License..
 
 use crate::fmt;
-use crate::sync::{SgxCondvar as Condvar, SgxMutex as Mutex};
+use crate::sync::atomic::{AtomicUsize, Ordering};
+use crate::sys::futex::{futex_wait, futex_wake_all};
 
 /// A barrier enables multiple threads to synchronize the beginning
 /// of some computation.
 ///
+/// The barrier keeps its state in enclave memory. Arriving threads only
+/// leave the enclave to sleep, and the last one wakes all of them up with a
+/// single OCALL.
+///
 /// # Examples
 ///
 /// ```
@@ -45,16 +50,16 @@ use crate::sync::{SgxCondvar as Condvar, SgxMutex as Mutex};
 /// }
 /// ```
 pub struct Barrier {
-    lock: Mutex<BarrierState>,
-    cvar: Condvar,
+    // The current generation in the high half and the number of threads that
+    // arrived in it in the low half. Keeping both in one word lets the leader
+    // reset the count and release the generation in a single step, so a
+    // thread arriving at that moment is counted in exactly one of them.
+    state: AtomicUsize,
     num_threads: usize,
 }
 
-// The inner state of a double barrier
-struct BarrierState {
-    count: usize,
-    generation_id: usize,
-}
+const COUNT_BITS: u32 = usize::BITS / 2;
+const COUNT_MASK: usize = (1 << COUNT_BITS) - 1;
 
 /// A `BarrierWaitResult` is returned by [`Barrier::wait()`] when all threads
 /// in the [`Barrier`] have rendezvoused.
@@ -91,11 +96,8 @@ impl Barrier {
     /// let barrier = Barrier::new(10);
     /// ```
     pub fn new(n: usize) -> Barrier {
-        Barrier {
-            lock: Mutex::new(BarrierState { count: 0, generation_id: 0 }),
-            cvar: Condvar::new(),
-            num_threads: n,
-        }
+        assert!(n <= COUNT_MASK, "too many threads for a barrier");
+        Barrier { state: AtomicUsize::new(0), num_threads: n }
     }
 
     /// Blocks the current thread until all threads have rendezvoused here.
@@ -132,21 +134,36 @@ impl Barrier {
     /// }
     /// ```
     pub fn wait(&self) -> BarrierWaitResult {
-        let mut lock = self.lock.lock().unwrap();
-        let local_gen = lock.generation_id;
-        lock.count += 1;
-        if lock.count < self.num_threads {
-            // We need a while loop to guard against spurious wakeups.
-            // https://en.wikipedia.org/wiki/Spurious_wakeup
-            while local_gen == lock.generation_id && lock.count < self.num_threads {
-                lock = self.cvar.wait(lock).unwrap();
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            let local_gen = state & !COUNT_MASK;
+            let count = (state & COUNT_MASK) + 1;
+            // The last thread to arrive starts the next generation with no
+            // threads in it; the others are added to the current one.
+            let new = if count < self.num_threads {
+                local_gen | count
+            } else {
+                local_gen.wrapping_add(1 << COUNT_BITS)
+            };
+            let result =
+                self.state.compare_exchange_weak(state, new, Ordering::AcqRel, Ordering::Relaxed);
+            match result {
+                Ok(_) if count < self.num_threads => {
+                    // We need a while loop to guard against spurious wakeups.
+                    // https://en.wikipedia.org/wiki/Spurious_wakeup
+                    let mut current = new;
+                    while current & !COUNT_MASK == local_gen {
+                        futex_wait(&self.state, current);
+                        current = self.state.load(Ordering::Acquire);
+                    }
+                    return BarrierWaitResult(false);
+                }
+                Ok(_) => {
+                    futex_wake_all(&self.state);
+                    return BarrierWaitResult(true);
+                }
+                Err(actual) => state = actual,
             }
-            BarrierWaitResult(false)
-        } else {
-            lock.count = 0;
-            lock.generation_id = lock.generation_id.wrapping_add(1);
-            self.cvar.notify_all();
-            BarrierWaitResult(true)
         }
     }
 }
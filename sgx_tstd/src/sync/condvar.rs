@@ -30,6 +30,7 @@
 use crate::alloc::AllocError;
 use crate::fmt;
 use crate::sync::{mutex, poison, LockResult, SgxMutexGuard, PoisonError};
+use crate::sys::tsc::{self, Deadline};
 use crate::sys_common::condvar as sys;
 use crate::time::{Duration, Instant};
 #[cfg(not(feature = "untrusted_time"))]
@@ -47,7 +48,22 @@ pub use crate::sys_common::condvar::SgxThreadCondvar;
 ///
 /// [`wait_timeout`]: Condvar::wait_timeout
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
-pub struct WaitTimeoutResult(bool);
+pub struct WaitTimeoutResult(pub(super) bool, pub(super) TimeSource);
+
+/// The clock a timed wait was measured against.
+///
+/// It is returned by [`WaitTimeoutResult::time_source`].
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TimeSource {
+    /// The time stamp counter, read inside the enclave. The host may delay
+    /// the end of the wait, but can't make it time out early.
+    ///
+    /// Used once the enclave pinned the TSC frequency with
+    /// [`time::set_tsc_frequency`](crate::time::set_tsc_frequency).
+    Enclave,
+    /// The host's wake-up, which the enclave has to take on trust.
+    Host,
+}
 
 impl WaitTimeoutResult {
     /// Returns `true` if the wait was known to have timed out.
@@ -97,6 +113,18 @@ impl WaitTimeoutResult {
     pub fn timed_out(&self) -> bool {
         self.0
     }
+
+    /// Returns the clock the timeout was measured against.
+    ///
+    /// With [`TimeSource::Enclave`], a timed out wait is known to have lasted
+    /// at least the requested duration.
+    pub fn time_source(&self) -> TimeSource {
+        self.1
+    }
+}
+
+fn time_source() -> TimeSource {
+    if tsc::frequency().is_some() { TimeSource::Enclave } else { TimeSource::Host }
 }
 
 /// A Condition Variable
@@ -403,10 +431,14 @@ impl SgxCondvar {
         guard: SgxMutexGuard<'a, T>,
         dur: Duration,
     ) -> LockResult<(SgxMutexGuard<'a, T>, WaitTimeoutResult)> {
+        let source = time_source();
         let (poisoned, result) = unsafe {
             let lock = mutex::guard_lock(&guard);
             let result = self.inner.wait_timeout(lock, dur);
-            (mutex::guard_poison(&guard).get(), WaitTimeoutResult(result.err() == Some(libc::ETIMEDOUT)))
+            (
+                mutex::guard_poison(&guard).get(),
+                WaitTimeoutResult(result.err() == Some(libc::ETIMEDOUT), source),
+            )
         };
         if poisoned { Err(PoisonError::new((guard, result))) } else { Ok((guard, result)) }
     }
@@ -472,14 +504,20 @@ impl SgxCondvar {
     where
         F: FnMut(&mut T) -> bool,
     {
+        let source = time_source();
+        let deadline = Deadline::after(dur);
         let start = Instant::now();
         loop {
             if !condition(&mut *guard) {
-                return Ok((guard, WaitTimeoutResult(false)));
+                return Ok((guard, WaitTimeoutResult(false, source)));
             }
-            let timeout = match dur.checked_sub(start.elapsed()) {
+            let remaining = match deadline {
+                Some(ref deadline) => deadline.remaining(),
+                None => dur.checked_sub(start.elapsed()),
+            };
+            let timeout = match remaining {
                 Some(timeout) => timeout,
-                None => return Ok((guard, WaitTimeoutResult(true))),
+                None => return Ok((guard, WaitTimeoutResult(true, source))),
             };
             guard = self.wait_timeout(guard, timeout)?.0;
         }
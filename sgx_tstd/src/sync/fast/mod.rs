@@ -0,0 +1,30 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Synchronization primitives tuned for enclaves.
+//!
+//! Leaving the enclave is the dominant cost of a blocking primitive. The
+//! types in this module keep all state in enclave memory and only leave the
+//! enclave for a futex-style host wait once spinning with exponential backoff
+//! has failed. The uncontended paths are a single atomic instruction.
+//!
+//! Unlike their counterparts in [`std::sync`](crate::sync), they are not
+//! poisoned when a thread panics while holding them.
+
+pub use self::rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+mod rwlock;
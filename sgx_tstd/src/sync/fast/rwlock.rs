@@ -0,0 +1,463 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use crate::cell::UnsafeCell;
+use crate::collections::LinkedList;
+use crate::fmt;
+use crate::hint;
+use crate::ops::{Deref, DerefMut};
+use crate::sync::adaptive::{self, SpinPrimitive};
+use crate::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use crate::sync::SgxThreadSpinlock;
+use crate::sys::mutex::{thread_set_event, thread_wait_event};
+use crate::time::Duration;
+use crate::u64;
+use crate::vec::Vec;
+
+use sgx_trts::enclave::SgxThreadData;
+
+// Layout of the state word: a writer bit, a bit telling that some thread is
+// asleep in the queue, and the number of readers in the remaining bits.
+const WRITER: usize = 1;
+const PARKED: usize = 1 << 1;
+const ONE_READER: usize = 1 << 2;
+const READERS: usize = !(WRITER | PARKED);
+
+// Upper bound of one backoff step, in spin iterations.
+const MAX_BACKOFF: u32 = 64;
+
+struct Waiter {
+    tcs: usize,
+    write: bool,
+    woken: AtomicBool,
+}
+
+struct RawRwLock {
+    state: AtomicUsize,
+    // Protects `queue`, and the PARKED bit is only changed while holding it.
+    lock: SgxThreadSpinlock,
+    queue: UnsafeCell<LinkedList<*const Waiter>>,
+}
+
+impl RawRwLock {
+    const fn new() -> RawRwLock {
+        RawRwLock {
+            state: AtomicUsize::new(0),
+            lock: SgxThreadSpinlock::new(),
+            queue: UnsafeCell::new(LinkedList::new()),
+        }
+    }
+
+    #[inline]
+    fn try_read(&self) -> bool {
+        // Once a thread sleeps in the queue new readers stay away, so that a
+        // waiting writer is not starved.
+        let state = self.state.load(Ordering::Relaxed);
+        state & (WRITER | PARKED) == 0
+            && state.checked_add(ONE_READER).is_some()
+            && self
+                .state
+                .compare_exchange(state, state + ONE_READER, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+    }
+
+    #[inline]
+    fn try_write(&self) -> bool {
+        self.state.compare_exchange(0, WRITER, Ordering::Acquire, Ordering::Relaxed).is_ok()
+    }
+
+    #[inline]
+    fn read(&self) {
+        if !self.try_read() {
+            self.lock_slow(false);
+        }
+    }
+
+    #[inline]
+    fn write(&self) {
+        if !self.try_write() {
+            self.lock_slow(true);
+        }
+    }
+
+    #[inline]
+    unsafe fn read_unlock(&self) {
+        let state = self.state.fetch_sub(ONE_READER, Ordering::Release);
+        if state & READERS == ONE_READER && state & PARKED != 0 {
+            self.unlock_slow();
+        }
+    }
+
+    #[inline]
+    unsafe fn write_unlock(&self) {
+        if self.state.compare_exchange(WRITER, 0, Ordering::Release, Ordering::Relaxed).is_err() {
+            self.state.fetch_and(!WRITER, Ordering::Release);
+            self.unlock_slow();
+        }
+    }
+
+    // Acquires the lock on behalf of a thread that already went through the
+    // slow path. Unlike the fast path this ignores PARKED, which lets woken
+    // and spinning threads take the lock while others sleep.
+    fn try_lock_contended(&self, write: bool) -> bool {
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            let new = if write {
+                if state & !PARKED != 0 {
+                    return false;
+                }
+                state | WRITER
+            } else {
+                if state & WRITER != 0 {
+                    return false;
+                }
+                match state.checked_add(ONE_READER) {
+                    Some(new) => new,
+                    None => return false,
+                }
+            };
+            match self.state.compare_exchange_weak(state, new, Ordering::Acquire, Ordering::Relaxed)
+            {
+                Ok(_) => return true,
+                Err(s) => state = s,
+            }
+        }
+    }
+
+    #[cold]
+    fn lock_slow(&self, write: bool) {
+        let primitive = SpinPrimitive::RwLock;
+        loop {
+            // Spin with exponential backoff, as long as nobody is asleep in
+            // the queue, which tells that the lock is held for long.
+            let mut budget = adaptive::spin_limit(primitive);
+            let mut backoff = 1;
+            while budget > 0 && self.state.load(Ordering::Relaxed) & PARKED == 0 {
+                let steps = backoff.min(budget);
+                for _ in 0..steps {
+                    hint::spin_loop();
+                }
+                budget -= steps;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                if self.try_lock_contended(write) {
+                    adaptive::record_spin_acquired(primitive);
+                    return;
+                }
+            }
+
+            if self.try_lock_contended(write) {
+                return;
+            }
+
+            let waiter = Waiter {
+                tcs: SgxThreadData::current().get_tcs(),
+                write,
+                woken: AtomicBool::new(false),
+            };
+            if !unsafe { self.enqueue(&waiter) } {
+                continue;
+            }
+
+            adaptive::record_ocall_wait(primitive);
+            while !waiter.woken.load(Ordering::Acquire) {
+                unsafe {
+                    thread_wait_event(waiter.tcs, Duration::new(u64::MAX, 1_000_000_000 - 1));
+                }
+            }
+            if self.try_lock_contended(write) {
+                return;
+            }
+        }
+    }
+
+    // Queues `waiter` and sets PARKED, unless the lock became available in
+    // the meantime. The state must not change between the check and setting
+    // PARKED, so that the thread releasing the lock sees the bit and wakes
+    // the queue up.
+    unsafe fn enqueue(&self, waiter: &Waiter) -> bool {
+        self.lock.lock();
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            let available = if waiter.write { state & !PARKED == 0 } else { state & WRITER == 0 };
+            if available {
+                self.lock.unlock();
+                return false;
+            }
+            if state & PARKED != 0 {
+                break;
+            }
+            match self.state.compare_exchange_weak(
+                state,
+                state | PARKED,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(s) => state = s,
+            }
+        }
+        (*self.queue.get()).push_back(waiter as *const Waiter);
+        self.lock.unlock();
+        true
+    }
+
+    // Wakes the writer at the front of the queue, or every queued reader.
+    // Woken threads compete for the lock again rather than being handed it.
+    #[cold]
+    unsafe fn unlock_slow(&self) {
+        self.lock.lock();
+        let state = self.state.load(Ordering::Relaxed);
+        if state & !PARKED != 0 {
+            // Someone took the lock in the meantime; they will wake the queue
+            // up when they release it.
+            self.lock.unlock();
+            return;
+        }
+
+        let queue = &mut *self.queue.get();
+        let mut woken = Vec::new();
+        let front_writes = match queue.front() {
+            Some(&front) => (*front).write,
+            None => false,
+        };
+        if front_writes {
+            woken.push(queue.pop_front().unwrap());
+        } else {
+            let (readers, writers): (LinkedList<_>, LinkedList<_>) =
+                queue.split_off(0).into_iter().partition(|&w| !(*w).write);
+            *queue = writers;
+            woken.extend(readers);
+        }
+        if queue.is_empty() {
+            self.state.fetch_and(!PARKED, Ordering::Relaxed);
+        }
+
+        // A woken waiter may return and free its node as soon as it sees
+        // `woken`, so that must be the last access to it.
+        let tcss: Vec<usize> = woken
+            .into_iter()
+            .map(|w| {
+                let tcs = (*w).tcs;
+                (*w).woken.store(true, Ordering::Release);
+                tcs
+            })
+            .collect();
+        self.lock.unlock();
+
+        for tcs in tcss {
+            adaptive::record_ocall_wake(SpinPrimitive::RwLock);
+            thread_set_event(tcs);
+        }
+    }
+}
+
+/// A reader-writer lock that stays inside the enclave unless contended.
+///
+/// Any number of readers or at most one writer hold the lock at a time. Both
+/// acquiring and releasing an uncontended lock are a single atomic operation
+/// and never leave the enclave. A thread that finds the lock taken spins with
+/// exponential backoff, within the budget set by
+/// [`adaptive::set_spin_limit`](crate::sync::adaptive::set_spin_limit) for
+/// [`SpinPrimitive::RwLock`], and only then sleeps through a host wait
+/// OCALL.
+///
+/// Once a thread sleeps waiting for the lock, new readers queue up behind it,
+/// so writers are not starved by a steady stream of readers. As a
+/// consequence, acquiring a read lock recursively may deadlock.
+///
+/// The lock is not reentrant and is not poisoned by panics.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::fast::RwLock;
+///
+/// let lock = RwLock::new(5);
+/// {
+///     let r1 = lock.read();
+///     let r2 = lock.read();
+///     assert_eq!(*r1 + *r2, 10);
+/// }
+/// *lock.write() += 1;
+/// assert_eq!(*lock.read(), 6);
+/// ```
+pub struct RwLock<T: ?Sized> {
+    raw: RawRwLock,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+/// RAII structure used to release the shared read access of a lock when
+/// dropped.
+///
+/// This structure is created by the [`read`] and [`try_read`] methods on
+/// [`RwLock`].
+///
+/// [`read`]: RwLock::read
+/// [`try_read`]: RwLock::try_read
+#[must_use = "if unused the RwLock will immediately unlock"]
+pub struct RwLockReadGuard<'a, T: ?Sized + 'a> {
+    lock: &'a RwLock<T>,
+}
+
+unsafe impl<T: ?Sized + Sync> Sync for RwLockReadGuard<'_, T> {}
+
+/// RAII structure used to release the exclusive write access of a lock when
+/// dropped.
+///
+/// This structure is created by the [`write`] and [`try_write`] methods on
+/// [`RwLock`].
+///
+/// [`write`]: RwLock::write
+/// [`try_write`]: RwLock::try_write
+#[must_use = "if unused the RwLock will immediately unlock"]
+pub struct RwLockWriteGuard<'a, T: ?Sized + 'a> {
+    lock: &'a RwLock<T>,
+}
+
+unsafe impl<T: ?Sized + Sync> Sync for RwLockWriteGuard<'_, T> {}
+
+impl<T> RwLock<T> {
+    /// Creates a new unlocked instance of an `RwLock<T>`.
+    pub const fn new(t: T) -> RwLock<T> {
+        RwLock { raw: RawRwLock::new(), data: UnsafeCell::new(t) }
+    }
+
+    /// Consumes this `RwLock`, returning the underlying data.
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    /// Locks this rwlock with shared read access, blocking the current thread
+    /// until it can be acquired.
+    #[inline]
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.raw.read();
+        RwLockReadGuard { lock: self }
+    }
+
+    /// Attempts to acquire this rwlock with shared read access, without
+    /// blocking.
+    #[inline]
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        if self.raw.try_read() {
+            Some(RwLockReadGuard { lock: self })
+        } else {
+            None
+        }
+    }
+
+    /// Locks this rwlock with exclusive write access, blocking the current
+    /// thread until it can be acquired.
+    #[inline]
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        self.raw.write();
+        RwLockWriteGuard { lock: self }
+    }
+
+    /// Attempts to lock this rwlock with exclusive write access, without
+    /// blocking.
+    #[inline]
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        if self.raw.try_write() {
+            Some(RwLockWriteGuard { lock: self })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the `RwLock` mutably, no actual locking needs
+    /// to take place.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("RwLock");
+        match self.try_read() {
+            Some(guard) => d.field("data", &&*guard),
+            None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish_non_exhaustive()
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    fn default() -> RwLock<T> {
+        RwLock::new(Default::default())
+    }
+}
+
+impl<T> From<T> for RwLock<T> {
+    fn from(t: T) -> Self {
+        RwLock::new(t)
+    }
+}
+
+impl<T: ?Sized> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        unsafe { self.lock.raw.read_unlock() }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        unsafe { self.lock.raw.write_unlock() }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLockReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLockWriteGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
@@ -31,20 +31,31 @@ pub use alloc_crate::sync::{Arc, Weak};
 pub use core::sync::atomic;
 
 pub use self::barrier::{Barrier, BarrierWaitResult};
-pub use self::condvar::{SgxCondvar, SgxThreadCondvar, WaitTimeoutResult};
+pub use self::condvar::{SgxCondvar, SgxThreadCondvar, TimeSource, WaitTimeoutResult};
+pub use self::lazy_lock::LazyLock;
 pub use self::mutex::{SgxMutex, SgxMutexGuard, SgxThreadMutex};
 pub use self::once::{Once, OnceState, ONCE_INIT};
+pub use self::once_lock::OnceLock;
 pub use self::poison::{LockResult, PoisonError, TryLockError, TryLockResult};
 pub use self::rwlock::{SgxRwLock, SgxRwLockReadGuard, SgxRwLockWriteGuard, SgxThreadRwLock};
 pub use self::spinlock::{SgxSpinlock, SgxSpinlockGuard, SgxThreadSpinlock};
+pub use self::wait_group::WaitGroup;
 
+pub mod adaptive;
+pub mod fast;
+#[cfg(feature = "thread")]
+pub mod mpmc;
 #[cfg(feature = "thread")]
 pub mod mpsc;
+pub mod switchless;
 
 mod barrier;
 mod condvar;
+mod lazy_lock;
 mod mutex;
 mod once;
+mod once_lock;
 mod poison;
 mod rwlock;
 mod spinlock;
+mod wait_group;
@@ -0,0 +1,481 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Bounded multi-producer, multi-consumer FIFO channels.
+//!
+//! Unlike [`mpsc`](super::mpsc), both halves of an `mpmc` channel can be
+//! cloned: any number of threads may send, and any number may receive, with
+//! each message delivered to exactly one receiver. This suits the common
+//! pattern of a pool of enclave worker threads draining a shared job queue.
+//!
+//! The channel created by [`bounded`] holds at most a fixed number of
+//! messages. Sending to a full channel blocks, or fails with [`try_send`],
+//! or gives up after a while with [`send_timeout`].
+//!
+//! Receivers can be waited on together with a [`Select`], which reports the
+//! first receiver that has a message ready.
+//!
+//! ## Disconnection
+//!
+//! A channel is disconnected once all its senders or all its receivers are
+//! dropped. Receivers still get the messages that were sent before the last
+//! sender went away; after that, receiving fails. Sending to a channel
+//! without receivers fails and hands the message back.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::sync::mpmc;
+//! use std::thread;
+//!
+//! let (tx, rx) = mpmc::bounded(4);
+//! let workers: Vec<_> = (0..2)
+//!     .map(|_| {
+//!         let rx = rx.clone();
+//!         thread::spawn(move || rx.iter().sum::<u32>())
+//!     })
+//!     .collect();
+//! for i in 1..=10 {
+//!     tx.send(i).unwrap();
+//! }
+//! drop(tx);
+//! let total: u32 = workers.into_iter().map(|w| w.join().unwrap()).sum();
+//! assert_eq!(total, 55);
+//! ```
+//!
+//! [`try_send`]: Sender::try_send
+//! [`send_timeout`]: Sender::send_timeout
+
+use crate::collections::VecDeque;
+use crate::error;
+use crate::fmt;
+use crate::sync::{Arc, PoisonError, SgxCondvar, SgxMutex, SgxMutexGuard};
+use crate::time::Duration;
+
+pub use self::select::{ReadyTimeoutError, Select, TryReadyError};
+pub use super::mpsc::{RecvError, RecvTimeoutError, SendError, TryRecvError, TrySendError};
+
+mod select;
+
+use self::select::Signal;
+
+struct State<T> {
+    queue: VecDeque<T>,
+    senders: usize,
+    receivers: usize,
+}
+
+struct Chan<T> {
+    cap: usize,
+    state: SgxMutex<State<T>>,
+    not_empty: SgxCondvar,
+    not_full: SgxCondvar,
+    // The `Select`s currently waiting on a receiver of this channel.
+    observers: SgxMutex<Vec<Arc<Signal>>>,
+}
+
+impl<T> Chan<T> {
+    fn lock(&self) -> SgxMutexGuard<'_, State<T>> {
+        // No user code runs with the lock held, so the state stays consistent
+        // even if some thread panicked.
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn notify_receivers(&self) {
+        self.not_empty.notify_one();
+        for signal in self.observers.lock().unwrap_or_else(PoisonError::into_inner).iter() {
+            signal.fire();
+        }
+    }
+
+    fn notify_disconnected(&self) {
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+        for signal in self.observers.lock().unwrap_or_else(PoisonError::into_inner).iter() {
+            signal.fire();
+        }
+    }
+}
+
+/// Creates a channel that buffers at most `cap` messages.
+///
+/// # Panics
+///
+/// Panics if `cap` is zero.
+pub fn bounded<T>(cap: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(cap > 0, "capacity of a bounded channel must be positive");
+    let chan = Arc::new(Chan {
+        cap,
+        state: SgxMutex::new(State {
+            queue: VecDeque::with_capacity(cap),
+            senders: 1,
+            receivers: 1,
+        }),
+        not_empty: SgxCondvar::new(),
+        not_full: SgxCondvar::new(),
+        observers: SgxMutex::new(Vec::new()),
+    });
+    (Sender { chan: chan.clone() }, Receiver { chan })
+}
+
+/// The sending half of a channel created by [`bounded`].
+///
+/// Senders can be cloned and shared between threads.
+pub struct Sender<T> {
+    chan: Arc<Chan<T>>,
+}
+
+/// The receiving half of a channel created by [`bounded`].
+///
+/// Receivers can be cloned and shared between threads. Each message is
+/// received by only one of them.
+pub struct Receiver<T> {
+    chan: Arc<Chan<T>>,
+}
+
+/// An error returned from [`Sender::send_timeout`].
+///
+/// The message that could not be sent is handed back.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum SendTimeoutError<T> {
+    /// The channel stayed full for the whole timeout.
+    Timeout(T),
+    /// All receivers have been dropped.
+    Disconnected(T),
+}
+
+impl<T> Sender<T> {
+    /// Sends a message, blocking while the channel is full.
+    ///
+    /// Fails, handing the message back, if all receivers have been dropped.
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        let chan = &*self.chan;
+        let mut state = chan.lock();
+        loop {
+            if state.receivers == 0 {
+                return Err(SendError(msg));
+            }
+            if state.queue.len() < chan.cap {
+                break;
+            }
+            state = chan.not_full.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+        state.queue.push_back(msg);
+        drop(state);
+        chan.notify_receivers();
+        Ok(())
+    }
+
+    /// Sends a message if the channel has room for it, without blocking.
+    pub fn try_send(&self, msg: T) -> Result<(), TrySendError<T>> {
+        let chan = &*self.chan;
+        let mut state = chan.lock();
+        if state.receivers == 0 {
+            return Err(TrySendError::Disconnected(msg));
+        }
+        if state.queue.len() == chan.cap {
+            return Err(TrySendError::Full(msg));
+        }
+        state.queue.push_back(msg);
+        drop(state);
+        chan.notify_receivers();
+        Ok(())
+    }
+
+    /// Sends a message, blocking for at most `timeout` while the channel is
+    /// full.
+    pub fn send_timeout(&self, msg: T, timeout: Duration) -> Result<(), SendTimeoutError<T>> {
+        let chan = &*self.chan;
+        let state = chan.lock();
+        let (mut state, _) = chan
+            .not_full
+            .wait_timeout_while(state, timeout, |s| s.receivers > 0 && s.queue.len() == chan.cap)
+            .unwrap_or_else(PoisonError::into_inner);
+        if state.receivers == 0 {
+            return Err(SendTimeoutError::Disconnected(msg));
+        }
+        if state.queue.len() == chan.cap {
+            return Err(SendTimeoutError::Timeout(msg));
+        }
+        state.queue.push_back(msg);
+        drop(state);
+        chan.notify_receivers();
+        Ok(())
+    }
+
+    /// Returns the number of messages in the channel.
+    pub fn len(&self) -> usize {
+        self.chan.lock().queue.len()
+    }
+
+    /// Returns `true` if the channel is empty.
+    pub fn is_empty(&self) -> bool {
+        self.chan.lock().queue.is_empty()
+    }
+
+    /// Returns `true` if the channel is full.
+    pub fn is_full(&self) -> bool {
+        self.chan.lock().queue.len() == self.chan.cap
+    }
+
+    /// Returns the capacity of the channel.
+    pub fn capacity(&self) -> usize {
+        self.chan.cap
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Sender<T> {
+        self.chan.lock().senders += 1;
+        Sender { chan: self.chan.clone() }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.chan.lock();
+        state.senders -= 1;
+        let last = state.senders == 0;
+        drop(state);
+        if last {
+            self.chan.notify_disconnected();
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Receives a message, blocking while the channel is empty.
+    ///
+    /// Fails once the channel is empty and all senders have been dropped.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let chan = &*self.chan;
+        let mut state = chan.lock();
+        loop {
+            if let Some(msg) = state.queue.pop_front() {
+                drop(state);
+                chan.not_full.notify_one();
+                return Ok(msg);
+            }
+            if state.senders == 0 {
+                return Err(RecvError);
+            }
+            state = chan.not_empty.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Receives a message if one is available, without blocking.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let chan = &*self.chan;
+        let mut state = chan.lock();
+        match state.queue.pop_front() {
+            Some(msg) => {
+                drop(state);
+                chan.not_full.notify_one();
+                Ok(msg)
+            }
+            None if state.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Receives a message, blocking for at most `timeout` while the channel
+    /// is empty.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let chan = &*self.chan;
+        let state = chan.lock();
+        let (mut state, _) = chan
+            .not_empty
+            .wait_timeout_while(state, timeout, |s| s.senders > 0 && s.queue.is_empty())
+            .unwrap_or_else(PoisonError::into_inner);
+        match state.queue.pop_front() {
+            Some(msg) => {
+                drop(state);
+                chan.not_full.notify_one();
+                Ok(msg)
+            }
+            None if state.senders == 0 => Err(RecvTimeoutError::Disconnected),
+            None => Err(RecvTimeoutError::Timeout),
+        }
+    }
+
+    /// Returns an iterator that blocks waiting for messages, and ends once
+    /// the channel is disconnected.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { rx: self }
+    }
+
+    /// Returns an iterator over the messages available right now.
+    pub fn try_iter(&self) -> TryIter<'_, T> {
+        TryIter { rx: self }
+    }
+
+    /// Returns the number of messages in the channel.
+    pub fn len(&self) -> usize {
+        self.chan.lock().queue.len()
+    }
+
+    /// Returns `true` if the channel is empty.
+    pub fn is_empty(&self) -> bool {
+        self.chan.lock().queue.is_empty()
+    }
+
+    /// Returns `true` if the channel is full.
+    pub fn is_full(&self) -> bool {
+        self.chan.lock().queue.len() == self.chan.cap
+    }
+
+    /// Returns the capacity of the channel.
+    pub fn capacity(&self) -> usize {
+        self.chan.cap
+    }
+
+    // Whether `recv` would return right away.
+    fn is_ready(&self) -> bool {
+        let state = self.chan.lock();
+        !state.queue.is_empty() || state.senders == 0
+    }
+
+    fn watch(&self, signal: &Arc<Signal>) {
+        self.chan.observers.lock().unwrap_or_else(PoisonError::into_inner).push(signal.clone());
+    }
+
+    fn unwatch(&self, signal: &Arc<Signal>) {
+        let mut observers = self.chan.observers.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(pos) = observers.iter().position(|s| Arc::ptr_eq(s, signal)) {
+            observers.swap_remove(pos);
+        }
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Receiver<T> {
+        self.chan.lock().receivers += 1;
+        Receiver { chan: self.chan.clone() }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.chan.lock();
+        state.receivers -= 1;
+        let last = state.receivers == 0;
+        drop(state);
+        if last {
+            self.chan.notify_disconnected();
+        }
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver").finish_non_exhaustive()
+    }
+}
+
+/// A blocking iterator over the messages of a [`Receiver`], created by
+/// [`Receiver::iter`].
+#[derive(Debug)]
+pub struct Iter<'a, T: 'a> {
+    rx: &'a Receiver<T>,
+}
+
+/// A non-blocking iterator over the messages of a [`Receiver`], created by
+/// [`Receiver::try_iter`].
+#[derive(Debug)]
+pub struct TryIter<'a, T: 'a> {
+    rx: &'a Receiver<T>,
+}
+
+/// An owning blocking iterator over the messages of a [`Receiver`].
+#[derive(Debug)]
+pub struct IntoIter<T> {
+    rx: Receiver<T>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+
+impl<'a, T> Iterator for TryIter<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.try_recv().ok()
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Receiver<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for Receiver<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { rx: self }
+    }
+}
+
+impl<T> fmt::Debug for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            SendTimeoutError::Timeout(..) => "Timeout(..)".fmt(f),
+            SendTimeoutError::Disconnected(..) => "Disconnected(..)".fmt(f),
+        }
+    }
+}
+
+impl<T> fmt::Display for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            SendTimeoutError::Timeout(..) => "timed out waiting on send operation".fmt(f),
+            SendTimeoutError::Disconnected(..) => "sending on a closed channel".fmt(f),
+        }
+    }
+}
+
+impl<T: Send> error::Error for SendTimeoutError<T> {}
+
+impl<T> From<SendError<T>> for SendTimeoutError<T> {
+    fn from(err: SendError<T>) -> SendTimeoutError<T> {
+        SendTimeoutError::Disconnected(err.0)
+    }
+}
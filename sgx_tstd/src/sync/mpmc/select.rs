@@ -0,0 +1,227 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use crate::error;
+use crate::fmt;
+use crate::sync::{Arc, PoisonError, SgxCondvar, SgxMutex};
+use crate::time::{Duration, Instant};
+#[cfg(not(feature = "untrusted_time"))]
+use crate::untrusted::time::InstantEx;
+
+use super::Receiver;
+
+// Fired by a channel when one of its receivers may have become ready.
+pub(super) struct Signal {
+    fired: SgxMutex<bool>,
+    cvar: SgxCondvar,
+}
+
+impl Signal {
+    fn new() -> Signal {
+        Signal { fired: SgxMutex::new(false), cvar: SgxCondvar::new() }
+    }
+
+    pub(super) fn fire(&self) {
+        *self.fired.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        self.cvar.notify_all();
+    }
+}
+
+trait SelectHandle {
+    fn is_ready(&self) -> bool;
+    fn watch(&self, signal: &Arc<Signal>);
+    fn unwatch(&self, signal: &Arc<Signal>);
+}
+
+impl<T> SelectHandle for Receiver<T> {
+    fn is_ready(&self) -> bool {
+        Receiver::is_ready(self)
+    }
+
+    fn watch(&self, signal: &Arc<Signal>) {
+        Receiver::watch(self, signal)
+    }
+
+    fn unwatch(&self, signal: &Arc<Signal>) {
+        Receiver::unwatch(self, signal)
+    }
+}
+
+/// Waits until one of several receivers is ready.
+///
+/// A receiver is ready when a message is available or its channel is
+/// disconnected, that is, when [`Receiver::recv`] would not block. Receivers
+/// are added with [`recv`], which returns the index that identifies them in
+/// the result of [`ready`] and its variants. The receivers may carry
+/// different message types.
+///
+/// When several receivers are ready, they are reported in turn, so that
+/// none of them is starved.
+///
+/// Another thread may take the message before the caller gets to it, so
+/// receive with [`Receiver::try_recv`] and select again if it comes back
+/// empty.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::mpmc::{self, Select};
+///
+/// let (tx1, rx1) = mpmc::bounded::<u32>(1);
+/// let (tx2, rx2) = mpmc::bounded::<&str>(1);
+/// tx2.send("hello").unwrap();
+///
+/// let mut sel = Select::new();
+/// let i1 = sel.recv(&rx1);
+/// let i2 = sel.recv(&rx2);
+/// let ready = sel.ready();
+/// assert_eq!(ready, i2);
+/// assert_eq!(rx2.try_recv(), Ok("hello"));
+/// # drop((tx1, i1));
+/// ```
+///
+/// [`recv`]: Select::recv
+/// [`ready`]: Select::ready
+pub struct Select<'a> {
+    handles: Vec<&'a dyn SelectHandle>,
+    next: usize,
+}
+
+/// An error returned from [`Select::try_ready`] when no receiver is ready.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct TryReadyError;
+
+/// An error returned from [`Select::ready_timeout`] when no receiver became
+/// ready in time.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct ReadyTimeoutError;
+
+impl<'a> Select<'a> {
+    /// Creates an empty set of receivers.
+    pub fn new() -> Select<'a> {
+        Select { handles: Vec::new(), next: 0 }
+    }
+
+    /// Adds `rx` to the set, and returns its index.
+    pub fn recv<T>(&mut self, rx: &'a Receiver<T>) -> usize {
+        self.handles.push(rx);
+        self.handles.len() - 1
+    }
+
+    /// Returns the index of a ready receiver, if any, without blocking.
+    pub fn try_ready(&mut self) -> Result<usize, TryReadyError> {
+        self.poll().ok_or(TryReadyError)
+    }
+
+    /// Blocks until a receiver is ready, and returns its index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no receiver was added.
+    pub fn ready(&mut self) -> usize {
+        assert!(!self.handles.is_empty(), "no receivers to select on");
+        self.wait(None).unwrap()
+    }
+
+    /// Blocks for at most `timeout` until a receiver is ready, and returns
+    /// its index.
+    pub fn ready_timeout(&mut self, timeout: Duration) -> Result<usize, ReadyTimeoutError> {
+        self.wait(Some(timeout)).ok_or(ReadyTimeoutError)
+    }
+
+    fn poll(&mut self) -> Option<usize> {
+        let len = self.handles.len();
+        for i in 0..len {
+            let index = (self.next + i) % len;
+            if self.handles[index].is_ready() {
+                self.next = index + 1;
+                return Some(index);
+            }
+        }
+        None
+    }
+
+    fn wait(&mut self, timeout: Option<Duration>) -> Option<usize> {
+        if let Some(index) = self.poll() {
+            return Some(index);
+        }
+
+        // Ask the channels to fire the signal on any change, then check
+        // again: a message sent before the signal was registered would
+        // otherwise be missed.
+        let signal = Arc::new(Signal::new());
+        for handle in &self.handles {
+            handle.watch(&signal);
+        }
+        let start = Instant::now();
+        let result = loop {
+            if let Some(index) = self.poll() {
+                break Some(index);
+            }
+            let fired = signal.fired.lock().unwrap_or_else(PoisonError::into_inner);
+            let mut fired = match timeout {
+                Some(timeout) => match timeout.checked_sub(start.elapsed()) {
+                    Some(left) => {
+                        signal
+                            .cvar
+                            .wait_timeout_while(fired, left, |fired| !*fired)
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .0
+                    }
+                    None => break self.poll(),
+                },
+                None => signal
+                    .cvar
+                    .wait_while(fired, |fired| !*fired)
+                    .unwrap_or_else(PoisonError::into_inner),
+            };
+            *fired = false;
+        };
+        for handle in &self.handles {
+            handle.unwatch(&signal);
+        }
+        result
+    }
+}
+
+impl Default for Select<'_> {
+    fn default() -> Self {
+        Select::new()
+    }
+}
+
+impl fmt::Debug for Select<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Select").field("len", &self.handles.len()).finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for TryReadyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "no receiver is ready".fmt(f)
+    }
+}
+
+impl error::Error for TryReadyError {}
+
+impl fmt::Display for ReadyTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "timed out waiting for a ready receiver".fmt(f)
+    }
+}
+
+impl error::Error for ReadyTimeoutError {}
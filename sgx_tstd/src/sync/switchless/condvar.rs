@@ -0,0 +1,170 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use crate::fmt;
+use crate::sync::atomic::{AtomicUsize, Ordering};
+use crate::sync::{TimeSource, WaitTimeoutResult};
+use crate::time::Duration;
+
+use super::mutex::MutexGuard;
+use super::park::{self, Timer};
+
+/// A condition variable whose waiters spin inside the enclave.
+///
+/// Waiting threads spin on a bit of their own until they are notified. Used
+/// with a switchless [`Mutex`](super::Mutex), neither waiting nor notifying
+/// makes an OCALL. Timed waits are measured with the TSC once the enclave
+/// pinned its frequency with
+/// [`time::set_tsc_frequency`](crate::time::set_tsc_frequency), and
+/// otherwise look at the host clock every so often.
+///
+/// As with other condition variables, waits can end spuriously, so the
+/// condition has to be checked again when they return.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::sync::switchless::{Condvar, Mutex};
+/// use std::thread;
+///
+/// let pair = Arc::new((Mutex::new(false), Condvar::new()));
+/// let pair2 = Arc::clone(&pair);
+///
+/// thread::spawn(move || {
+///     let (lock, cvar) = &*pair2;
+///     *lock.lock() = true;
+///     cvar.notify_one();
+/// });
+///
+/// let (lock, cvar) = &*pair;
+/// let started = cvar.wait_while(lock.lock(), |started| !*started);
+/// assert!(*started);
+/// ```
+pub struct Condvar {
+    // Bumped by every notification, and waited on by the waiters.
+    seq: AtomicUsize,
+}
+
+impl Condvar {
+    /// Creates a new condition variable.
+    pub const fn new() -> Condvar {
+        Condvar { seq: AtomicUsize::new(0) }
+    }
+
+    /// Releases the lock held by `guard` and spins until this condition
+    /// variable is notified, then re-acquires the lock.
+    pub fn wait<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        let seq = self.seq.load(Ordering::Relaxed);
+        unsafe { guard.lock.raw.unlock() };
+        park::wait(&self.seq, seq, None);
+        guard.lock.raw.lock();
+        guard
+    }
+
+    /// Waits on this condition variable as long as `condition` returns
+    /// `true`.
+    pub fn wait_while<'a, T: ?Sized, F>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        mut condition: F,
+    ) -> MutexGuard<'a, T>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        while condition(&mut *guard) {
+            guard = self.wait(guard);
+        }
+        guard
+    }
+
+    /// Waits on this condition variable for a notification, for at most
+    /// `dur`.
+    ///
+    /// The returned [`WaitTimeoutResult`] tells whether the time ran out, and
+    /// which clock measured it.
+    pub fn wait_timeout<'a, T: ?Sized>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        dur: Duration,
+    ) -> (MutexGuard<'a, T>, WaitTimeoutResult) {
+        let timer = Timer::after(dur);
+        let seq = self.seq.load(Ordering::Relaxed);
+        unsafe { guard.lock.raw.unlock() };
+        let woken = park::wait(&self.seq, seq, Some(&timer));
+        guard.lock.raw.lock();
+        (guard, WaitTimeoutResult(!woken, time_source(&timer)))
+    }
+
+    /// Waits on this condition variable as long as `condition` returns
+    /// `true`, for at most `dur` in total.
+    pub fn wait_timeout_while<'a, T: ?Sized, F>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        dur: Duration,
+        mut condition: F,
+    ) -> (MutexGuard<'a, T>, WaitTimeoutResult)
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        let timer = Timer::after(dur);
+        let source = time_source(&timer);
+        loop {
+            if !condition(&mut *guard) {
+                return (guard, WaitTimeoutResult(false, source));
+            }
+            if timer.remaining().is_none() {
+                return (guard, WaitTimeoutResult(true, source));
+            }
+            let seq = self.seq.load(Ordering::Relaxed);
+            unsafe { guard.lock.raw.unlock() };
+            park::wait(&self.seq, seq, Some(&timer));
+            guard.lock.raw.lock();
+        }
+    }
+
+    /// Wakes up one thread waiting on this condition variable.
+    pub fn notify_one(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+        park::wake(&self.seq, 1);
+    }
+
+    /// Wakes up all threads waiting on this condition variable.
+    pub fn notify_all(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+        park::wake(&self.seq, usize::MAX);
+    }
+}
+
+fn time_source(timer: &Timer) -> TimeSource {
+    match *timer {
+        Timer::Enclave(_) => TimeSource::Enclave,
+        Timer::Host(..) => TimeSource::Host,
+    }
+}
+
+impl fmt::Debug for Condvar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Condvar").finish_non_exhaustive()
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Condvar {
+        Condvar::new()
+    }
+}
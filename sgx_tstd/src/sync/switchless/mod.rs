@@ -0,0 +1,37 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Synchronization primitives for the switchless call model.
+//!
+//! With switchless calls, host and enclave worker threads poll shared
+//! memory instead of crossing the enclave boundary. The [`Mutex`] and
+//! [`Condvar`] in this module apply the same idea to waiting: a blocked
+//! thread spins in enclave memory instead of sleeping in an OCALL, and the
+//! thread releasing it just sets a bit. Waiting and waking need no OCALL,
+//! except that a timed wait which cannot read the TSC inside the enclave
+//! checks the host clock every so often. The price is a busy core per
+//! waiting thread, so they suit short waits on hot paths.
+//!
+//! Like the ones in [`sync::fast`](crate::sync::fast), these primitives are
+//! not poisoned when a thread panics while holding them.
+
+pub use self::condvar::Condvar;
+pub use self::mutex::{Mutex, MutexGuard};
+
+mod condvar;
+mod mutex;
+mod park;
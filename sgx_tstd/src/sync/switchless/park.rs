@@ -0,0 +1,159 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Spin-waiting inside the enclave.
+//!
+//! A waiter is queued in enclave memory under the address of the word it
+//! waits on, and spins on a `woken` bit of its own until a waker sets it.
+
+use crate::cell::UnsafeCell;
+use crate::hint;
+use crate::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use crate::sync::SgxThreadSpinlock;
+use crate::sys::tsc::Deadline;
+use crate::time::{Duration, Instant};
+#[cfg(not(feature = "untrusted_time"))]
+use crate::untrusted::time::InstantEx;
+use crate::vec::Vec;
+
+// Spins between two looks at a host clock.
+const HOST_CLOCK_INTERVAL: u32 = 1024;
+
+struct Waiter {
+    key: usize,
+    woken: AtomicBool,
+}
+
+struct Table {
+    lock: SgxThreadSpinlock,
+    waiters: UnsafeCell<Vec<*const Waiter>>,
+}
+
+// `waiters` is only accessed while holding `lock`.
+unsafe impl Sync for Table {}
+
+static TABLE: Table =
+    Table { lock: SgxThreadSpinlock::new(), waiters: UnsafeCell::new(Vec::new()) };
+
+/// When a timed wait gives up.
+pub enum Timer {
+    /// Measured with the TSC, without leaving the enclave.
+    Enclave(Deadline),
+    /// Measured with the host clock, read through an OCALL every so often.
+    Host(Instant, Duration),
+}
+
+impl Timer {
+    pub fn after(dur: Duration) -> Timer {
+        match Deadline::after(dur) {
+            Some(deadline) => Timer::Enclave(deadline),
+            None => Timer::Host(Instant::now(), dur),
+        }
+    }
+
+    pub fn remaining(&self) -> Option<Duration> {
+        match *self {
+            Timer::Enclave(ref deadline) => deadline.remaining(),
+            Timer::Host(start, dur) => {
+                dur.checked_sub(start.elapsed()).filter(|d| *d > Duration::ZERO)
+            }
+        }
+    }
+}
+
+/// Spins while `word` holds `expected`, until [`wake`] releases the thread
+/// or `timer` runs out.
+///
+/// Returns `false` on timeout. Like a futex, the wait may also end
+/// spuriously, so the caller has to check its condition again.
+pub fn wait(word: &AtomicUsize, expected: usize, timer: Option<&Timer>) -> bool {
+    let key = word as *const AtomicUsize as usize;
+
+    unsafe {
+        // Wakers change the word before taking the table lock, so it cannot
+        // change unnoticed between this check and queueing.
+        TABLE.lock.lock();
+        if word.load(Ordering::Acquire) != expected {
+            TABLE.lock.unlock();
+            return true;
+        }
+        let waiter = Waiter { key, woken: AtomicBool::new(false) };
+        (*TABLE.waiters.get()).push(&waiter as *const Waiter);
+        TABLE.lock.unlock();
+
+        let mut spins = 0u32;
+        loop {
+            if waiter.woken.load(Ordering::Acquire) {
+                break true;
+            }
+            hint::spin_loop();
+
+            spins = spins.wrapping_add(1);
+            if let Some(timer) = timer {
+                let check = match timer {
+                    Timer::Enclave(_) => true,
+                    Timer::Host(..) => spins % HOST_CLOCK_INTERVAL == 0,
+                };
+                if check && timer.remaining().is_none() && dequeue(&waiter) {
+                    break false;
+                }
+            }
+        }
+    }
+}
+
+// Takes a timed-out waiter off the queue, unless a waker got to it first.
+unsafe fn dequeue(waiter: &Waiter) -> bool {
+    TABLE.lock.lock();
+    let waiters = &mut *TABLE.waiters.get();
+    let removed = match waiters.iter().position(|&w| w == waiter as *const Waiter) {
+        Some(index) => {
+            waiters.remove(index);
+            true
+        }
+        None => false,
+    };
+    TABLE.lock.unlock();
+    removed
+}
+
+/// Releases up to `count` threads waiting on `word`, in arrival order, and
+/// returns how many there were.
+pub fn wake(word: &AtomicUsize, count: usize) -> usize {
+    let key = word as *const AtomicUsize as usize;
+    let mut woken = 0;
+
+    unsafe {
+        TABLE.lock.lock();
+        let waiters = &mut *TABLE.waiters.get();
+        let mut i = 0;
+        while i < waiters.len() && woken < count {
+            let w = waiters[i];
+            if (*w).key == key {
+                waiters.remove(i);
+                woken += 1;
+                // A woken waiter may return and free its node as soon as it
+                // sees `woken`, so that must be the last access to it.
+                (*w).woken.store(true, Ordering::Release);
+            } else {
+                i += 1;
+            }
+        }
+        TABLE.lock.unlock();
+    }
+    woken
+}
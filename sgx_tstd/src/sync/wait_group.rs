@@ -0,0 +1,124 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use crate::fmt;
+use crate::sync::atomic::{AtomicUsize, Ordering};
+use crate::sys::futex::{futex_wait, futex_wake_all};
+
+/// A counter that lets threads wait for a set of tasks to finish, in the
+/// manner of Go's `sync.WaitGroup`.
+///
+/// [`add`] raises the number of outstanding tasks, each task calls [`done`]
+/// when it finishes, and [`wait`] blocks until the count drops to zero. The
+/// count lives in enclave memory; waiting threads only leave the enclave to
+/// sleep, and the last [`done`] wakes all of them with a single OCALL.
+///
+/// A `WaitGroup` can be reused once [`wait`] has returned.
+///
+/// [`add`]: WaitGroup::add
+/// [`done`]: WaitGroup::done
+/// [`wait`]: WaitGroup::wait
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::{Arc, WaitGroup};
+/// use std::thread;
+///
+/// let wg = Arc::new(WaitGroup::new());
+/// for _ in 0..4 {
+///     wg.add(1);
+///     let wg = Arc::clone(&wg);
+///     thread::spawn(move || {
+///         // Do some work.
+///         wg.done();
+///     });
+/// }
+/// wg.wait();
+/// ```
+pub struct WaitGroup {
+    count: AtomicUsize,
+}
+
+impl WaitGroup {
+    /// Creates a new `WaitGroup` with no outstanding tasks.
+    pub const fn new() -> WaitGroup {
+        WaitGroup { count: AtomicUsize::new(0) }
+    }
+
+    /// Adds `n` outstanding tasks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the count overflows.
+    pub fn add(&self, n: usize) {
+        if self
+            .count
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |c| c.checked_add(n))
+            .is_err()
+        {
+            panic!("WaitGroup counter overflow");
+        }
+    }
+
+    /// Marks one task as finished, and wakes up the waiting threads if it
+    /// was the last one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no outstanding task.
+    pub fn done(&self) {
+        match self.count.fetch_update(Ordering::AcqRel, Ordering::Relaxed, |c| c.checked_sub(1)) {
+            Ok(1) => futex_wake_all(&self.count),
+            Ok(_) => {}
+            Err(_) => panic!("negative WaitGroup counter"),
+        }
+    }
+
+    /// Blocks the current thread until there are no outstanding tasks.
+    ///
+    /// Everything the tasks did before calling [`done`] is visible to the
+    /// current thread once this returns.
+    ///
+    /// [`done`]: WaitGroup::done
+    pub fn wait(&self) {
+        loop {
+            let count = self.count.load(Ordering::Acquire);
+            if count == 0 {
+                return;
+            }
+            futex_wait(&self.count, count);
+        }
+    }
+
+    /// Returns the number of outstanding tasks.
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for WaitGroup {
+    fn default() -> WaitGroup {
+        WaitGroup::new()
+    }
+}
+
+impl fmt::Debug for WaitGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitGroup").field("count", &self.count()).finish()
+    }
+}
@@ -0,0 +1,83 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Program arguments, passed in once through `t_global_args_ecall`.
+
+use crate::ffi::OsString;
+use crate::fmt;
+use crate::os::unix::ffi::OsStringExt;
+use crate::sync::Once;
+use crate::vec;
+
+static ARGS_INIT: Once = Once::new();
+// Written once, inside `ARGS_INIT`, and only read after it has completed.
+static mut ARGS: Vec<OsString> = Vec::new();
+
+/// Sets the arguments from `blob`, where each argument is terminated by a
+/// NUL byte. Only the first call has an effect.
+pub fn init(blob: &[u8]) {
+    ARGS_INIT.call_once(|| unsafe {
+        ARGS = parse(blob);
+    });
+}
+
+fn parse(blob: &[u8]) -> Vec<OsString> {
+    // A missing final terminator is tolerated.
+    let blob = blob.strip_suffix(&[0]).unwrap_or(blob);
+    if blob.is_empty() {
+        return Vec::new();
+    }
+    blob.split(|&b| b == 0).map(|arg| OsString::from_vec(arg.to_vec())).collect()
+}
+
+/// Returns the arguments, or none if they were never set.
+pub fn args() -> Args {
+    let args = if ARGS_INIT.is_completed() { unsafe { ARGS.clone() } } else { Vec::new() };
+    Args { iter: args.into_iter() }
+}
+
+pub struct Args {
+    iter: vec::IntoIter<OsString>,
+}
+
+impl fmt::Debug for Args {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.iter.as_slice().fmt(f)
+    }
+}
+
+impl Iterator for Args {
+    type Item = OsString;
+    fn next(&mut self) -> Option<OsString> {
+        self.iter.next()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Args {
+    fn len(&self) -> usize {
+        self.iter.len()
+    }
+}
+
+impl DoubleEndedIterator for Args {
+    fn next_back(&mut self) -> Option<OsString> {
+        self.iter.next_back()
+    }
+}
@@ -21,6 +21,7 @@ use crate::collections::LinkedList;
 use crate::io::{self, Error};
 use crate::sync::SgxThreadSpinlock;
 use crate::sys::mutex::{self, SgxThreadMutex};
+use crate::sys::tsc::Deadline;
 use crate::thread::rsgx_thread_self;
 use crate::time::Duration;
 use crate::u64;
@@ -80,6 +81,10 @@ impl SgxThreadCondvarInner {
     }
 
     pub unsafe fn wait_timeout(&mut self, mutex: &SgxThreadMutex, dur: Duration) -> SysError {
+        // With a TSC deadline the host can only delay the timeout: its claim
+        // that the wait timed out is checked against the TSC.
+        let deadline = Deadline::after(dur);
+        let mut dur = dur;
         self.lock.lock();
         self.queue.push_back(rsgx_thread_self());
         let mut waiter: sgx_thread_t = SGX_THREAD_T_NULL;
@@ -111,7 +116,19 @@ impl SgxThreadCondvarInner {
                 .position(|&waiter| waiter == rsgx_thread_self())
             {
                 Some(pos) => {
-                    if result < 0 && Error::last_os_error().kind() == io::ErrorKind::TimedOut {
+                    let expired = match deadline {
+                        Some(ref deadline) => match deadline.remaining() {
+                            Some(left) => {
+                                dur = left;
+                                false
+                            }
+                            None => true,
+                        },
+                        None => {
+                            result < 0 && Error::last_os_error().kind() == io::ErrorKind::TimedOut
+                        }
+                    };
+                    if expired {
                         self.queue.remove(pos);
                         ret = Err(libc::ETIMEDOUT);
                         break;
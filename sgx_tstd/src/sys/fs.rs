@@ -32,6 +32,7 @@ use crate::sys_common::{AsInner, AsInnerMut, FromInner, IntoInner};
 use crate::untrusted::fs;
 
 use sgx_libc::{c_int, dirent64, mode_t, off64_t, stat64};
+use sgx_trts::trts::rsgx_raw_is_outside_enclave;
 
 pub use crate::sys_common::fs::{remove_dir_all, try_exists};
 
@@ -398,6 +399,39 @@ impl File {
         }
     }
 
+    pub fn lock_shared(&self) -> io::Result<()> {
+        self.flock(libc::LOCK_SH)
+    }
+
+    pub fn lock_exclusive(&self) -> io::Result<()> {
+        self.flock(libc::LOCK_EX)
+    }
+
+    pub fn try_lock_shared(&self) -> io::Result<bool> {
+        self.try_flock(libc::LOCK_SH)
+    }
+
+    pub fn try_lock_exclusive(&self) -> io::Result<bool> {
+        self.try_flock(libc::LOCK_EX)
+    }
+
+    pub fn unlock(&self) -> io::Result<()> {
+        self.flock(libc::LOCK_UN)
+    }
+
+    fn flock(&self, operation: c_int) -> io::Result<()> {
+        cvt_r(|| unsafe { libc::flock(self.as_raw_fd(), operation) })?;
+        Ok(())
+    }
+
+    fn try_flock(&self, operation: c_int) -> io::Result<bool> {
+        match cvt_r(|| unsafe { libc::flock(self.as_raw_fd(), operation | libc::LOCK_NB) }) {
+            Ok(_) => Ok(true),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
     pub fn truncate(&self, size: u64) -> io::Result<()> {
         use crate::convert::TryInto;
         let size: off64_t =
@@ -622,6 +656,10 @@ pub fn readlink(p: &Path) -> io::Result<PathBuf> {
         }
 
         if buf_read != buf.capacity() {
+            // A link target is a non-empty C string on the host.
+            if buf.is_empty() || buf.contains(&0) {
+                return Err(invalid_path(&"host returned an invalid link target"));
+            }
             buf.shrink_to_fit();
 
             return Ok(PathBuf::from(OsString::from_vec(buf)));
@@ -664,18 +702,50 @@ pub fn lstat(p: &Path) -> io::Result<FileAttr> {
 
 pub fn canonicalize(p: &Path) -> io::Result<PathBuf> {
     let path = CString::new(p.as_os_str().as_bytes())?;
-    let buf;
-    unsafe {
+    let buf = unsafe {
         let r = libc::realpath(path.as_ptr());
         if r.is_null() {
             return Err(io::Error::last_os_error());
         }
-        buf = CStr::from_ptr(r).to_bytes().to_vec();
+        let buf = copy_untrusted_path(r as *const u8);
         libc::free(r as *mut _);
+        buf
+    };
+    let buf = buf.ok_or_else(|| invalid_path(&"host returned an unterminated path"))?;
+
+    // Access checks inside the enclave are made on the resolved path, so it
+    // must really be resolved: absolute, without `.`, `..` or empty
+    // components that the host could use to point somewhere else.
+    let resolved = buf.first() == Some(&b'/')
+        && (buf.len() == 1
+            || buf[1..].split(|&b| b == b'/').all(|c| !c.is_empty() && c != b"." && c != b".."));
+    if !resolved {
+        return Err(invalid_path(&"host returned a path that is not canonical"));
     }
     Ok(PathBuf::from(OsString::from_vec(buf)))
 }
 
+// Copies a NUL-terminated string out of untrusted memory. Every byte is read
+// exactly once, so the host cannot move the terminator after it is found.
+unsafe fn copy_untrusted_path(s: *const u8) -> Option<Vec<u8>> {
+    let mut buf = Vec::new();
+    for i in 0..libc::PATH_MAX as usize {
+        let p = s.add(i);
+        if !rsgx_raw_is_outside_enclave(p, 1) {
+            return None;
+        }
+        match ptr::read_volatile(p) {
+            0 => return Some(buf),
+            b => buf.push(b),
+        }
+    }
+    None
+}
+
+fn invalid_path(msg: &'static &'static str) -> io::Error {
+    io::Error::new_const(io::ErrorKind::InvalidData, msg)
+}
+
 fn open_from(from: &Path) -> io::Result<(fs::File, fs::Metadata)> {
     use crate::untrusted::fs::File;
     use crate::sys_common::fs::NOT_FILE_ERROR;
@@ -723,9 +793,9 @@ pub fn chroot(_dir: &Path) -> io::Result<()> {
 
 mod libc {
     pub use sgx_libc::ocall::{
-        chmod, closedir, dirfd, fchmod, fcntl_arg0, fdatasync, free, fstat64, fstatat64, fsync,
-        ftruncate64, linkat, lseek64, lstat64, mkdir, open64, opendir, readdir64_r, readlink,
-        realpath, rename, rmdir, stat64, symlink, unlink,
+        chmod, closedir, dirfd, fchmod, fcntl_arg0, fdatasync, flock, free, fstat64, fstatat64,
+        fsync, ftruncate64, linkat, lseek64, lstat64, mkdir, open64, opendir, readdir64_r,
+        readlink, realpath, rename, rmdir, stat64, symlink, unlink,
     };
     pub use sgx_libc::*;
 }
@@ -0,0 +1,177 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Futex-style waiting on an atomic word, kept inside the enclave.
+//!
+//! The waiters are recorded in an enclave-side table keyed by the address of
+//! the word, so only the sleep itself and the wake-up leave the enclave. The
+//! host can at worst wake a waiter early, which callers already handle since
+//! waits may return spuriously.
+
+use crate::cell::UnsafeCell;
+use crate::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use crate::sync::SgxThreadSpinlock;
+use crate::sys::mutex::{thread_set_event, thread_set_multiple_events, thread_wait_event};
+use crate::time::Duration;
+use crate::u64;
+use crate::vec::Vec;
+
+use sgx_trts::enclave::SgxThreadData;
+
+struct Waiter {
+    addr: usize,
+    tcs: usize,
+    woken: AtomicBool,
+}
+
+struct Table {
+    lock: SgxThreadSpinlock,
+    waiters: UnsafeCell<Vec<*const Waiter>>,
+}
+
+// `waiters` is only accessed while holding `lock`.
+unsafe impl Sync for Table {}
+
+static TABLE: Table =
+    Table { lock: SgxThreadSpinlock::new(), waiters: UnsafeCell::new(Vec::new()) };
+
+/// Blocks the current thread while `futex` holds `expected`.
+///
+/// Returns immediately if the value differs. Otherwise sleeps until a call to
+/// [`futex_wake_all`] on the same word, or spuriously, so the caller has to
+/// check its condition again.
+pub fn futex_wait(futex: &AtomicUsize, expected: usize) {
+    let waiter = Waiter::new(futex);
+    unsafe {
+        if !enqueue(futex, expected, &waiter) {
+            return;
+        }
+        while !waiter.woken.load(Ordering::Acquire) {
+            thread_wait_event(waiter.tcs, Duration::new(u64::MAX, 1_000_000_000 - 1));
+        }
+    }
+}
+
+/// Like [`futex_wait`], but gives up after `timeout`.
+///
+/// Returns `false` if the wait ended without a wake-up, because `timeout`
+/// elapsed or the host returned early. The host decides when the timeout
+/// elapsed, so callers that care have to check the time themselves.
+pub fn futex_wait_timeout(futex: &AtomicUsize, expected: usize, timeout: Duration) -> bool {
+    let waiter = Waiter::new(futex);
+    unsafe {
+        if !enqueue(futex, expected, &waiter) {
+            return true;
+        }
+        while !waiter.woken.load(Ordering::Acquire) {
+            thread_wait_event(waiter.tcs, timeout);
+            // Once the waiter is off the table, wakers won't touch it, so it
+            // can return.
+            if remove(&waiter) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl Waiter {
+    fn new(futex: &AtomicUsize) -> Waiter {
+        Waiter {
+            addr: futex as *const AtomicUsize as usize,
+            tcs: SgxThreadData::current().get_tcs(),
+            woken: AtomicBool::new(false),
+        }
+    }
+}
+
+// Queues `waiter`, unless `futex` no longer holds `expected`.
+unsafe fn enqueue(futex: &AtomicUsize, expected: usize, waiter: &Waiter) -> bool {
+    // The waker changes the value before taking the table lock, so the value
+    // cannot change unnoticed between this check and queueing.
+    TABLE.lock.lock();
+    if futex.load(Ordering::Acquire) != expected {
+        TABLE.lock.unlock();
+        return false;
+    }
+    (*TABLE.waiters.get()).push(waiter as *const Waiter);
+    TABLE.lock.unlock();
+    true
+}
+
+// Takes `waiter` off the table, unless a waker got to it first.
+unsafe fn remove(waiter: &Waiter) -> bool {
+    TABLE.lock.lock();
+    let waiters = &mut *TABLE.waiters.get();
+    let removed = match waiters.iter().position(|&w| w == waiter as *const Waiter) {
+        Some(index) => {
+            waiters.remove(index);
+            true
+        }
+        None => false,
+    };
+    TABLE.lock.unlock();
+    removed
+}
+
+/// Wakes every thread blocked in [`futex_wait`] on `futex`, with a single
+/// OCALL.
+pub fn futex_wake_all(futex: &AtomicUsize) {
+    futex_wake_many(&[futex as *const AtomicUsize as usize]);
+}
+
+/// Wakes every thread blocked in [`futex_wait`] on any of the words at
+/// `addrs`, with a single OCALL.
+///
+/// Only the addresses are compared, so the words may be gone by now. A word
+/// reusing the address may then see a spurious wake-up.
+pub fn futex_wake_many(addrs: &[usize]) {
+    let tcss = unsafe { dequeue(addrs) };
+    match tcss.len() {
+        0 => {}
+        1 => unsafe {
+            thread_set_event(tcss[0]);
+        },
+        _ => unsafe {
+            thread_set_multiple_events(&tcss);
+        },
+    }
+}
+
+// Removes the waiters on the words at `addrs`, marks them woken, and returns
+// their TCSs.
+unsafe fn dequeue(addrs: &[usize]) -> Vec<usize> {
+    let mut tcss = Vec::new();
+
+    TABLE.lock.lock();
+    let waiters = &mut *TABLE.waiters.get();
+    let mut i = 0;
+    while i < waiters.len() {
+        let w = waiters[i];
+        if addrs.contains(&(*w).addr) {
+            waiters.remove(i);
+            // A woken waiter may return and free its node as soon as it sees
+            // `woken`, so that must be the last access to it.
+            tcss.push((*w).tcs);
+            (*w).woken.store(true, Ordering::Release);
+        } else {
+            i += 1;
+        }
+    }
+    TABLE.lock.unlock();
+    tcss
+}
@@ -21,6 +21,7 @@ use sgx_trts::trts;
 
 pub use self::rand::hashmap_random_keys;
 
+pub mod args;
 #[cfg(feature = "backtrace")]
 pub mod backtrace;
 pub mod cmath;
@@ -28,6 +29,7 @@ pub mod condvar;
 pub mod env;
 pub mod fd;
 pub mod fs;
+pub mod futex;
 pub mod io;
 pub mod memchr;
 pub mod mutex;
@@ -38,6 +40,8 @@ pub mod os_str;
 pub mod path;
 #[cfg(feature = "pipe")]
 pub mod pipe;
+#[cfg(feature = "process")]
+pub mod process;
 pub mod rand;
 pub mod rwlock;
 pub mod sgxfs;
@@ -50,6 +54,9 @@ pub mod thread_local_dtor;
 #[cfg(feature = "thread")]
 pub mod thread_local_key;
 pub mod time;
+#[cfg(feature = "thread")]
+pub mod timer;
+pub mod tsc;
 
 pub fn decode_error_kind(errno: i32) -> ErrorKind {
     use ErrorKind::*;
@@ -19,7 +19,9 @@ use crate::boxed::Box;
 use crate::cell::UnsafeCell;
 use crate::cmp;
 use crate::collections::LinkedList;
+use crate::hint;
 use crate::ptr;
+use crate::sync::adaptive::{self, SpinPrimitive};
 use crate::sync::SgxThreadSpinlock;
 use crate::thread::rsgx_thread_self;
 use crate::time::Duration;
@@ -192,6 +194,8 @@ impl SgxThreadMutexInner {
     }
 
     unsafe fn lock(&mut self) -> SysError {
+        let mut spins = adaptive::spin_limit(SpinPrimitive::Mutex);
+        let mut spun = false;
         loop {
             self.lock.lock();
             if self.control == SgxThreadMutexControl::SGX_THREAD_MUTEX_RECURSIVE
@@ -212,14 +216,35 @@ impl SgxThreadMutexInner {
                 self.owner = rsgx_thread_self();
                 self.refcount += 1;
                 self.lock.unlock();
+                if spun {
+                    adaptive::record_spin_acquired(SpinPrimitive::Mutex);
+                }
                 return Ok(());
             }
 
+            // Nobody is sleeping on the mutex yet, so the owner is likely to
+            // release it soon. Spin outside the spinlock until the mutex looks
+            // free instead of leaving the enclave right away. A spinning thread
+            // is not in the queue, so the owner doesn't need an OCALL to wake it.
+            if spins > 0 && self.queue.is_empty() {
+                self.lock.unlock();
+                spun = true;
+                while spins > 0 {
+                    spins -= 1;
+                    hint::spin_loop();
+                    if ptr::read_volatile(&self.owner) == SGX_THREAD_T_NULL {
+                        break;
+                    }
+                }
+                continue;
+            }
+
             if !self.queue.contains(&rsgx_thread_self()) {
                 self.queue.push_back(rsgx_thread_self());
             }
 
             self.lock.unlock();
+            adaptive::record_ocall_wait(SpinPrimitive::Mutex);
             thread_wait_event(
                 SgxThreadData::current().get_tcs(),
                 Duration::new(u64::MAX, 1_000_000_000 - 1),
@@ -259,6 +284,7 @@ impl SgxThreadMutexInner {
 
         if thread_waiter != SGX_THREAD_T_NULL {
             // wake the waiter up
+            adaptive::record_ocall_wake(SpinPrimitive::Mutex);
             thread_set_event(SgxThreadData::from_raw(thread_waiter).get_tcs());
         }
         Ok(())
@@ -18,10 +18,12 @@
 use crate::cmp;
 use crate::ffi::CStr;
 use crate::io::{self, IoSlice, IoSliceMut};
-use crate::mem;
+use crate::mem::{self, ManuallyDrop};
+use crate::ptr;
 use crate::net::{Shutdown, SocketAddr};
 use crate::os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, RawFd};
 use crate::str;
+use crate::sync::atomic::{AtomicPtr, Ordering};
 use crate::sys::fd::FileDesc;
 use crate::sys_common::net::{getsockopt, setsockopt, sockaddr_to_addr};
 use crate::sys_common::{AsInner, FromInner, IntoInner};
@@ -35,10 +37,21 @@ pub use crate::sys::{cvt, cvt_r};
 
 pub type wrlen_t = size_t;
 
-pub struct Socket(FileDesc);
+pub struct Socket(ManuallyDrop<FileDesc>);
 
 pub fn init() {}
 
+static CLOSE_ERROR_HANDLER: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+pub fn set_close_error_handler(handler: fn(&io::Error)) {
+    CLOSE_ERROR_HANDLER.store(handler as *mut (), Ordering::SeqCst);
+}
+
+pub fn take_close_error_handler() -> Option<fn(&io::Error)> {
+    let handler = CLOSE_ERROR_HANDLER.swap(ptr::null_mut(), Ordering::SeqCst);
+    if handler.is_null() { None } else { Some(unsafe { mem::transmute(handler) }) }
+}
+
 pub fn cvt_gai(err: c_int) -> io::Result<()> {
     if err == 0 {
         return Ok(());
@@ -68,7 +81,7 @@ impl Socket {
     pub fn new(sockfd: c_int) -> io::Result<Socket> {
         let fd = unsafe { FileDesc::from_raw_fd(sockfd) };
         fd.set_cloexec()?;
-        Ok(Socket(fd))
+        Ok(Socket::from_inner(fd))
     }
 
     pub fn new_socket_addr_type(addr: &SocketAddr, ty: c_int) -> io::Result<Socket> {
@@ -80,12 +93,16 @@ impl Socket {
     }
 
     pub fn new_raw(fam: c_int, ty: c_int) -> io::Result<Socket> {
+        Socket::new_proto(fam, ty, 0)
+    }
+
+    pub fn new_proto(fam: c_int, ty: c_int, protocol: c_int) -> io::Result<Socket> {
         unsafe {
             // On platforms that support it we pass the SOCK_CLOEXEC
             // flag to atomically create the socket and set it as
             // CLOEXEC. On Linux this was added in 2.6.27.
-            let fd = cvt(libc::socket(fam, ty | libc::SOCK_CLOEXEC, 0))?;
-            Ok(Socket(FileDesc::from_raw_fd(fd)))
+            let fd = cvt(libc::socket(fam, ty | libc::SOCK_CLOEXEC, protocol))?;
+            Ok(Socket::from_inner(FileDesc::from_raw_fd(fd)))
         }
     }
 
@@ -95,7 +112,10 @@ impl Socket {
 
             // Like above, set cloexec atomically
             cvt(libc::socketpair(fam, ty | libc::SOCK_CLOEXEC, 0, fds.as_mut_ptr()))?;
-            Ok((Socket(FileDesc::from_raw_fd(fds[0])), Socket(FileDesc::from_raw_fd(fds[1]))))
+            Ok((
+                Socket::from_inner(FileDesc::from_raw_fd(fds[0])),
+                Socket::from_inner(FileDesc::from_raw_fd(fds[1])),
+            ))
         }
     }
 
@@ -169,6 +189,21 @@ impl Socket {
         }
     }
 
+    // Starts connecting to `addr` without waiting for the handshake, leaving
+    // the socket in nonblocking mode. Returns whether it completed at once.
+    pub fn connect_nonblocking(&self, addr: &SocketAddr) -> io::Result<bool> {
+        self.set_nonblocking(true)?;
+        let r = unsafe {
+            let (addrp, len) = addr.into_inner();
+            cvt(libc::connect(self.as_raw_fd(), addrp, len))
+        };
+        match r {
+            Ok(_) => Ok(true),
+            Err(ref e) if e.raw_os_error() == Some(libc::EINPROGRESS) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
     // Attention:
     // this function is a blocking function, which make an OCALL
     // and block itself **in the untrusted OS**. This is very much
@@ -186,12 +221,17 @@ impl Socket {
         // glibc 2.10 and musl 0.9.5.
         unsafe {
             let fd = cvt_r(|| libc::accept4(self.as_raw_fd(), storage, len, libc::SOCK_CLOEXEC))?;
-            Ok(Socket(FileDesc::from_raw_fd(fd)))
+            Ok(Socket::from_inner(FileDesc::from_raw_fd(fd)))
         }
     }
 
+    pub fn close(self) -> io::Result<()> {
+        let fd = self.into_raw_fd();
+        cvt(unsafe { libc::close(fd) }).map(drop)
+    }
+
     pub fn duplicate(&self) -> io::Result<Socket> {
-        self.0.duplicate().map(Socket)
+        self.0.duplicate().map(Socket::from_inner)
     }
 
     fn recv_with_flags(&self, buf: &mut [u8], flags: c_int) -> io::Result<usize> {
@@ -248,10 +288,134 @@ impl Socket {
         Ok(n as usize)
     }
 
+    pub fn recv_vectored(&self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<(usize, bool)> {
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_iov = bufs.as_mut_ptr().cast();
+        msg.msg_iovlen = bufs.len() as _;
+
+        let n = self.recv_msg(&mut msg)?;
+        Ok((n, msg.msg_flags & libc::MSG_TRUNC == libc::MSG_TRUNC))
+    }
+
+    // Receives a datagram along with the TOS (IPv4) or traffic class (IPv6)
+    // byte delivered in the control message, or 0 if there is none.
+    pub fn recv_from_with_tos(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr, u8)> {
+        let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+        let mut bufs = [IoSliceMut::new(buf)];
+        // Room for one IP_TOS or IPV6_TCLASS message, suitably aligned.
+        let mut control = [0_usize; 8];
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_name = &mut storage as *mut _ as *mut _;
+        msg.msg_namelen = mem::size_of_val(&storage) as libc::socklen_t;
+        msg.msg_iov = bufs.as_mut_ptr().cast();
+        msg.msg_iovlen = bufs.len() as _;
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = mem::size_of_val(&control) as _;
+
+        let n = self.recv_msg(&mut msg)?;
+        let addr = sockaddr_to_addr(&storage, msg.msg_namelen as usize)?;
+
+        let mut tos = 0;
+        let control_end = msg.msg_control as usize + msg.msg_controllen as usize;
+        unsafe {
+            let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+            while !cmsg.is_null() {
+                let hdr = &*cmsg;
+                // The control data is written by the host, so bound every
+                // read by what fits in our buffer.
+                if cmsg as usize + hdr.cmsg_len as usize > control_end {
+                    break;
+                }
+                let data = libc::CMSG_DATA(cmsg);
+                let data_len = (cmsg as usize + hdr.cmsg_len as usize).saturating_sub(data as usize);
+                match (hdr.cmsg_level, hdr.cmsg_type) {
+                    (libc::IPPROTO_IP, libc::IP_TOS) if data_len >= 1 => {
+                        tos = *data;
+                    }
+                    (libc::IPPROTO_IPV6, libc::IPV6_TCLASS)
+                        if data_len >= mem::size_of::<c_int>() =>
+                    {
+                        tos = ptr::read_unaligned(data as *const c_int) as u8;
+                    }
+                    _ => {}
+                }
+                cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+            }
+        }
+        Ok((n, addr, tos))
+    }
+
+    pub fn recv_from_vectored(
+        &self,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> io::Result<(usize, bool, SocketAddr)> {
+        let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_name = &mut storage as *mut _ as *mut _;
+        msg.msg_namelen = mem::size_of_val(&storage) as libc::socklen_t;
+        msg.msg_iov = bufs.as_mut_ptr().cast();
+        msg.msg_iovlen = bufs.len() as _;
+
+        let n = self.recv_msg(&mut msg)?;
+        let truncated = msg.msg_flags & libc::MSG_TRUNC == libc::MSG_TRUNC;
+        Ok((n, truncated, sockaddr_to_addr(&storage, msg.msg_namelen as usize)?))
+    }
+
     pub fn peek_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
         self.recv_from_with_flags(buf, MSG_PEEK)
     }
 
+    // Waits for `events` with a single poll, without consuming any data.
+    pub fn wait(&self, events: libc::c_short, timeout: Option<Duration>) -> io::Result<()> {
+        let mut fds = [libc::pollfd { fd: self.as_raw_fd(), events, revents: 0 }];
+        if poll(&mut fds, timeout)? > 0 && fds[0].revents != 0 {
+            Ok(())
+        } else {
+            Err(io::Error::new_const(io::ErrorKind::TimedOut, &"timed out waiting for readiness"))
+        }
+    }
+
+    // Waits until the socket reports any of `events` or `deadline` passes.
+    // The socket's own timeout options are not involved.
+    pub fn wait_until(&self, events: libc::c_short, deadline: Instant) -> io::Result<()> {
+        let mut fds = [libc::pollfd { fd: self.as_raw_fd(), events, revents: 0 }];
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(io::Error::new_const(io::ErrorKind::TimedOut, &"deadline has elapsed"));
+            }
+            fds[0].revents = 0;
+            if poll(&mut fds, Some(deadline - now))? > 0 && fds[0].revents != 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    pub fn recv_deadline(&self, buf: &mut [u8], deadline: Instant) -> io::Result<usize> {
+        loop {
+            self.wait_until(libc::POLLIN, deadline)?;
+            // Another reader may have raced us to the datagram; don't block on it.
+            match self.recv_with_flags(buf, libc::MSG_DONTWAIT) {
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                other => return other,
+            }
+        }
+    }
+
+    pub fn recv_from_deadline(
+        &self,
+        buf: &mut [u8],
+        deadline: Instant,
+    ) -> io::Result<(usize, SocketAddr)> {
+        loop {
+            self.wait_until(libc::POLLIN, deadline)?;
+            match self.recv_from_with_flags(buf, libc::MSG_DONTWAIT) {
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                other => return other,
+            }
+        }
+    }
+
     pub fn write(&self, buf: &[u8]) -> io::Result<usize> {
         self.0.write(buf)
     }
@@ -260,6 +424,78 @@ impl Socket {
         self.0.write_vectored(bufs)
     }
 
+    // Writes as much of `buf` as the peer accepts within `timeout`. Returns
+    // the number of bytes written, which is less than `buf.len()` only if the
+    // timeout elapsed first.
+    pub fn write_all_timeout(&self, mut buf: &[u8], timeout: Duration) -> io::Result<usize> {
+        let deadline = Instant::now() + timeout;
+        let mut written = 0;
+        while !buf.is_empty() {
+            match self.wait_until(libc::POLLOUT, deadline) {
+                Ok(()) => {}
+                Err(ref e) if e.kind() == io::ErrorKind::TimedOut => break,
+                Err(e) => return Err(e),
+            }
+            let ret = cvt(unsafe {
+                libc::send(
+                    self.as_raw_fd(),
+                    buf.as_ptr() as *const c_void,
+                    buf.len(),
+                    libc::MSG_DONTWAIT | libc::MSG_NOSIGNAL,
+                )
+            });
+            match ret {
+                Ok(0) => {
+                    return Err(io::Error::new_const(
+                        io::ErrorKind::WriteZero,
+                        &"failed to write whole buffer",
+                    ));
+                }
+                Ok(n) => {
+                    // The count comes from the untrusted host.
+                    let n = cmp::min(n as usize, buf.len());
+                    written += n;
+                    buf = &buf[n..];
+                }
+                Err(ref e)
+                    if e.kind() == io::ErrorKind::WouldBlock
+                        || e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(written)
+    }
+
+    // Sends the first `len` bytes of `staging` in full. Partial writes are
+    // resumed from the same untrusted buffer, so nothing is copied again.
+    pub fn send_all_staged(&self, staging: &StagingBuf, len: usize) -> io::Result<()> {
+        assert!(len <= staging.cap);
+        let mut sent = 0;
+        while sent < len {
+            let ret = cvt(unsafe {
+                libc::send_untrusted(
+                    self.as_raw_fd(),
+                    staging.ptr.add(sent) as *const c_void,
+                    len - sent,
+                    libc::MSG_NOSIGNAL,
+                )
+            });
+            match ret {
+                Ok(0) => {
+                    return Err(io::Error::new_const(
+                        io::ErrorKind::WriteZero,
+                        &"failed to write whole buffer",
+                    ));
+                }
+                // The count comes from the untrusted host.
+                Ok(n) => sent += cmp::min(n as usize, len - sent),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
     #[inline]
     pub fn is_write_vectored(&self) -> bool {
         self.0.is_write_vectored()
@@ -270,33 +506,36 @@ impl Socket {
         Ok(n as usize)
     }
 
-    pub fn set_timeout(&self, dur: Option<Duration>, kind: c_int) -> io::Result<()> {
-        let timeout = match dur {
-            Some(dur) => {
-                if dur.as_secs() == 0 && dur.subsec_nanos() == 0 {
-                    return Err(io::Error::new_const(
-                        io::ErrorKind::InvalidInput,
-                        &"cannot set a 0 duration timeout",
-                    ));
-                }
+    // Sends the concatenation of `bufs` as a single datagram, to `dst` or to
+    // the connected peer. A datagram is sent whole or not at all, so anything
+    // but the total length from the host is rejected.
+    pub fn send_to_vectored(
+        &self,
+        bufs: &[IoSlice<'_>],
+        dst: Option<&SocketAddr>,
+    ) -> io::Result<usize> {
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        if let Some(dst) = dst {
+            let (dstp, dstlen) = dst.into_inner();
+            msg.msg_name = dstp as *mut _;
+            msg.msg_namelen = dstlen;
+        }
+        msg.msg_iov = bufs.as_ptr() as *mut _;
+        msg.msg_iovlen = bufs.len() as _;
 
-                let secs = if dur.as_secs() > libc::time_t::MAX as u64 {
-                    libc::time_t::MAX
-                } else {
-                    dur.as_secs() as libc::time_t
-                };
-                let mut timeout = libc::timeval {
-                    tv_sec: secs,
-                    tv_usec: dur.subsec_micros() as libc::suseconds_t,
-                };
-                if timeout.tv_sec == 0 && timeout.tv_usec == 0 {
-                    timeout.tv_usec = 1;
-                }
-                timeout
-            }
-            None => libc::timeval { tv_sec: 0, tv_usec: 0 },
-        };
-        setsockopt(self, libc::SOL_SOCKET, kind, timeout)
+        let n = self.send_msg(&mut msg)?;
+        let total = bufs.iter().fold(0_usize, |total, buf| total.saturating_add(buf.len()));
+        if n != total {
+            return Err(io::Error::new_const(
+                io::ErrorKind::InvalidData,
+                &"sendmsg reported a partially sent datagram",
+            ));
+        }
+        Ok(n)
+    }
+
+    pub fn set_timeout(&self, dur: Option<Duration>, kind: c_int) -> io::Result<()> {
+        setsockopt(self, libc::SOL_SOCKET, kind, timeout_to_timeval(dur)?)
     }
 
     pub fn timeout(&self, kind: c_int) -> io::Result<Option<Duration>> {
@@ -311,30 +550,111 @@ impl Socket {
     }
 
     pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
-        let how = match how {
-            Shutdown::Write => libc::SHUT_WR,
-            Shutdown::Read => libc::SHUT_RD,
-            Shutdown::Both => libc::SHUT_RDWR,
-        };
-        cvt(unsafe { libc::shutdown(self.as_raw_fd(), how) })?;
+        cvt(unsafe { libc::shutdown(self.as_raw_fd(), how.as_raw()) })?;
         Ok(())
     }
 
     pub fn set_linger(&self, linger: Option<Duration>) -> io::Result<()> {
-        let linger = libc::linger {
-            l_onoff: linger.is_some() as c_int,
-            l_linger: linger.unwrap_or_default().as_secs() as c_int,
-        };
+        let secs = linger.unwrap_or_default().as_secs();
+        if secs > c_int::MAX as u64 {
+            return Err(io::Error::new_const(
+                io::ErrorKind::InvalidInput,
+                &"linger duration is too large",
+            ));
+        }
+        let linger = libc::linger { l_onoff: linger.is_some() as c_int, l_linger: secs as c_int };
 
         setsockopt(self, libc::SOL_SOCKET, libc::SO_LINGER, linger)
     }
 
     pub fn linger(&self) -> io::Result<Option<Duration>> {
         let val: libc::linger = getsockopt(self, libc::SOL_SOCKET, libc::SO_LINGER)?;
+        if val.l_linger < 0 {
+            return Err(io::Error::new_const(
+                io::ErrorKind::InvalidData,
+                &"SO_LINGER returned a negative duration",
+            ));
+        }
 
         Ok((val.l_onoff != 0).then(|| Duration::from_secs(val.l_linger as u64)))
     }
 
+    pub fn set_keepalive(&self, keepalive: bool) -> io::Result<()> {
+        setsockopt(self, libc::SOL_SOCKET, libc::SO_KEEPALIVE, keepalive as c_int)
+    }
+
+    pub fn keepalive(&self) -> io::Result<bool> {
+        let raw: c_int = getsockopt(self, libc::SOL_SOCKET, libc::SO_KEEPALIVE)?;
+        Ok(raw != 0)
+    }
+
+    pub fn set_keepalive_idle(&self, idle: Duration) -> io::Result<()> {
+        setsockopt(self, libc::IPPROTO_TCP, libc::TCP_KEEPIDLE, keepalive_secs(idle)?)
+    }
+
+    pub fn keepalive_idle(&self) -> io::Result<Duration> {
+        keepalive_duration(getsockopt(self, libc::IPPROTO_TCP, libc::TCP_KEEPIDLE)?)
+    }
+
+    pub fn set_keepalive_interval(&self, interval: Duration) -> io::Result<()> {
+        setsockopt(self, libc::IPPROTO_TCP, libc::TCP_KEEPINTVL, keepalive_secs(interval)?)
+    }
+
+    pub fn keepalive_interval(&self) -> io::Result<Duration> {
+        keepalive_duration(getsockopt(self, libc::IPPROTO_TCP, libc::TCP_KEEPINTVL)?)
+    }
+
+    pub fn set_keepalive_count(&self, count: u32) -> io::Result<()> {
+        if count == 0 || count > MAX_TCP_KEEPCNT {
+            return Err(io::Error::new_const(
+                io::ErrorKind::InvalidInput,
+                &"keepalive probe count must be between 1 and 127",
+            ));
+        }
+        setsockopt(self, libc::IPPROTO_TCP, libc::TCP_KEEPCNT, count as c_int)
+    }
+
+    pub fn keepalive_count(&self) -> io::Result<u32> {
+        let raw: c_int = getsockopt(self, libc::IPPROTO_TCP, libc::TCP_KEEPCNT)?;
+        if raw < 1 || raw as u32 > MAX_TCP_KEEPCNT {
+            return Err(io::Error::new_const(
+                io::ErrorKind::InvalidData,
+                &"TCP_KEEPCNT returned an out of range count",
+            ));
+        }
+        Ok(raw as u32)
+    }
+
+    pub fn set_reuse_address(&self, reuse: bool) -> io::Result<()> {
+        setsockopt(self, libc::SOL_SOCKET, libc::SO_REUSEADDR, reuse as c_int)
+    }
+
+    pub fn reuse_address(&self) -> io::Result<bool> {
+        let raw: c_int = getsockopt(self, libc::SOL_SOCKET, libc::SO_REUSEADDR)?;
+        Ok(raw != 0)
+    }
+
+    pub fn set_tos(&self, tos: u32) -> io::Result<()> {
+        if tos > u8::MAX as u32 {
+            return Err(io::Error::new_const(
+                io::ErrorKind::InvalidInput,
+                &"IP_TOS must fit in a byte",
+            ));
+        }
+        setsockopt(self, libc::IPPROTO_IP, libc::IP_TOS, tos as c_int)
+    }
+
+    pub fn tos(&self) -> io::Result<u32> {
+        let raw: c_int = getsockopt(self, libc::IPPROTO_IP, libc::IP_TOS)?;
+        if !(0..=u8::MAX as c_int).contains(&raw) {
+            return Err(io::Error::new_const(
+                io::ErrorKind::InvalidData,
+                &"IP_TOS returned a value that does not fit in a byte",
+            ));
+        }
+        Ok(raw as u32)
+    }
+
     pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
         setsockopt(self, libc::IPPROTO_TCP, libc::TCP_NODELAY, nodelay as c_int)
     }
@@ -344,6 +664,15 @@ impl Socket {
         Ok(raw != 0)
     }
 
+    pub fn set_quickack(&self, quickack: bool) -> io::Result<()> {
+        setsockopt(self, libc::IPPROTO_TCP, libc::TCP_QUICKACK, quickack as c_int)
+    }
+
+    pub fn quickack(&self) -> io::Result<bool> {
+        let raw: c_int = getsockopt(self, libc::IPPROTO_TCP, libc::TCP_QUICKACK)?;
+        Ok(raw != 0)
+    }
+
     pub fn set_passcred(&self, passcred: bool) -> io::Result<()> {
         setsockopt(self, libc::SOL_SOCKET, libc::SO_PASSCRED, passcred as libc::c_int)
     }
@@ -369,6 +698,169 @@ impl Socket {
     }
 }
 
+// Sleeps in the host through the `nanosleep` OCALL. Unlike `thread::sleep`
+// this is available without the `thread` feature.
+pub fn sleep(dur: Duration) {
+    let mut ts = libc::timespec {
+        tv_sec: cmp::min(libc::time_t::MAX as u64, dur.as_secs()) as libc::time_t,
+        tv_nsec: dur.subsec_nanos() as _,
+    };
+    let ts_ptr = &mut ts as *mut _;
+    // If we're awoken with a signal, nanosleep fills in `ts` with the
+    // remaining time.
+    while unsafe { libc::nanosleep(ts_ptr, ts_ptr) } == -1 {
+        if io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+            break;
+        }
+    }
+}
+
+// Linux caps `TCP_KEEPIDLE` and `TCP_KEEPINTVL` at this many seconds, and
+// `TCP_KEEPCNT` at `MAX_TCP_KEEPCNT` probes.
+const MAX_TCP_KEEPALIVE_SECS: u64 = 32767;
+const MAX_TCP_KEEPCNT: u32 = 127;
+
+fn keepalive_secs(dur: Duration) -> io::Result<c_int> {
+    // The kernel counts whole seconds; round up so that a sub-second
+    // duration doesn't become zero.
+    let secs = dur.as_secs().saturating_add((dur.subsec_nanos() != 0) as u64);
+    if secs == 0 || secs > MAX_TCP_KEEPALIVE_SECS {
+        return Err(io::Error::new_const(
+            io::ErrorKind::InvalidInput,
+            &"keepalive time must be between 1 and 32767 seconds",
+        ));
+    }
+    Ok(secs as c_int)
+}
+
+fn keepalive_duration(raw: c_int) -> io::Result<Duration> {
+    if raw < 1 || raw as u64 > MAX_TCP_KEEPALIVE_SECS {
+        return Err(io::Error::new_const(
+            io::ErrorKind::InvalidData,
+            &"keepalive option returned an out of range time",
+        ));
+    }
+    Ok(Duration::from_secs(raw as u64))
+}
+
+// Converts a timeout to the milliseconds taken by `poll` and `epoll_wait`.
+fn timeout_to_ms(timeout: Option<Duration>) -> c_int {
+    match timeout {
+        None => -1,
+        Some(dur) => {
+            // Round up so that a non-zero timeout never turns into a busy poll.
+            let mut ms = dur
+                .as_secs()
+                .saturating_mul(1_000)
+                .saturating_add(dur.subsec_nanos() as u64 / 1_000_000);
+            if ms == 0 && dur.subsec_nanos() != 0 {
+                ms = 1;
+            }
+            cmp::min(ms, c_int::MAX as u64) as c_int
+        }
+    }
+}
+
+pub fn poll(fds: &mut [libc::pollfd], timeout: Option<Duration>) -> io::Result<usize> {
+    let timeout = timeout_to_ms(timeout);
+    let n = cvt_r(|| unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) })?;
+    // The count comes from the untrusted host, don't let it exceed what we passed in.
+    if n as usize > fds.len() {
+        return Err(io::Error::new_const(
+            io::ErrorKind::InvalidData,
+            &"poll returned more ready descriptors than requested",
+        ));
+    }
+    Ok(n as usize)
+}
+
+pub struct Epoll(FileDesc);
+
+impl Epoll {
+    pub fn new() -> io::Result<Epoll> {
+        let fd = cvt(unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) })?;
+        Ok(Epoll(unsafe { FileDesc::from_raw_fd(fd) }))
+    }
+
+    pub fn ctl(&self, op: c_int, fd: RawFd, events: u32, data: u64) -> io::Result<()> {
+        let mut event = libc::epoll_event { events, u64: data };
+        cvt(unsafe { libc::epoll_ctl(self.0.as_raw_fd(), op, fd, &mut event) }).map(drop)
+    }
+
+    pub fn wait(
+        &self,
+        events: &mut [libc::epoll_event],
+        timeout: Option<Duration>,
+    ) -> io::Result<usize> {
+        let maxevents = cmp::min(events.len(), c_int::MAX as usize) as c_int;
+        let timeout = timeout_to_ms(timeout);
+        let n = cvt_r(|| unsafe {
+            libc::epoll_wait(self.0.as_raw_fd(), events.as_mut_ptr(), maxevents, timeout)
+        })?;
+        // The count comes from the untrusted host, don't let it exceed what we passed in.
+        if n as usize > maxevents as usize {
+            return Err(io::Error::new_const(
+                io::ErrorKind::InvalidData,
+                &"epoll_wait returned more events than requested",
+            ));
+        }
+        Ok(n as usize)
+    }
+}
+
+impl AsRawFd for Epoll {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+// Converts a socket timeout to the `SO_RCVTIMEO`/`SO_SNDTIMEO` payload, where
+// a zeroed `timeval` means no timeout.
+pub fn timeout_to_timeval(dur: Option<Duration>) -> io::Result<libc::timeval> {
+    let timeout = match dur {
+        Some(dur) => {
+            if dur.as_secs() == 0 && dur.subsec_nanos() == 0 {
+                return Err(io::Error::new_const(
+                    io::ErrorKind::InvalidInput,
+                    &"cannot set a 0 duration timeout",
+                ));
+            }
+
+            let secs = if dur.as_secs() > libc::time_t::MAX as u64 {
+                libc::time_t::MAX
+            } else {
+                dur.as_secs() as libc::time_t
+            };
+            let mut timeout = libc::timeval {
+                tv_sec: secs,
+                tv_usec: dur.subsec_micros() as libc::suseconds_t,
+            };
+            if timeout.tv_sec == 0 && timeout.tv_usec == 0 {
+                timeout.tv_usec = 1;
+            }
+            timeout
+        }
+        None => libc::timeval { tv_sec: 0, tv_usec: 0 },
+    };
+    Ok(timeout)
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        // SAFETY: the descriptor is not used again after `drop`.
+        let fd = unsafe { ManuallyDrop::take(&mut self.0) }.into_raw_fd();
+        // As with `OwnedFd`, close is never retried, but an error is passed
+        // to the handler installed with `net::set_close_error_handler`.
+        if let Err(e) = cvt(unsafe { libc::close(fd) }) {
+            let handler = CLOSE_ERROR_HANDLER.load(Ordering::SeqCst);
+            if !handler.is_null() {
+                let handler: fn(&io::Error) = unsafe { mem::transmute(handler) };
+                handler(&e);
+            }
+        }
+    }
+}
+
 impl AsInner<FileDesc> for Socket {
     fn as_inner(&self) -> &FileDesc {
         &self.0
@@ -377,13 +869,50 @@ impl AsInner<FileDesc> for Socket {
 
 impl IntoInner<FileDesc> for Socket {
     fn into_inner(self) -> FileDesc {
-        self.0
+        let mut this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never dropped, so the descriptor is taken only once.
+        unsafe { ManuallyDrop::take(&mut this.0) }
     }
 }
 
 impl FromInner<FileDesc> for Socket {
     fn from_inner(file_desc: FileDesc) -> Self {
-        Self(file_desc)
+        Self(ManuallyDrop::new(file_desc))
+    }
+}
+
+// A buffer in untrusted memory that stays allocated for a whole transfer.
+// `send` stages every call through a fresh untrusted allocation, which above
+// 16 KiB costs a malloc and a free OCALL on top of the send itself; data
+// copied here once is sent with a single OCALL.
+pub struct StagingBuf {
+    ptr: *mut u8,
+    cap: usize,
+}
+
+impl StagingBuf {
+    pub fn new(cap: usize) -> io::Result<StagingBuf> {
+        let ptr = unsafe { libc::malloc(cap) } as *mut u8;
+        if ptr.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(StagingBuf { ptr, cap })
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    // Copies `data` to the start of the buffer.
+    pub fn fill(&mut self, data: &[u8]) {
+        assert!(data.len() <= self.cap);
+        unsafe { ptr::copy_nonoverlapping(data.as_ptr(), self.ptr, data.len()) }
+    }
+}
+
+impl Drop for StagingBuf {
+    fn drop(&mut self) {
+        unsafe { libc::free(self.ptr as *mut c_void) }
     }
 }
 
@@ -401,20 +930,21 @@ impl AsRawFd for Socket {
 
 impl IntoRawFd for Socket {
     fn into_raw_fd(self) -> RawFd {
-        self.0.into_raw_fd()
+        self.into_inner().into_raw_fd()
     }
 }
 
 impl FromRawFd for Socket {
     unsafe fn from_raw_fd(raw_fd: RawFd) -> Self {
-        Self(FromRawFd::from_raw_fd(raw_fd))
+        Self::from_inner(FromRawFd::from_raw_fd(raw_fd))
     }
 }
 
 mod libc {
     pub use sgx_libc::ocall::{
-        accept4, connect, gai_strerror, ioctl_arg1, poll, recv, recvfrom, recvmsg, sendmsg, shutdown, socket,
-        socketpair,
+        accept4, close, connect, epoll_create1, epoll_ctl, epoll_wait, free, gai_strerror,
+        ioctl_arg1, malloc, nanosleep, poll, recv, recvfrom, recvmsg, send, send_untrusted,
+        sendmsg, shutdown, socket, socketpair,
     };
     pub use sgx_libc::*;
 }
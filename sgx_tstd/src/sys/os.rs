@@ -27,7 +27,9 @@ use crate::path::{self, PathBuf};
 use crate::ptr;
 use crate::slice;
 use crate::str;
-use crate::sync::SgxThreadRwLock;
+#[cfg(feature = "trusted_env")]
+use crate::sync::Once;
+use crate::sync::{SgxThreadRwLock, SgxThreadSpinlock};
 use crate::sys::cvt;
 use crate::sys::memchr;
 use crate::vec;
@@ -38,6 +40,16 @@ use sgx_types::metadata::SE_PAGE_SIZE;
 const TMPBUF_SZ: usize = 128;
 const PATH_SEPARATOR: u8 = b':';
 static ENV_LOCK: SgxThreadRwLock = SgxThreadRwLock::new();
+static TEMP_DIR_LOCK: SgxThreadSpinlock = SgxThreadSpinlock::new();
+static ENV_WATCH_LOCK: SgxThreadSpinlock = SgxThreadSpinlock::new();
+static mut ENV_WATCHERS: Vec<(usize, OsString, fn(Option<&OsStr>))> = Vec::new();
+static mut ENV_WATCH_NEXT_ID: usize = 0;
+static mut TEMP_DIR_DEFAULT: Option<PathBuf> = None;
+// The enclave's own copy of the environment, guarded by `ENV_LOCK`.
+#[cfg(feature = "trusted_env")]
+static mut ENV_TABLE: Vec<(OsString, OsString)> = Vec::new();
+#[cfg(feature = "trusted_env")]
+static ENV_SNAPSHOT: Once = Once::new();
 
 pub fn errno() -> i32 {
     trts_error::errno()
@@ -125,9 +137,12 @@ impl<'a> Iterator for SplitPaths<'a> {
 }
 
 #[derive(Debug)]
-pub struct JoinPathsError;
+pub enum JoinPathsError {
+    Separator,
+    Empty,
+}
 
-pub fn join_paths<I, T>(paths: I) -> Result<OsString, JoinPathsError>
+pub fn join_paths<I, T>(paths: I, strict: bool) -> Result<OsString, JoinPathsError>
 where
     I: Iterator<Item = T>,
     T: AsRef<OsStr>,
@@ -140,7 +155,12 @@ where
             joined.push(PATH_SEPARATOR)
         }
         if path.contains(&PATH_SEPARATOR) {
-            return Err(JoinPathsError);
+            return Err(JoinPathsError::Separator);
+        }
+        // An empty segment is interpreted as the current directory by most
+        // `PATH` consumers.
+        if strict && path.is_empty() {
+            return Err(JoinPathsError::Empty);
         }
         joined.extend_from_slice(path);
     }
@@ -149,7 +169,12 @@ where
 
 impl fmt::Display for JoinPathsError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "path segment contains separator `{}`", char::from(PATH_SEPARATOR))
+        match *self {
+            JoinPathsError::Separator => {
+                write!(f, "path segment contains separator `{}`", char::from(PATH_SEPARATOR))
+            }
+            JoinPathsError::Empty => write!(f, "path segment is empty"),
+        }
     }
 }
 
@@ -187,6 +212,12 @@ impl Iterator for Env {
     }
 }
 
+impl ExactSizeIterator for Env {
+    fn len(&self) -> usize {
+        self.iter.len()
+    }
+}
+
 pub unsafe fn environ() -> *const *const libc::c_char {
     libc::environ()
 }
@@ -194,22 +225,124 @@ pub unsafe fn environ() -> *const *const libc::c_char {
 /// Returns a vector of (variable, value) byte-vector pairs for all the
 /// environment variables of the current process.
 pub fn env() -> Env {
+    env_with_prefix(OsStr::new(""))
+}
+
+/// Like `env`, but only for the variables whose name starts with the bytes
+/// of `prefix`. Other entries are skipped without being copied.
+pub fn env_with_prefix(prefix: &OsStr) -> Env {
+    init_env();
     unsafe {
         ENV_LOCK.read();
-        let mut environ = environ();
-        let mut result = Vec::new();
-        if !environ.is_null() {
-            while !(*environ).is_null() {
-                if let Some(key_value) = parse(CStr::from_ptr(*environ).to_bytes()) {
-                    result.push(key_value);
+        let result = read_environ(prefix.as_bytes());
+        ENV_LOCK.read_unlock();
+        Env { iter: result.into_iter() }
+    }
+}
+
+/// Copies the host environment into the enclave, the first time it is
+/// called. From then on the environment functions only use that copy, so
+/// the host can neither observe nor change the enclave's variables.
+#[cfg(feature = "trusted_env")]
+pub fn init_env() {
+    ENV_SNAPSHOT.call_once(|| unsafe {
+        ENV_LOCK.write();
+        ENV_TABLE = read_host_environ(b"");
+        ENV_LOCK.write_unlock();
+    });
+}
+
+#[cfg(not(feature = "trusted_env"))]
+pub fn init_env() {}
+
+// Copies out the entries whose name starts with `prefix`. The caller must
+// hold `ENV_LOCK`, for reading or writing.
+#[cfg(feature = "trusted_env")]
+unsafe fn read_environ(prefix: &[u8]) -> Vec<(OsString, OsString)> {
+    ENV_TABLE.iter().filter(|(k, _)| k.as_bytes().starts_with(prefix)).cloned().collect()
+}
+
+#[cfg(not(feature = "trusted_env"))]
+unsafe fn read_environ(prefix: &[u8]) -> Vec<(OsString, OsString)> {
+    read_host_environ(prefix)
+}
+
+// Looks up `k`. The caller must hold `ENV_LOCK`, for reading or writing.
+#[cfg(feature = "trusted_env")]
+unsafe fn env_get(k: &CStr) -> Option<OsString> {
+    let k = k.to_bytes();
+    ENV_TABLE.iter().find(|(key, _)| key.as_bytes() == k).map(|(_, v)| v.clone())
+}
+
+#[cfg(not(feature = "trusted_env"))]
+unsafe fn env_get(k: &CStr) -> Option<OsString> {
+    host_getenv(k)
+}
+
+unsafe fn host_getenv(k: &CStr) -> Option<OsString> {
+    let s = libc::getenv(k.as_ptr()) as *const libc::c_char;
+    if s.is_null() {
+        None
+    } else {
+        Some(OsStringExt::from_vec(CStr::from_ptr(s).to_bytes().to_vec()))
+    }
+}
+
+// Sets `k` to `v`. The caller must hold `ENV_LOCK` for writing.
+#[cfg(feature = "trusted_env")]
+unsafe fn env_set(k: &CStr, v: &CStr) -> io::Result<()> {
+    let k = k.to_bytes();
+    // The same names `setenv` rejects.
+    if k.is_empty() || k.contains(&b'=') {
+        return Err(io::Error::from_raw_os_error(libc::EINVAL));
+    }
+    let v = OsStr::from_bytes(v.to_bytes()).to_os_string();
+    match ENV_TABLE.iter_mut().find(|(key, _)| key.as_bytes() == k) {
+        Some((_, value)) => *value = v,
+        None => ENV_TABLE.push((OsStr::from_bytes(k).to_os_string(), v)),
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "trusted_env"))]
+unsafe fn env_set(k: &CStr, v: &CStr) -> io::Result<()> {
+    cvt(libc::setenv(k.as_ptr(), v.as_ptr(), 1)).map(drop)
+}
+
+// Removes `k`. The caller must hold `ENV_LOCK` for writing.
+#[cfg(feature = "trusted_env")]
+unsafe fn env_unset(k: &CStr) -> io::Result<()> {
+    let k = k.to_bytes();
+    if k.is_empty() || k.contains(&b'=') {
+        return Err(io::Error::from_raw_os_error(libc::EINVAL));
+    }
+    ENV_TABLE.retain(|(key, _)| key.as_bytes() != k);
+    Ok(())
+}
+
+#[cfg(not(feature = "trusted_env"))]
+unsafe fn env_unset(k: &CStr) -> io::Result<()> {
+    cvt(libc::unsetenv(k.as_ptr())).map(drop)
+}
+
+unsafe fn read_host_environ(prefix: &[u8]) -> Vec<(OsString, OsString)> {
+    let mut environ = environ();
+    let mut result = Vec::new();
+    if !environ.is_null() {
+        while !(*environ).is_null() {
+            let input = CStr::from_ptr(*environ).to_bytes();
+            if input.starts_with(prefix) {
+                if let Some(key_value) = parse(input) {
+                    // The prefix may contain '=' and run into the value.
+                    if key_value.0.as_bytes().starts_with(prefix) {
+                        result.push(key_value);
+                    }
                 }
-                environ = environ.add(1);
             }
+            environ = environ.add(1);
         }
-        let ret = Env { iter: result.into_iter() };
-        ENV_LOCK.read_unlock();
-        return ret;
     }
+    return result;
 
     fn parse(input: &[u8]) -> Option<(OsString, OsString)> {
         // Strategy (copied from glibc): Variable name and value are separated
@@ -229,31 +362,142 @@ pub fn env() -> Env {
     }
 }
 
+pub fn export_env() -> Vec<(OsString, OsString)> {
+    init_env();
+    unsafe {
+        ENV_LOCK.read();
+        let result = read_environ(b"");
+        ENV_LOCK.read_unlock();
+        result
+    }
+}
+
+/// Replaces the whole environment with `snapshot`, under a single write
+/// lock. Every entry is checked before anything is changed.
+pub fn import_env(snapshot: &[(OsString, OsString)]) -> io::Result<()> {
+    let mut entries = Vec::with_capacity(snapshot.len());
+    for (i, (k, v)) in snapshot.iter().enumerate() {
+        let k = k.as_bytes();
+        if k.is_empty() || k.contains(&b'=') || k.contains(&0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "invalid environment variable name {:?} at index {}",
+                    OsStr::from_bytes(k),
+                    i
+                ),
+            ));
+        }
+        let kbuf = CString::new(k)?;
+        let vbuf = CString::new(v.as_bytes()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "value of environment variable {:?} at index {} contains a nul byte",
+                    OsStr::from_bytes(k),
+                    i
+                ),
+            )
+        })?;
+        entries.push((kbuf, vbuf));
+    }
+
+    init_env();
+    let (old, ret) = unsafe {
+        ENV_LOCK.write();
+        let old = read_environ(b"");
+        let ret = old
+            .iter()
+            .try_for_each(|(k, _)| env_unset(&CString::new(k.as_bytes())?))
+            .and_then(|_| entries.iter().try_for_each(|(k, v)| env_set(k, v)));
+        ENV_LOCK.write_unlock();
+        (old, ret)
+    };
+
+    if ret.is_ok() {
+        // Only the variables whose value actually changed are reported.
+        for (k, _) in &old {
+            if !snapshot.iter().any(|(key, _)| key == k) {
+                notify_env_watchers(k, None);
+            }
+        }
+        for (i, (k, v)) in snapshot.iter().enumerate() {
+            // With duplicate names, the last entry is the one that was set.
+            if snapshot[i + 1..].iter().any(|(key, _)| key == k) {
+                continue;
+            }
+            if old.iter().find(|(key, _)| key == k).map(|(_, old_v)| old_v) != Some(v) {
+                notify_env_watchers(k, Some(v));
+            }
+        }
+    }
+    ret
+}
+
+/// Returns the host's value of each of `names`, even when the enclave keeps
+/// its own copy of the environment. Only the named variables are read.
+pub fn host_env(names: &[CString]) -> Vec<Option<OsString>> {
+    unsafe {
+        ENV_LOCK.read();
+        let values = names.iter().map(|k| host_getenv(k)).collect();
+        ENV_LOCK.read_unlock();
+        values
+    }
+}
+
+/// Sets, or removes where the value is `None`, each variable in `vars`
+/// under a single write lock. The names must already have been checked.
+pub fn update_env(vars: &[(CString, Option<CString>)]) -> io::Result<()> {
+    init_env();
+    let (changed, ret) = unsafe {
+        ENV_LOCK.write();
+        let mut changed = Vec::new();
+        let ret = vars.iter().try_for_each(|(k, v)| {
+            let old = env_get(k);
+            match v {
+                Some(v) => env_set(k, v)?,
+                None => env_unset(k)?,
+            }
+            let new = v.as_ref().map(|v| OsStr::from_bytes(v.to_bytes()).to_os_string());
+            if old != new {
+                changed.push((OsStr::from_bytes(k.to_bytes()).to_os_string(), new));
+            }
+            Ok(())
+        });
+        ENV_LOCK.write_unlock();
+        (changed, ret)
+    };
+    for (k, v) in &changed {
+        notify_env_watchers(k, v.as_deref());
+    }
+    ret
+}
+
 pub fn getenv(k: &OsStr) -> io::Result<Option<OsString>> {
     // environment variables with a nul byte can't be set, so their value is
     // always None as well
     let k = CString::new(k.as_bytes())?;
+    init_env();
     unsafe {
         ENV_LOCK.read();
-        let s = libc::getenv(k.as_ptr()) as *const libc::c_char;
-        let ret = if s.is_null() {
-            None
-        } else {
-            Some(OsStringExt::from_vec(CStr::from_ptr(s).to_bytes().to_vec()))
-        };
+        let ret = env_get(&k);
         ENV_LOCK.read_unlock();
         Ok(ret)
     }
 }
 
 pub fn setenv(k: &OsStr, v: &OsStr) -> io::Result<()> {
-    let k = CString::new(k.as_bytes())?;
-    let v = CString::new(v.as_bytes())?;
+    let kbuf = CString::new(k.as_bytes())?;
+    let vbuf = CString::new(v.as_bytes())?;
 
+    init_env();
     unsafe {
         ENV_LOCK.write();
-        let ret = cvt(libc::setenv(k.as_ptr(), v.as_ptr(), 1)).map(drop);
+        let ret = env_set(&kbuf, &vbuf);
         ENV_LOCK.write_unlock();
+        if ret.is_ok() {
+            notify_env_watchers(k, Some(v));
+        }
         ret
     }
 }
@@ -261,22 +505,100 @@ pub fn setenv(k: &OsStr, v: &OsStr) -> io::Result<()> {
 pub fn unsetenv(n: &OsStr) -> io::Result<()> {
     let nbuf = CString::new(n.as_bytes())?;
 
+    init_env();
+    unsafe {
+        ENV_LOCK.write();
+        let ret = env_unset(&nbuf);
+        ENV_LOCK.write_unlock();
+        if ret.is_ok() {
+            notify_env_watchers(n, None);
+        }
+        ret
+    }
+}
+
+pub fn takeenv(n: &OsStr) -> io::Result<Option<OsString>> {
+    let nbuf = CString::new(n.as_bytes())?;
+
+    init_env();
     unsafe {
         ENV_LOCK.write();
-        let ret = cvt(libc::unsetenv(nbuf.as_ptr())).map(drop);
+        let ret = match env_get(&nbuf) {
+            None => Ok(None),
+            Some(v) => env_unset(&nbuf).map(|_| Some(v)),
+        };
         ENV_LOCK.write_unlock();
+        if let Ok(Some(_)) = ret {
+            notify_env_watchers(n, None);
+        }
         ret
     }
 }
 
+pub fn watch_env(k: &OsStr, f: fn(Option<&OsStr>)) -> usize {
+    unsafe {
+        ENV_WATCH_LOCK.lock();
+        let id = ENV_WATCH_NEXT_ID;
+        ENV_WATCH_NEXT_ID += 1;
+        ENV_WATCHERS.push((id, k.to_os_string(), f));
+        ENV_WATCH_LOCK.unlock();
+        id
+    }
+}
+
+pub fn unwatch_env(id: usize) {
+    unsafe {
+        ENV_WATCH_LOCK.lock();
+        ENV_WATCHERS.retain(|&(watch_id, _, _)| watch_id != id);
+        ENV_WATCH_LOCK.unlock();
+    }
+}
+
+// Called after `ENV_LOCK` has been released, and the callbacks are copied out
+// before any of them runs, so a watcher may read or change the environment
+// (or its own registration) without deadlocking.
+fn notify_env_watchers(k: &OsStr, v: Option<&OsStr>) {
+    let callbacks: Vec<fn(Option<&OsStr>)> = unsafe {
+        ENV_WATCH_LOCK.lock();
+        let callbacks = ENV_WATCHERS
+            .iter()
+            .filter(|(_, key, _)| key.as_os_str() == k)
+            .map(|&(_, _, f)| f)
+            .collect();
+        ENV_WATCH_LOCK.unlock();
+        callbacks
+    };
+    for f in callbacks {
+        f(v);
+    }
+}
+
 pub fn page_size() -> usize {
     SE_PAGE_SIZE
 }
 
 pub fn temp_dir() -> PathBuf {
-    crate::env::var_os("TMPDIR").map(PathBuf::from).unwrap_or_else(|| {
-        PathBuf::from("/tmp")
-    })
+    match crate::env::var_os("TMPDIR") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => temp_dir_default(),
+    }
+}
+
+fn temp_dir_default() -> PathBuf {
+    unsafe {
+        TEMP_DIR_LOCK.lock();
+        let dir = TEMP_DIR_DEFAULT.clone();
+        TEMP_DIR_LOCK.unlock();
+        dir.unwrap_or_else(|| PathBuf::from("/tmp"))
+    }
+}
+
+pub fn set_temp_dir_default(dir: PathBuf) {
+    unsafe {
+        TEMP_DIR_LOCK.lock();
+        TEMP_DIR_DEFAULT = Some(dir);
+        TEMP_DIR_LOCK.unlock();
+    }
 }
 
 pub fn home_dir() -> Option<PathBuf> {
@@ -287,27 +609,45 @@ pub fn home_dir() -> Option<PathBuf> {
             n if n < 0 => 512_usize,
             n => n as usize,
         };
-        let mut buf = Vec::with_capacity(amt);
+        let mut buf = vec![0_u8; amt];
         let mut passwd: libc::passwd = mem::zeroed();
         let mut result = ptr::null_mut();
         match libc::getpwuid_r(
             libc::getuid(),
             &mut passwd,
-            buf.as_mut_ptr(),
-            buf.capacity(),
+            buf.as_mut_ptr() as *mut libc::c_char,
+            buf.len(),
             &mut result,
         ) {
             0 if !result.is_null() => {
-                let ptr = passwd.pw_dir as *const _;
-                let bytes = CStr::from_ptr(ptr).to_bytes().to_vec();
-                Some(OsStringExt::from_vec(bytes))
+                passwd_dir(&buf, passwd.pw_dir).map(|dir| OsStringExt::from_vec(dir.to_vec()))
             }
             _ => None,
         }
     }
 }
 
+// The passwd entry is filled in by the untrusted host, so `pw_dir` is only
+// read if it points into `buf` and is nul-terminated within it.
+pub fn passwd_dir(buf: &[u8], pw_dir: *const libc::c_char) -> Option<&[u8]> {
+    let offset = (pw_dir as usize).checked_sub(buf.as_ptr() as usize)?;
+    let avail = buf.get(offset..).filter(|avail| !avail.is_empty())?;
+    let len = memchr::memchr(0, avail)?;
+    Some(&avail[..len])
+}
+
+#[cfg(feature = "process")]
+pub fn getpid() -> u32 {
+    unsafe { libc::getpid() as u32 }
+}
+
+// Tells the host how the enclave is about to end. The caller locks the
+// enclave afterwards, whatever the host does with it.
+pub fn report_exit(code: i32, aborted: bool) {
+    unsafe { libc::enclave_exit(crate::enclave::get_enclave_id(), code, aborted as libc::c_int) }
+}
+
 mod libc {
-    pub use sgx_libc::ocall::{environ, getenv, setenv, unsetenv, getcwd, chdir, sysconf, getuid, getpwuid_r};
+    pub use sgx_libc::ocall::{environ, getenv, setenv, unsetenv, getcwd, chdir, sysconf, getuid, getpwuid_r, getpid, enclave_exit};
     pub use sgx_libc::*;
 }
\ No newline at end of file
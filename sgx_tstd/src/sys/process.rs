@@ -0,0 +1,442 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use crate::ffi::{CString, OsStr, OsString};
+use crate::fmt;
+use crate::io;
+use crate::os::unix::ffi::OsStrExt;
+use crate::os::unix::io::AsRawFd;
+use crate::ptr;
+use crate::sys::pipe::{self, AnonPipe};
+use crate::sys::{cvt, cvt_r};
+
+////////////////////////////////////////////////////////////////////////////////
+// Command
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct Command {
+    program: CString,
+    args: Vec<CString>,
+    env: Vec<(OsString, Option<OsString>)>,
+    env_clear: bool,
+    cwd: Option<CString>,
+    stdin: Option<Stdio>,
+    stdout: Option<Stdio>,
+    stderr: Option<Stdio>,
+    saw_nul: bool,
+}
+
+// The pipes the parent keeps for talking to the child.
+pub struct StdioPipes {
+    pub stdin: Option<AnonPipe>,
+    pub stdout: Option<AnonPipe>,
+    pub stderr: Option<AnonPipe>,
+}
+
+pub enum Stdio {
+    Inherit,
+    Null,
+    MakePipe,
+    Fd(AnonPipe),
+}
+
+// Values understood by `u_spawn_ocall` in place of a descriptor.
+const STDIO_INHERIT: libc::c_int = -1;
+const STDIO_NULL: libc::c_int = -2;
+
+impl Command {
+    pub fn new(program: &OsStr) -> Command {
+        let mut saw_nul = false;
+        let program = os2c(program, &mut saw_nul);
+        Command {
+            args: vec![program.clone()],
+            program,
+            env: Vec::new(),
+            env_clear: false,
+            cwd: None,
+            stdin: None,
+            stdout: None,
+            stderr: None,
+            saw_nul,
+        }
+    }
+
+    pub fn arg(&mut self, arg: &OsStr) {
+        let arg = os2c(arg, &mut self.saw_nul);
+        self.args.push(arg);
+    }
+
+    pub fn env_mut(&mut self) -> CommandEnv<'_> {
+        CommandEnv { cmd: self }
+    }
+
+    pub fn cwd(&mut self, dir: &OsStr) {
+        self.cwd = Some(os2c(dir, &mut self.saw_nul));
+    }
+
+    pub fn stdin(&mut self, stdin: Stdio) {
+        self.stdin = Some(stdin);
+    }
+
+    pub fn stdout(&mut self, stdout: Stdio) {
+        self.stdout = Some(stdout);
+    }
+
+    pub fn stderr(&mut self, stderr: Stdio) {
+        self.stderr = Some(stderr);
+    }
+
+    pub fn get_program(&self) -> &OsStr {
+        OsStr::from_bytes(self.program.as_bytes())
+    }
+
+    pub fn get_args(&self) -> impl Iterator<Item = &OsStr> + ExactSizeIterator {
+        self.args[1..].iter().map(|arg| OsStr::from_bytes(arg.as_bytes()))
+    }
+
+    pub fn get_current_dir(&self) -> Option<&OsStr> {
+        self.cwd.as_ref().map(|cwd| OsStr::from_bytes(cwd.as_bytes()))
+    }
+
+    pub fn spawn(
+        &mut self,
+        default: Stdio,
+        needs_stdin: bool,
+    ) -> io::Result<(Process, StdioPipes)> {
+        if self.saw_nul {
+            return Err(io::Error::new_const(
+                io::ErrorKind::InvalidInput,
+                &"nul byte found in provided data",
+            ));
+        }
+        let env = self.encode_env()?;
+
+        let null = Stdio::Null;
+        let default_stdin = if needs_stdin { &default } else { &null };
+        let stdin = self.stdin.as_ref().unwrap_or(default_stdin);
+        let stdout = self.stdout.as_ref().unwrap_or(&default);
+        let stderr = self.stderr.as_ref().unwrap_or(&default);
+        let (their_stdin, our_stdin) = stdin.to_child_stdio(true)?;
+        let (their_stdout, our_stdout) = stdout.to_child_stdio(false)?;
+        let (their_stderr, our_stderr) = stderr.to_child_stdio(false)?;
+        let stdio = [their_stdin.fd(), their_stdout.fd(), their_stderr.fd()];
+
+        let mut args = Vec::new();
+        for arg in &self.args {
+            args.extend_from_slice(arg.as_bytes_with_nul());
+        }
+        let cwd = self.cwd.as_ref().map_or(ptr::null(), |cwd| cwd.as_ptr());
+        let pid = cvt(unsafe {
+            libc::spawn(
+                args.as_ptr() as *const libc::c_char,
+                args.len(),
+                env.as_ptr() as *const libc::c_char,
+                env.len(),
+                self.env_clear as libc::c_int,
+                cwd,
+                stdio.as_ptr(),
+            )
+        })?;
+
+        // The child holds its own copies of these now.
+        drop((their_stdin, their_stdout, their_stderr));
+        Ok((
+            Process { pid, status: None },
+            StdioPipes { stdin: our_stdin, stdout: our_stdout, stderr: our_stderr },
+        ))
+    }
+
+    // Encodes the environment changes as `KEY=VALUE` entries to set and
+    // `KEY` entries to remove, each terminated by a NUL byte.
+    fn encode_env(&self) -> io::Result<Vec<u8>> {
+        let mut env = Vec::new();
+        for (key, value) in &self.env {
+            let key = key.as_bytes();
+            if key.is_empty() || key.contains(&b'=') {
+                return Err(io::Error::new_const(
+                    io::ErrorKind::InvalidInput,
+                    &"invalid environment variable name",
+                ));
+            }
+            env.extend_from_slice(key);
+            if let Some(value) = value {
+                env.push(b'=');
+                env.extend_from_slice(value.as_bytes());
+            }
+            env.push(0);
+        }
+        Ok(env)
+    }
+}
+
+impl fmt::Debug for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.env_clear {
+            write!(f, "env -i ")?;
+        }
+        for (key, value) in &self.env {
+            match value {
+                Some(value) => write!(f, "{:?}={:?} ", key, value)?,
+                None => write!(f, "-u {:?} ", key)?,
+            }
+        }
+        write!(f, "{:?}", self.program)?;
+        for arg in &self.args[1..] {
+            write!(f, " {:?}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct CommandEnv<'a> {
+    cmd: &'a mut Command,
+}
+
+impl CommandEnv<'_> {
+    pub fn set(&mut self, key: &OsStr, value: &OsStr) {
+        self.check(key);
+        self.check(value);
+        self.remove_entry(key);
+        self.cmd.env.push((key.to_owned(), Some(value.to_owned())));
+    }
+
+    pub fn remove(&mut self, key: &OsStr) {
+        self.check(key);
+        self.remove_entry(key);
+        if !self.cmd.env_clear {
+            self.cmd.env.push((key.to_owned(), None));
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cmd.env.clear();
+        self.cmd.env_clear = true;
+    }
+
+    fn remove_entry(&mut self, key: &OsStr) {
+        self.cmd.env.retain(|(k, _)| k.as_os_str() != key);
+    }
+
+    fn check(&mut self, s: &OsStr) {
+        if s.as_bytes().contains(&0) {
+            self.cmd.saw_nul = true;
+        }
+    }
+}
+
+fn os2c(s: &OsStr, saw_nul: &mut bool) -> CString {
+    CString::new(s.as_bytes()).unwrap_or_else(|_e| {
+        *saw_nul = true;
+        CString::new("<string-with-nul>").unwrap()
+    })
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Stdio
+////////////////////////////////////////////////////////////////////////////////
+
+// The descriptor handed to the child, kept open until the spawn returns.
+enum ChildStdio<'a> {
+    Inherit,
+    Null,
+    Explicit(&'a AnonPipe),
+    Owned(AnonPipe),
+}
+
+impl ChildStdio<'_> {
+    fn fd(&self) -> libc::c_int {
+        match self {
+            ChildStdio::Inherit => STDIO_INHERIT,
+            ChildStdio::Null => STDIO_NULL,
+            ChildStdio::Explicit(pipe) => pipe.as_raw_fd(),
+            ChildStdio::Owned(pipe) => pipe.as_raw_fd(),
+        }
+    }
+}
+
+impl Stdio {
+    fn to_child_stdio(&self, readable: bool) -> io::Result<(ChildStdio<'_>, Option<AnonPipe>)> {
+        match self {
+            Stdio::Inherit => Ok((ChildStdio::Inherit, None)),
+            Stdio::Null => Ok((ChildStdio::Null, None)),
+            Stdio::MakePipe => {
+                let (reader, writer) = pipe::anon_pipe()?;
+                let (ours, theirs) = if readable { (writer, reader) } else { (reader, writer) };
+                Ok((ChildStdio::Owned(theirs), Some(ours)))
+            }
+            Stdio::Fd(pipe) => Ok((ChildStdio::Explicit(pipe), None)),
+        }
+    }
+}
+
+impl From<AnonPipe> for Stdio {
+    fn from(pipe: AnonPipe) -> Stdio {
+        Stdio::Fd(pipe)
+    }
+}
+
+impl fmt::Debug for Stdio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stdio::Inherit => f.write_str("Inherit"),
+            Stdio::Null => f.write_str("Null"),
+            Stdio::MakePipe => f.write_str("MakePipe"),
+            Stdio::Fd(pipe) => f.debug_tuple("Fd").field(&pipe.as_raw_fd()).finish(),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Processes
+////////////////////////////////////////////////////////////////////////////////
+
+// A child of the host process, spawned on behalf of the enclave.
+pub struct Process {
+    pid: libc::pid_t,
+    status: Option<ExitStatus>,
+}
+
+impl Process {
+    pub fn id(&self) -> u32 {
+        self.pid as u32
+    }
+
+    pub fn kill(&mut self) -> io::Result<()> {
+        // If we've already waited on this process then the pid can be recycled
+        // and used for another process, and we probably shouldn't be killing
+        // random processes, so just return an error.
+        if self.status.is_some() {
+            Err(io::Error::new_const(
+                io::ErrorKind::InvalidInput,
+                &"invalid argument: can't kill an exited process",
+            ))
+        } else {
+            cvt_r(|| unsafe { libc::kill(self.pid, libc::SIGKILL) }).map(drop)
+        }
+    }
+
+    pub fn wait(&mut self) -> io::Result<ExitStatus> {
+        if let Some(status) = self.status {
+            return Ok(status);
+        }
+        let mut status = 0 as libc::c_int;
+        cvt_r(|| unsafe { libc::waitpid(self.pid, &mut status, 0) })?;
+        self.status = Some(ExitStatus::new(status));
+        Ok(ExitStatus::new(status))
+    }
+
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        if let Some(status) = self.status {
+            return Ok(Some(status));
+        }
+        let mut status = 0 as libc::c_int;
+        let pid = cvt_r(|| unsafe { libc::waitpid(self.pid, &mut status, libc::WNOHANG) })?;
+        if pid == 0 {
+            Ok(None)
+        } else {
+            self.status = Some(ExitStatus::new(status));
+            Ok(Some(ExitStatus::new(status)))
+        }
+    }
+}
+
+/// Unix exit statuses
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct ExitStatus(libc::c_int);
+
+impl ExitStatus {
+    pub fn new(status: libc::c_int) -> ExitStatus {
+        ExitStatus(status)
+    }
+
+    pub fn success(&self) -> bool {
+        self.code() == Some(0)
+    }
+
+    pub fn code(&self) -> Option<i32> {
+        if libc::WIFEXITED(self.0) {
+            Some(libc::WEXITSTATUS(self.0))
+        } else {
+            None
+        }
+    }
+
+    pub fn signal(&self) -> Option<i32> {
+        if libc::WIFSIGNALED(self.0) {
+            Some(libc::WTERMSIG(self.0))
+        } else {
+            None
+        }
+    }
+
+    pub fn core_dumped(&self) -> bool {
+        libc::WIFSIGNALED(self.0) && libc::WCOREDUMP(self.0)
+    }
+
+    pub fn stopped_signal(&self) -> Option<i32> {
+        if libc::WIFSTOPPED(self.0) {
+            Some(libc::WSTOPSIG(self.0))
+        } else {
+            None
+        }
+    }
+
+    pub fn continued(&self) -> bool {
+        libc::WIFCONTINUED(self.0)
+    }
+
+    pub fn into_raw(&self) -> libc::c_int {
+        self.0
+    }
+}
+
+impl From<libc::c_int> for ExitStatus {
+    fn from(a: libc::c_int) -> ExitStatus {
+        ExitStatus(a)
+    }
+}
+
+impl fmt::Debug for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("unix_wait_status").field(&self.0).finish()
+    }
+}
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(code) = self.code() {
+            write!(f, "exit status: {}", code)
+        } else if let Some(signal) = self.signal() {
+            if self.core_dumped() {
+                write!(f, "signal: {} (core dumped)", signal)
+            } else {
+                write!(f, "signal: {}", signal)
+            }
+        } else if let Some(signal) = self.stopped_signal() {
+            write!(f, "stopped (not terminated) by signal: {}", signal)
+        } else if self.continued() {
+            write!(f, "continued (WIFCONTINUED)")
+        } else {
+            write!(f, "unrecognised wait status: {} {:#x}", self.0, self.0)
+        }
+    }
+}
+
+mod libc {
+    pub use sgx_libc::ocall::{kill, spawn, waitpid};
+    pub use sgx_libc::*;
+}
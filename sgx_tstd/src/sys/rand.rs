@@ -27,6 +27,10 @@ pub fn hashmap_random_keys() -> (u64, u64) {
     v
 }
 
+pub fn fill_bytes(v: &mut [u8]) {
+    imp::fill_bytes(v)
+}
+
 mod imp {
     use sgx_types::SgxError;
     use sgx_trts::trts;
@@ -16,15 +16,28 @@
 // under the License..
 
 use crate::os::unix::prelude::*;
-use crate::ffi::{CString, CStr};
+use crate::cmp;
+use crate::ffi::{CString, CStr, OsStr, OsString};
 use crate::io::{self, Error, SeekFrom};
-use crate::path::Path;
+use crate::path::{Path, PathBuf};
+use crate::sys::fs;
+use crate::sys::time::SystemTime;
 use crate::sys_common::FromInner;
 use sgx_libc as libc;
 use sgx_tprotected_fs::{self, SgxFileStream};
-use sgx_types::{sgx_status_t, sgx_key_128bit_t, sgx_align_key_128bit_t};
+use sgx_types::{sgx_status_t, sgx_key_128bit_t, sgx_align_key_128bit_t, SysResult};
 
-pub struct SgxFile(SgxFileStream);
+pub struct SgxFile {
+    stream: SgxFileStream,
+    // Where the file was opened, so that `file_attr` can ask the host for
+    // its timestamps.
+    path: Option<CString>,
+}
+
+pub struct FileAttr {
+    len: u64,
+    host: io::Result<fs::FileAttr>,
+}
 
 #[derive(Clone, Debug)]
 pub struct OpenOptions {
@@ -101,7 +114,20 @@ impl SgxFile {
             SgxFileStream::open(path, opts, key)
         };
 
-        file.map(SgxFile)
+        SgxFile::from_open_result(file, path)
+    }
+
+    #[cfg(feature = "sgxfs_integrity_only")]
+    pub fn open_integrity_only(path: &Path, opts: &OpenOptions) -> io::Result<SgxFile> {
+        let path = cstr(path)?;
+        let mode = opts.get_access_mode()?;
+        let opts = CString::new(mode.as_bytes())?;
+        let file = SgxFileStream::open_integrity_only(&path, &opts);
+        SgxFile::from_open_result(file, &path)
+    }
+
+    fn from_open_result(file: SysResult<SgxFileStream>, path: &CStr) -> io::Result<SgxFile> {
+        file.map(|stream| SgxFile { stream, path: Some(path.to_owned()) })
             .map_err(|err| {
                 match err {
                     1 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_UNEXPECTED),
@@ -118,7 +144,7 @@ impl SgxFile {
     }
 
     pub fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
-        self.0.read(buf).map_err(|err| {
+        self.stream.read(buf).map_err(|err| {
             match err {
                 1 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_UNEXPECTED),
                 2 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
@@ -134,7 +160,7 @@ impl SgxFile {
     }
 
     pub fn write(&self, buf: &[u8]) -> io::Result<usize> {
-        self.0.write(buf).map_err(|err| {
+        self.stream.write(buf).map_err(|err| {
             match err {
                 1 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_UNEXPECTED),
                 2 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
@@ -150,7 +176,7 @@ impl SgxFile {
     }
 
     pub fn tell(&self) -> io::Result<u64> {
-        self.0.tell().map_err(|err| {
+        self.stream.tell().map_err(|err| {
             match err {
                 r if r > 4096 => {
                     let status = sgx_status_t::from_repr(r as u32).unwrap_or(sgx_status_t::SGX_ERROR_UNEXPECTED);
@@ -169,7 +195,7 @@ impl SgxFile {
             SeekFrom::Current(off) => (sgx_tprotected_fs::SeekFrom::Current, off),
         };
 
-        self.0.seek(offset, whence).map_err(|err| {
+        self.stream.seek(offset, whence).map_err(|err| {
             match err {
                 r if r > 4096 => {
                     let status = sgx_status_t::from_repr(r as u32).unwrap_or(sgx_status_t::SGX_ERROR_UNEXPECTED);
@@ -184,7 +210,7 @@ impl SgxFile {
     }
 
     pub fn flush(&self) -> io::Result<()> {
-        self.0.flush().map_err(|err| {
+        self.stream.flush().map_err(|err| {
             match err {
                 1 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_UNEXPECTED),
                 2 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
@@ -199,16 +225,151 @@ impl SgxFile {
         })
     }
 
+    pub fn len(&self) -> io::Result<u64> {
+        let pos = self.tell()?;
+        let len = self.seek(SeekFrom::End(0))?;
+        self.seek(SeekFrom::Start(pos))?;
+        Ok(len)
+    }
+
+    pub fn set_len(&self, size: u64) -> io::Result<()> {
+        let pos = self.tell()?;
+        let len = self.seek(SeekFrom::End(0))?;
+        let ret = if size < len {
+            Err(Error::new_const(io::ErrorKind::Unsupported, &"protected files cannot be truncated"))
+        } else {
+            // Protected files cannot have holes, so the extension is written out.
+            self.write_zeros(size - len)
+        };
+        self.seek(SeekFrom::Start(pos))?;
+        ret
+    }
+
+    fn write_zeros(&self, mut count: u64) -> io::Result<()> {
+        let zeros = [0_u8; 4096];
+        while count > 0 {
+            let n = cmp::min(count, zeros.len() as u64) as usize;
+            match self.write(&zeros[..n])? {
+                0 => {
+                    return Err(Error::new_const(
+                        io::ErrorKind::WriteZero,
+                        &"failed to write whole buffer",
+                    ));
+                }
+                written => count -= cmp::min(written, n) as u64,
+            }
+        }
+        Ok(())
+    }
+
+    pub fn file_attr(&self) -> io::Result<FileAttr> {
+        let len = self.len()?;
+        let host = match self.path {
+            Some(ref path) => fs::stat(Path::new(OsStr::from_bytes(path.as_bytes()))),
+            None => Err(Error::new_const(
+                io::ErrorKind::Unsupported,
+                &"the file was not opened by path",
+            )),
+        };
+        Ok(FileAttr { len, host })
+    }
+
+    pub fn rekey(&self, key: Option<&sgx_key_128bit_t>) -> io::Result<()> {
+        let path = match self.path {
+            Some(ref path) => Path::new(OsStr::from_bytes(path.as_bytes())),
+            None => {
+                return Err(Error::new_const(
+                    io::ErrorKind::Unsupported,
+                    &"the file was not opened by path",
+                ));
+            }
+        };
+        let (dir, staged) = rekey_staging_paths(path)?;
+
+        // A previous rotation may have crashed before or after the rename;
+        // either way the original path holds a complete file, so whatever
+        // was left behind can be discarded.
+        remove_staging(&dir, &staged);
+        fs::DirBuilder::new().mkdir(&dir)?;
+
+        let ret = self.rekey_into(path, &staged, key);
+        if ret.is_err() {
+            remove_staging(&dir, &staged);
+        } else {
+            let _ = fs::rmdir(&dir);
+        }
+        ret
+    }
+
+    fn rekey_into(
+        &self,
+        path: &Path,
+        staged: &Path,
+        key: Option<&sgx_key_128bit_t>,
+    ) -> io::Result<()> {
+        let perm = fs::stat(path)?.perm();
+        self.flush()?;
+
+        {
+            let staged = cstr(staged)?;
+            let mode = CString::new("wb")?;
+            let writer = match key {
+                Some(key) => SgxFile::open_c(&staged, &mode, key, false)?,
+                None => SgxFile::open_c(&staged, &mode, &sgx_key_128bit_t::default(), true)?,
+            };
+
+            let pos = self.tell()?;
+            self.seek(SeekFrom::Start(0))?;
+            let ret = self.copy_to(&writer);
+            self.seek(SeekFrom::Start(pos))?;
+            ret?;
+            // The new file is only complete once it is closed; the protected
+            // FS recovery file covers a crash during the final flush.
+            writer.flush()?;
+        }
+
+        fs::set_perm(staged, perm)?;
+        fs::rename(staged, path)
+    }
+
+    fn copy_to(&self, writer: &SgxFile) -> io::Result<()> {
+        let mut buf = [0_u8; 4096];
+        loop {
+            let n = match self.read(&mut buf)? {
+                0 if self.is_eof() => return Ok(()),
+                0 => {
+                    return Err(Error::new_const(
+                        io::ErrorKind::UnexpectedEof,
+                        &"failed to read the whole file",
+                    ));
+                }
+                n => n,
+            };
+            let mut written = 0;
+            while written < n {
+                match writer.write(&buf[written..n])? {
+                    0 => {
+                        return Err(Error::new_const(
+                            io::ErrorKind::WriteZero,
+                            &"failed to write whole buffer",
+                        ));
+                    }
+                    m => written += m,
+                }
+            }
+        }
+    }
+
     pub fn is_eof(&self) -> bool {
-        self.0.is_eof()
+        self.stream.is_eof()
     }
 
     pub fn clearerr(&self) {
-        self.0.clearerr()
+        self.stream.clearerr()
     }
 
     pub fn clear_cache(&self) -> io::Result<()> {
-        self.0.clear_cache().map_err(|err| {
+        self.stream.clear_cache().map_err(|err| {
             match err {
                 1 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_UNEXPECTED),
                 2 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
@@ -224,6 +385,27 @@ impl SgxFile {
     }
 }
 
+impl FileAttr {
+    pub fn size(&self) -> u64 {
+        self.len
+    }
+
+    pub fn modified(&self) -> io::Result<SystemTime> {
+        self.host()?.modified()
+    }
+
+    pub fn accessed(&self) -> io::Result<SystemTime> {
+        self.host()?.accessed()
+    }
+
+    fn host(&self) -> io::Result<&fs::FileAttr> {
+        self.host.as_ref().map_err(|e| match e.raw_os_error() {
+            Some(code) => Error::from_raw_os_error(code),
+            None => Error::new_const(e.kind(), &"timestamps are not available"),
+        })
+    }
+}
+
 pub fn remove(path: &Path) -> io::Result<()> {
     let path = cstr(path)?;
     sgx_tprotected_fs::remove(&path).map_err(|err| {
@@ -292,13 +474,41 @@ pub fn import_auto_key(path: &Path, key: &sgx_key_128bit_t) -> io::Result<()> {
     })
 }
 
+// Protected files bind their file name into the encrypted metadata, so the
+// rotated copy cannot be written under a temporary name and renamed into
+// place. Instead it is written with the same name into a hidden sibling
+// directory, from which the rename is atomic.
+fn rekey_staging_paths(path: &Path) -> io::Result<(PathBuf, PathBuf)> {
+    let name = path.file_name().ok_or_else(|| {
+        Error::new_const(io::ErrorKind::InvalidInput, &"path does not name a file")
+    })?;
+    let mut dir_name = OsString::from(".");
+    dir_name.push(name);
+    dir_name.push(".rekey");
+    let dir = path.with_file_name(dir_name);
+    let staged = dir.join(name);
+    Ok((dir, staged))
+}
+
+fn remove_staging(dir: &Path, staged: &Path) {
+    let _ = fs::unlink(staged);
+    let _ = fs::unlink(&recovery_path(staged));
+    let _ = fs::rmdir(dir);
+}
+
+fn recovery_path(path: &Path) -> PathBuf {
+    let mut recovery = path.as_os_str().to_os_string();
+    recovery.push("_recovery");
+    PathBuf::from(recovery)
+}
+
 fn cstr(path: &Path) -> io::Result<CString> {
     Ok(CString::new(path.as_os_str().as_bytes())?)
 }
 
 impl FromInner<SgxFileStream> for SgxFile {
     fn from_inner(stream: SgxFileStream) -> SgxFile {
-        SgxFile(stream)
+        SgxFile { stream, path: None }
     }
 }
 
@@ -136,6 +136,19 @@ impl Drop for Thread {
     }
 }
 
+// Every thread runs on the stack of the TCS it is bound to, which is sized by
+// `StackMaxSize` in the enclave configuration.
+pub fn check_stack_size(size: usize) -> io::Result<()> {
+    if size > enclave::rsgx_get_stack_max_size() {
+        Err(io::Error::new_const(
+            io::ErrorKind::InvalidInput,
+            &"requested stack size exceeds the StackMaxSize of the enclave",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
 pub fn available_concurrency() -> io::Result<NonZeroUsize> {
     let cpus = enclave::rsgx_get_cpu_core_num();
     NonZeroUsize::new(cpus as usize).ok_or_else(|| io::Error::new_const(
@@ -37,6 +37,18 @@ impl Timespec {
         Timespec { t: libc::timespec { tv_sec: 0, tv_nsec: 0 } }
     }
 
+    #[cfg(feature = "tsc_time")]
+    fn from_nanos(nanos: u128) -> Timespec {
+        let secs = (nanos / NSEC_PER_SEC as u128).min(libc::time_t::MAX as u128);
+        let nsec = (nanos % NSEC_PER_SEC as u128) as libc::c_long;
+        Timespec { t: libc::timespec { tv_sec: secs as libc::time_t, tv_nsec: nsec } }
+    }
+
+    #[cfg(feature = "tsc_time")]
+    fn as_nanos(&self) -> u128 {
+        self.t.tv_sec.max(0) as u128 * NSEC_PER_SEC as u128 + self.t.tv_nsec.max(0) as u128
+    }
+
     fn sub_timespec(&self, other: &Timespec) -> Result<Duration, Duration> {
         if self >= other {
             // NOTE(eddyb) two aspects of this `if`-`else` are required for LLVM
@@ -154,6 +166,12 @@ mod inner {
 
     impl Instant {
         pub fn now() -> Instant {
+            #[cfg(feature = "tsc_time")]
+            {
+                if let Some(t) = tsc_clock::now() {
+                    return Instant { t };
+                }
+            }
             Instant { t: now(libc::CLOCK_MONOTONIC) }
         }
 
@@ -239,4 +257,65 @@ mod inner {
         pub use sgx_libc::ocall::clock_gettime;
         pub use sgx_libc::*;
     }
+
+    // The monotonic clock, read with RDTSC instead of an OCALL.
+    //
+    // The TSC is anchored to the host's monotonic clock the first time it is
+    // read, so that both clocks give comparable instants. Unless the enclave
+    // pinned the TSC frequency by then, it is also calibrated, by counting
+    // ticks across a few milliseconds of host time. The frequency is kept
+    // from then on, so that the clock does not jump.
+    #[cfg(feature = "tsc_time")]
+    mod tsc_clock {
+        use super::{libc, now as host_now, Timespec};
+        use crate::sync::OnceLock;
+        use crate::sys::tsc;
+
+        const NSEC_PER_SEC: u128 = 1_000_000_000;
+        const CALIBRATION_NANOS: u128 = 10_000_000;
+
+        struct Anchor {
+            ticks: u64,
+            nanos: u128,
+            hz: u64,
+        }
+
+        static ANCHOR: OnceLock<Option<Anchor>> = OnceLock::new();
+
+        pub fn now() -> Option<Timespec> {
+            let anchor = ANCHOR.get_or_init(anchor).as_ref()?;
+            let elapsed = tsc::ticks().wrapping_sub(anchor.ticks) as u128;
+            Some(Timespec::from_nanos(anchor.nanos + elapsed * NSEC_PER_SEC / anchor.hz as u128))
+        }
+
+        fn anchor() -> Option<Anchor> {
+            if !tsc::is_supported() {
+                return None;
+            }
+            let nanos = host_nanos();
+            let ticks = tsc::ticks();
+            let hz = match tsc::frequency() {
+                Some(hz) => hz,
+                None => calibrate(ticks, nanos)?,
+            };
+            Some(Anchor { ticks, nanos, hz })
+        }
+
+        fn calibrate(start_ticks: u64, start_nanos: u128) -> Option<u64> {
+            loop {
+                let nanos = host_nanos();
+                let ticks = tsc::ticks();
+                let elapsed = nanos.checked_sub(start_nanos)?;
+                if elapsed >= CALIBRATION_NANOS {
+                    let count = ticks.wrapping_sub(start_ticks) as u128;
+                    let hz = (count * NSEC_PER_SEC / elapsed) as u64;
+                    return if hz == 0 { None } else { Some(hz) };
+                }
+            }
+        }
+
+        fn host_nanos() -> u128 {
+            host_now(libc::CLOCK_MONOTONIC).as_nanos()
+        }
+    }
 }
@@ -0,0 +1,124 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! A timer service for sleeping threads.
+//!
+//! Instead of one host timer per sleeping thread, sleepers are queued by
+//! deadline inside the enclave. Only the one with the earliest deadline waits
+//! with a host timeout; the others wait on the futex layer without one. When
+//! that timeout fires, the sleeper releases every sleeper whose deadline
+//! passed, together with the one now at the front, which takes the host timer
+//! over, in a single OCALL.
+
+use crate::cell::UnsafeCell;
+use crate::sync::atomic::{AtomicUsize, Ordering};
+use crate::sync::SgxThreadSpinlock;
+use crate::sys::futex::{futex_wait, futex_wait_timeout, futex_wake_many};
+use crate::sys::thread::Thread;
+use crate::time::{Duration, Instant};
+use crate::vec::Vec;
+
+// Waiting to be released.
+const WAITING: usize = 0;
+// At the front of the queue, so waiting with a host timeout.
+const TIMER: usize = 1;
+// Released, and off the queue.
+const DONE: usize = 2;
+
+struct Sleeper {
+    deadline: Instant,
+    state: AtomicUsize,
+}
+
+struct Queue {
+    lock: SgxThreadSpinlock,
+    // Sorted by deadline.
+    sleepers: UnsafeCell<Vec<*const Sleeper>>,
+}
+
+// `sleepers` is only accessed while holding `lock`.
+unsafe impl Sync for Queue {}
+
+static QUEUE: Queue =
+    Queue { lock: SgxThreadSpinlock::new(), sleepers: UnsafeCell::new(Vec::new()) };
+
+/// Puts the current thread to sleep for at least `dur`.
+pub fn sleep(dur: Duration) {
+    if dur == Duration::ZERO {
+        return;
+    }
+    let deadline = match Instant::_now().checked_add(dur) {
+        Some(deadline) => deadline,
+        // Practically forever.
+        None => return Thread::sleep(dur),
+    };
+    let sleeper = Sleeper { deadline, state: AtomicUsize::new(WAITING) };
+
+    unsafe {
+        QUEUE.lock.lock();
+        let sleepers = &mut *QUEUE.sleepers.get();
+        let index = sleepers.partition_point(|&s| (*s).deadline <= deadline);
+        sleepers.insert(index, &sleeper as *const Sleeper);
+        if index == 0 {
+            // The sleeper at the front before keeps its host timer, and
+            // simply finds nothing to do when it fires.
+            sleeper.state.store(TIMER, Ordering::Relaxed);
+        }
+        QUEUE.lock.unlock();
+    }
+
+    loop {
+        match sleeper.state.load(Ordering::Acquire) {
+            DONE => return,
+            TIMER => {
+                let now = Instant::_now();
+                if now >= deadline {
+                    release(now);
+                } else {
+                    futex_wait_timeout(&sleeper.state, TIMER, deadline - now);
+                }
+            }
+            state => futex_wait(&sleeper.state, state),
+        }
+    }
+}
+
+// Releases the sleepers whose deadline passed, and hands the host timer to
+// the one left at the front.
+fn release(now: Instant) {
+    let mut addrs = Vec::new();
+
+    unsafe {
+        QUEUE.lock.lock();
+        let sleepers = &mut *QUEUE.sleepers.get();
+        let expired = sleepers.partition_point(|&s| (*s).deadline <= now);
+        for s in sleepers.drain(..expired) {
+            // A released sleeper may return as soon as it sees `DONE`, so
+            // that must be the last access to it.
+            addrs.push(&(*s).state as *const AtomicUsize as usize);
+            (*s).state.store(DONE, Ordering::Release);
+        }
+        if let Some(&front) = sleepers.first() {
+            if (*front).state.swap(TIMER, Ordering::Release) != TIMER {
+                addrs.push(&(*front).state as *const AtomicUsize as usize);
+            }
+        }
+        QUEUE.lock.unlock();
+    }
+
+    futex_wake_many(&addrs);
+}
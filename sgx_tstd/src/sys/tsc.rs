@@ -0,0 +1,92 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Time measured inside the enclave with the time stamp counter.
+//!
+//! The host controls every clock the enclave reads through an OCALL, and can
+//! make a timeout expire early by reporting a later time. The TSC is read
+//! with RDTSC inside the enclave instead. Converting ticks to time needs the
+//! TSC frequency, which the enclave has to pin itself, since the CPUID leaves
+//! that report it are answered by the host as well.
+
+use crate::sync::atomic::{AtomicU64, Ordering};
+use crate::time::Duration;
+
+use core::arch::x86_64::_rdtsc;
+use sgx_trts::enclave::rsgx_is_supported_EDMM;
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+// TSC frequency in Hz, or 0 while the enclave has not pinned it.
+static FREQUENCY: AtomicU64 = AtomicU64::new(0);
+
+// RDTSC is only allowed inside enclaves on SGX2 processors, which we detect
+// through EDMM support.
+pub fn is_supported() -> bool {
+    rsgx_is_supported_EDMM()
+}
+
+pub fn set_frequency(hz: u64) -> bool {
+    if hz == 0 || !is_supported() {
+        return false;
+    }
+    FREQUENCY.store(hz, Ordering::Relaxed);
+    true
+}
+
+pub fn frequency() -> Option<u64> {
+    match FREQUENCY.load(Ordering::Relaxed) {
+        0 => None,
+        hz => Some(hz),
+    }
+}
+
+#[inline]
+pub fn ticks() -> u64 {
+    unsafe { _rdtsc() }
+}
+
+/// A point in time measured with the TSC.
+#[derive(Copy, Clone, Debug)]
+pub struct Deadline {
+    start: u64,
+    ticks: u64,
+    hz: u64,
+}
+
+impl Deadline {
+    /// Returns the deadline `dur` from now, or `None` if the TSC can't be
+    /// used.
+    pub fn after(dur: Duration) -> Option<Deadline> {
+        let hz = frequency()?;
+        // Round up, so that the deadline is never early.
+        let count = dur.as_nanos().saturating_mul(hz as u128).saturating_add(NANOS_PER_SEC - 1)
+            / NANOS_PER_SEC;
+        Some(Deadline { start: ticks(), ticks: count.min(u64::MAX as u128) as u64, hz })
+    }
+
+    /// Returns the time left until the deadline, or `None` once it passed.
+    pub fn remaining(&self) -> Option<Duration> {
+        let elapsed = ticks().wrapping_sub(self.start);
+        if elapsed >= self.ticks {
+            return None;
+        }
+        let left = (self.ticks - elapsed) as u128;
+        let nanos = (left * NANOS_PER_SEC + self.hz as u128 - 1) / self.hz as u128;
+        Some(Duration::from_nanos(nanos.min(u64::MAX as u128) as u64))
+    }
+}
@@ -21,13 +21,21 @@ use crate::cmp;
 use crate::convert::{TryFrom, TryInto};
 use crate::ffi::CString;
 use crate::fmt;
-use crate::io::{self, Error, ErrorKind, IoSlice, IoSliceMut};
+use crate::io::{self, Error, ErrorKind, IoSlice, IoSliceMut, Read, Seek, SeekFrom};
 use crate::mem;
-use crate::net::{Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr};
+use crate::net::{
+    Ipv4Addr, Ipv6Addr, MetricsSink, Shutdown, SocketAddr, SocketKind, SocketOp, SocketOptions,
+};
 use crate::ptr;
-use crate::sys::net::{cvt, cvt_gai, cvt_r, init, wrlen_t, Socket};
+use crate::slice;
+use crate::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use crate::sync::{PoisonError, SgxMutex};
+use crate::sgxfs::SgxFile;
+use crate::sys::net::{cvt, cvt_gai, cvt_r, init, timeout_to_timeval, wrlen_t, Socket, StagingBuf};
 use crate::sys_common::{AsInner, FromInner, IntoInner};
-use crate::time::Duration;
+use crate::time::{Duration, Instant};
+#[cfg(not(feature = "untrusted_time"))]
+use crate::untrusted::time::InstantEx;
 
 
 use sgx_libc::{c_int, c_uint, c_void};
@@ -56,6 +64,90 @@ pub fn getsockopt<T: Copy>(sock: &Socket, opt: c_int, val: c_int) -> io::Result<
     }
 }
 
+// Socket options collected to be set with a single OCALL. Each option keeps
+// its name so that a failure can say which one was rejected.
+#[derive(Default)]
+struct SockoptBatch {
+    names: Vec<&'static str>,
+    levels: Vec<c_int>,
+    optnames: Vec<c_int>,
+    optlens: Vec<c::socklen_t>,
+    optvals: Vec<u8>,
+}
+
+impl SockoptBatch {
+    fn push<T: Copy>(&mut self, name: &'static str, opt: c_int, val: c_int, payload: T) {
+        let bytes = unsafe {
+            slice::from_raw_parts(&payload as *const T as *const u8, mem::size_of::<T>())
+        };
+        self.names.push(name);
+        self.levels.push(opt);
+        self.optnames.push(val);
+        self.optlens.push(bytes.len() as c::socklen_t);
+        self.optvals.extend_from_slice(bytes);
+    }
+
+    fn apply(&self, sock: &Socket) -> io::Result<()> {
+        let mut failed = 0;
+        let ret = unsafe {
+            c::setsockopts(
+                sock.as_raw(),
+                self.levels.as_ptr(),
+                self.optnames.as_ptr(),
+                self.optlens.as_ptr(),
+                self.optvals.as_ptr() as *const c_void,
+                self.optvals.len(),
+                self.names.len(),
+                &mut failed,
+            )
+        };
+        match cvt(ret) {
+            Ok(_) => Ok(()),
+            Err(e) => match self.names.get(failed) {
+                Some(name) => Err(io::Error::new(e.kind(), format!("failed to set {}: {}", name, e))),
+                None => Err(e),
+            },
+        }
+    }
+}
+
+static METRICS_SINK: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+pub fn install_metrics(sink: MetricsSink) {
+    METRICS_SINK.store(sink as *mut (), Ordering::SeqCst);
+}
+
+pub fn uninstall_metrics() -> Option<MetricsSink> {
+    let sink = METRICS_SINK.swap(ptr::null_mut(), Ordering::SeqCst);
+    if sink.is_null() { None } else { Some(unsafe { mem::transmute(sink) }) }
+}
+
+// Reports a successful transfer of `n` bytes to the installed metrics sink.
+// The sink is a code pointer with no data behind it, so a relaxed load is
+// enough, and it is all this costs when nothing is installed.
+#[inline]
+fn record(kind: SocketKind, op: SocketOp, n: usize) {
+    let sink = METRICS_SINK.load(Ordering::Relaxed);
+    if !sink.is_null() {
+        let sink: MetricsSink = unsafe { mem::transmute(sink) };
+        sink(kind, op, n);
+    }
+}
+
+#[inline]
+fn metered(kind: SocketKind, op: SocketOp, res: io::Result<usize>) -> io::Result<usize> {
+    if let Ok(n) = res {
+        record(kind, op, n);
+    }
+    res
+}
+
+fn buffer_size(size: usize) -> io::Result<c_int> {
+    c_int::try_from(size).map_err(|_| {
+        io::Error::new_const(ErrorKind::InvalidInput, &"socket buffer size is too large")
+    })
+}
+
 fn sockname<F>(f: F) -> io::Result<SocketAddr>
 where
     F: FnOnce(*mut c::sockaddr, *mut c::socklen_t) -> c_int,
@@ -90,6 +182,24 @@ fn to_ipv6mr_interface(value: u32) -> c_uint {
     value as c_uint
 }
 
+fn set_mark(sock: &Socket, mark: u32) -> io::Result<()> {
+    setsockopt(sock, c::SOL_SOCKET, c::SO_MARK, mark as c_int).map_err(|e| {
+        if e.raw_os_error() == Some(c::EPERM) {
+            Error::new_const(
+                ErrorKind::PermissionDenied,
+                &"setting SO_MARK requires CAP_NET_ADMIN on the host",
+            )
+        } else {
+            e
+        }
+    })
+}
+
+fn mark(sock: &Socket) -> io::Result<u32> {
+    let raw: c_int = getsockopt(sock, c::SOL_SOCKET, c::SO_MARK)?;
+    Ok(raw as u32)
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // get_host_addresses
 ////////////////////////////////////////////////////////////////////////////////
@@ -174,22 +284,23 @@ impl<'a> TryFrom<(&'a str, u16)> for LookupHost {
 
 pub struct TcpStream {
     inner: Socket,
+    eof_on_reset: AtomicBool,
 }
 
 impl TcpStream {
     pub fn new(sockfd: c_int) -> io::Result<TcpStream> {
         let sock = Socket::new(sockfd)?;
-        Ok(TcpStream { inner: sock })
+        Ok(TcpStream::from_inner(sock))
     }
 
     pub fn new_v4() -> io::Result<TcpStream> {
         let sock = Socket::new_raw(c::AF_INET, c::SOCK_STREAM)?;
-        Ok(TcpStream { inner: sock })
+        Ok(TcpStream::from_inner(sock))
     }
 
     pub fn new_v6() -> io::Result<TcpStream> {
         let sock = Socket::new_raw(c::AF_INET6, c::SOCK_STREAM)?;
-        Ok(TcpStream { inner: sock })
+        Ok(TcpStream::from_inner(sock))
     }
 
     pub fn connect(addr: io::Result<&SocketAddr>) -> io::Result<TcpStream> {
@@ -200,7 +311,73 @@ impl TcpStream {
         let sock = Socket::new_socket_addr_type(addr, c::SOCK_STREAM)?;
         let (addrp, len) = addr.into_inner();
         cvt_r(|| unsafe { c::connect(sock.as_raw(), addrp, len) })?;
-        Ok(TcpStream { inner: sock })
+        Ok(TcpStream::from_inner(sock))
+    }
+
+    // Races connection attempts to `addrs`, starting them in order `stagger`
+    // apart, and returns the first to complete; the others are closed. When
+    // every started attempt has failed, the next one starts without waiting.
+    pub fn connect_racing(addrs: &[SocketAddr], stagger: Duration) -> io::Result<TcpStream> {
+        init();
+
+        let mut pending: Vec<Socket> = Vec::new();
+        let mut next = 0;
+        let mut next_start = Instant::now();
+        let mut last_err = None;
+        loop {
+            let now = Instant::now();
+            if next < addrs.len() && (now >= next_start || pending.is_empty()) {
+                let addr = &addrs[next];
+                next += 1;
+                next_start = now + stagger;
+                let sock = Socket::new_socket_addr_type(addr, c::SOCK_STREAM)?;
+                match sock.connect_nonblocking(addr) {
+                    Ok(true) => return TcpStream::finish_racing(sock),
+                    Ok(false) => pending.push(sock),
+                    Err(e) => last_err = Some(e),
+                }
+                continue;
+            }
+            if pending.is_empty() {
+                break;
+            }
+
+            let mut fds: Vec<c::pollfd> = pending
+                .iter()
+                .map(|sock| c::pollfd { fd: sock.as_raw(), events: c::POLLOUT, revents: 0 })
+                .collect();
+            let timeout = if next < addrs.len() {
+                Some(next_start.saturating_duration_since(now))
+            } else {
+                None
+            };
+            if crate::sys::net::poll(&mut fds, timeout)? == 0 {
+                continue;
+            }
+            // Going backwards, `swap_remove` only moves sockets already looked at.
+            for i in (0..fds.len()).rev() {
+                let revents = fds[i].revents;
+                if revents == 0 {
+                    continue;
+                }
+                let sock = pending.swap_remove(i);
+                // Linux reports a refused connection as POLLOUT|POLLERR|POLLHUP.
+                if revents & (c::POLLERR | c::POLLHUP) == 0 {
+                    return TcpStream::finish_racing(sock);
+                }
+                last_err = Some(sock.take_error()?.unwrap_or_else(|| {
+                    Error::new_const(ErrorKind::Uncategorized, &"no error set after POLLHUP")
+                }));
+            }
+        }
+        Err(last_err.unwrap_or_else(|| {
+            Error::new_const(ErrorKind::InvalidInput, &"could not resolve to any addresses")
+        }))
+    }
+
+    fn finish_racing(sock: Socket) -> io::Result<TcpStream> {
+        sock.set_nonblocking(false)?;
+        Ok(TcpStream::from_inner(sock))
     }
 
     pub fn connect_socket(&self, addr: io::Result<&SocketAddr>) -> io::Result<()> {
@@ -217,7 +394,7 @@ impl TcpStream {
 
         let sock = Socket::new_socket_addr_type(addr, c::SOCK_STREAM)?;
         sock.connect_timeout(addr, timeout)?;
-        Ok(TcpStream { inner: sock })
+        Ok(TcpStream::from_inner(sock))
     }
 
     pub fn connect_socket_timeout(&self, addr: &SocketAddr, timeout: Duration) -> io::Result<()> {
@@ -232,6 +409,10 @@ impl TcpStream {
         self.inner
     }
 
+    pub fn close(self) -> io::Result<()> {
+        self.inner.close()
+    }
+
     pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
         self.inner.set_timeout(dur, c::SO_RCVTIMEO)
     }
@@ -253,11 +434,31 @@ impl TcpStream {
     }
 
     pub fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
-        self.inner.read(buf)
+        metered(SocketKind::Tcp, SocketOp::Read, self.map_reset(self.inner.read(buf)))
     }
 
     pub fn read_vectored(&self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
-        self.inner.read_vectored(bufs)
+        metered(SocketKind::Tcp, SocketOp::Read, self.map_reset(self.inner.read_vectored(bufs)))
+    }
+
+    fn map_reset(&self, res: io::Result<usize>) -> io::Result<usize> {
+        match res {
+            Err(ref e)
+                if e.kind() == ErrorKind::ConnectionReset
+                    && self.eof_on_reset.load(Ordering::Relaxed) =>
+            {
+                Ok(0)
+            }
+            res => res,
+        }
+    }
+
+    pub fn set_eof_on_reset(&self, eof_on_reset: bool) {
+        self.eof_on_reset.store(eof_on_reset, Ordering::Relaxed);
+    }
+
+    pub fn eof_on_reset(&self) -> bool {
+        self.eof_on_reset.load(Ordering::Relaxed)
     }
 
     #[inline]
@@ -270,11 +471,71 @@ impl TcpStream {
         let ret = cvt(unsafe {
             c::send(self.inner.as_raw(), buf.as_ptr() as *const c_void, len, c::MSG_NOSIGNAL)
         })?;
+        record(SocketKind::Tcp, SocketOp::Write, ret as usize);
         Ok(ret as usize)
     }
 
     pub fn write_vectored(&self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
-        self.inner.write_vectored(bufs)
+        metered(SocketKind::Tcp, SocketOp::Write, self.inner.write_vectored(bufs))
+    }
+
+    pub fn write_all_timeout(&self, buf: &[u8], timeout: Duration) -> io::Result<usize> {
+        metered(SocketKind::Tcp, SocketOp::Write, self.inner.write_all_timeout(buf, timeout))
+    }
+
+    pub fn send_file(&self, file: &SgxFile, start: u64, end: Option<u64>) -> io::Result<u64> {
+        let mut remaining = end.map(|end| end.saturating_sub(start));
+        let chunk = cmp::min(remaining.unwrap_or(u64::MAX), SEND_FILE_CHUNK as u64) as usize;
+        if chunk == 0 {
+            return Ok(0);
+        }
+
+        // The file is read through its shared cursor, which is put back
+        // afterwards so that the caller sees no change, as with sendfile.
+        let mut file = file;
+        let pos = file.stream_position()?;
+        let mut transfer = || -> io::Result<u64> {
+            file.seek(SeekFrom::Start(start))?;
+            let mut buf = vec![0_u8; chunk];
+            let mut staging = StagingBuf::new(chunk)?;
+            let mut sent = 0;
+            loop {
+                let want = match remaining {
+                    Some(0) => break,
+                    Some(remaining) => cmp::min(remaining, chunk as u64) as usize,
+                    None => chunk,
+                };
+                // Protected files are decrypted into enclave memory, so each
+                // chunk is copied out once into the staging buffer.
+                let n = match file.read(&mut buf[..want]) {
+                    Ok(0) => break,
+                    Ok(n) => n,
+                    Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                };
+                staging.fill(&buf[..n]);
+                self.inner.send_all_staged(&staging, n)?;
+                record(SocketKind::Tcp, SocketOp::Write, n);
+                sent += n as u64;
+                if let Some(ref mut remaining) = remaining {
+                    *remaining -= n as u64;
+                }
+            }
+            Ok(sent)
+        };
+        let result = transfer();
+        let restored = file.seek(SeekFrom::Start(pos));
+        let sent = result?;
+        restored?;
+        Ok(sent)
+    }
+
+    pub fn wait_readable(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.inner.wait(c::POLLIN, timeout)
+    }
+
+    pub fn wait_writable(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.inner.wait(c::POLLOUT, timeout)
     }
 
     #[inline]
@@ -295,7 +556,7 @@ impl TcpStream {
     }
 
     pub fn duplicate(&self) -> io::Result<TcpStream> {
-        self.inner.duplicate().map(|s| TcpStream { inner: s })
+        self.inner.duplicate().map(TcpStream::from_inner)
     }
 
     pub fn set_linger(&self, linger: Option<Duration>) -> io::Result<()> {
@@ -306,6 +567,87 @@ impl TcpStream {
         self.inner.linger()
     }
 
+    pub fn set_keepalive(&self, keepalive: bool) -> io::Result<()> {
+        self.inner.set_keepalive(keepalive)
+    }
+
+    pub fn keepalive(&self) -> io::Result<bool> {
+        self.inner.keepalive()
+    }
+
+    pub fn set_keepalive_idle(&self, idle: Duration) -> io::Result<()> {
+        self.inner.set_keepalive_idle(idle)
+    }
+
+    pub fn keepalive_idle(&self) -> io::Result<Duration> {
+        self.inner.keepalive_idle()
+    }
+
+    pub fn set_keepalive_interval(&self, interval: Duration) -> io::Result<()> {
+        self.inner.set_keepalive_interval(interval)
+    }
+
+    pub fn keepalive_interval(&self) -> io::Result<Duration> {
+        self.inner.keepalive_interval()
+    }
+
+    pub fn set_keepalive_count(&self, count: u32) -> io::Result<()> {
+        self.inner.set_keepalive_count(count)
+    }
+
+    pub fn keepalive_count(&self) -> io::Result<u32> {
+        self.inner.keepalive_count()
+    }
+
+    pub fn set_reuse_address(&self, reuse: bool) -> io::Result<()> {
+        self.inner.set_reuse_address(reuse)
+    }
+
+    pub fn reuse_address(&self) -> io::Result<bool> {
+        self.inner.reuse_address()
+    }
+
+    pub fn set_tos(&self, tos: u32) -> io::Result<()> {
+        self.inner.set_tos(tos)
+    }
+
+    pub fn tos(&self) -> io::Result<u32> {
+        self.inner.tos()
+    }
+
+    pub fn configure(&self, opts: &SocketOptions) -> io::Result<()> {
+        let mut batch = SockoptBatch::default();
+        if let Some(nodelay) = opts.nodelay {
+            batch.push("TCP_NODELAY", c::IPPROTO_TCP, c::TCP_NODELAY, nodelay as c_int);
+        }
+        if let Some(ttl) = opts.ttl {
+            batch.push("IP_TTL", c::IPPROTO_IP, c::IP_TTL, ttl as c_int);
+        }
+        if let Some(dur) = opts.read_timeout {
+            batch.push("SO_RCVTIMEO", c::SOL_SOCKET, c::SO_RCVTIMEO, timeout_to_timeval(dur)?);
+        }
+        if let Some(dur) = opts.write_timeout {
+            batch.push("SO_SNDTIMEO", c::SOL_SOCKET, c::SO_SNDTIMEO, timeout_to_timeval(dur)?);
+        }
+        if let Some(size) = opts.recv_buffer_size {
+            batch.push("SO_RCVBUF", c::SOL_SOCKET, c::SO_RCVBUF, buffer_size(size)?);
+        }
+        if let Some(size) = opts.send_buffer_size {
+            batch.push("SO_SNDBUF", c::SOL_SOCKET, c::SO_SNDBUF, buffer_size(size)?);
+        }
+        batch.apply(&self.inner)
+    }
+
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        let raw: c_int = getsockopt(&self.inner, c::SOL_SOCKET, c::SO_RCVBUF)?;
+        Ok(raw as usize)
+    }
+
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        let raw: c_int = getsockopt(&self.inner, c::SOL_SOCKET, c::SO_SNDBUF)?;
+        Ok(raw as usize)
+    }
+
     pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
         self.inner.set_nodelay(nodelay)
     }
@@ -314,6 +656,14 @@ impl TcpStream {
         self.inner.nodelay()
     }
 
+    pub fn set_quickack(&self, quickack: bool) -> io::Result<()> {
+        self.inner.set_quickack(quickack)
+    }
+
+    pub fn quickack(&self) -> io::Result<bool> {
+        self.inner.quickack()
+    }
+
     pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
         setsockopt(&self.inner, c::IPPROTO_IP, c::IP_TTL, ttl as c_int)
     }
@@ -323,6 +673,14 @@ impl TcpStream {
         Ok(raw as u32)
     }
 
+    pub fn set_mark(&self, mark: u32) -> io::Result<()> {
+        set_mark(&self.inner, mark)
+    }
+
+    pub fn mark(&self) -> io::Result<u32> {
+        mark(&self.inner)
+    }
+
     pub fn take_error(&self) -> io::Result<Option<io::Error>> {
         self.inner.take_error()
     }
@@ -334,7 +692,7 @@ impl TcpStream {
 
 impl FromInner<Socket> for TcpStream {
     fn from_inner(socket: Socket) -> TcpStream {
-        TcpStream { inner: socket }
+        TcpStream { inner: socket, eof_on_reset: AtomicBool::new(false) }
     }
 }
 
@@ -355,31 +713,44 @@ impl fmt::Debug for TcpStream {
     }
 }
 
+// The size of the chunks `TcpStream::send_file` decrypts and sends at a time.
+const SEND_FILE_CHUNK: usize = 64 * 1024;
+
 ////////////////////////////////////////////////////////////////////////////////
 // TCP listeners
 ////////////////////////////////////////////////////////////////////////////////
 
+// The backlog used by `TcpListener::bind` when none is given.
+const DEFAULT_LISTEN_BACKLOG: c_int = 128;
+
 pub struct TcpListener {
     inner: Socket,
+    // Set by `set_accept_timeout`. Accept timeouts are implemented with poll
+    // rather than a socket option, so the value lives in the enclave.
+    accept_timeout: SgxMutex<Option<Duration>>,
 }
 
 impl TcpListener {
     pub fn new(sockfd: c_int) -> io::Result<TcpListener> {
         let sock = Socket::new(sockfd)?;
-        Ok(TcpListener { inner: sock })
+        Ok(TcpListener::from_inner(sock))
     }
 
     pub fn new_v4() -> io::Result<TcpListener> {
         let sock = Socket::new_raw(c::AF_INET, c::SOCK_STREAM)?;
-        Ok(TcpListener { inner: sock })
+        Ok(TcpListener::from_inner(sock))
     }
 
     pub fn new_v6() -> io::Result<TcpListener> {
         let sock = Socket::new_raw(c::AF_INET6, c::SOCK_STREAM)?;
-        Ok(TcpListener { inner: sock })
+        Ok(TcpListener::from_inner(sock))
     }
 
     pub fn bind(addr: io::Result<&SocketAddr>) -> io::Result<TcpListener> {
+        TcpListener::bind_with_backlog(addr, DEFAULT_LISTEN_BACKLOG)
+    }
+
+    pub fn bind_with_backlog(addr: io::Result<&SocketAddr>, backlog: c_int) -> io::Result<TcpListener> {
         let addr = addr?;
 
         init();
@@ -396,8 +767,8 @@ impl TcpListener {
         cvt(unsafe { c::bind(sock.as_raw(), addrp, len as _) })?;
 
         // Start listening
-        cvt(unsafe { c::listen(sock.as_raw(), 128) })?;
-        Ok(TcpListener { inner: sock })
+        cvt(unsafe { c::listen(sock.as_raw(), cmp::max(backlog, 1)) })?;
+        Ok(TcpListener::from_inner(sock))
     }
 
     pub fn bind_socket(&self, addr: io::Result<&SocketAddr>) -> io::Result<()> {
@@ -408,7 +779,7 @@ impl TcpListener {
         setsockopt(&self.inner, c::SOL_SOCKET, c::SO_REUSEADDR, 1_i32)?;
         let (addrp, len) = addr.into_inner();
         cvt(unsafe { c::bind(self.inner.as_raw(), addrp, len as _) })?;
-        cvt(unsafe { c::listen(self.inner.as_raw(), 128) }).map(drop)
+        cvt(unsafe { c::listen(self.inner.as_raw(), DEFAULT_LISTEN_BACKLOG) }).map(drop)
     }
 
     pub fn socket(&self) -> &Socket {
@@ -419,20 +790,77 @@ impl TcpListener {
         self.inner
     }
 
+    pub fn close(self) -> io::Result<()> {
+        self.inner.close()
+    }
+
     pub fn socket_addr(&self) -> io::Result<SocketAddr> {
         sockname(|buf, len| unsafe { c::getsockname(self.inner.as_raw(), buf, len) })
     }
 
     pub fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
+        match self.get_accept_timeout() {
+            Some(timeout) => self.accept_timeout(timeout),
+            None => self.accept_now(),
+        }
+    }
+
+    fn accept_now(&self) -> io::Result<(TcpStream, SocketAddr)> {
         let mut storage: c::sockaddr_storage = unsafe { mem::zeroed() };
         let mut len = mem::size_of_val(&storage) as c::socklen_t;
         let sock = self.inner.accept(&mut storage as *mut _ as *mut _, &mut len)?;
         let addr = sockaddr_to_addr(&storage, len as usize)?;
-        Ok((TcpStream { inner: sock }, addr))
+        Ok((TcpStream::from_inner(sock), addr))
+    }
+
+    pub fn accept_timeout(&self, timeout: Duration) -> io::Result<(TcpStream, SocketAddr)> {
+        if timeout == Duration::new(0, 0) {
+            return Err(Error::new_const(
+                ErrorKind::InvalidInput,
+                &"cannot set a 0 duration timeout",
+            ));
+        }
+        // A timeout too long to represent never expires.
+        let deadline = match Instant::now().checked_add(timeout) {
+            Some(deadline) => deadline,
+            None => return self.accept_now(),
+        };
+        loop {
+            self.inner.wait_until(c::POLLIN, deadline).map_err(|e| {
+                if e.kind() == ErrorKind::TimedOut {
+                    Error::new_const(ErrorKind::TimedOut, &"accept timed out")
+                } else {
+                    e
+                }
+            })?;
+            // In nonblocking mode another thread may have taken the connection.
+            match self.accept_now() {
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => {}
+                res => return res,
+            }
+        }
+    }
+
+    pub fn set_accept_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        if timeout == Some(Duration::new(0, 0)) {
+            return Err(Error::new_const(
+                ErrorKind::InvalidInput,
+                &"cannot set a 0 duration timeout",
+            ));
+        }
+        *self.accept_timeout.lock().unwrap_or_else(PoisonError::into_inner) = timeout;
+        Ok(())
+    }
+
+    pub fn get_accept_timeout(&self) -> Option<Duration> {
+        *self.accept_timeout.lock().unwrap_or_else(PoisonError::into_inner)
     }
 
     pub fn duplicate(&self) -> io::Result<TcpListener> {
-        self.inner.duplicate().map(|s| TcpListener { inner: s })
+        let listener = self.inner.duplicate().map(TcpListener::from_inner)?;
+        *listener.accept_timeout.lock().unwrap_or_else(PoisonError::into_inner) =
+            self.get_accept_timeout();
+        Ok(listener)
     }
 
     pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
@@ -453,6 +881,62 @@ impl TcpListener {
         Ok(raw != 0)
     }
 
+    pub fn set_linger(&self, linger: Option<Duration>) -> io::Result<()> {
+        self.inner.set_linger(linger)
+    }
+
+    pub fn linger(&self) -> io::Result<Option<Duration>> {
+        self.inner.linger()
+    }
+
+    pub fn set_keepalive(&self, keepalive: bool) -> io::Result<()> {
+        self.inner.set_keepalive(keepalive)
+    }
+
+    pub fn keepalive(&self) -> io::Result<bool> {
+        self.inner.keepalive()
+    }
+
+    pub fn set_keepalive_idle(&self, idle: Duration) -> io::Result<()> {
+        self.inner.set_keepalive_idle(idle)
+    }
+
+    pub fn keepalive_idle(&self) -> io::Result<Duration> {
+        self.inner.keepalive_idle()
+    }
+
+    pub fn set_keepalive_interval(&self, interval: Duration) -> io::Result<()> {
+        self.inner.set_keepalive_interval(interval)
+    }
+
+    pub fn keepalive_interval(&self) -> io::Result<Duration> {
+        self.inner.keepalive_interval()
+    }
+
+    pub fn set_keepalive_count(&self, count: u32) -> io::Result<()> {
+        self.inner.set_keepalive_count(count)
+    }
+
+    pub fn keepalive_count(&self) -> io::Result<u32> {
+        self.inner.keepalive_count()
+    }
+
+    pub fn set_reuse_address(&self, reuse: bool) -> io::Result<()> {
+        self.inner.set_reuse_address(reuse)
+    }
+
+    pub fn reuse_address(&self) -> io::Result<bool> {
+        self.inner.reuse_address()
+    }
+
+    pub fn set_tos(&self, tos: u32) -> io::Result<()> {
+        self.inner.set_tos(tos)
+    }
+
+    pub fn tos(&self) -> io::Result<u32> {
+        self.inner.tos()
+    }
+
     pub fn take_error(&self) -> io::Result<Option<io::Error>> {
         self.inner.take_error()
     }
@@ -464,7 +948,7 @@ impl TcpListener {
 
 impl FromInner<Socket> for TcpListener {
     fn from_inner(socket: Socket) -> TcpListener {
-        TcpListener { inner: socket }
+        TcpListener { inner: socket, accept_timeout: SgxMutex::new(None) }
     }
 }
 
@@ -487,22 +971,26 @@ impl fmt::Debug for TcpListener {
 
 pub struct UdpSocket {
     inner: Socket,
+    // Result of the first `getsockname` that returned an assigned port, so
+    // repeated `local_addr` calls do not each leave the enclave. Cleared by
+    // anything that can change the local address.
+    local_addr: SgxMutex<Option<SocketAddr>>,
 }
 
 impl UdpSocket {
     pub fn new(sockfd: c_int) -> io::Result<UdpSocket> {
         let sock = Socket::new(sockfd)?;
-        Ok(UdpSocket { inner: sock })
+        Ok(UdpSocket::from_inner(sock))
     }
 
     pub fn new_v4() -> io::Result<UdpSocket> {
         let sock = Socket::new_raw(c::AF_INET, c::SOCK_DGRAM)?;
-        Ok(UdpSocket { inner: sock })
+        Ok(UdpSocket::from_inner(sock))
     }
 
     pub fn new_v6() -> io::Result<UdpSocket> {
         let sock = Socket::new_raw(c::AF_INET6, c::SOCK_DGRAM)?;
-        Ok(UdpSocket { inner: sock })
+        Ok(UdpSocket::from_inner(sock))
     }
 
     pub fn bind(addr: io::Result<&SocketAddr>) -> io::Result<UdpSocket> {
@@ -513,7 +1001,7 @@ impl UdpSocket {
         let sock = Socket::new_socket_addr_type(addr, c::SOCK_DGRAM)?;
         let (addrp, len) = addr.into_inner();
         cvt(unsafe { c::bind(sock.as_raw(), addrp, len as _) })?;
-        Ok(UdpSocket { inner: sock })
+        Ok(UdpSocket::from_inner(sock))
     }
 
     pub fn bind_socket(&self, addr: io::Result<&SocketAddr>) -> io::Result<()> {
@@ -522,7 +1010,9 @@ impl UdpSocket {
         init();
 
         let (addrp, len) = addr.into_inner();
-        cvt(unsafe { c::bind(self.inner.as_raw(), addrp, len as _) }).map(drop)
+        let ret = cvt(unsafe { c::bind(self.inner.as_raw(), addrp, len as _) }).map(drop);
+        self.invalidate_local_addr();
+        ret
     }
 
     pub fn socket(&self) -> &Socket {
@@ -533,16 +1023,80 @@ impl UdpSocket {
         self.inner
     }
 
+    pub fn close(self) -> io::Result<()> {
+        self.inner.close()
+    }
+
     pub fn peer_addr(&self) -> io::Result<SocketAddr> {
         sockname(|buf, len| unsafe { c::getpeername(self.inner.as_raw(), buf, len) })
     }
 
     pub fn socket_addr(&self) -> io::Result<SocketAddr> {
-        sockname(|buf, len| unsafe { c::getsockname(self.inner.as_raw(), buf, len) })
+        let mut cached = self.local_addr.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(addr) = *cached {
+            return Ok(addr);
+        }
+        let addr = sockname(|buf, len| unsafe { c::getsockname(self.inner.as_raw(), buf, len) })?;
+        // An unbound socket reports port 0 until the host assigns one on the
+        // first send, so only an assigned address is worth remembering.
+        if addr.port() != 0 {
+            *cached = Some(addr);
+        }
+        Ok(addr)
+    }
+
+    fn invalidate_local_addr(&self) {
+        *self.local_addr.lock().unwrap_or_else(PoisonError::into_inner) = None;
     }
 
     pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
-        self.inner.recv_from(buf)
+        let ret = self.inner.recv_from(buf)?;
+        record(SocketKind::Udp, SocketOp::RecvFrom, ret.0);
+        Ok(ret)
+    }
+
+    pub fn recv_from_deadline(
+        &self,
+        buf: &mut [u8],
+        deadline: Instant,
+    ) -> io::Result<(usize, SocketAddr)> {
+        let ret = self.inner.recv_from_deadline(buf, deadline)?;
+        record(SocketKind::Udp, SocketOp::RecvFrom, ret.0);
+        Ok(ret)
+    }
+
+    pub fn recv_from_vectored(
+        &self,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> io::Result<(usize, bool, SocketAddr)> {
+        let ret = self.inner.recv_from_vectored(bufs)?;
+        record(SocketKind::Udp, SocketOp::RecvFrom, ret.0);
+        Ok(ret)
+    }
+
+    pub fn recv_from_with_tos(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr, u8)> {
+        let ret = self.inner.recv_from_with_tos(buf)?;
+        record(SocketKind::Udp, SocketOp::RecvFrom, ret.0);
+        Ok(ret)
+    }
+
+    pub fn set_recv_tos(&self, recv_tos: bool) -> io::Result<()> {
+        match self.socket_addr()? {
+            SocketAddr::V4(..) => {
+                setsockopt(&self.inner, c::IPPROTO_IP, c::IP_RECVTOS, recv_tos as c_int)
+            }
+            SocketAddr::V6(..) => {
+                setsockopt(&self.inner, c::IPPROTO_IPV6, c::IPV6_RECVTCLASS, recv_tos as c_int)
+            }
+        }
+    }
+
+    pub fn recv_tos(&self) -> io::Result<bool> {
+        let raw: c_int = match self.socket_addr()? {
+            SocketAddr::V4(..) => getsockopt(&self.inner, c::IPPROTO_IP, c::IP_RECVTOS)?,
+            SocketAddr::V6(..) => getsockopt(&self.inner, c::IPPROTO_IPV6, c::IPV6_RECVTCLASS)?,
+        };
+        Ok(raw != 0)
     }
 
     pub fn peek_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
@@ -562,11 +1116,16 @@ impl UdpSocket {
                 dstlen,
             )
         })?;
+        record(SocketKind::Udp, SocketOp::SendTo, ret as usize);
         Ok(ret as usize)
     }
 
+    pub fn send_to_vectored(&self, bufs: &[IoSlice<'_>], dst: &SocketAddr) -> io::Result<usize> {
+        metered(SocketKind::Udp, SocketOp::SendTo, self.inner.send_to_vectored(bufs, Some(dst)))
+    }
+
     pub fn duplicate(&self) -> io::Result<UdpSocket> {
-        self.inner.duplicate().map(|s| UdpSocket { inner: s })
+        self.inner.duplicate().map(UdpSocket::from_inner)
     }
 
     pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
@@ -631,6 +1190,45 @@ impl UdpSocket {
         Ok(raw != 0)
     }
 
+    pub fn set_multicast_hops_v6(&self, multicast_hops_v6: u32) -> io::Result<()> {
+        if multicast_hops_v6 > u8::MAX as u32 {
+            return Err(io::Error::new_const(
+                ErrorKind::InvalidInput,
+                &"multicast hop limit must fit in a byte",
+            ));
+        }
+        setsockopt(&self.inner, c::IPPROTO_IPV6, c::IPV6_MULTICAST_HOPS, multicast_hops_v6 as c_int)
+    }
+
+    pub fn multicast_hops_v6(&self) -> io::Result<u32> {
+        let raw: c_int = getsockopt(&self.inner, c::IPPROTO_IPV6, c::IPV6_MULTICAST_HOPS)?;
+        if !(0..=u8::MAX as c_int).contains(&raw) {
+            return Err(io::Error::new_const(
+                ErrorKind::InvalidData,
+                &"IPV6_MULTICAST_HOPS returned an out of range hop limit",
+            ));
+        }
+        Ok(raw as u32)
+    }
+
+    pub fn set_multicast_if_v4(&self, interface: &Ipv4Addr) -> io::Result<()> {
+        setsockopt(&self.inner, c::IPPROTO_IP, c::IP_MULTICAST_IF, interface.into_inner())
+    }
+
+    pub fn multicast_if_v4(&self) -> io::Result<Ipv4Addr> {
+        let raw: c::in_addr = getsockopt(&self.inner, c::IPPROTO_IP, c::IP_MULTICAST_IF)?;
+        Ok(Ipv4Addr::from(u32::from_be(raw.s_addr)))
+    }
+
+    pub fn set_multicast_if_v6(&self, interface: u32) -> io::Result<()> {
+        setsockopt(&self.inner, c::IPPROTO_IPV6, c::IPV6_MULTICAST_IF, interface as c_int)
+    }
+
+    pub fn multicast_if_v6(&self) -> io::Result<u32> {
+        let raw: c_int = getsockopt(&self.inner, c::IPPROTO_IPV6, c::IPV6_MULTICAST_IF)?;
+        Ok(raw as u32)
+    }
+
     pub fn join_multicast_v4(&self, multiaddr: &Ipv4Addr, interface: &Ipv4Addr) -> io::Result<()> {
         let mreq = c::ip_mreq {
             imr_multiaddr: multiaddr.into_inner(),
@@ -672,6 +1270,14 @@ impl UdpSocket {
         Ok(raw as u32)
     }
 
+    pub fn set_mark(&self, mark: u32) -> io::Result<()> {
+        set_mark(&self.inner, mark)
+    }
+
+    pub fn mark(&self) -> io::Result<u32> {
+        mark(&self.inner)
+    }
+
     pub fn take_error(&self) -> io::Result<Option<io::Error>> {
         self.inner.take_error()
     }
@@ -681,7 +1287,17 @@ impl UdpSocket {
     }
 
     pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
-        self.inner.read(buf)
+        metered(SocketKind::Udp, SocketOp::Read, self.inner.read(buf))
+    }
+
+    pub fn recv_deadline(&self, buf: &mut [u8], deadline: Instant) -> io::Result<usize> {
+        metered(SocketKind::Udp, SocketOp::Read, self.inner.recv_deadline(buf, deadline))
+    }
+
+    pub fn recv_vectored(&self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<(usize, bool)> {
+        let ret = self.inner.recv_vectored(bufs)?;
+        record(SocketKind::Udp, SocketOp::Read, ret.0);
+        Ok(ret)
     }
 
     pub fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
@@ -693,18 +1309,26 @@ impl UdpSocket {
         let ret = cvt(unsafe {
             c::send(self.inner.as_raw(), buf.as_ptr() as *const c_void, len, c::MSG_NOSIGNAL)
         })?;
+        record(SocketKind::Udp, SocketOp::Write, ret as usize);
         Ok(ret as usize)
     }
 
+    pub fn send_vectored(&self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        metered(SocketKind::Udp, SocketOp::Write, self.inner.send_to_vectored(bufs, None))
+    }
+
     pub fn connect(&self, addr: io::Result<&SocketAddr>) -> io::Result<()> {
         let (addrp, len) = addr?.into_inner();
-        cvt_r(|| unsafe { c::connect(self.inner.as_raw(), addrp, len) }).map(drop)
+        let ret = cvt_r(|| unsafe { c::connect(self.inner.as_raw(), addrp, len) }).map(drop);
+        // Connecting picks a concrete local IP for a wildcard-bound socket.
+        self.invalidate_local_addr();
+        ret
     }
 }
 
 impl FromInner<Socket> for UdpSocket {
     fn from_inner(socket: Socket) -> UdpSocket {
-        UdpSocket { inner: socket }
+        UdpSocket { inner: socket, local_addr: SgxMutex::new(None) }
     }
 }
 
@@ -721,9 +1345,159 @@ impl fmt::Debug for UdpSocket {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// Raw sockets
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "raw_socket")]
+pub struct RawSocket {
+    inner: Socket,
+}
+
+#[cfg(feature = "raw_socket")]
+impl RawSocket {
+    pub fn new(fam: c_int, ty: c_int, protocol: c_int) -> io::Result<RawSocket> {
+        init();
+
+        let sock = Socket::new_proto(fam, ty, protocol)?;
+        Ok(RawSocket::from_inner(sock))
+    }
+
+    pub fn bind(&self, addr: io::Result<&SocketAddr>) -> io::Result<()> {
+        let (addrp, len) = addr?.into_inner();
+        cvt(unsafe { c::bind(self.inner.as_raw(), addrp, len as _) }).map(drop)
+    }
+
+    pub fn connect(&self, addr: io::Result<&SocketAddr>) -> io::Result<()> {
+        let (addrp, len) = addr?.into_inner();
+        cvt_r(|| unsafe { c::connect(self.inner.as_raw(), addrp, len) }).map(drop)
+    }
+
+    pub fn socket(&self) -> &Socket {
+        &self.inner
+    }
+
+    pub fn into_socket(self) -> Socket {
+        self.inner
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        sockname(|buf, len| unsafe { c::getpeername(self.inner.as_raw(), buf, len) })
+    }
+
+    pub fn socket_addr(&self) -> io::Result<SocketAddr> {
+        sockname(|buf, len| unsafe { c::getsockname(self.inner.as_raw(), buf, len) })
+    }
+
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.inner.recv_from(buf)
+    }
+
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        let len = cmp::min(buf.len(), <wrlen_t>::MAX as usize) as wrlen_t;
+        let ret = cvt(unsafe {
+            c::send(self.inner.as_raw(), buf.as_ptr() as *const c_void, len, c::MSG_NOSIGNAL)
+        })?;
+        Ok(ret as usize)
+    }
+
+    pub fn send_to(&self, buf: &[u8], dst: &SocketAddr) -> io::Result<usize> {
+        let len = cmp::min(buf.len(), <wrlen_t>::MAX as usize) as wrlen_t;
+        let (dstp, dstlen) = dst.into_inner();
+        let ret = cvt(unsafe {
+            c::sendto(
+                self.inner.as_raw(),
+                buf.as_ptr() as *const c_void,
+                len,
+                c::MSG_NOSIGNAL,
+                dstp,
+                dstlen,
+            )
+        })?;
+        Ok(ret as usize)
+    }
+
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.inner.set_timeout(dur, c::SO_RCVTIMEO)
+    }
+
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.inner.set_timeout(dur, c::SO_SNDTIMEO)
+    }
+
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        self.inner.timeout(c::SO_RCVTIMEO)
+    }
+
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        self.inner.timeout(c::SO_SNDTIMEO)
+    }
+
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        setsockopt(&self.inner, c::IPPROTO_IP, c::IP_TTL, ttl as c_int)
+    }
+
+    pub fn ttl(&self) -> io::Result<u32> {
+        let raw: c_int = getsockopt(&self.inner, c::IPPROTO_IP, c::IP_TTL)?;
+        Ok(raw as u32)
+    }
+
+    pub fn set_unicast_hops_v6(&self, hops: u32) -> io::Result<()> {
+        setsockopt(&self.inner, c::IPPROTO_IPV6, c::IPV6_UNICAST_HOPS, hops as c_int)
+    }
+
+    pub fn unicast_hops_v6(&self) -> io::Result<u32> {
+        let raw: c_int = getsockopt(&self.inner, c::IPPROTO_IPV6, c::IPV6_UNICAST_HOPS)?;
+        Ok(raw as u32)
+    }
+
+    pub fn set_header_included(&self, included: bool) -> io::Result<()> {
+        setsockopt(&self.inner, c::IPPROTO_IP, c::IP_HDRINCL, included as c_int)
+    }
+
+    pub fn header_included(&self) -> io::Result<bool> {
+        let raw: c_int = getsockopt(&self.inner, c::IPPROTO_IP, c::IP_HDRINCL)?;
+        Ok(raw != 0)
+    }
+
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.inner.take_error()
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.inner.set_nonblocking(nonblocking)
+    }
+}
+
+#[cfg(feature = "raw_socket")]
+impl FromInner<Socket> for RawSocket {
+    fn from_inner(socket: Socket) -> RawSocket {
+        RawSocket { inner: socket }
+    }
+}
+
+#[cfg(feature = "raw_socket")]
+impl fmt::Debug for RawSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut res = f.debug_struct("RawSocket");
+
+        if let Ok(addr) = self.socket_addr() {
+            res.field("addr", &addr);
+        }
+
+        let name = "fd";
+        res.field(name, &self.inner.as_raw()).finish()
+    }
+}
+
 mod c {
     pub use sgx_libc::ocall::{
         bind, connect, freeaddrinfo, getaddrinfo, getpeername, getsockname, getsockopt, listen, send, sendto, setsockopt,
+        setsockopts,
     };
     pub use sgx_libc::*;
 }
@@ -17,37 +17,71 @@
 
 #![allow(dead_code)] // sys isn't exported yet
 
+use crate::cell::Cell;
 use crate::ptr;
 use crate::sys_common::thread_local_key::StaticKey;
 
+type List = Vec<(*mut u8, unsafe extern "C" fn(*mut u8))>;
+
+// The destructors registered by the current thread. The list lives in the
+// TLS of the TCS rather than behind an OS-based TLS key, so that threads that
+// entered through an ECALL, which the pthread library knows nothing about,
+// can register destructors as well.
+#[thread_local]
+static DTORS: Cell<*mut List> = Cell::new(ptr::null_mut());
+
+// Bumped every time the host tears the current thread down. With the Bound
+// TCS policy the TCS, and its TLS with it, is later handed to another host
+// thread, which must be able to use thread locals destroyed by an earlier
+// teardown again.
+#[thread_local]
+static GENERATION: Cell<usize> = Cell::new(0);
+
+// Threads created by pthread_create run their destructors when they exit
+// through the destructor of this key.
+static EXIT_KEY: StaticKey = StaticKey::new(Some(run_dtors_on_exit));
+
+unsafe extern "C" fn run_dtors_on_exit(_: *mut u8) {
+    run_dtors();
+}
+
 pub unsafe fn register_dtor_fallback(t: *mut u8, dtor: unsafe extern "C" fn(*mut u8)) {
-    // The fallback implementation uses a vanilla OS-based TLS key to track
-    // the list of destructors that need to be run for this thread. The key
-    // then has its own destructor which runs all the other destructors.
-    //
-    // The destructor for DTORS is a little special in that it has a `while`
-    // loop to continuously drain the list of registered destructors. It
-    // *should* be the case that this loop always terminates because we
-    // provide the guarantee that a TLS key cannot be set after it is
-    // flagged for destruction.
-
-    static DTORS: StaticKey = StaticKey::new(Some(run_dtors));
-    type List = Vec<(*mut u8, unsafe extern "C" fn(*mut u8))>;
     if DTORS.get().is_null() {
         let v: Box<List> = box Vec::new();
-        DTORS.set(Box::into_raw(v) as *mut u8);
+        DTORS.set(Box::into_raw(v));
+        if crate::thread::is_pthread() {
+            EXIT_KEY.set(1 as *mut u8);
+        }
     }
-    let list: &mut List = &mut *(DTORS.get() as *mut List);
+    let list: &mut List = &mut *DTORS.get();
     list.push((t, dtor));
+}
 
-    unsafe extern "C" fn run_dtors(mut ptr: *mut u8) {
-        while !ptr.is_null() {
-            let list: Box<List> = Box::from_raw(ptr as *mut List);
-            for (ptr, dtor) in list.into_iter() {
-                dtor(ptr);
-            }
-            ptr = DTORS.get();
-            DTORS.set(ptr::null_mut());
+// Runs the destructors of the current thread in reverse order of their
+// registration. Destructors registered while they run form a new batch, run
+// after the current one, again in reverse order. This loop always terminates
+// because a thread local cannot be initialized again once its destructor
+// has run.
+pub unsafe fn run_dtors() {
+    loop {
+        let ptr = DTORS.replace(ptr::null_mut());
+        if ptr.is_null() {
+            break;
+        }
+        let list: Box<List> = Box::from_raw(ptr);
+        for (ptr, dtor) in list.into_iter().rev() {
+            dtor(ptr);
         }
     }
-}
\ No newline at end of file
+}
+
+// Runs the destructors of a thread that the host tears down, and lets the
+// thread locals be initialized again by the next host thread on this TCS.
+pub unsafe fn teardown() {
+    run_dtors();
+    GENERATION.set(GENERATION.get().wrapping_add(1));
+}
+
+pub fn generation() -> usize {
+    GENERATION.get()
+}
@@ -17,6 +17,8 @@
 
 //! Parker implementaiton based on a Mutex and Condvar.
 
+use crate::hint;
+use crate::sync::adaptive::{self, SpinPrimitive};
 use crate::sync::atomic::AtomicUsize;
 use crate::sync::atomic::Ordering::SeqCst;
 use crate::sync::{SgxCondvar as Condvar, SgxMutex as Mutex};
@@ -47,6 +49,11 @@ impl Parker {
             return;
         }
 
+        // Then spin for a while in case `unpark` is about to be called.
+        if self.spin() {
+            return;
+        }
+
         // Otherwise we need to coordinate going to sleep
         let mut m = self.lock.lock().unwrap();
         match self.state.compare_exchange(EMPTY, PARKED, SeqCst, SeqCst) {
@@ -64,6 +71,7 @@ impl Parker {
             } // should consume this notification, so prohibit spurious wakeups in next park.
             Err(_) => panic!("inconsistent park state"),
         }
+        adaptive::record_ocall_wait(SpinPrimitive::Parker);
         loop {
             m = self.cvar.wait(m).unwrap();
             match self.state.compare_exchange(NOTIFIED, EMPTY, SeqCst, SeqCst) {
@@ -82,6 +90,9 @@ impl Parker {
         if self.state.compare_exchange(NOTIFIED, EMPTY, SeqCst, SeqCst).is_ok() {
             return;
         }
+        if self.spin() {
+            return;
+        }
         let m = self.lock.lock().unwrap();
         match self.state.compare_exchange(EMPTY, PARKED, SeqCst, SeqCst) {
             Ok(_) => {}
@@ -98,6 +109,7 @@ impl Parker {
         // from a notification we just want to unconditionally set the state back to
         // empty, either consuming a notification or un-flagging ourselves as
         // parked.
+        adaptive::record_ocall_wait(SpinPrimitive::Parker);
         let (_m, _result) = self.cvar.wait_timeout(m, dur).unwrap();
         match self.state.swap(EMPTY, SeqCst) {
             NOTIFIED => {} // got a notification, hurray!
@@ -131,7 +143,25 @@ impl Parker {
         // Releasing `lock` before the call to `notify_one` means that when the
         // parked thread wakes it doesn't get woken only to have to wait for us
         // to release `lock`.
+        adaptive::record_ocall_wake(SpinPrimitive::Parker);
         drop(self.lock.lock().unwrap());
         self.cvar.notify_one()
     }
+
+    // Spins while the state is still `EMPTY`, so an `unpark` that arrives
+    // shortly doesn't cost a wait and a wake OCALL. Returns whether a
+    // notification was consumed. Since the state is never `PARKED` while we
+    // spin, `unpark` only has to store `NOTIFIED` to wake us.
+    fn spin(&self) -> bool {
+        for _ in 0..adaptive::spin_limit(SpinPrimitive::Parker) {
+            hint::spin_loop();
+            if self.state.load(SeqCst) == NOTIFIED
+                && self.state.compare_exchange(NOTIFIED, EMPTY, SeqCst, SeqCst).is_ok()
+            {
+                adaptive::record_spin_acquired(SpinPrimitive::Parker);
+                return true;
+            }
+        }
+        false
+    }
 }
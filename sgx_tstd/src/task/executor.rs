@@ -0,0 +1,206 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+
+use crate::boxed::Box;
+use crate::cell::RefCell;
+use crate::collections::VecDeque;
+use crate::fmt;
+use crate::future::Future;
+use crate::io;
+use crate::mem;
+use crate::pin::Pin;
+use crate::rc::Rc;
+use crate::sync::{Arc, PoisonError, SgxMutex};
+use crate::task::{Context, Poll, Wake, Waker};
+use crate::time::{Instant, TimerWheel};
+use crate::vec::Vec;
+
+use super::reactor::{self, Reactor};
+
+// The id the future passed to `block_on` is scheduled under.
+const MAIN: usize = usize::MAX;
+
+type ReadyQueue = Arc<SgxMutex<VecDeque<usize>>>;
+
+struct TaskWaker {
+    id: usize,
+    ready: ReadyQueue,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.ready.lock().unwrap_or_else(PoisonError::into_inner).push_back(self.id);
+    }
+}
+
+struct Slot {
+    // `None` while the slot is free or while its task is being polled.
+    task: Option<Pin<Box<dyn Future<Output = ()>>>>,
+    waker: Waker,
+}
+
+/// A single-threaded executor for futures that perform socket I/O.
+///
+/// [`block_on`] runs a future to completion on the current thread, polling
+/// any tasks started with [`spawn`] alongside it. Tasks are polled whenever
+/// they are woken; once none is ready the executor blocks in a single
+/// `epoll_wait` OCALL until one of the sockets they wait on becomes ready.
+///
+/// Each executor also drives a [`TimerWheel`], returned by [`timers`], so
+/// tasks can wait for deadlines by registering their waker with it. The
+/// `epoll_wait` is bounded by the wheel's next deadline, and due timers are
+/// fired before the woken tasks are polled.
+///
+/// Tasks may be woken from other threads, but such wake-ups are only noticed
+/// the next time the executor is not blocked on I/O.
+///
+/// [`block_on`]: LocalExecutor::block_on
+/// [`spawn`]: LocalExecutor::spawn
+/// [`timers`]: LocalExecutor::timers
+pub struct LocalExecutor {
+    reactor: Rc<Reactor>,
+    timers: Arc<TimerWheel>,
+    slots: RefCell<Vec<Slot>>,
+    free: RefCell<Vec<usize>>,
+    ready: ReadyQueue,
+}
+
+impl LocalExecutor {
+    /// Creates an executor with no tasks.
+    pub fn new() -> io::Result<LocalExecutor> {
+        Ok(LocalExecutor {
+            reactor: Rc::new(Reactor::new()?),
+            timers: Arc::new(TimerWheel::new()),
+            slots: RefCell::new(Vec::new()),
+            free: RefCell::new(Vec::new()),
+            ready: Arc::new(SgxMutex::new(VecDeque::new())),
+        })
+    }
+
+    /// Starts running `future` as a background task.
+    ///
+    /// The task only makes progress while [`LocalExecutor::block_on`] is
+    /// running, and is dropped along with the executor if it never completes.
+    pub fn spawn<F>(&self, future: F)
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        let task = Box::pin(future);
+        let reused = self.free.borrow_mut().pop();
+        let id = match reused {
+            Some(id) => {
+                self.slots.borrow_mut()[id].task = Some(task);
+                id
+            }
+            None => {
+                let mut slots = self.slots.borrow_mut();
+                let id = slots.len();
+                slots.push(Slot { task: Some(task), waker: self.waker(id) });
+                id
+            }
+        };
+        self.schedule(id);
+    }
+
+    /// Returns the timer wheel driven by this executor.
+    ///
+    /// The wheel is advanced whenever no task is ready, so its timers should
+    /// be used from tasks running on this executor. Callbacks run on the
+    /// executor's thread between polls.
+    pub fn timers(&self) -> &Arc<TimerWheel> {
+        &self.timers
+    }
+
+    /// Runs `future` to completion, polling spawned tasks while it waits.
+    ///
+    /// Returns an error if waiting for socket readiness fails, or if neither
+    /// `future` nor any task can make progress and none of them waits on a
+    /// socket or a timer, in which case the executor would block forever.
+    pub fn block_on<F: Future>(&self, future: F) -> io::Result<F::Output> {
+        let _enter = reactor::enter(&self.reactor);
+        let mut future = Box::pin(future);
+        let waker = self.waker(MAIN);
+        let mut cx = Context::from_waker(&waker);
+
+        self.schedule(MAIN);
+        loop {
+            let ready = mem::take(&mut *self.ready.lock().unwrap_or_else(PoisonError::into_inner));
+            if ready.is_empty() {
+                let deadline = self.timers.next_deadline();
+                if deadline.is_none() && !self.reactor.has_waiters() {
+                    return Err(io::Error::new_const(
+                        io::ErrorKind::Other,
+                        &"all tasks are blocked and none waits for I/O or a timer",
+                    ));
+                }
+                let timeout =
+                    deadline.map(|deadline| deadline.saturating_duration_since(Instant::_now()));
+                self.reactor.react(timeout)?;
+                self.timers.advance(Instant::_now());
+                continue;
+            }
+            for id in ready {
+                if id == MAIN {
+                    if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                        return Ok(output);
+                    }
+                } else {
+                    self.run_task(id);
+                }
+            }
+        }
+    }
+
+    fn run_task(&self, id: usize) {
+        // Take the task out of its slot so that it can spawn while being polled.
+        let taken = match self.slots.borrow_mut().get_mut(id) {
+            Some(slot) => slot.task.take().map(|task| (task, slot.waker.clone())),
+            None => None,
+        };
+        // Stale wake-ups for finished tasks are ignored.
+        let (mut task, waker) = match taken {
+            Some(taken) => taken,
+            None => return,
+        };
+        let mut cx = Context::from_waker(&waker);
+        match task.as_mut().poll(&mut cx) {
+            Poll::Ready(()) => self.free.borrow_mut().push(id),
+            Poll::Pending => self.slots.borrow_mut()[id].task = Some(task),
+        }
+    }
+
+    fn schedule(&self, id: usize) {
+        self.ready.lock().unwrap_or_else(PoisonError::into_inner).push_back(id);
+    }
+
+    fn waker(&self, id: usize) -> Waker {
+        Waker::from(Arc::new(TaskWaker { id, ready: self.ready.clone() }))
+    }
+}
+
+impl fmt::Debug for LocalExecutor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalExecutor")
+            .field("tasks", &(self.slots.borrow().len() - self.free.borrow().len()))
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,523 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use crate::collections::BTreeMap;
+use crate::convert::TryFrom;
+use crate::fmt;
+use crate::fs::File;
+use crate::io;
+use crate::mem;
+use crate::os::unix::io::{AsRawFd, RawFd};
+use crate::ptr;
+use crate::sync::atomic::{AtomicU32, Ordering};
+use crate::sync::{Arc, PoisonError, SgxMutex, SgxMutexGuard};
+use crate::task::{Context, Poll, Wake, Waker};
+use crate::vec::Vec;
+use sgx_trts::trts::rsgx_raw_is_outside_enclave;
+
+use super::reactor::{self, PollFn};
+
+mod libc {
+    pub use sgx_libc::ocall::{aio_destroy, aio_setup, aio_submit, free, malloc, read};
+    pub use sgx_libc::*;
+}
+
+// Operations in flight per ring. A power of two, so that the free-running
+// head and tail indices stay consistent when they wrap.
+const RING_ENTRIES: u32 = 256;
+// Host threads that carry out the operations of one ring.
+const HOST_THREADS: usize = 4;
+
+// Operation codes understood by `u_aio_submit_ocall`.
+const OP_READ: i32 = 0;
+const OP_WRITE: i32 = 1;
+const OP_FSYNC: i32 = 2;
+const OP_FDATASYNC: i32 = 3;
+
+// The completion ring the host allocates. The header is followed by
+// `RING_ENTRIES` completions. The host advances `tail` as it posts
+// completions and the enclave advances `head` as it consumes them. The
+// layout is shared with `sgx_urts` and `sgx_ustdc`.
+#[repr(C)]
+struct RingHeader {
+    head: AtomicU32,
+    tail: AtomicU32,
+    entries: u32,
+    _pad: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Completion {
+    user_data: u64,
+    // Bytes transferred, or a negated errno.
+    res: i64,
+}
+
+/// An untrusted file whose reads and writes can be awaited on a
+/// [`LocalExecutor`](super::LocalExecutor).
+///
+/// Each operation is carried out by a pool of host threads on a copy of the
+/// data in untrusted memory, so the enclave thread stays free to poll other
+/// tasks in the meantime. Operations take an explicit offset and do not move
+/// the file's cursor, and several can be in flight on the same file at once.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::task::{AsyncFile, LocalExecutor};
+/// use std::untrusted::fs::File;
+///
+/// let executor = LocalExecutor::new().unwrap();
+/// executor.block_on(async {
+///     let file = AsyncFile::from_std(File::open("log.bin").unwrap());
+///     let mut header = [0u8; 64];
+///     file.read_exact_at(&mut header, 0).await.unwrap();
+/// }).unwrap();
+/// ```
+pub struct AsyncFile {
+    inner: File,
+}
+
+impl AsyncFile {
+    /// Wraps an open file.
+    pub fn from_std(file: File) -> AsyncFile {
+        AsyncFile { inner: file }
+    }
+
+    /// Reads into `buf` from `offset`, returning the number of bytes read.
+    ///
+    /// Returns `Ok(0)` at the end of the file.
+    pub async fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.execute(OP_READ, buf.len(), offset, None, Some(buf)).await
+    }
+
+    /// Reads exactly `buf.len()` bytes from `offset`.
+    pub async fn read_exact_at(&self, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.read_at(buf, offset).await? {
+                0 => {
+                    return Err(io::Error::new_const(
+                        io::ErrorKind::UnexpectedEof,
+                        &"failed to fill whole buffer",
+                    ));
+                }
+                n => {
+                    buf = &mut buf[n..];
+                    offset += n as u64;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes `buf` at `offset`, returning the number of bytes written.
+    pub async fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.execute(OP_WRITE, buf.len(), offset, Some(buf), None).await
+    }
+
+    /// Writes all of `buf` at `offset`.
+    pub async fn write_all_at(&self, mut buf: &[u8], mut offset: u64) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.write_at(buf, offset).await? {
+                0 => {
+                    return Err(io::Error::new_const(
+                        io::ErrorKind::WriteZero,
+                        &"failed to write whole buffer",
+                    ));
+                }
+                n => {
+                    buf = &buf[n..];
+                    offset += n as u64;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes the file's data and metadata to the host's disk.
+    pub async fn sync_all(&self) -> io::Result<()> {
+        self.execute(OP_FSYNC, 0, 0, None, None).await.map(drop)
+    }
+
+    /// Flushes the file's data, but not necessarily its metadata, to the
+    /// host's disk.
+    pub async fn sync_data(&self) -> io::Result<()> {
+        self.execute(OP_FDATASYNC, 0, 0, None, None).await.map(drop)
+    }
+
+    /// Returns a reference to the underlying file.
+    pub fn get_ref(&self) -> &File {
+        &self.inner
+    }
+
+    /// Returns the underlying file.
+    pub fn into_inner(self) -> File {
+        self.inner
+    }
+
+    async fn execute(
+        &self,
+        opcode: i32,
+        len: usize,
+        offset: u64,
+        input: Option<&[u8]>,
+        mut output: Option<&mut [u8]>,
+    ) -> io::Result<usize> {
+        if offset > i64::MAX as u64 {
+            return Err(io::Error::new_const(io::ErrorKind::InvalidInput, &"offset is too large"));
+        }
+        let reactor = reactor::current()?;
+        let ring = reactor.ring()?;
+        let mut op = Operation { ring: &ring, id: None };
+        let fd = self.inner.as_raw_fd();
+
+        PollFn(|cx: &mut Context<'_>| {
+            let id = match op.id {
+                Some(id) => id,
+                None => match ring.submit(opcode, fd, len, offset, input, cx.waker()) {
+                    Ok(Some(id)) => {
+                        op.id = Some(id);
+                        id
+                    }
+                    // Every entry is in use, wait for one to complete.
+                    Ok(None) => {
+                        return match reactor.arm_ring() {
+                            Ok(()) => Poll::Pending,
+                            Err(e) => Poll::Ready(Err(e)),
+                        };
+                    }
+                    Err(e) => return Poll::Ready(Err(e)),
+                },
+            };
+            match ring.complete(id, output.as_deref_mut(), cx.waker()) {
+                Some(result) => {
+                    op.id = None;
+                    Poll::Ready(result)
+                }
+                None => match reactor.arm_ring() {
+                    Ok(()) => Poll::Pending,
+                    Err(e) => Poll::Ready(Err(e)),
+                },
+            }
+        })
+        .await
+    }
+}
+
+impl AsRawFd for AsyncFile {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl fmt::Debug for AsyncFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AsyncFile").field(&self.inner).finish()
+    }
+}
+
+// An operation submitted to the ring. If the future awaiting it is dropped
+// first, the untrusted buffer is left to the ring to free once the host is
+// done with it.
+struct Operation<'a> {
+    ring: &'a Ring,
+    id: Option<u64>,
+}
+
+impl Drop for Operation<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            self.ring.abandon(id);
+        }
+    }
+}
+
+struct Op {
+    // Untrusted staging buffer, null for operations without data.
+    buf: *mut u8,
+    len: usize,
+    result: Option<io::Result<usize>>,
+    waker: Option<Waker>,
+    abandoned: bool,
+}
+
+struct RingState {
+    // The next completion to consume. Kept in the enclave, the copy in the
+    // ring header only tells the host which entries it may reuse.
+    head: u32,
+    next_id: u64,
+    ops: BTreeMap<u64, Op>,
+    // Tasks waiting for an entry to become free.
+    waiting: Vec<Waker>,
+}
+
+/// A host thread pool and the completion ring it posts to.
+///
+/// Every operation in `ops` owns an entry, so the host never has more
+/// completions to post than the ring can hold.
+pub(super) struct Ring {
+    ctx: *mut libc::c_void,
+    header: *const RingHeader,
+    completions: *const Completion,
+    notify_fd: RawFd,
+    state: SgxMutex<RingState>,
+}
+
+// The raw pointers refer to untrusted memory, which is only touched with
+// `state` locked.
+unsafe impl Send for Ring {}
+unsafe impl Sync for Ring {}
+
+impl Ring {
+    pub(super) fn new() -> io::Result<Arc<Ring>> {
+        let mut notify_fd = -1;
+        let mut ring = ptr::null_mut();
+        let ctx = unsafe {
+            libc::aio_setup(RING_ENTRIES as usize, HOST_THREADS, &mut notify_fd, &mut ring)
+        };
+        if ctx.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let size =
+            mem::size_of::<RingHeader>() + RING_ENTRIES as usize * mem::size_of::<Completion>();
+        if ring.is_null()
+            || ring as usize % mem::align_of::<Completion>() != 0
+            || !rsgx_raw_is_outside_enclave(ring as *const u8, size)
+        {
+            unsafe { libc::aio_destroy(ctx) };
+            return Err(io::Error::new_const(
+                io::ErrorKind::InvalidData,
+                &"host returned an invalid completion ring",
+            ));
+        }
+        let header = ring as *const RingHeader;
+        Ok(Arc::new(Ring {
+            ctx,
+            header,
+            completions: unsafe { header.add(1) } as *const Completion,
+            notify_fd,
+            state: SgxMutex::new(RingState {
+                head: 0,
+                next_id: 0,
+                ops: BTreeMap::new(),
+                waiting: Vec::new(),
+            }),
+        }))
+    }
+
+    pub(super) fn notify_fd(&self) -> RawFd {
+        self.notify_fd
+    }
+
+    /// Returns `true` if some operation has yet to complete.
+    pub(super) fn in_flight(&self) -> bool {
+        self.lock().ops.values().any(|op| op.result.is_none())
+    }
+
+    fn lock(&self) -> SgxMutexGuard<'_, RingState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Hands an operation to the host, or returns `None` after registering
+    // `waker` if every entry is in use.
+    fn submit(
+        &self,
+        opcode: i32,
+        fd: RawFd,
+        len: usize,
+        offset: u64,
+        input: Option<&[u8]>,
+        waker: &Waker,
+    ) -> io::Result<Option<u64>> {
+        let mut state = self.lock();
+        if state.ops.len() >= RING_ENTRIES as usize {
+            state.waiting.push(waker.clone());
+            return Ok(None);
+        }
+        let buf = if len == 0 {
+            ptr::null_mut()
+        } else {
+            let buf = unsafe { libc::malloc(len) } as *mut u8;
+            if buf.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            buf
+        };
+        if let Some(input) = input {
+            unsafe { ptr::copy_nonoverlapping(input.as_ptr(), buf, input.len()) };
+        }
+        let id = state.next_id;
+        state.next_id += 1;
+        state.ops.insert(id, Op { buf, len, result: None, waker: None, abandoned: false });
+        drop(state);
+
+        let ret = unsafe {
+            libc::aio_submit(self.ctx, id, opcode, fd, buf as *mut libc::c_void, len, offset as i64)
+        };
+        if ret == -1 {
+            let err = io::Error::last_os_error();
+            let mut wakers = Vec::new();
+            self.release(&mut self.lock(), id, &mut wakers);
+            wakers.into_iter().for_each(Waker::wake);
+            return Err(err);
+        }
+        Ok(Some(id))
+    }
+
+    // Returns the result of operation `id` once it has completed, copying
+    // what was read into `output`. Until then, `waker` is woken when the
+    // completion arrives.
+    fn complete(
+        &self,
+        id: u64,
+        output: Option<&mut [u8]>,
+        waker: &Waker,
+    ) -> Option<io::Result<usize>> {
+        let mut wakers = Vec::new();
+        let mut state = self.lock();
+        self.drain(&mut state, &mut wakers);
+
+        let op = state.ops.get_mut(&id).expect("operation is not in flight");
+        let result = match op.result.take() {
+            Some(result) => result,
+            None => {
+                match &op.waker {
+                    Some(old) if old.will_wake(waker) => {}
+                    _ => op.waker = Some(waker.clone()),
+                }
+                drop(state);
+                wakers.into_iter().for_each(Waker::wake);
+                return None;
+            }
+        };
+        if let (Ok(n), Some(output)) = (&result, output) {
+            unsafe { ptr::copy_nonoverlapping(op.buf, output.as_mut_ptr(), *n) };
+        }
+        self.release(&mut state, id, &mut wakers);
+        drop(state);
+        wakers.into_iter().for_each(Waker::wake);
+        Some(result)
+    }
+
+    fn abandon(&self, id: u64) {
+        let mut wakers = Vec::new();
+        let mut state = self.lock();
+        match state.ops.get_mut(&id) {
+            Some(op) if op.result.is_none() => op.abandoned = true,
+            Some(_) => self.release(&mut state, id, &mut wakers),
+            None => {}
+        }
+        drop(state);
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    // Frees the entry and buffer of operation `id`, collecting the tasks
+    // that wait for an entry.
+    fn release(&self, state: &mut RingState, id: u64, wakers: &mut Vec<Waker>) {
+        if let Some(op) = state.ops.remove(&id) {
+            if !op.buf.is_null() {
+                unsafe { libc::free(op.buf as *mut libc::c_void) };
+            }
+            wakers.append(&mut state.waiting);
+        }
+    }
+
+    // Consumes the completions the host has posted, collecting the wakers
+    // of the tasks whose operations have finished.
+    fn drain(&self, state: &mut RingState, wakers: &mut Vec<Waker>) {
+        let header = unsafe { &*self.header };
+        let tail = header.tail.load(Ordering::Acquire);
+        let posted = tail.wrapping_sub(state.head);
+        // The tail is written by the host. One that claims more completions
+        // than there can be in flight is ignored rather than trusted.
+        if posted == 0 || posted > RING_ENTRIES {
+            return;
+        }
+        for _ in 0..posted {
+            let index = (state.head % RING_ENTRIES) as usize;
+            let completion = unsafe { ptr::read_volatile(self.completions.add(index)) };
+            state.head = state.head.wrapping_add(1);
+
+            // Completions for unknown or already completed operations are
+            // dropped.
+            let op = match state.ops.get_mut(&completion.user_data) {
+                Some(op) if op.result.is_none() => op,
+                _ => continue,
+            };
+            let result = if completion.res < 0 {
+                match completion.res.checked_neg().and_then(|e| i32::try_from(e).ok()) {
+                    Some(errno) => Err(io::Error::from_raw_os_error(errno)),
+                    None => Err(invalid_completion()),
+                }
+            } else if completion.res as u64 > op.len as u64 {
+                Err(invalid_completion())
+            } else {
+                Ok(completion.res as usize)
+            };
+            if op.abandoned {
+                self.release(state, completion.user_data, wakers);
+            } else {
+                op.result = Some(result);
+                wakers.extend(op.waker.take());
+            }
+        }
+        header.head.store(state.head, Ordering::Release);
+    }
+}
+
+fn invalid_completion() -> io::Error {
+    io::Error::new_const(io::ErrorKind::InvalidData, &"host reported an invalid completion")
+}
+
+// Woken by the reactor when the host signals the notification descriptor.
+impl Wake for Ring {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        // Reset the counter before draining, so that completions posted from
+        // here on signal the descriptor again.
+        let mut count = 0u64;
+        let buf = &mut count as *mut u64 as *mut libc::c_void;
+        unsafe { libc::read(self.notify_fd, buf, mem::size_of::<u64>()) };
+        let mut wakers = Vec::new();
+        self.drain(&mut self.lock(), &mut wakers);
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+impl Drop for Ring {
+    fn drop(&mut self) {
+        // Returns once the host threads have stopped, after which no buffer
+        // is in use.
+        unsafe { libc::aio_destroy(self.ctx) };
+        let state = self.state.get_mut().unwrap_or_else(PoisonError::into_inner);
+        for (_, op) in mem::take(&mut state.ops) {
+            if !op.buf.is_null() {
+                unsafe { libc::free(op.buf as *mut libc::c_void) };
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+
+//! Types and Traits for working with asynchronous tasks.
+//!
+//! With the `net` feature this module also provides a small single-threaded
+//! runtime for driving socket I/O from async code:
+//!
+//! * [`LocalExecutor`] runs futures on the current thread
+//! * [`AsyncTcpListener`] and [`AsyncTcpStream`] are non-blocking TCP sockets
+//!   whose operations can be awaited
+//! * [`AsyncFile`] hands reads and writes of an untrusted file to a pool of
+//!   host threads and awaits their completion
+//!
+//! Every socket operation is first attempted directly, and only when it would
+//! block is the socket registered with the executor's `epoll` reactor. The
+//! executor leaves the enclave to wait for readiness only once no task can make
+//! progress, so a busy runtime pays one OCALL per batch of ready sockets
+//! rather than one per operation.
+//!
+//! File operations cannot be made non-blocking, so they are submitted to host
+//! worker threads instead. Finished operations are posted to a completion
+//! ring in untrusted memory and signalled through an `eventfd` that the
+//! reactor waits on like any socket, so no enclave thread sits in a blocking
+//! file OCALL.
+
+#[doc(inline)]
+pub use core::task::*;
+
+#[doc(inline)]
+pub use alloc_crate::task::*;
+
+#[cfg(feature = "net")]
+pub use self::executor::LocalExecutor;
+#[cfg(feature = "net")]
+pub use self::file::AsyncFile;
+#[cfg(feature = "net")]
+pub use self::tcp::{AsyncTcpListener, AsyncTcpStream};
+
+#[cfg(feature = "net")]
+mod executor;
+#[cfg(feature = "net")]
+mod file;
+#[cfg(feature = "net")]
+mod reactor;
+#[cfg(feature = "net")]
+mod tcp;
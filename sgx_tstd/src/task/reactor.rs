@@ -0,0 +1,306 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+
+use crate::cell::{Cell, RefCell};
+use crate::collections::BTreeMap;
+use crate::future::Future;
+use crate::io;
+use crate::net::{self, Events, Interest};
+use crate::os::unix::io::RawFd;
+use crate::pin::Pin;
+use crate::rc::Rc;
+use crate::sync::Arc;
+use crate::task::{Context, Poll, Waker};
+use crate::time::Duration;
+use crate::vec::Vec;
+
+use super::file::Ring;
+
+// Readiness events fetched by one `epoll_wait` OCALL.
+const EVENTS_CAPACITY: usize = 64;
+
+thread_local! {
+    static CURRENT: RefCell<Option<Rc<Reactor>>> = RefCell::new(None);
+}
+
+#[derive(Copy, Clone)]
+pub(super) enum Direction {
+    Read,
+    Write,
+}
+
+struct Source {
+    fd: RawFd,
+    // What the socket is currently registered for in the epoll instance.
+    registered: Option<Interest>,
+    reader: Option<Waker>,
+    writer: Option<Waker>,
+}
+
+impl Source {
+    fn wanted(&self) -> Option<Interest> {
+        match (self.reader.is_some(), self.writer.is_some()) {
+            (true, true) => Some(Interest::READABLE | Interest::WRITABLE),
+            (true, false) => Some(Interest::READABLE),
+            (false, true) => Some(Interest::WRITABLE),
+            (false, false) => None,
+        }
+    }
+
+    // Brings the epoll registration in line with the wakers still waiting.
+    //
+    // Sockets are only registered while a task waits on them, otherwise a
+    // level-triggered epoll would keep reporting an idle writable socket.
+    fn update(&mut self, poll: &net::Poll, token: usize) -> io::Result<()> {
+        let wanted = self.wanted();
+        match (self.registered, wanted) {
+            (None, Some(interest)) => poll.register(&self.fd, token, interest)?,
+            (Some(old), Some(interest)) if old != interest => {
+                poll.reregister(&self.fd, token, interest)?
+            }
+            (Some(_), None) => poll.deregister(&self.fd)?,
+            _ => {}
+        }
+        self.registered = wanted;
+        Ok(())
+    }
+}
+
+pub(super) struct Reactor {
+    poll: net::Poll,
+    events: RefCell<Events>,
+    sources: RefCell<BTreeMap<usize, Source>>,
+    next_token: Cell<usize>,
+    // The completion ring for file I/O, its token and the waker that drains it.
+    ring: RefCell<Option<(Arc<Ring>, usize, Waker)>>,
+}
+
+impl Reactor {
+    pub(super) fn new() -> io::Result<Reactor> {
+        Ok(Reactor {
+            poll: net::Poll::new()?,
+            events: RefCell::new(Events::with_capacity(EVENTS_CAPACITY)),
+            sources: RefCell::new(BTreeMap::new()),
+            next_token: Cell::new(0),
+            ring: RefCell::new(None),
+        })
+    }
+
+    fn insert(&self, fd: RawFd) -> usize {
+        let token = self.next_token.get();
+        self.next_token.set(token + 1);
+        let source = Source { fd, registered: None, reader: None, writer: None };
+        self.sources.borrow_mut().insert(token, source);
+        token
+    }
+
+    fn remove(&self, token: usize) {
+        if let Some(source) = self.sources.borrow_mut().remove(&token) {
+            if source.registered.is_some() {
+                let _ = self.poll.deregister(&source.fd);
+            }
+        }
+    }
+
+    fn set_waker(&self, token: usize, direction: Direction, waker: &Waker) -> io::Result<()> {
+        let mut sources = self.sources.borrow_mut();
+        let source = match sources.get_mut(&token) {
+            Some(source) => source,
+            None => {
+                return Err(io::Error::new_const(
+                    io::ErrorKind::NotFound,
+                    &"socket is not registered with the reactor",
+                ));
+            }
+        };
+        let slot = match direction {
+            Direction::Read => &mut source.reader,
+            Direction::Write => &mut source.writer,
+        };
+        match slot {
+            Some(old) if old.will_wake(waker) => {}
+            _ => *slot = Some(waker.clone()),
+        }
+        source.update(&self.poll, token)
+    }
+
+    /// Returns the completion ring for asynchronous file I/O, setting it up
+    /// on first use.
+    pub(super) fn ring(&self) -> io::Result<Arc<Ring>> {
+        let mut slot = self.ring.borrow_mut();
+        if let Some((ring, ..)) = slot.as_ref() {
+            return Ok(ring.clone());
+        }
+        let ring = Ring::new()?;
+        let token = self.insert(ring.notify_fd());
+        let waker = Waker::from(ring.clone());
+        *slot = Some((ring.clone(), token, waker));
+        Ok(ring)
+    }
+
+    /// Waits for the completion ring's notification descriptor, so that the
+    /// next `react` drains the ring and wakes the tasks whose operations
+    /// have finished.
+    pub(super) fn arm_ring(&self) -> io::Result<()> {
+        let slot = self.ring.borrow();
+        let (_, token, waker) = slot.as_ref().expect("completion ring is not set up");
+        self.set_waker(*token, Direction::Read, waker)
+    }
+
+    /// Returns `true` if some task is waiting for a socket to become ready.
+    pub(super) fn has_waiters(&self) -> bool {
+        self.sources.borrow().values().any(|source| source.registered.is_some())
+    }
+
+    /// Waits for registered sockets to become ready, or until `timeout` has
+    /// passed, and wakes the tasks waiting on them.
+    pub(super) fn react(&self, timeout: Option<Duration>) -> io::Result<()> {
+        let mut events = self.events.borrow_mut();
+        self.poll.poll(&mut events, timeout)?;
+
+        let mut wakers = Vec::new();
+        let mut result = Ok(());
+        {
+            let mut sources = self.sources.borrow_mut();
+            for event in events.iter() {
+                let token = event.token();
+                let source = match sources.get_mut(&token) {
+                    Some(source) => source,
+                    None => continue,
+                };
+                // Errors and hang-ups are reported to both directions, the
+                // next attempt at the operation surfaces them.
+                let failed = event.is_error() || event.is_hangup();
+                if event.is_readable() || failed {
+                    wakers.extend(source.reader.take());
+                }
+                if event.is_writable() || failed {
+                    wakers.extend(source.writer.take());
+                }
+                if let Err(e) = source.update(&self.poll, token) {
+                    result = Err(e);
+                }
+            }
+        }
+        for waker in wakers {
+            waker.wake();
+        }
+        // The tasks just woken may not be the ones whose file operations
+        // are still in flight, so keep waiting for those completions.
+        let ring = self.ring.borrow();
+        if let Some((ring, token, waker)) = ring.as_ref() {
+            if ring.in_flight() {
+                if let Err(e) = self.set_waker(*token, Direction::Read, waker) {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Makes `reactor` the one sockets register with until the guard is dropped.
+pub(super) fn enter(reactor: &Rc<Reactor>) -> Enter {
+    Enter(CURRENT.with(|current| current.replace(Some(reactor.clone()))))
+}
+
+pub(super) struct Enter(Option<Rc<Reactor>>);
+
+impl Drop for Enter {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+pub(super) fn current() -> io::Result<Rc<Reactor>> {
+    CURRENT.with(|current| current.borrow().clone()).ok_or_else(|| {
+        io::Error::new_const(io::ErrorKind::Other, &"no LocalExecutor is running on this thread")
+    })
+}
+
+/// The link between a non-blocking socket and the reactor of the executor
+/// that polls it.
+///
+/// Sockets join a reactor the first time an operation would block, so they
+/// can be created outside of the executor.
+pub(super) struct Registration {
+    fd: RawFd,
+    inner: RefCell<Option<(Rc<Reactor>, usize)>>,
+}
+
+impl Registration {
+    pub(super) fn new(fd: RawFd) -> Registration {
+        Registration { fd, inner: RefCell::new(None) }
+    }
+
+    /// Retries `op` until it stops failing with `WouldBlock`, waiting for the
+    /// socket to become ready in `direction` in between.
+    pub(super) async fn io<R, F>(&self, direction: Direction, mut op: F) -> io::Result<R>
+    where
+        F: FnMut() -> io::Result<R>,
+    {
+        PollFn(|cx: &mut Context<'_>| loop {
+            match op() {
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    return match self.set_waker(direction, cx.waker()) {
+                        Ok(()) => Poll::Pending,
+                        Err(e) => Poll::Ready(Err(e)),
+                    };
+                }
+                result => return Poll::Ready(result),
+            }
+        })
+        .await
+    }
+
+    fn set_waker(&self, direction: Direction, waker: &Waker) -> io::Result<()> {
+        let mut inner = self.inner.borrow_mut();
+        if inner.is_none() {
+            let reactor = current()?;
+            let token = reactor.insert(self.fd);
+            *inner = Some((reactor, token));
+        }
+        let (reactor, token) = inner.as_ref().unwrap();
+        reactor.set_waker(*token, direction, waker)
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        if let Some((reactor, token)) = self.inner.get_mut().take() {
+            reactor.remove(token);
+        }
+    }
+}
+
+pub(super) struct PollFn<F>(pub(super) F);
+
+impl<F> Unpin for PollFn<F> {}
+
+impl<T, F> Future for PollFn<F>
+where
+    F: FnMut(&mut Context<'_>) -> Poll<T>,
+{
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        (self.0)(cx)
+    }
+}
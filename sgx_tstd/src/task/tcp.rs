@@ -0,0 +1,229 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+
+use crate::fmt;
+use crate::io::{self, Read, Write};
+use crate::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use crate::os::unix::io::{AsRawFd, RawFd};
+
+use super::reactor::{Direction, Registration};
+
+/// A TCP stream whose reads and writes can be awaited on a
+/// [`LocalExecutor`](super::LocalExecutor).
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::task::{AsyncTcpStream, LocalExecutor};
+///
+/// let executor = LocalExecutor::new().unwrap();
+/// executor.block_on(async {
+///     let stream = AsyncTcpStream::connect("127.0.0.1:8080").await.unwrap();
+///     stream.write_all(b"ping").await.unwrap();
+/// }).unwrap();
+/// ```
+pub struct AsyncTcpStream {
+    // Declared first so the socket leaves the reactor before it is closed.
+    registration: Registration,
+    inner: TcpStream,
+}
+
+impl AsyncTcpStream {
+    /// Wraps a connected stream, switching it to non-blocking mode.
+    pub fn from_std(stream: TcpStream) -> io::Result<AsyncTcpStream> {
+        stream.set_nonblocking(true)?;
+        Ok(AsyncTcpStream { registration: Registration::new(stream.as_raw_fd()), inner: stream })
+    }
+
+    /// Opens a TCP connection to a remote host.
+    ///
+    /// Each address `addr` resolves to is tried in turn, and the error of the
+    /// last attempt is returned if none succeeds. Host name resolution itself
+    /// still blocks.
+    pub async fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<AsyncTcpStream> {
+        let mut last_err = None;
+        for addr in addr.to_socket_addrs()? {
+            match AsyncTcpStream::connect_addr(&addr).await {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| {
+            io::Error::new_const(
+                io::ErrorKind::InvalidInput,
+                &"could not resolve to any addresses",
+            )
+        }))
+    }
+
+    async fn connect_addr(addr: &SocketAddr) -> io::Result<AsyncTcpStream> {
+        let stream = match addr {
+            SocketAddr::V4(..) => TcpStream::new_v4()?,
+            SocketAddr::V6(..) => TcpStream::new_v6()?,
+        };
+        let stream = AsyncTcpStream::from_std(stream)?;
+        match stream.inner.connect_socket(addr) {
+            Ok(()) => return Ok(stream),
+            Err(ref e) if e.raw_os_error() == Some(sgx_libc::EINPROGRESS) => {}
+            Err(e) => return Err(e),
+        }
+        // The connection is established, or has failed, once the socket
+        // becomes writable.
+        stream
+            .registration
+            .io(Direction::Write, || match stream.inner.take_error()? {
+                Some(e) => Err(e),
+                None => match stream.inner.peer_addr() {
+                    Ok(_) => Ok(()),
+                    Err(ref e) if e.kind() == io::ErrorKind::NotConnected => {
+                        Err(io::Error::from(io::ErrorKind::WouldBlock))
+                    }
+                    Err(e) => Err(e),
+                },
+            })
+            .await?;
+        Ok(stream)
+    }
+
+    /// Reads into `buf`, waiting until data is available.
+    ///
+    /// Returns `Ok(0)` once the peer has shut down its side of the stream.
+    pub async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.registration.io(Direction::Read, || (&self.inner).read(buf)).await
+    }
+
+    /// Reads exactly `buf.len()` bytes.
+    pub async fn read_exact(&self, mut buf: &mut [u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.read(buf).await? {
+                0 => {
+                    return Err(io::Error::new_const(
+                        io::ErrorKind::UnexpectedEof,
+                        &"failed to fill whole buffer",
+                    ));
+                }
+                n => buf = &mut buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes from `buf`, waiting until the socket can accept data.
+    pub async fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        self.registration.io(Direction::Write, || (&self.inner).write(buf)).await
+    }
+
+    /// Writes all of `buf`.
+    pub async fn write_all(&self, mut buf: &[u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf).await? {
+                0 => {
+                    return Err(io::Error::new_const(
+                        io::ErrorKind::WriteZero,
+                        &"failed to write whole buffer",
+                    ));
+                }
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the socket address of the remote peer.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.peer_addr()
+    }
+
+    /// Returns the socket address of the local half of this connection.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    /// Shuts down the read, write, or both halves of this connection.
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.inner.shutdown(how)
+    }
+
+    /// Returns a reference to the underlying non-blocking stream.
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.inner
+    }
+}
+
+impl AsRawFd for AsyncTcpStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl fmt::Debug for AsyncTcpStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AsyncTcpStream").field(&self.inner).finish()
+    }
+}
+
+/// A TCP listener whose connections can be awaited on a
+/// [`LocalExecutor`](super::LocalExecutor).
+pub struct AsyncTcpListener {
+    registration: Registration,
+    inner: TcpListener,
+}
+
+impl AsyncTcpListener {
+    /// Creates a listener bound to `addr`.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<AsyncTcpListener> {
+        AsyncTcpListener::from_std(TcpListener::bind(addr)?)
+    }
+
+    /// Wraps a bound listener, switching it to non-blocking mode.
+    pub fn from_std(listener: TcpListener) -> io::Result<AsyncTcpListener> {
+        listener.set_nonblocking(true)?;
+        Ok(AsyncTcpListener {
+            registration: Registration::new(listener.as_raw_fd()),
+            inner: listener,
+        })
+    }
+
+    /// Waits for a new connection.
+    pub async fn accept(&self) -> io::Result<(AsyncTcpStream, SocketAddr)> {
+        let (stream, addr) = self.registration.io(Direction::Read, || self.inner.accept()).await?;
+        Ok((AsyncTcpStream::from_std(stream)?, addr))
+    }
+
+    /// Returns the local socket address of this listener.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    /// Returns a reference to the underlying non-blocking listener.
+    pub fn get_ref(&self) -> &TcpListener {
+        &self.inner
+    }
+}
+
+impl AsRawFd for AsyncTcpListener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl fmt::Debug for AsyncTcpListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AsyncTcpListener").field(&self.inner).finish()
+    }
+}
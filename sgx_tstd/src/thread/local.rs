@@ -19,6 +19,8 @@
 
 use crate::error::Error;
 use crate::fmt;
+#[cfg(feature = "thread")]
+use crate::thread::{self, SgxThreadPolicy};
 
 /// A thread local storage key which owns its contents.
 ///
@@ -219,6 +221,80 @@ impl fmt::Display for AccessError {
 
 impl Error for AccessError {}
 
+/// Registers `dtor` to be called with `t` when the current thread exits.
+///
+/// Needs the `thread` feature.
+///
+/// This is the hook behind the destructors of [`thread_local!`] values, and
+/// lets foreign code, such as C++ `thread_local` objects, tie the lifetime of
+/// its own per-thread data to the enclave thread.
+///
+/// # Ordering
+///
+/// The destructors of a thread run in reverse order of their registration.
+/// A [`thread_local!`] value registers its destructor on first use, so values
+/// are destroyed in reverse order of initialization, interleaved with the
+/// destructors registered by this function. Destructors registered while the
+/// destructors run, for example by initializing another thread local, form a
+/// new batch that runs next, again in reverse order. A thread local cannot
+/// be initialized again once its destructor has run.
+///
+/// # When destructors run
+///
+/// * For a thread spawned inside the enclave, when the thread exits.
+/// * For a host thread that entered through an ECALL, when the host tears it
+///   down with the `t_thread_exit_ecall` ECALL of `sgx_tstd.edl`
+///   (`SgxEnclave::exit_thread` in `sgx_urts`). The host must issue it as the
+///   last, non-nested ECALL of the thread. Afterwards the TCS may serve a new
+///   host thread, which starts with fresh thread locals.
+///
+/// Destructors never run if the host thread exits without the teardown ECALL,
+/// or if the enclave is destroyed first.
+///
+/// # Errors
+///
+/// Under the Unbound TCS policy the TLS does not outlive an ECALL, and an
+/// error is returned.
+///
+/// # Safety
+///
+/// `dtor` must be safe to call with `t` on this thread when it exits, and `t`
+/// must stay valid until then.
+#[cfg(feature = "thread")]
+pub unsafe fn register_dtor(
+    t: *mut u8,
+    dtor: unsafe extern "C" fn(*mut u8),
+) -> Result<(), AccessError> {
+    if thread::thread_policy() != SgxThreadPolicy::Bound {
+        return Err(AccessError::new(
+            "If TLS data needs to be destructed, TCS policy must be bound."
+        ));
+    }
+    crate::sys::thread_local_dtor::register_dtor(t, dtor);
+    Ok(())
+}
+
+/// Registers a closure to run when the current thread exits.
+///
+/// This is a safe wrapper around [`register_dtor`], with the same ordering
+/// and the same requirements.
+#[cfg(feature = "thread")]
+pub fn at_thread_exit<F: FnOnce() + 'static>(f: F) -> Result<(), AccessError> {
+    unsafe extern "C" fn run(ptr: *mut u8) {
+        let f = Box::from_raw(ptr as *mut Box<dyn FnOnce()>);
+        f();
+    }
+
+    let f: Box<Box<dyn FnOnce()>> = Box::new(Box::new(f));
+    let ptr = Box::into_raw(f) as *mut u8;
+    unsafe {
+        register_dtor(ptr, run).map_err(|e| {
+            drop(Box::from_raw(ptr as *mut Box<dyn FnOnce()>));
+            e
+        })
+    }
+}
+
 impl<T: 'static> LocalKey<T> {
     pub const unsafe fn new(inner: unsafe fn() -> Result<&'static T, AccessError>) -> LocalKey<T> {
         LocalKey { inner }
@@ -400,6 +476,13 @@ if #[cfg(feature = "thread")] {
         #[thread_local]
         static pthread_info_tls: pthread_info;
     }
+
+    // Whether the current thread was created by pthread_create, which we can
+    // tell from the m_pthread member of the pthread_info TLS of the pthread
+    // library in intel sgx sdk.
+    pub(crate) fn is_pthread() -> bool {
+        unsafe { !pthread_info_tls.m_pthread.is_null() }
+    }
 }
 } // cfg_if!
 
@@ -411,6 +494,7 @@ pub mod fast {
     use crate::fmt;
     use crate::mem;
     use crate::sys::thread_local_dtor::register_dtor;
+    use crate::sys_common::thread_local_dtor;
     use crate::thread::{self, SgxThreadPolicy};
 
     #[derive(Copy, Clone)]
@@ -439,6 +523,9 @@ pub mod fast {
         // Metadata to keep track of the state of the destructor. Remember that
         // this variable is thread-local, not global.
         dtor_state: Cell<DtorState>,
+
+        // The teardown generation in which the destructor ran.
+        dtor_gen: Cell<usize>,
     }
 
     impl<T> fmt::Debug for Key<T> {
@@ -449,7 +536,11 @@ pub mod fast {
 
     impl<T> Key<T> {
         pub const fn new() -> Key<T> {
-            Key { inner: LazyKeyInner::new(), dtor_state: Cell::new(DtorState::Unregistered) }
+            Key {
+                inner: LazyKeyInner::new(),
+                dtor_state: Cell::new(DtorState::Unregistered),
+                dtor_gen: Cell::new(0),
+            }
         }
 
         pub unsafe fn get<F: FnOnce() -> T>(&self, init: F) -> Result<&'static T, AccessError> {
@@ -481,24 +572,15 @@ pub mod fast {
                 ));
             }
 
-            if !super::pthread_info_tls.m_pthread.is_null() {
-                // Note:
-                // If the current thread was created by pthread_create, we should call
-                // the try_register_dtor function. You can know whether the current thread has
-                // been created by pthread_create() through the m_thread member of pthread_info
-                // (thread local storage) of pthread library in intel sgx sdk.
-                //
-                // Destructor will only be called when a thread created by pthread_create exits,
-                // because sys_common::thread_local::StaticKey does not call pthread_key_delete
-                // to trigger the destructor.
-                if !mem::needs_drop::<T>() || self.try_register_dtor() {
+            // Note:
+            // The destructor runs when a thread created by pthread_create exits,
+            // or when the host tears down a thread that entered through an ECALL,
+            // see `thread::register_dtor`.
+            if !mem::needs_drop::<T>() || self.try_register_dtor() {
                 // SAFETY: See comment above (his function doc).
-                    Ok(self.inner.initialize(init))
-                } else {
-                    Err(AccessError::new("Failed to register destructor."))
-                }
-            } else {
                 Ok(self.inner.initialize(init))
+            } else {
+                Err(AccessError::new("Failed to register destructor."))
             }
         }
 
@@ -507,6 +589,15 @@ pub mod fast {
         // other thread_local's, or it is being recursively initialized.
         unsafe fn try_register_dtor(&self) -> bool {
             match self.dtor_state.get() {
+                // A thread local destroyed by an earlier teardown of the host
+                // thread is treated as never initialized once the TCS serves
+                // a new host thread.
+                DtorState::RunningOrHasRun
+                    if self.dtor_gen.get() != thread_local_dtor::generation() =>
+                {
+                    self.dtor_state.set(DtorState::Unregistered);
+                    self.try_register_dtor()
+                }
                 DtorState::Unregistered => {
                     // SAFETY: dtor registration happens before initialization.
                     // Passing `self` as a pointer while using `destroy_value<T>`
@@ -541,6 +632,7 @@ pub mod fast {
         // which will now fail, and return `None`.
         let value = (*ptr).inner.take();
         (*ptr).dtor_state.set(DtorState::RunningOrHasRun);
+        (*ptr).dtor_gen.set(thread_local_dtor::generation());
         drop(value);
     }
 }
@@ -48,6 +48,9 @@ use sgx_trts::enclave::*;
 
 pub use sgx_trts::enclave::SgxThreadPolicy;
 
+#[cfg(feature = "thread")]
+pub mod pool;
+
 ////////////////////////////////////////////////////////////////////////////////
 // Thread-local storage
 ////////////////////////////////////////////////////////////////////////////////
@@ -56,6 +59,10 @@ pub use sgx_trts::enclave::SgxThreadPolicy;
 mod local;
 
 pub use self::local::{AccessError, LocalKey};
+#[cfg(feature = "thread")]
+pub use self::local::{at_thread_exit, register_dtor};
+#[cfg(feature = "thread")]
+pub(crate) use self::local::is_pthread;
 
 pub use self::local::statik::Key as __StaticLocalKeyInner;
 #[cfg(feature = "thread")]
@@ -80,6 +87,7 @@ pub const fn require_unstable_const_init_thread_local() {}
 /// The two configurations available are:
 ///
 /// - [`name`]: specifies an [associated name for the thread][naming-threads]
+/// - [`stack_size`]: specifies the desired stack size for the thread
 ///
 /// The [`spawn`] method will take ownership of the builder and create an
 /// [`io::Result`] to the thread handle with the given configuration.
@@ -117,6 +125,8 @@ pub const fn require_unstable_const_init_thread_local() {}
 pub struct Builder {
     // A name for the thread-to-be, for identification in panic messages
     name: Option<String>,
+    // The size of the stack the thread-to-be needs
+    stack_size: Option<usize>,
 }
 
 #[cfg(feature = "thread")]
@@ -143,7 +153,7 @@ impl Builder {
         if rsgx_get_thread_policy() != SgxThreadPolicy::Bound {
             panic!("The sgx thread policy must be Bound!");
         }
-        Builder { name: None }
+        Builder { name: None, stack_size: None }
     }
 
     /// Names the thread-to-be. Currently the name is used for identification
@@ -175,6 +185,30 @@ impl Builder {
         self
     }
 
+    /// Sets the size of the stack (in bytes) for the new thread.
+    ///
+    /// The stack of every enclave thread is set by `StackMaxSize` in the
+    /// enclave configuration and cannot be changed at runtime. Instead,
+    /// [`spawn`](Builder::spawn) checks that the configured stack is at least
+    /// `size` bytes, and returns an error rather than start a thread that
+    /// would overflow it. With EDMM, the stack is committed as it grows, up to
+    /// the same limit.
+    ///
+    /// Without this, the thread runs on the configured stack, whatever its
+    /// size.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::thread;
+    ///
+    /// let builder = thread::Builder::new().stack_size(32 * 1024);
+    /// ```
+    pub fn stack_size(mut self, size: usize) -> Builder {
+        self.stack_size = Some(size);
+        self
+    }
+
     /// Spawns a new thread by taking ownership of the `Builder`, and returns an
     /// [`io::Result`] to its [`JoinHandle`].
     ///
@@ -191,7 +225,12 @@ impl Builder {
     /// [`io::Result`] to capture any failure to create the thread at
     /// the OS level.
     ///
+    /// Returns an [`InvalidInput`] error if a [`stack_size`] was set that is
+    /// larger than the stack of the enclave's threads.
+    ///
     /// [`io::Result`]: crate::io::Result
+    /// [`InvalidInput`]: io::ErrorKind::InvalidInput
+    /// [`stack_size`]: Builder::stack_size
     ///
     /// # Panics
     ///
@@ -283,7 +322,11 @@ impl Builder {
         F: Send + 'a,
         T: Send + 'a,
     {
-        let Builder { name } = self;
+        let Builder { name, stack_size } = self;
+
+        if let Some(size) = stack_size {
+            imp::check_stack_size(size)?;
+        }
 
         let my_thread = SgxThread::new(name);
         let their_thread = my_thread.clone();
@@ -605,6 +648,11 @@ pub fn sleep_ms(ms: u32) {
 /// If the intention is to yield the current time-slice you may want to use
 /// [`yield_now`] instead.
 ///
+/// Inside the enclave, sleeping threads share one host timer rather than
+/// each making a [`nanosleep`] OCALL: only the thread with the earliest
+/// deadline waits with a timeout, and it wakes the others whose deadline
+/// passed with a single OCALL.
+///
 /// [`nanosleep`]: https://linux.die.net/man/2/nanosleep
 /// [`Sleep`]: https://docs.microsoft.com/en-us/windows/win32/api/synchapi/nf-synchapi-sleep
 ///
@@ -622,7 +670,7 @@ pub fn sleep_ms(ms: u32) {
 /// ```
 #[cfg(feature = "thread")]
 pub fn sleep(dur: Duration) {
-    imp::Thread::sleep(dur)
+    crate::sys::timer::sleep(dur)
 }
 
 /// Blocks unless or until the current thread's token is made available.
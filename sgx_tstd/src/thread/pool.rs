@@ -0,0 +1,352 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! A pool of enclave threads that respects the TCS budget.
+//!
+//! Every thread spawned inside the enclave occupies a TCS for its whole
+//! lifetime, and an enclave only has as many TCS as its configuration sets
+//! (`TCSNum`, or `TCSMaxNum` with EDMM). A server that spawns a thread per
+//! request runs out of them quickly, and `thread::spawn` then fails.
+//!
+//! [`ThreadPool`] runs jobs on a bounded set of worker threads instead. Its
+//! size is derived from the TCS budget of the enclave, workers are started on
+//! demand, and jobs are queued while all of them are busy.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::thread::pool::ThreadPool;
+//!
+//! let pool = ThreadPool::new().unwrap();
+//! let job = pool.spawn(|| 6 * 7);
+//! assert_eq!(job.join().unwrap(), 42);
+//! ```
+
+use crate::collections::VecDeque;
+use crate::fmt;
+use crate::io;
+use crate::panic::{self, AssertUnwindSafe};
+use crate::string::String;
+use crate::sync::{Arc, SgxCondvar, SgxMutex};
+use crate::thread::{self, JoinHandle};
+use crate::vec::Vec;
+
+use sgx_trts::enclave::rsgx_get_tcs_max_num;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+struct State {
+    queue: VecDeque<Job>,
+    workers: usize,
+    idle: usize,
+    active: usize,
+    shutdown: bool,
+}
+
+struct Shared {
+    state: SgxMutex<State>,
+    // Signalled when a job is queued or the pool shuts down.
+    job_ready: SgxCondvar,
+    // Signalled when the queue drains and no job is running.
+    all_done: SgxCondvar,
+}
+
+/// Configuration for a [`ThreadPool`].
+///
+/// # Examples
+///
+/// ```
+/// use std::thread::pool;
+///
+/// let pool = pool::Builder::new()
+///     .name("worker".into())
+///     .num_threads(2)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Builder {
+    num_threads: Option<usize>,
+    reserved_tcs: usize,
+    name: Option<String>,
+    stack_size: Option<usize>,
+}
+
+impl Default for Builder {
+    fn default() -> Builder {
+        Builder::new()
+    }
+}
+
+impl Builder {
+    /// Creates the default configuration: as many workers as the TCS budget
+    /// allows, keeping one TCS free for the ECALL that creates the pool.
+    pub fn new() -> Builder {
+        Builder { num_threads: None, reserved_tcs: 1, name: None, stack_size: None }
+    }
+
+    /// Sets the maximum number of worker threads.
+    ///
+    /// [`build`](Builder::build) fails if this exceeds the TCS budget.
+    pub fn num_threads(mut self, num_threads: usize) -> Builder {
+        self.num_threads = Some(num_threads);
+        self
+    }
+
+    /// Sets how many TCS are left out of the budget of the pool, for host
+    /// threads that enter the enclave through ECALLs and for threads spawned
+    /// outside the pool. Defaults to 1.
+    pub fn reserved_tcs(mut self, reserved: usize) -> Builder {
+        self.reserved_tcs = reserved;
+        self
+    }
+
+    /// Names the worker threads. Each worker is named `name-N`.
+    pub fn name(mut self, name: String) -> Builder {
+        self.name = Some(name);
+        self
+    }
+
+    /// Sets the stack size of the worker threads, see
+    /// [`thread::Builder::stack_size`].
+    pub fn stack_size(mut self, size: usize) -> Builder {
+        self.stack_size = Some(size);
+        self
+    }
+
+    /// Creates the pool and starts its first worker.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the TCS budget is empty
+    /// or smaller than the requested number of threads, and with the error of
+    /// [`thread::Builder::spawn`] if the first worker cannot be started.
+    pub fn build(self) -> io::Result<ThreadPool> {
+        let budget = (rsgx_get_tcs_max_num() as usize).saturating_sub(self.reserved_tcs);
+        let max_threads = match self.num_threads {
+            Some(n) if n > budget => {
+                return Err(io::Error::new_const(
+                    io::ErrorKind::InvalidInput,
+                    &"requested number of threads exceeds the TCS budget of the enclave",
+                ));
+            }
+            Some(n) => n,
+            None => budget,
+        };
+        if max_threads == 0 {
+            return Err(io::Error::new_const(
+                io::ErrorKind::InvalidInput,
+                &"thread pool has no TCS to run on",
+            ));
+        }
+
+        let pool = ThreadPool {
+            shared: Arc::new(Shared {
+                state: SgxMutex::new(State {
+                    queue: VecDeque::new(),
+                    workers: 0,
+                    idle: 0,
+                    active: 0,
+                    shutdown: false,
+                }),
+                job_ready: SgxCondvar::new(),
+                all_done: SgxCondvar::new(),
+            }),
+            handles: SgxMutex::new(Vec::new()),
+            max_threads,
+            name: self.name,
+            stack_size: self.stack_size,
+        };
+        {
+            let mut state = pool.shared.state.lock().unwrap();
+            pool.start_worker(&mut state)?;
+        }
+        Ok(pool)
+    }
+}
+
+/// A pool of worker threads sized from the TCS budget of the enclave.
+///
+/// Jobs are run in the order they are spawned. A job that panics does not
+/// take its worker down: the panic is caught and handed to whoever joins the
+/// job through its [`JobHandle`].
+///
+/// Dropping the pool waits for the queued jobs to finish and for the workers
+/// to exit, which frees their TCS.
+pub struct ThreadPool {
+    shared: Arc<Shared>,
+    handles: SgxMutex<Vec<JoinHandle<()>>>,
+    max_threads: usize,
+    name: Option<String>,
+    stack_size: Option<usize>,
+}
+
+impl ThreadPool {
+    /// Creates a pool with the default [`Builder`] configuration.
+    pub fn new() -> io::Result<ThreadPool> {
+        Builder::new().build()
+    }
+
+    /// Runs `f` on a worker thread, and returns a handle to its result.
+    ///
+    /// A new worker is started if all workers are busy and the pool is below
+    /// its maximum size. Otherwise, or if no TCS is free to start one, the
+    /// job waits in the queue until a worker is available.
+    pub fn spawn<F, T>(&self, f: F) -> JobHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let packet: Arc<Packet<T>> =
+            Arc::new(Packet { result: SgxMutex::new(None), done: SgxCondvar::new() });
+        let their_packet = packet.clone();
+        let job: Job = Box::new(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(f));
+            *their_packet.result.lock().unwrap() = Some(result);
+            their_packet.done.notify_all();
+        });
+
+        let mut state = self.shared.state.lock().unwrap();
+        state.queue.push_back(job);
+        if state.idle < state.queue.len() && state.workers < self.max_threads {
+            // Some worker is still running, so the job runs eventually even
+            // if no TCS is free right now.
+            let _ = self.start_worker(&mut state);
+        }
+        drop(state);
+        self.shared.job_ready.notify_one();
+
+        JobHandle { packet }
+    }
+
+    /// Blocks until every job spawned so far has finished.
+    pub fn join(&self) {
+        let mut state = self.shared.state.lock().unwrap();
+        while !state.queue.is_empty() || state.active > 0 {
+            state = self.shared.all_done.wait(state).unwrap();
+        }
+    }
+
+    /// Returns the maximum number of worker threads.
+    pub fn max_threads(&self) -> usize {
+        self.max_threads
+    }
+
+    /// Returns the number of worker threads started so far.
+    pub fn num_threads(&self) -> usize {
+        self.shared.state.lock().unwrap().workers
+    }
+
+    /// Returns the number of jobs waiting for a worker.
+    pub fn queued_jobs(&self) -> usize {
+        self.shared.state.lock().unwrap().queue.len()
+    }
+
+    fn start_worker(&self, state: &mut State) -> io::Result<()> {
+        let mut builder = thread::Builder::new();
+        if let Some(ref name) = self.name {
+            builder = builder.name(format!("{}-{}", name, state.workers));
+        }
+        if let Some(size) = self.stack_size {
+            builder = builder.stack_size(size);
+        }
+        let shared = self.shared.clone();
+        let handle = builder.spawn(move || work(&shared))?;
+        state.workers += 1;
+        self.handles.lock().unwrap().push(handle);
+        Ok(())
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().shutdown = true;
+        self.shared.job_ready.notify_all();
+        for handle in self.handles.get_mut().unwrap().drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl fmt::Debug for ThreadPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThreadPool")
+            .field("max_threads", &self.max_threads)
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+fn work(shared: &Shared) {
+    let mut state = shared.state.lock().unwrap();
+    loop {
+        if let Some(job) = state.queue.pop_front() {
+            state.active += 1;
+            drop(state);
+            job();
+            state = shared.state.lock().unwrap();
+            state.active -= 1;
+            if state.active == 0 && state.queue.is_empty() {
+                shared.all_done.notify_all();
+            }
+        } else if state.shutdown {
+            return;
+        } else {
+            state.idle += 1;
+            state = shared.job_ready.wait(state).unwrap();
+            state.idle -= 1;
+        }
+    }
+}
+
+struct Packet<T> {
+    result: SgxMutex<Option<thread::Result<T>>>,
+    done: SgxCondvar,
+}
+
+/// An owned permission to wait for a job of a [`ThreadPool`] and get its
+/// result.
+///
+/// Dropping the handle discards the result; the job still runs.
+pub struct JobHandle<T> {
+    packet: Arc<Packet<T>>,
+}
+
+impl<T> JobHandle<T> {
+    /// Waits for the job to finish.
+    ///
+    /// If the job panicked, [`Err`] is returned with the panic payload, as
+    /// with [`thread::JoinHandle::join`].
+    pub fn join(self) -> thread::Result<T> {
+        let mut result = self.packet.result.lock().unwrap();
+        loop {
+            if let Some(result) = result.take() {
+                return result;
+            }
+            result = self.packet.done.wait(result).unwrap();
+        }
+    }
+
+    /// Returns whether the job has finished.
+    pub fn is_finished(&self) -> bool {
+        self.packet.result.lock().unwrap().is_some()
+    }
+}
+
+impl<T> fmt::Debug for JobHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JobHandle").finish_non_exhaustive()
+    }
+}
@@ -48,16 +48,29 @@
 
 #![allow(clippy::needless_doctest_main)]
 
+#[cfg(any(feature = "untrusted_time", feature = "trusted_time"))]
+mod cached;
 mod monotonic;
+mod trusted;
+mod wheel;
 
 use crate::error::Error;
 use crate::fmt;
+use crate::io;
 use crate::ops::{Add, AddAssign, Sub, SubAssign};
 use crate::sys::time;
+use crate::sys::tsc;
 use crate::sys_common::FromInner;
 
 pub use core::time::Duration;
 
+#[cfg(any(feature = "untrusted_time", feature = "trusted_time"))]
+pub use self::cached::cached_now;
+pub use self::trusted::{
+    set_trusted_time, trusted_now, AttestedTime, TimestampVerifier, TrustedTime,
+};
+pub use self::wheel::{TimerId, TimerWheel};
+
 /// A measurement of a monotonically nondecreasing clock.
 /// Opaque and useful only with [`Duration`].
 ///
@@ -131,6 +144,12 @@ pub use core::time::Duration;
 ///
 /// **Disclaimer:** These system calls might change over time.
 ///
+/// With the `tsc_time` feature, an enclave that may execute RDTSC reads the
+/// time stamp counter instead, and `now()` does not leave the enclave. The
+/// counter is anchored to the host's monotonic clock on first use and, unless
+/// the frequency was pinned with [`set_tsc_frequency`] before, calibrated
+/// against it for about 10ms. Other enclaves keep using the OCALL.
+///
 /// > Note: mathematical operations like [`add`] may panic if the underlying
 /// > structure cannot represent the new point in time.
 ///
@@ -466,6 +485,12 @@ impl SystemTime {
 
     /// Returns the system time corresponding to "now".
     ///
+    /// With the `trusted_time` feature, the time comes from the provider
+    /// installed with [`set_trusted_time`] whenever it can vouch for it, and
+    /// from the host otherwise, for example while no provider is installed.
+    /// Callers that must not fall back to the host use [`trusted_now`], which
+    /// reports the failure instead.
+    ///
     /// # Examples
     ///
     /// ```
@@ -474,9 +499,9 @@ impl SystemTime {
     ///
     /// let sys_time = SystemTime::now();
     /// ```
-    #[cfg(feature = "untrusted_time")]
+    #[cfg(any(feature = "untrusted_time", feature = "trusted_time"))]
     pub fn now() -> SystemTime {
-        SystemTime::_now()
+        wall_clock()
     }
 
     pub(crate) fn _now() -> SystemTime {
@@ -669,4 +694,50 @@ impl FromInner<time::SystemTime> for SystemTime {
     fn from_inner(time: time::SystemTime) -> SystemTime {
         SystemTime(time)
     }
-}
\ No newline at end of file
+}
+
+// The clock behind `SystemTime::now`.
+#[cfg(any(feature = "untrusted_time", feature = "trusted_time"))]
+fn wall_clock() -> SystemTime {
+    #[cfg(feature = "trusted_time")]
+    {
+        trusted_now().unwrap_or_else(|_| SystemTime::_now())
+    }
+    #[cfg(not(feature = "trusted_time"))]
+    {
+        SystemTime::_now()
+    }
+}
+
+/// Pins the frequency of the time stamp counter, in Hz, so that timeouts can
+/// be measured inside the enclave.
+///
+/// Once set, timed waits such as [`SgxCondvar::wait_timeout`] check the
+/// host's claim that a timeout elapsed against the TSC, so the host can
+/// delay them but not end them early.
+///
+/// The frequency must come from a trusted source, for example a value built
+/// into the enclave or provisioned after attestation, and not from CPUID,
+/// which the host answers. A value above the real frequency only makes
+/// timeouts longer; one below it makes them shorter. Note that privileged
+/// software can still offset or scale the TSC.
+///
+/// # Errors
+///
+/// Fails with [`io::ErrorKind::Unsupported`] if RDTSC can't be executed
+/// inside the enclave, which needs SGX2, and with
+/// [`io::ErrorKind::InvalidInput`] if `hz` is zero.
+///
+/// [`SgxCondvar::wait_timeout`]: crate::sync::SgxCondvar::wait_timeout
+pub fn set_tsc_frequency(hz: u64) -> io::Result<()> {
+    if !tsc::is_supported() {
+        return Err(io::Error::new_const(
+            io::ErrorKind::Unsupported,
+            &"the TSC can't be read inside this enclave",
+        ));
+    }
+    if !tsc::set_frequency(hz) {
+        return Err(io::Error::new_const(io::ErrorKind::InvalidInput, &"TSC frequency is zero"));
+    }
+    Ok(())
+}
@@ -0,0 +1,94 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! A wall clock read from the host only every so often.
+
+use crate::sync::atomic::{AtomicU64, Ordering};
+use crate::sync::fast::RwLock;
+use crate::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Copy, Clone)]
+struct Cache {
+    fetched: SystemTime,
+    at: Instant,
+}
+
+static CACHE: RwLock<Option<Cache>> = RwLock::new(None);
+
+// The latest time returned, in nanoseconds since the epoch.
+static LAST: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the system time, asking the host for it at most once every
+/// `max_staleness`.
+///
+/// The time fetched from the host is cached inside the enclave and carried
+/// forward with [`Instant`], so the result stays close to
+/// [`SystemTime::now`] while the cache is fresh. Once it is older than
+/// `max_staleness`, the next call fetches the time again. Only the age of the
+/// cache is measured without leaving the enclave, so this saves OCALLs when
+/// the `tsc_time` feature lets [`Instant`] read the TSC.
+///
+/// The result never goes backwards, even if the host's clock does: it is at
+/// least the latest time returned by any thread.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::{self, Duration};
+///
+/// let now = time::cached_now(Duration::from_millis(100));
+/// assert!(time::cached_now(Duration::from_millis(100)) >= now);
+/// ```
+pub fn cached_now(max_staleness: Duration) -> SystemTime {
+    let now = Instant::_now();
+    let cached = *CACHE.read();
+    let time = match cached {
+        Some(cache) if now.saturating_duration_since(cache.at) <= max_staleness => {
+            cache.fetched + now.saturating_duration_since(cache.at)
+        }
+        _ => refresh(now, max_staleness),
+    };
+    monotonize(time)
+}
+
+#[cold]
+fn refresh(now: Instant, max_staleness: Duration) -> SystemTime {
+    let mut cache = CACHE.write();
+    // Another thread may have refreshed the cache while we waited.
+    if let Some(cache) = *cache {
+        if now.saturating_duration_since(cache.at) <= max_staleness {
+            return cache.fetched + now.saturating_duration_since(cache.at);
+        }
+    }
+    let fetched = super::wall_clock();
+    *cache = Some(Cache { fetched, at: Instant::_now() });
+    fetched
+}
+
+fn monotonize(time: SystemTime) -> SystemTime {
+    let nanos = match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_nanos().min(u64::MAX as u128) as u64,
+        // Times before the epoch are left alone.
+        Err(_) => return time,
+    };
+    let last = LAST.fetch_max(nanos, Ordering::Relaxed);
+    if last > nanos {
+        UNIX_EPOCH + Duration::from_nanos(last)
+    } else {
+        time
+    }
+}
@@ -0,0 +1,217 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Wall-clock time the enclave does not have to take from the host on trust.
+
+use crate::boxed::Box;
+use crate::fmt;
+use crate::io;
+use crate::sync::{OnceLock, SgxMutex as Mutex};
+use crate::time::{Duration, Instant, SystemTime};
+
+/// A source of the current wall-clock time that the enclave can vouch for.
+///
+/// Once installed with [`set_trusted_time`], the provider answers
+/// [`trusted_now`]. With the `trusted_time` feature it also answers
+/// [`SystemTime::now`], which falls back to the host's clock when the
+/// provider fails.
+pub trait TrustedTime: Send + Sync {
+    /// Returns the current time, or an error if it can't be established.
+    fn now(&self) -> io::Result<SystemTime>;
+}
+
+static PROVIDER: OnceLock<Box<dyn TrustedTime>> = OnceLock::new();
+
+/// Installs the provider of trusted time for the whole enclave.
+///
+/// # Errors
+///
+/// Fails with [`io::ErrorKind::AlreadyExists`] if a provider was already
+/// installed; it can't be replaced.
+pub fn set_trusted_time<T: TrustedTime + 'static>(provider: T) -> io::Result<()> {
+    PROVIDER.set(Box::new(provider)).map_err(|_| {
+        io::Error::new_const(io::ErrorKind::AlreadyExists, &"a trusted time provider is installed")
+    })
+}
+
+/// Returns the current time from the provider installed with
+/// [`set_trusted_time`].
+///
+/// # Errors
+///
+/// Fails with [`io::ErrorKind::NotFound`] if no provider is installed, and
+/// otherwise with the provider's error.
+pub fn trusted_now() -> io::Result<SystemTime> {
+    match PROVIDER.get() {
+        Some(provider) => provider.now(),
+        None => Err(io::Error::new_const(
+            io::ErrorKind::NotFound,
+            &"no trusted time provider is installed",
+        )),
+    }
+}
+
+/// Checks a signed timestamp, such as a Roughtime response or the time
+/// learned over an NTS session terminated inside the enclave.
+pub trait TimestampVerifier: Send + Sync {
+    /// Verifies the signature on `response`, and returns the time it vouches
+    /// for together with its uncertainty radius.
+    ///
+    /// The verifier is responsible for freshness, typically by checking that
+    /// the response signs a nonce the enclave chose for the request.
+    fn verify(&self, response: &[u8]) -> io::Result<(SystemTime, Duration)>;
+}
+
+#[derive(Copy, Clone)]
+struct Anchor {
+    time: SystemTime,
+    radius: Duration,
+    at: Instant,
+}
+
+impl Anchor {
+    // The time the anchor vouches for now, and the uncertainty around it.
+    fn project(&self) -> (SystemTime, Duration) {
+        let elapsed = Instant::_now().saturating_duration_since(self.at);
+        (self.time + elapsed, self.radius)
+    }
+}
+
+/// A [`TrustedTime`] that accepts the host's clock only while it agrees with
+/// the last signed timestamp.
+///
+/// The application feeds it signed timestamps with [`update`]. The time one
+/// vouches for is carried forward with the monotonic clock, and
+/// [`now`](TrustedTime::now) returns the host's time only if it is within the
+/// timestamp's radius plus `tolerance` of that projection. Once the last
+/// timestamp is older than `max_age`, a new one is required.
+///
+/// The monotonic clock comes from the host as well, unless the `tsc_time`
+/// feature lets the enclave read the TSC; `max_age` bounds how far the host
+/// can skew it.
+///
+/// [`update`]: AttestedTime::update
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io;
+/// use std::time::{self, AttestedTime, Duration, SystemTime, TimestampVerifier};
+///
+/// struct Roughtime;
+///
+/// impl TimestampVerifier for Roughtime {
+///     fn verify(&self, response: &[u8]) -> io::Result<(SystemTime, Duration)> {
+///         // Check the server's signature and the nonce, then decode the
+///         // midpoint and radius.
+///         # unimplemented!()
+///     }
+/// }
+///
+/// # fn main() -> io::Result<()> {
+/// # let response = Vec::new();
+/// let clock = AttestedTime::new(Roughtime, Duration::from_secs(1), Duration::from_secs(3600));
+/// clock.update(&response)?;
+/// time::set_trusted_time(clock)?;
+/// let now = time::trusted_now()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct AttestedTime<V> {
+    verifier: V,
+    tolerance: Duration,
+    max_age: Duration,
+    anchor: Mutex<Option<Anchor>>,
+}
+
+impl<V: TimestampVerifier> AttestedTime<V> {
+    /// Creates a provider that checks signed timestamps with `verifier`.
+    ///
+    /// `tolerance` is how far, beyond the timestamp's own radius, the host's
+    /// clock may be off, and `max_age` how long a timestamp stays usable.
+    pub fn new(verifier: V, tolerance: Duration, max_age: Duration) -> AttestedTime<V> {
+        AttestedTime { verifier, tolerance, max_age, anchor: Mutex::new(None) }
+    }
+
+    /// Verifies a signed timestamp and makes it the reference for the host's
+    /// clock.
+    ///
+    /// # Errors
+    ///
+    /// Fails with the verifier's error, or with
+    /// [`io::ErrorKind::InvalidData`] if the timestamp is earlier than the
+    /// current reference allows, as a replayed response would be.
+    pub fn update(&self, response: &[u8]) -> io::Result<()> {
+        let (time, radius) = self.verifier.verify(response)?;
+        let mut anchor = self.anchor.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(ref current) = *anchor {
+            let (expected, current_radius) = current.project();
+            let slack = radius + current_radius + self.tolerance;
+            if time + slack < expected {
+                return Err(io::Error::new_const(
+                    io::ErrorKind::InvalidData,
+                    &"signed timestamp is older than the current one",
+                ));
+            }
+        }
+        *anchor = Some(Anchor { time, radius, at: Instant::_now() });
+        Ok(())
+    }
+}
+
+impl<V: TimestampVerifier> TrustedTime for AttestedTime<V> {
+    fn now(&self) -> io::Result<SystemTime> {
+        let anchor = match *self.anchor.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some(anchor) => anchor,
+            None => {
+                return Err(io::Error::new_const(
+                    io::ErrorKind::NotFound,
+                    &"no signed timestamp was provided",
+                ));
+            }
+        };
+        if Instant::_now().saturating_duration_since(anchor.at) > self.max_age {
+            return Err(io::Error::new_const(
+                io::ErrorKind::TimedOut,
+                &"the signed timestamp is too old",
+            ));
+        }
+
+        let (expected, radius) = anchor.project();
+        let host = SystemTime::_now();
+        let skew = match host.duration_since(expected) {
+            Ok(d) => d,
+            Err(e) => e.duration(),
+        };
+        if skew > radius + self.tolerance {
+            return Err(io::Error::new_const(
+                io::ErrorKind::InvalidData,
+                &"host time disagrees with the signed timestamp",
+            ));
+        }
+        Ok(host)
+    }
+}
+
+impl<V> fmt::Debug for AttestedTime<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AttestedTime")
+            .field("tolerance", &self.tolerance)
+            .field("max_age", &self.max_age)
+            .finish_non_exhaustive()
+    }
+}
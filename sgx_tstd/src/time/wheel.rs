@@ -0,0 +1,490 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! A hierarchical timer wheel.
+//!
+//! Deadlines are kept in ticks of one millisecond since the wheel was
+//! created. The wheel has `LEVELS` levels of `SLOTS` slots; a slot on level
+//! `n` spans `SLOTS^n` ticks, so a timer sits on the lowest level whose slot
+//! still tells its deadline apart from the current tick. When the wheel
+//! reaches a slot on a higher level, its timers either fire or move down to
+//! a finer level. Registering and cancelling a timer is O(1), and finding the
+//! next slot to process only scans one occupancy bitmap per level.
+
+use crate::boxed::Box;
+use crate::fmt;
+use crate::mem;
+use crate::sync::{PoisonError, SgxCondvar, SgxMutex, SgxMutexGuard};
+use crate::task::Waker;
+use crate::time::{Duration, Instant};
+use crate::vec::Vec;
+
+const SLOT_BITS: usize = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+const SLOT_MASK: u64 = SLOTS as u64 - 1;
+const LEVELS: usize = 6;
+
+// Ticks the wheel can tell apart, a little over two years. Timers further out
+// are parked on the last level and looked at again once it comes around.
+const MAX_TICKS: u64 = 1 << (SLOT_BITS * LEVELS);
+
+const NIL: usize = usize::MAX;
+
+/// Identifies a timer registered with a [`TimerWheel`].
+///
+/// Cancelling a timer that has already fired has no effect, even if its
+/// storage has since been reused by a later timer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TimerId(u64);
+
+impl TimerId {
+    fn new(index: usize, gen: u32) -> TimerId {
+        TimerId((u64::from(gen) << 32) | index as u64)
+    }
+
+    fn index(self) -> usize {
+        (self.0 & u64::from(u32::MAX)) as usize
+    }
+
+    fn gen(self) -> u32 {
+        (self.0 >> 32) as u32
+    }
+}
+
+enum Action {
+    Call(Box<dyn FnOnce() + Send>),
+    Wake(Waker),
+}
+
+impl Action {
+    fn fire(self) {
+        match self {
+            Action::Call(f) => f(),
+            Action::Wake(waker) => waker.wake(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Loc {
+    Free,
+    // Already due when registered, fired by the next `advance`.
+    Pending,
+    Slot(usize, usize),
+}
+
+struct Entry {
+    when: u64,
+    // Bumped every time the entry is freed, so that stale ids miss.
+    gen: u32,
+    action: Option<Action>,
+    loc: Loc,
+    prev: usize,
+    next: usize,
+}
+
+#[derive(Copy, Clone)]
+struct Level {
+    occupied: u64,
+    heads: [usize; SLOTS],
+}
+
+impl Level {
+    // Returns the first occupied slot at or after `now` and the tick it
+    // starts at.
+    fn next_slot(&self, level: usize, now: u64) -> Option<(usize, u64)> {
+        if self.occupied == 0 {
+            return None;
+        }
+        let slot_range = 1u64 << (SLOT_BITS * level);
+        let level_range = slot_range << SLOT_BITS;
+        let now_slot = ((now / slot_range) & SLOT_MASK) as u32;
+        let zeros = self.occupied.rotate_right(now_slot).trailing_zeros();
+        let slot = (zeros + now_slot) as usize % SLOTS;
+        let mut start = (now & !(level_range - 1)) + slot as u64 * slot_range;
+        // Slots behind the current one belong to the next turn of the level.
+        if start < now {
+            start += level_range;
+        }
+        Some((slot, start))
+    }
+}
+
+struct Inner {
+    // The tick the wheel has advanced to.
+    elapsed: u64,
+    levels: [Level; LEVELS],
+    pending: usize,
+    entries: Vec<Entry>,
+    free: Vec<usize>,
+    len: usize,
+    // The tick a thread in `run` sleeps until, `u64::MAX` if none does.
+    wakeup: u64,
+    shutdown: bool,
+}
+
+impl Inner {
+    fn head(&mut self, loc: Loc) -> &mut usize {
+        match loc {
+            Loc::Pending => &mut self.pending,
+            Loc::Slot(level, slot) => &mut self.levels[level].heads[slot],
+            Loc::Free => unreachable!(),
+        }
+    }
+
+    fn link(&mut self, index: usize, loc: Loc) {
+        let head = mem::replace(self.head(loc), index);
+        if head != NIL {
+            self.entries[head].prev = index;
+        }
+        let entry = &mut self.entries[index];
+        entry.loc = loc;
+        entry.prev = NIL;
+        entry.next = head;
+        if let Loc::Slot(level, slot) = loc {
+            self.levels[level].occupied |= 1 << slot;
+        }
+    }
+
+    fn unlink(&mut self, index: usize) {
+        let Entry { loc, prev, next, .. } = self.entries[index];
+        if prev == NIL {
+            *self.head(loc) = next;
+        } else {
+            self.entries[prev].next = next;
+        }
+        if next != NIL {
+            self.entries[next].prev = prev;
+        }
+        if let Loc::Slot(level, slot) = loc {
+            if self.levels[level].heads[slot] == NIL {
+                self.levels[level].occupied &= !(1 << slot);
+            }
+        }
+        self.entries[index].loc = Loc::Free;
+    }
+
+    // Takes the whole list starting at `loc`, leaving it empty.
+    fn take_list(&mut self, loc: Loc) -> usize {
+        if let Loc::Slot(level, slot) = loc {
+            self.levels[level].occupied &= !(1 << slot);
+        }
+        mem::replace(self.head(loc), NIL)
+    }
+
+    fn insert(&mut self, index: usize) {
+        let when = self.entries[index].when;
+        if when <= self.elapsed {
+            self.link(index, Loc::Pending);
+            return;
+        }
+        let when = when.min(self.elapsed + MAX_TICKS - 1);
+        let masked = ((self.elapsed ^ when) | SLOT_MASK).min(MAX_TICKS - 1);
+        let level = (63 - masked.leading_zeros() as usize) / SLOT_BITS;
+        let slot = ((when >> (SLOT_BITS * level)) & SLOT_MASK) as usize;
+        self.link(index, Loc::Slot(level, slot));
+    }
+
+    fn register(&mut self, when: u64, action: Action) -> TimerId {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.entries.push(Entry {
+                    when: 0,
+                    gen: 0,
+                    action: None,
+                    loc: Loc::Free,
+                    prev: NIL,
+                    next: NIL,
+                });
+                self.entries.len() - 1
+            }
+        };
+        let entry = &mut self.entries[index];
+        entry.when = when;
+        entry.action = Some(action);
+        let id = TimerId::new(index, entry.gen);
+        self.insert(index);
+        self.len += 1;
+        id
+    }
+
+    fn release(&mut self, index: usize) -> Option<Action> {
+        let entry = &mut self.entries[index];
+        entry.gen = entry.gen.wrapping_add(1);
+        self.free.push(index);
+        self.len -= 1;
+        self.entries[index].action.take()
+    }
+
+    fn cancel(&mut self, id: TimerId) -> Option<Action> {
+        let index = id.index();
+        match self.entries.get(index) {
+            Some(entry) if entry.gen == id.gen() && entry.loc != Loc::Free => {}
+            _ => return None,
+        }
+        self.unlink(index);
+        self.release(index)
+    }
+
+    // Returns the next list to process and the tick it is due at.
+    fn next_expiration(&self) -> Option<(Loc, u64)> {
+        if self.pending != NIL {
+            return Some((Loc::Pending, self.elapsed));
+        }
+        (0..LEVELS)
+            .filter_map(|level| {
+                let (slot, start) = self.levels[level].next_slot(level, self.elapsed)?;
+                Some((Loc::Slot(level, slot), start))
+            })
+            .min_by_key(|&(_, start)| start)
+    }
+
+    // Turns the wheel to `now`, collecting the actions of the timers due.
+    fn advance(&mut self, now: u64, fired: &mut Vec<Action>) {
+        while let Some((loc, start)) = self.next_expiration() {
+            if start > now {
+                break;
+            }
+            self.elapsed = self.elapsed.max(start);
+            let mut index = self.take_list(loc);
+            while index != NIL {
+                let next = self.entries[index].next;
+                self.entries[index].loc = Loc::Free;
+                if self.entries[index].when <= now {
+                    fired.extend(self.release(index));
+                } else {
+                    self.insert(index);
+                }
+                index = next;
+            }
+        }
+        self.elapsed = self.elapsed.max(now);
+    }
+}
+
+/// A hierarchical timer wheel for keeping large numbers of timeouts.
+///
+/// Timers are registered with a deadline and either a callback or a
+/// [`Waker`], and are fired once the wheel is advanced past their deadline.
+/// The wheel only stores timers; it is driven either by calling
+/// [`advance`] from an event loop, using [`next_deadline`] to decide how
+/// long to block, or by a dedicated thread that calls [`run`]. Either way a
+/// single thread waits for all timers, so a protocol keeping millions of
+/// timeouts does not need one thread or one OCALL per timer.
+///
+/// Deadlines are rounded up to whole milliseconds, so timers never fire
+/// early but may fire up to a millisecond late, or later if the wheel is
+/// not advanced in time. Callbacks run on the thread advancing the wheel and
+/// should be short; heavier work belongs on a task woken through a
+/// [`Waker`].
+///
+/// [`advance`]: TimerWheel::advance
+/// [`next_deadline`]: TimerWheel::next_deadline
+/// [`run`]: TimerWheel::run
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+/// use std::time::{Duration, Instant, TimerWheel};
+///
+/// let wheel = Arc::new(TimerWheel::new());
+/// let driver = {
+///     let wheel = wheel.clone();
+///     thread::spawn(move || wheel.run())
+/// };
+///
+/// let id = wheel.register(Instant::now() + Duration::from_secs(30), || {
+///     println!("request timed out");
+/// });
+/// // The response arrived in time.
+/// assert!(wheel.cancel(id));
+///
+/// wheel.shutdown();
+/// driver.join().unwrap();
+/// ```
+pub struct TimerWheel {
+    start: Instant,
+    inner: SgxMutex<Inner>,
+    cvar: SgxCondvar,
+}
+
+impl TimerWheel {
+    /// Creates an empty timer wheel.
+    pub fn new() -> TimerWheel {
+        TimerWheel {
+            start: Instant::_now(),
+            inner: SgxMutex::new(Inner {
+                elapsed: 0,
+                levels: [Level { occupied: 0, heads: [NIL; SLOTS] }; LEVELS],
+                pending: NIL,
+                entries: Vec::new(),
+                free: Vec::new(),
+                len: 0,
+                wakeup: u64::MAX,
+                shutdown: false,
+            }),
+            cvar: SgxCondvar::new(),
+        }
+    }
+
+    /// Registers `callback` to be called once `deadline` has passed.
+    ///
+    /// A deadline that has already passed fires on the next advance.
+    pub fn register<F>(&self, deadline: Instant, callback: F) -> TimerId
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.register_action(deadline, Action::Call(Box::new(callback)))
+    }
+
+    /// Registers `waker` to be woken once `deadline` has passed.
+    pub fn register_waker(&self, deadline: Instant, waker: Waker) -> TimerId {
+        self.register_action(deadline, Action::Wake(waker))
+    }
+
+    fn register_action(&self, deadline: Instant, action: Action) -> TimerId {
+        let when = self.deadline_tick(deadline);
+        let mut inner = self.lock();
+        let id = inner.register(when, action);
+        // A driver asleep past the new deadline has to look again.
+        if when < inner.wakeup {
+            self.cvar.notify_one();
+        }
+        id
+    }
+
+    /// Cancels a timer.
+    ///
+    /// Returns `true` if the timer was still waiting, and `false` if it has
+    /// already fired or been cancelled.
+    pub fn cancel(&self, id: TimerId) -> bool {
+        let action = self.lock().cancel(id);
+        // The callback is dropped outside the lock.
+        action.is_some()
+    }
+
+    /// Returns the number of timers waiting to fire.
+    pub fn len(&self) -> usize {
+        self.lock().len
+    }
+
+    /// Returns `true` if no timer is waiting to fire.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns when the wheel next needs to be advanced, or `None` if no
+    /// timer is waiting.
+    ///
+    /// This may be earlier than the next timer's deadline, when timers
+    /// registered far ahead have to be moved down the wheel.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let tick = self.lock().next_expiration()?.1;
+        Some(self.start + Duration::from_millis(tick))
+    }
+
+    /// Fires every timer whose deadline is at or before `now`, returning how
+    /// many fired.
+    ///
+    /// Callbacks are called and wakers woken after the wheel is unlocked, so
+    /// they may register or cancel timers themselves.
+    pub fn advance(&self, now: Instant) -> usize {
+        let mut fired = Vec::new();
+        self.lock().advance(self.now_tick(now), &mut fired);
+        let count = fired.len();
+        for action in fired {
+            action.fire();
+        }
+        count
+    }
+
+    /// Drives the wheel from the current thread until [`shutdown`] is
+    /// called.
+    ///
+    /// The thread sleeps until the next deadline, and is woken early when a
+    /// timer with an earlier deadline is registered.
+    ///
+    /// [`shutdown`]: TimerWheel::shutdown
+    pub fn run(&self) {
+        loop {
+            self.advance(Instant::_now());
+            let mut inner = self.lock();
+            if inner.shutdown {
+                return;
+            }
+            let now = self.now_tick(Instant::_now());
+            inner.wakeup = match inner.next_expiration() {
+                Some((_, tick)) if tick <= now => continue,
+                Some((_, tick)) => tick,
+                None => u64::MAX,
+            };
+            inner = if inner.wakeup == u64::MAX {
+                self.cvar.wait(inner).unwrap_or_else(PoisonError::into_inner)
+            } else {
+                let timeout = Duration::from_millis(inner.wakeup - now);
+                self.cvar.wait_timeout(inner, timeout).unwrap_or_else(PoisonError::into_inner).0
+            };
+            inner.wakeup = u64::MAX;
+        }
+    }
+
+    /// Makes [`run`] return, now and on every later call.
+    ///
+    /// Timers still waiting are kept and can be fired with [`advance`].
+    ///
+    /// [`run`]: TimerWheel::run
+    /// [`advance`]: TimerWheel::advance
+    pub fn shutdown(&self) {
+        self.lock().shutdown = true;
+        self.cvar.notify_all();
+    }
+
+    fn lock(&self) -> SgxMutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Ticks elapsed at `now`, rounded down.
+    fn now_tick(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.start).as_millis() as u64
+    }
+
+    // The first tick at or after `deadline`.
+    fn deadline_tick(&self, deadline: Instant) -> u64 {
+        let since = deadline.saturating_duration_since(self.start);
+        let ticks = since.as_millis() as u64;
+        if since > Duration::from_millis(ticks) {
+            ticks + 1
+        } else {
+            ticks
+        }
+    }
+}
+
+impl Default for TimerWheel {
+    fn default() -> TimerWheel {
+        TimerWheel::new()
+    }
+}
+
+impl fmt::Debug for TimerWheel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimerWheel").field("len", &self.len()).finish_non_exhaustive()
+    }
+}
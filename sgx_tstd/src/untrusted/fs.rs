@@ -16,4 +16,9 @@
 // under the License..
 
 //! Filesystem manipulation operations.
-pub use crate::fs::*;
\ No newline at end of file
+pub use crate::fs::*;
+pub use self::integrity::IntegrityFile;
+pub use self::watch::{Event, WatchDescriptor, WatchMask, Watcher};
+
+mod integrity;
+mod watch;
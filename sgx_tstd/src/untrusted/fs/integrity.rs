@@ -0,0 +1,410 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Merkle-tree verified reads from plain host files.
+
+use crate::cmp;
+use crate::collections::HashMap;
+use crate::ffi::OsString;
+use crate::fmt;
+use crate::fs::File;
+use crate::io::{self, Read, Seek, SeekFrom, Write};
+use crate::os::unix::fs::FileExt;
+use crate::path::{Path, PathBuf};
+use crate::sgxfs::SgxFile;
+use crate::sync::{PoisonError, SgxMutex};
+use sgx_types::*;
+
+const BLOCK_SIZE: usize = 4096;
+const HASH_SIZE: usize = SGX_SHA256_HASH_SIZE;
+// Bounds the verified-node cache so that a long scan of a huge file cannot
+// grow enclave memory without limit.
+const MAX_VERIFIED_NODES: usize = 64 * 1024;
+
+const LEAF_TAG: u8 = 0;
+const NODE_TAG: u8 = 1;
+const ROOT_TAG: u8 = 2;
+
+type Hash = sgx_sha256_hash_t;
+
+/// A plain host file whose reads are verified against a Merkle tree.
+///
+/// The file's contents stay unencrypted on the host. They are split into
+/// 4 KiB blocks whose SHA-256 hashes form a Merkle tree, stored next to the
+/// file with a `.merkle` suffix. Only the root has to be trusted: it also
+/// covers the file's length, and is sealed to the enclave in a protected
+/// file with a `.root` suffix. Every block that is read is hashed and its
+/// path checked up to the root, so any change the host makes to the data
+/// or the tree is reported as an error of kind
+/// [`io::ErrorKind::InvalidData`].
+///
+/// Compared with [`SgxFile`], this avoids decrypting every page, which suits
+/// large read-mostly data that need not be confidential. The file cannot
+/// be written through this type; call [`IntegrityFile::build`] again after
+/// changing it. Note that neither the tree nor the sealed root protects
+/// against the host rolling all three files back to an older, consistent
+/// state; callers that care should keep [`IntegrityFile::root`] elsewhere
+/// and use [`IntegrityFile::open_with_root`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::untrusted::fs::IntegrityFile;
+///
+/// IntegrityFile::build("dataset.bin").unwrap();
+///
+/// let file = IntegrityFile::open("dataset.bin").unwrap();
+/// let mut record = [0u8; 64];
+/// file.read_exact_at(&mut record, 1 << 20).unwrap();
+/// ```
+pub struct IntegrityFile {
+    data: File,
+    tree: File,
+    len: u64,
+    root: Hash,
+    levels: Vec<Level>,
+    pos: u64,
+    verified: SgxMutex<HashMap<(usize, u64), Hash>>,
+}
+
+// One level of the tree as stored in the `.merkle` file, leaves first.
+#[derive(Clone, Copy)]
+struct Level {
+    offset: u64,
+    count: u64,
+}
+
+impl IntegrityFile {
+    /// Computes the Merkle tree of the file at `path` and seals its root.
+    ///
+    /// The enclave must trust the file's current contents, for example
+    /// because it has just written them. Any existing `.merkle` and `.root`
+    /// files are replaced. The file is then opened as with
+    /// [`IntegrityFile::open`].
+    pub fn build<P: AsRef<Path>>(path: P) -> io::Result<IntegrityFile> {
+        let path = path.as_ref();
+        let mut data = File::open(path)?;
+        let len = data.metadata()?.len();
+        let levels = layout(len);
+
+        let mut nodes = Vec::with_capacity(levels[0].count as usize);
+        let mut block = vec![0u8; BLOCK_SIZE];
+        for index in 0..levels[0].count {
+            let n = block_len(len, index);
+            data.read_exact(&mut block[..n])?;
+            nodes.push(hash_leaf(&block[..n])?);
+        }
+
+        let mut tree = File::create(suffixed(path, ".merkle"))?;
+        loop {
+            for node in &nodes {
+                tree.write_all(node)?;
+            }
+            if nodes.len() == 1 {
+                break;
+            }
+            nodes = nodes
+                .chunks(2)
+                .map(|pair| match *pair {
+                    [ref left, ref right] => hash_node(left, right),
+                    [ref only] => Ok(*only),
+                    _ => unreachable!(),
+                })
+                .collect::<io::Result<Vec<Hash>>>()?;
+        }
+        tree.sync_all()?;
+
+        let root = hash_root(len, &nodes[0])?;
+        let mut sealed = SgxFile::create(suffixed(path, ".root"))?;
+        sealed.write_all(&root)?;
+        drop(sealed);
+
+        IntegrityFile::open_with_root(path, &root)
+    }
+
+    /// Opens a file whose tree was computed by [`IntegrityFile::build`],
+    /// trusting the root sealed next to it.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<IntegrityFile> {
+        let path = path.as_ref();
+        let mut root = [0u8; HASH_SIZE];
+        SgxFile::open(suffixed(path, ".root"))?.read_exact(&mut root)?;
+        IntegrityFile::open_with_root(path, &root)
+    }
+
+    /// Opens a file whose tree was computed by [`IntegrityFile::build`],
+    /// trusting `root` instead of the sealed root.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if the file's
+    /// length or tree does not match `root`.
+    pub fn open_with_root<P: AsRef<Path>>(
+        path: P,
+        root: &[u8; HASH_SIZE],
+    ) -> io::Result<IntegrityFile> {
+        let path = path.as_ref();
+        let data = File::open(path)?;
+        let tree = File::open(suffixed(path, ".merkle"))?;
+        let len = data.metadata()?.len();
+        let levels = layout(len);
+
+        let top = *levels.last().unwrap();
+        if tree.metadata()?.len() != (top.offset + top.count) * HASH_SIZE as u64 {
+            return Err(integrity_error());
+        }
+        let mut top_node = [0u8; HASH_SIZE];
+        tree.read_exact_at(&mut top_node, top.offset * HASH_SIZE as u64)?;
+        if hash_root(len, &top_node)? != *root {
+            return Err(integrity_error());
+        }
+
+        let mut verified = HashMap::new();
+        verified.insert((levels.len() - 1, 0), top_node);
+        Ok(IntegrityFile {
+            data,
+            tree,
+            len,
+            root: *root,
+            levels,
+            pos: 0,
+            verified: SgxMutex::new(verified),
+        })
+    }
+
+    /// Returns the root hash, which also covers the file's length.
+    pub fn root(&self) -> [u8; HASH_SIZE] {
+        self.root
+    }
+
+    /// Returns the length of the file in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Reads verified bytes starting at `offset` into `buf`, returning how
+    /// many were read. Fewer bytes than requested are only returned at the
+    /// end of the file.
+    ///
+    /// The cursor used by [`Read`] and [`Seek`] is not moved.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if a block
+    /// fails verification.
+    pub fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let mut block = vec![0u8; BLOCK_SIZE];
+        let mut read = 0;
+        while read < buf.len() {
+            let pos = offset.saturating_add(read as u64);
+            if pos >= self.len {
+                break;
+            }
+            let index = pos / BLOCK_SIZE as u64;
+            let n = block_len(self.len, index);
+            self.data.read_exact_at(&mut block[..n], index * BLOCK_SIZE as u64)?;
+            self.verify(index, &block[..n])?;
+
+            let within = (pos % BLOCK_SIZE as u64) as usize;
+            let count = cmp::min(buf.len() - read, n - within);
+            buf[read..read + count].copy_from_slice(&block[within..within + count]);
+            read += count;
+        }
+        Ok(read)
+    }
+
+    /// Reads exactly `buf.len()` verified bytes starting at `offset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`io::ErrorKind::UnexpectedEof`] if the file
+    /// ends before `buf` is filled, or of kind
+    /// [`io::ErrorKind::InvalidData`] if a block fails verification.
+    pub fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        if self.read_at(buf, offset)? < buf.len() {
+            return Err(io::Error::new_const(
+                io::ErrorKind::UnexpectedEof,
+                &"failed to fill whole buffer",
+            ));
+        }
+        Ok(())
+    }
+
+    // Checks a block against the tree, walking up until a node that has
+    // already been verified, or the root, is reached.
+    fn verify(&self, index: u64, block: &[u8]) -> io::Result<()> {
+        let mut verified = self.verified.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut node = hash_leaf(block)?;
+        let mut index = index;
+        let mut path = Vec::new();
+
+        for (level, info) in self.levels.iter().enumerate() {
+            if let Some(known) = verified.get(&(level, index)) {
+                if *known != node {
+                    return Err(integrity_error());
+                }
+                break;
+            }
+            if level == self.levels.len() - 1 {
+                // The top node is always cached when the file is opened.
+                return Err(integrity_error());
+            }
+            path.push(((level, index), node));
+
+            let sibling = index ^ 1;
+            node = if sibling >= info.count {
+                node
+            } else {
+                let mut other = [0u8; HASH_SIZE];
+                let at = (info.offset + sibling) * HASH_SIZE as u64;
+                self.tree.read_exact_at(&mut other, at)?;
+                if index & 1 == 0 {
+                    hash_node(&node, &other)?
+                } else {
+                    hash_node(&other, &node)?
+                }
+            };
+            index /= 2;
+        }
+
+        if verified.len() + path.len() > MAX_VERIFIED_NODES {
+            let top = self.levels.len() - 1;
+            verified.retain(|&(level, _), _| level == top);
+        }
+        verified.extend(path);
+        Ok(())
+    }
+}
+
+impl Read for IntegrityFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.read_at(buf, self.pos)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for IntegrityFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(n) => {
+                self.pos = n;
+                return Ok(n);
+            }
+            SeekFrom::End(n) => (self.len, n),
+            SeekFrom::Current(n) => (self.pos, n),
+        };
+        let new_pos = if offset >= 0 {
+            base.checked_add(offset as u64)
+        } else {
+            base.checked_sub(offset.wrapping_neg() as u64)
+        };
+        match new_pos {
+            Some(n) => {
+                self.pos = n;
+                Ok(n)
+            }
+            None => Err(io::Error::new_const(
+                io::ErrorKind::InvalidInput,
+                &"invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+impl fmt::Debug for IntegrityFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntegrityFile")
+            .field("data", &self.data)
+            .field("len", &self.len)
+            .field("pos", &self.pos)
+            .finish()
+    }
+}
+
+// An empty file still has a single, empty block so that the tree always
+// has a top node.
+fn layout(len: u64) -> Vec<Level> {
+    let mut count = cmp::max(1, (len + BLOCK_SIZE as u64 - 1) / BLOCK_SIZE as u64);
+    let mut offset = 0;
+    let mut levels = Vec::new();
+    loop {
+        levels.push(Level { offset, count });
+        if count == 1 {
+            return levels;
+        }
+        offset += count;
+        count = (count + 1) / 2;
+    }
+}
+
+fn block_len(len: u64, index: u64) -> usize {
+    let start = index * BLOCK_SIZE as u64;
+    cmp::min(BLOCK_SIZE as u64, len.saturating_sub(start)) as usize
+}
+
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn integrity_error() -> io::Error {
+    io::Error::new_const(io::ErrorKind::InvalidData, &"integrity check failed")
+}
+
+fn hash_leaf(block: &[u8]) -> io::Result<Hash> {
+    sha256(&[&[LEAF_TAG], block])
+}
+
+fn hash_node(left: &Hash, right: &Hash) -> io::Result<Hash> {
+    sha256(&[&[NODE_TAG], left, right])
+}
+
+fn hash_root(len: u64, top: &Hash) -> io::Result<Hash> {
+    sha256(&[&[ROOT_TAG], &len.to_le_bytes(), top])
+}
+
+fn sha256(parts: &[&[u8]]) -> io::Result<Hash> {
+    let mut handle: sgx_sha_state_handle_t = crate::ptr::null_mut();
+    let status = unsafe { sgx_sha256_init(&mut handle) };
+    if status != sgx_status_t::SGX_SUCCESS {
+        return Err(io::Error::from_sgx_error(status));
+    }
+
+    let mut hash = Hash::default();
+    let mut status = sgx_status_t::SGX_SUCCESS;
+    for part in parts {
+        status = unsafe { sgx_sha256_update(part.as_ptr(), part.len() as u32, handle) };
+        if status != sgx_status_t::SGX_SUCCESS {
+            break;
+        }
+    }
+    if status == sgx_status_t::SGX_SUCCESS {
+        status = unsafe { sgx_sha256_get_hash(handle, &mut hash) };
+    }
+    unsafe { sgx_sha256_close(handle) };
+
+    if status != sgx_status_t::SGX_SUCCESS {
+        return Err(io::Error::from_sgx_error(status));
+    }
+    Ok(hash)
+}
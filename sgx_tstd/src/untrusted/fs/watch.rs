@@ -0,0 +1,293 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! File change notifications backed by the host's inotify.
+
+use crate::ffi::{CString, OsStr, OsString};
+use crate::fmt;
+use crate::io;
+use crate::mem;
+use crate::ops::{BitOr, BitOrAssign};
+use crate::os::unix::ffi::OsStrExt;
+use crate::os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, RawFd};
+use crate::path::Path;
+use crate::ptr;
+use crate::sys::cvt;
+use crate::sys::fd::FileDesc;
+
+mod libc {
+    pub use sgx_libc::ocall::{inotify_add_watch, inotify_init1, inotify_rm_watch};
+    pub use sgx_libc::*;
+}
+
+// Large enough for several events, and at least one with a name of
+// NAME_MAX bytes.
+const EVENT_BUF_SIZE: usize = 4096;
+
+/// Watches files and directories on the host for changes.
+///
+/// This wraps a host inotify instance. Events are read with
+/// [`Watcher::read_events`], which blocks until at least one is available
+/// unless the watcher is [nonblocking](Watcher::set_nonblocking). The raw
+/// descriptor can also be handed to `poll` or `epoll` alongside sockets.
+///
+/// Notifications come from the host, which can drop, delay or invent them.
+/// Treat an event as a hint to re-read the file, and verify what is read,
+/// rather than as proof that anything changed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::untrusted::fs::{WatchMask, Watcher};
+///
+/// fn main() -> std::io::Result<()> {
+///     let mut watcher = Watcher::new()?;
+///     watcher.add_watch("/etc/enclave", WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO)?;
+///     loop {
+///         for event in watcher.read_events()? {
+///             println!("{:?} changed", event.name());
+///         }
+///     }
+/// }
+/// ```
+pub struct Watcher {
+    fd: FileDesc,
+    buf: Vec<u8>,
+}
+
+/// Identifies a watch added with [`Watcher::add_watch`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct WatchDescriptor(RawFd);
+
+/// A set of inotify event flags.
+///
+/// Used both to select the events a watch reports and to describe each
+/// event that is read.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct WatchMask(u32);
+
+/// A change reported by a [`Watcher`].
+#[derive(Clone, Debug)]
+pub struct Event {
+    wd: WatchDescriptor,
+    mask: WatchMask,
+    cookie: u32,
+    name: Option<OsString>,
+}
+
+impl WatchMask {
+    /// The file was read.
+    pub const ACCESS: WatchMask = WatchMask(libc::IN_ACCESS);
+    /// The file was written to.
+    pub const MODIFY: WatchMask = WatchMask(libc::IN_MODIFY);
+    /// Metadata such as permissions or timestamps changed.
+    pub const ATTRIB: WatchMask = WatchMask(libc::IN_ATTRIB);
+    /// A file opened for writing was closed.
+    pub const CLOSE_WRITE: WatchMask = WatchMask(libc::IN_CLOSE_WRITE);
+    /// A file not opened for writing was closed.
+    pub const CLOSE_NOWRITE: WatchMask = WatchMask(libc::IN_CLOSE_NOWRITE);
+    /// The file was opened.
+    pub const OPEN: WatchMask = WatchMask(libc::IN_OPEN);
+    /// A file was moved out of the watched directory.
+    pub const MOVED_FROM: WatchMask = WatchMask(libc::IN_MOVED_FROM);
+    /// A file was moved into the watched directory.
+    pub const MOVED_TO: WatchMask = WatchMask(libc::IN_MOVED_TO);
+    /// A file was created in the watched directory.
+    pub const CREATE: WatchMask = WatchMask(libc::IN_CREATE);
+    /// A file was deleted from the watched directory.
+    pub const DELETE: WatchMask = WatchMask(libc::IN_DELETE);
+    /// The watched file or directory itself was deleted.
+    pub const DELETE_SELF: WatchMask = WatchMask(libc::IN_DELETE_SELF);
+    /// The watched file or directory itself was moved.
+    pub const MOVE_SELF: WatchMask = WatchMask(libc::IN_MOVE_SELF);
+    /// All of the events above.
+    pub const ALL_EVENTS: WatchMask = WatchMask(libc::IN_ALL_EVENTS);
+
+    /// Only watch the path if it is a directory.
+    pub const ONLYDIR: WatchMask = WatchMask(libc::IN_ONLYDIR);
+    /// Do not follow the path if it is a symbolic link.
+    pub const DONT_FOLLOW: WatchMask = WatchMask(libc::IN_DONT_FOLLOW);
+    /// Add to the events of an existing watch instead of replacing them.
+    pub const MASK_ADD: WatchMask = WatchMask(libc::IN_MASK_ADD);
+    /// Remove the watch after its first event.
+    pub const ONESHOT: WatchMask = WatchMask(libc::IN_ONESHOT);
+
+    /// Set on events: the subject is a directory.
+    pub const ISDIR: WatchMask = WatchMask(libc::IN_ISDIR);
+    /// Set on events: the watch was removed.
+    pub const IGNORED: WatchMask = WatchMask(libc::IN_IGNORED);
+    /// Set on events: the host's event queue overflowed and events were lost.
+    pub const Q_OVERFLOW: WatchMask = WatchMask(libc::IN_Q_OVERFLOW);
+    /// Set on events: the file system holding the watched path was unmounted.
+    pub const UNMOUNT: WatchMask = WatchMask(libc::IN_UNMOUNT);
+
+    /// Creates a mask from raw inotify flags.
+    pub const fn from_bits(bits: u32) -> WatchMask {
+        WatchMask(bits)
+    }
+
+    /// Returns the raw inotify flags.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Returns `true` if every flag in `other` is also set in `self`.
+    pub const fn contains(self, other: WatchMask) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for WatchMask {
+    type Output = WatchMask;
+
+    fn bitor(self, rhs: WatchMask) -> WatchMask {
+        WatchMask(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for WatchMask {
+    fn bitor_assign(&mut self, rhs: WatchMask) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl Event {
+    /// Returns the watch that produced the event.
+    pub fn wd(&self) -> WatchDescriptor {
+        self.wd
+    }
+
+    /// Returns what happened.
+    pub fn mask(&self) -> WatchMask {
+        self.mask
+    }
+
+    /// Returns the cookie that pairs a [`WatchMask::MOVED_FROM`] event with
+    /// its [`WatchMask::MOVED_TO`] event, or 0.
+    pub fn cookie(&self) -> u32 {
+        self.cookie
+    }
+
+    /// Returns the name of the affected file, relative to the watched
+    /// directory. This is `None` for events on the watched path itself.
+    pub fn name(&self) -> Option<&OsStr> {
+        self.name.as_deref()
+    }
+}
+
+impl Watcher {
+    /// Creates a watcher with no watches.
+    pub fn new() -> io::Result<Watcher> {
+        let fd = cvt(unsafe { libc::inotify_init1(libc::IN_CLOEXEC) })?;
+        let fd = unsafe { FileDesc::from_raw_fd(fd) };
+        Ok(Watcher { fd, buf: vec![0; EVENT_BUF_SIZE] })
+    }
+
+    /// Starts watching `path` for the events in `mask`.
+    ///
+    /// Watching the same path again replaces its mask, unless
+    /// [`WatchMask::MASK_ADD`] is set, and returns the same descriptor.
+    pub fn add_watch<P: AsRef<Path>>(
+        &self,
+        path: P,
+        mask: WatchMask,
+    ) -> io::Result<WatchDescriptor> {
+        let path = CString::new(path.as_ref().as_os_str().as_bytes())?;
+        let wd = cvt(unsafe {
+            libc::inotify_add_watch(self.fd.as_raw_fd(), path.as_ptr(), mask.0)
+        })?;
+        Ok(WatchDescriptor(wd))
+    }
+
+    /// Stops watching. A final event with [`WatchMask::IGNORED`] set is
+    /// reported for the watch.
+    pub fn remove_watch(&self, wd: WatchDescriptor) -> io::Result<()> {
+        cvt(unsafe { libc::inotify_rm_watch(self.fd.as_raw_fd(), wd.0) })?;
+        Ok(())
+    }
+
+    /// Moves the watcher into or out of nonblocking mode.
+    ///
+    /// In nonblocking mode, [`Watcher::read_events`] fails with
+    /// [`io::ErrorKind::WouldBlock`] when no events are pending.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.fd.set_nonblocking(nonblocking)
+    }
+
+    /// Reads the pending events, waiting for at least one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if the host
+    /// returns a malformed event.
+    pub fn read_events(&mut self) -> io::Result<Vec<Event>> {
+        let n = self.fd.read(&mut self.buf)?;
+        parse_events(&self.buf[..n])
+    }
+}
+
+fn parse_events(mut buf: &[u8]) -> io::Result<Vec<Event>> {
+    const HEADER: usize = mem::size_of::<libc::inotify_event>();
+
+    let mut events = Vec::new();
+    while !buf.is_empty() {
+        if buf.len() < HEADER {
+            return Err(malformed_event());
+        }
+        let raw = unsafe { ptr::read_unaligned(buf.as_ptr() as *const libc::inotify_event) };
+        let len = raw.len as usize;
+        if len > buf.len() - HEADER {
+            return Err(malformed_event());
+        }
+        // The name is padded with NULs up to `len`.
+        let name = &buf[HEADER..HEADER + len];
+        let name = match name.iter().position(|&c| c == 0).unwrap_or(len) {
+            0 => None,
+            end => Some(OsStr::from_bytes(&name[..end]).to_os_string()),
+        };
+        events.push(Event {
+            wd: WatchDescriptor(raw.wd),
+            mask: WatchMask(raw.mask),
+            cookie: raw.cookie,
+            name,
+        });
+        buf = &buf[HEADER + len..];
+    }
+    Ok(events)
+}
+
+fn malformed_event() -> io::Error {
+    io::Error::new_const(io::ErrorKind::InvalidData, &"malformed inotify event")
+}
+
+impl AsRawFd for Watcher {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl AsFd for Watcher {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl fmt::Debug for Watcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watcher").field("fd", &self.fd.as_raw_fd()).finish()
+    }
+}
@@ -651,6 +651,9 @@ extern "C" {
 
     pub fn sgx_fopen_auto_key(filename: *const c_char, mode: *const c_char) -> SGX_FILE;
 
+    /* only provided by newer sgx_tprotected_fs libraries */
+    pub fn sgx_fopen_integrity_only(filename: *const c_char, mode: *const c_char) -> SGX_FILE;
+
     pub fn sgx_fwrite(ptr: *const c_void,
                       size: size_t,
                       count: size_t,
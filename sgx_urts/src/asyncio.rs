@@ -15,8 +15,15 @@
 // specific language governing permissions and limitations
 // under the License..
 
-use libc::{self, c_int, epoll_event, nfds_t, pollfd};
+use libc::{self, c_int, c_void, epoll_event, nfds_t, pollfd, size_t};
+use std::alloc::{self, Layout};
+use std::collections::VecDeque;
 use std::io::Error;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
 
 #[no_mangle]
 pub extern "C" fn u_poll_ocall(
@@ -94,3 +101,263 @@ pub extern "C" fn u_epoll_wait_ocall(
     }
     ret
 }
+
+// Operation codes of `u_aio_submit_ocall`.
+const AIO_OP_READ: c_int = 0;
+const AIO_OP_WRITE: c_int = 1;
+const AIO_OP_FSYNC: c_int = 2;
+const AIO_OP_FDATASYNC: c_int = 3;
+
+// The completion ring shared with the enclave: this header followed by
+// `entries` completions. Workers advance `tail` as they post completions
+// and the enclave advances `head` as it consumes them.
+#[repr(C)]
+struct AioRing {
+    head: AtomicU32,
+    tail: AtomicU32,
+    entries: u32,
+    _pad: u32,
+}
+
+#[repr(C)]
+struct AioCompletion {
+    user_data: u64,
+    // Bytes transferred, or a negated errno.
+    res: i64,
+}
+
+struct AioJob {
+    user_data: u64,
+    opcode: c_int,
+    // A duplicate owned by the job, so that closing the file in the enclave
+    // cannot redirect a queued operation to another file.
+    fd: c_int,
+    buf: usize,
+    len: size_t,
+    offset: i64,
+}
+
+struct AioQueue {
+    jobs: VecDeque<AioJob>,
+    shutdown: bool,
+}
+
+struct AioContext {
+    ring: *mut AioRing,
+    layout: Layout,
+    entries: u32,
+    notify_fd: c_int,
+    queue: Mutex<AioQueue>,
+    queued: Condvar,
+    // Serializes workers posting to the ring.
+    post: Mutex<()>,
+    workers: Mutex<Vec<JoinHandle<()>>>,
+}
+
+unsafe impl Send for AioContext {}
+unsafe impl Sync for AioContext {}
+
+impl AioContext {
+    fn run(&self) {
+        loop {
+            let job = {
+                let mut queue = self.queue.lock().unwrap();
+                loop {
+                    if queue.shutdown {
+                        return;
+                    }
+                    if let Some(job) = queue.jobs.pop_front() {
+                        break job;
+                    }
+                    queue = self.queued.wait(queue).unwrap();
+                }
+            };
+            let res = execute(&job);
+            unsafe { libc::close(job.fd) };
+            self.post(job.user_data, res);
+        }
+    }
+
+    fn post(&self, user_data: u64, res: i64) {
+        let _guard = self.post.lock().unwrap();
+        let ring = unsafe { &*self.ring };
+        let tail = ring.tail.load(Ordering::Relaxed);
+        // The enclave keeps no more operations in flight than the ring has
+        // entries, so this only waits for it to catch up.
+        while tail.wrapping_sub(ring.head.load(Ordering::Acquire)) >= self.entries {
+            if self.queue.lock().unwrap().shutdown {
+                return;
+            }
+            thread::yield_now();
+        }
+        unsafe {
+            let completions = self.ring.add(1) as *mut AioCompletion;
+            let slot = completions.add((tail & (self.entries - 1)) as usize);
+            ptr::write(slot, AioCompletion { user_data, res });
+        }
+        ring.tail.store(tail.wrapping_add(1), Ordering::Release);
+
+        let one: u64 = 1;
+        unsafe {
+            libc::write(self.notify_fd, &one as *const u64 as *const c_void, mem::size_of::<u64>())
+        };
+    }
+
+    fn shutdown(&self) {
+        let jobs = {
+            let mut queue = self.queue.lock().unwrap();
+            queue.shutdown = true;
+            mem::take(&mut queue.jobs)
+        };
+        self.queued.notify_all();
+        for job in jobs {
+            unsafe { libc::close(job.fd) };
+        }
+        let workers = mem::take(&mut *self.workers.lock().unwrap());
+        for worker in workers {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for AioContext {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.notify_fd);
+            alloc::dealloc(self.ring as *mut u8, self.layout);
+        }
+    }
+}
+
+fn execute(job: &AioJob) -> i64 {
+    loop {
+        let buf = job.buf as *mut c_void;
+        let ret = unsafe {
+            match job.opcode {
+                AIO_OP_READ => libc::pread64(job.fd, buf, job.len, job.offset) as i64,
+                AIO_OP_WRITE => libc::pwrite64(job.fd, buf, job.len, job.offset) as i64,
+                AIO_OP_FSYNC => libc::fsync(job.fd) as i64,
+                _ => libc::fdatasync(job.fd) as i64,
+            }
+        };
+        if ret >= 0 {
+            return ret;
+        }
+        let errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+        if errno != libc::EINTR {
+            return -(errno as i64);
+        }
+    }
+}
+
+fn set_error(error: *mut c_int, errno: c_int) {
+    if !error.is_null() {
+        unsafe {
+            *error = errno;
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn u_aio_setup_ocall(
+    error: *mut c_int,
+    entries: size_t,
+    threads: size_t,
+    notify_fd: *mut c_int,
+    ring: *mut *mut c_void,
+) -> *mut c_void {
+    if notify_fd.is_null()
+        || ring.is_null()
+        || threads == 0
+        || !entries.is_power_of_two()
+        || entries > u32::MAX as size_t / 2
+    {
+        set_error(error, libc::EINVAL);
+        return ptr::null_mut();
+    }
+    let size = mem::size_of::<AioRing>() + entries * mem::size_of::<AioCompletion>();
+    let layout = match Layout::from_size_align(size, mem::align_of::<AioCompletion>()) {
+        Ok(layout) => layout,
+        Err(_) => {
+            set_error(error, libc::EINVAL);
+            return ptr::null_mut();
+        }
+    };
+    let fd = unsafe { libc::eventfd(0, libc::EFD_NONBLOCK | libc::EFD_CLOEXEC) };
+    if fd < 0 {
+        set_error(error, Error::last_os_error().raw_os_error().unwrap_or(0));
+        return ptr::null_mut();
+    }
+    let ring_ptr = unsafe { alloc::alloc_zeroed(layout) } as *mut AioRing;
+    if ring_ptr.is_null() {
+        unsafe { libc::close(fd) };
+        set_error(error, libc::ENOMEM);
+        return ptr::null_mut();
+    }
+    unsafe { (*ring_ptr).entries = entries as u32 };
+
+    let ctx = Arc::new(AioContext {
+        ring: ring_ptr,
+        layout,
+        entries: entries as u32,
+        notify_fd: fd,
+        queue: Mutex::new(AioQueue { jobs: VecDeque::new(), shutdown: false }),
+        queued: Condvar::new(),
+        post: Mutex::new(()),
+        workers: Mutex::new(Vec::with_capacity(threads)),
+    });
+    for _ in 0..threads {
+        let worker = ctx.clone();
+        match thread::Builder::new().name("sgx-aio".into()).spawn(move || worker.run()) {
+            Ok(handle) => ctx.workers.lock().unwrap().push(handle),
+            Err(e) => {
+                ctx.shutdown();
+                set_error(error, e.raw_os_error().unwrap_or(libc::EAGAIN));
+                return ptr::null_mut();
+            }
+        }
+    }
+    unsafe {
+        *notify_fd = fd;
+        *ring = ring_ptr as *mut c_void;
+    }
+    set_error(error, 0);
+    Arc::into_raw(ctx) as *mut c_void
+}
+
+#[no_mangle]
+pub extern "C" fn u_aio_submit_ocall(
+    error: *mut c_int,
+    ctx: *mut c_void,
+    user_data: u64,
+    opcode: c_int,
+    fd: c_int,
+    buf: *mut c_void,
+    len: size_t,
+    offset: i64,
+) -> c_int {
+    if ctx.is_null() || !(AIO_OP_READ..=AIO_OP_FDATASYNC).contains(&opcode) {
+        set_error(error, libc::EINVAL);
+        return -1;
+    }
+    let ctx = unsafe { &*(ctx as *const AioContext) };
+    let fd = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 0) };
+    if fd < 0 {
+        set_error(error, Error::last_os_error().raw_os_error().unwrap_or(0));
+        return -1;
+    }
+    let job = AioJob { user_data, opcode, fd, buf: buf as usize, len, offset };
+    ctx.queue.lock().unwrap().jobs.push_back(job);
+    ctx.queued.notify_one();
+    set_error(error, 0);
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn u_aio_destroy_ocall(ctx: *mut c_void) {
+    if ctx.is_null() {
+        return;
+    }
+    let ctx = unsafe { Arc::from_raw(ctx as *const AioContext) };
+    ctx.shutdown();
+}
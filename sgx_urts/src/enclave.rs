@@ -15,12 +15,15 @@
 // specific language governing permissions and limitations
 // under the License..
 
+use libc::c_int;
 use sgx_types::*;
-use std::ffi::{CStr, CString};
+use std::collections::HashMap;
+use std::ffi::{CStr, CString, OsStr};
 use std::io;
 use std::os::unix::ffi::OsStrExt;
 use std::path::{Path, PathBuf};
 use std::ptr;
+use std::sync::{Mutex, Once};
 
 ///
 /// Loads the enclave using its file name and initializes it using a launch token.
@@ -551,6 +554,38 @@ fn cstr(path: &Path) -> io::Result<CString> {
     Ok(CString::new(path.as_os_str().as_bytes())?)
 }
 
+/// How an enclave ended through `std::process::exit` or
+/// `std::process::abort`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SgxExitStatus {
+    /// The enclave called `exit` with this code.
+    Exit(i32),
+    /// The enclave called `abort`.
+    Abort,
+}
+
+fn exit_statuses() -> &'static Mutex<HashMap<sgx_enclave_id_t, SgxExitStatus>> {
+    static INIT: Once = Once::new();
+    static mut STATUSES: *const Mutex<HashMap<sgx_enclave_id_t, SgxExitStatus>> = ptr::null();
+    unsafe {
+        INIT.call_once(|| STATUSES = Box::into_raw(Box::new(Mutex::new(HashMap::new()))));
+        &*STATUSES
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn u_exit_ocall(eid: sgx_enclave_id_t, code: c_int, aborted: c_int) {
+    let status = if aborted != 0 {
+        SgxExitStatus::Abort
+    } else {
+        SgxExitStatus::Exit(code)
+    };
+    if let Ok(mut statuses) = exit_statuses().lock() {
+        // Only the first report counts, the enclave is locked right after it.
+        statuses.entry(eid).or_insert(status);
+    }
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct SgxEnclave {
     id: sgx_enclave_id_t,
@@ -692,6 +727,71 @@ impl SgxEnclave {
         rsgx_get_target_info(self.id)
     }
 
+    /// Returns how the enclave ended, if it called `std::process::exit` or
+    /// `std::process::abort`.
+    ///
+    /// An ECALL that ended the enclave returns `SGX_ERROR_ENCLAVE_CRASHED`,
+    /// and this tells a controlled exit apart from a crash. It needs the
+    /// `global_init` feature, without which the enclave does not know its id.
+    pub fn exit_status(&self) -> Option<SgxExitStatus> {
+        exit_statuses()
+            .lock()
+            .ok()
+            .and_then(|statuses| statuses.get(&self.id).copied())
+    }
+
+    /// Passes `args` to the enclave, where they are returned by
+    /// `std::env::args`. Only the first call has an effect.
+    ///
+    /// Fails with `SGX_ERROR_INVALID_PARAMETER` if an argument contains a
+    /// NUL byte.
+    #[cfg(feature = "global_init")]
+    pub fn set_args<I, S>(&self, args: I) -> SgxResult<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        extern "C" {
+            fn t_global_args_ecall(
+                eid: sgx_enclave_id_t,
+                args: *const u8,
+                len: usize,
+            ) -> sgx_status_t;
+        }
+        let mut blob = Vec::new();
+        for arg in args {
+            let arg = arg.as_ref().as_bytes();
+            if arg.contains(&0) {
+                return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+            }
+            blob.extend_from_slice(arg);
+            blob.push(0);
+        }
+        let ret = unsafe { t_global_args_ecall(self.id, blob.as_ptr(), blob.len()) };
+        match ret {
+            sgx_status_t::SGX_SUCCESS => Ok(()),
+            _ => Err(ret),
+        }
+    }
+
+    /// Runs the destructors of the enclave thread-local data of the calling
+    /// host thread, such as `thread_local!` values.
+    ///
+    /// Call it as the last ECALL of a host thread that is about to exit, and
+    /// never from within an OCALL. The TCS the thread was bound to may then
+    /// serve another host thread, which starts with fresh thread locals.
+    #[cfg(feature = "global_init")]
+    pub fn exit_thread(&self) -> SgxResult<()> {
+        extern "C" {
+            fn t_thread_exit_ecall(eid: sgx_enclave_id_t) -> sgx_status_t;
+        }
+        let ret = unsafe { t_thread_exit_ecall(self.id) };
+        match ret {
+            sgx_status_t::SGX_SUCCESS => Ok(()),
+            _ => Err(ret),
+        }
+    }
+
     fn exit(&self) {
         #[cfg(feature = "global_exit")]
         {
@@ -731,5 +831,8 @@ impl Drop for SgxEnclave {
     fn drop(&mut self) {
         self.exit();
         let _ = rsgx_destroy_enclave(self.id);
+        if let Ok(mut statuses) = exit_statuses().lock() {
+            statuses.remove(&self.id);
+        }
     }
 }
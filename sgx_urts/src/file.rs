@@ -288,6 +288,21 @@ pub extern "C" fn u_fdatasync_ocall(error: *mut c_int, fd: c_int) -> c_int {
     ret
 }
 
+#[no_mangle]
+pub extern "C" fn u_flock_ocall(error: *mut c_int, fd: c_int, operation: c_int) -> c_int {
+    let mut errno = 0;
+    let ret = unsafe { libc::flock(fd, operation) };
+    if ret < 0 {
+        errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+    }
+    if !error.is_null() {
+        unsafe {
+            *error = errno;
+        }
+    }
+    ret
+}
+
 #[no_mangle]
 pub extern "C" fn u_fchmod_ocall(error: *mut c_int, fd: c_int, mode: mode_t) -> c_int {
     let mut errno = 0;
@@ -551,3 +566,53 @@ pub extern "C" fn u_fstatat64_ocall(
     }
     ret
 }
+
+#[no_mangle]
+pub extern "C" fn u_inotify_init1_ocall(error: *mut c_int, flags: c_int) -> c_int {
+    let mut errno = 0;
+    let ret = unsafe { libc::inotify_init1(flags) };
+    if ret < 0 {
+        errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+    }
+    if !error.is_null() {
+        unsafe {
+            *error = errno;
+        }
+    }
+    ret
+}
+
+#[no_mangle]
+pub extern "C" fn u_inotify_add_watch_ocall(
+    error: *mut c_int,
+    fd: c_int,
+    pathname: *const c_char,
+    mask: u32,
+) -> c_int {
+    let mut errno = 0;
+    let ret = unsafe { libc::inotify_add_watch(fd, pathname, mask) };
+    if ret < 0 {
+        errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+    }
+    if !error.is_null() {
+        unsafe {
+            *error = errno;
+        }
+    }
+    ret
+}
+
+#[no_mangle]
+pub extern "C" fn u_inotify_rm_watch_ocall(error: *mut c_int, fd: c_int, wd: c_int) -> c_int {
+    let mut errno = 0;
+    let ret = unsafe { libc::inotify_rm_watch(fd, wd) };
+    if ret < 0 {
+        errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+    }
+    if !error.is_null() {
+        unsafe {
+            *error = errno;
+        }
+    }
+    ret
+}
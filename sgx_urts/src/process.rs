@@ -15,8 +15,222 @@
 // specific language governing permissions and limitations
 // under the License..
 
-use libc::{self, pid_t};
+use libc::{self, c_char, c_int, c_void, pid_t, size_t};
+use std::env;
+use std::ffi::CString;
+use std::io::Error;
+use std::mem;
+use std::os::unix::ffi::OsStringExt;
+use std::ptr;
+use std::slice;
 #[no_mangle]
 pub extern "C" fn u_getpid_ocall() -> pid_t {
     unsafe { libc::getpid() }
 }
+
+fn set_error(error: *mut c_int, errno: c_int) {
+    if !error.is_null() {
+        unsafe {
+            *error = errno;
+        }
+    }
+}
+
+fn last_errno() -> c_int {
+    Error::last_os_error().raw_os_error().unwrap_or(0)
+}
+
+// Splits a buffer of NUL-terminated strings.
+fn split_blob(blob: *const c_char, len: size_t) -> Option<Vec<CString>> {
+    if blob.is_null() || len == 0 {
+        return Some(Vec::new());
+    }
+    let blob = unsafe { slice::from_raw_parts(blob as *const u8, len) };
+    if blob[len - 1] != 0 {
+        return None;
+    }
+    blob[..len - 1]
+        .split(|&b| b == 0)
+        .map(|s| CString::new(s).ok())
+        .collect()
+}
+
+// `args` holds the program followed by its arguments, and `env` holds
+// `KEY=VALUE` entries to set and `KEY` entries to remove, applied on top of
+// this process's environment unless `env_clear` is set. Each `stdio` entry is
+// a descriptor to install as the child's stdin, stdout or stderr, -1 to
+// inherit it, or -2 to connect it to /dev/null.
+#[no_mangle]
+pub extern "C" fn u_spawn_ocall(
+    error: *mut c_int,
+    args: *const c_char,
+    args_len: size_t,
+    env: *const c_char,
+    env_len: size_t,
+    env_clear: c_int,
+    cwd: *const c_char,
+    stdio: *const c_int,
+) -> pid_t {
+    let argv = match split_blob(args, args_len) {
+        Some(argv) if !argv.is_empty() && !argv[0].as_bytes().is_empty() => argv,
+        _ => {
+            set_error(error, libc::EINVAL);
+            return -1;
+        }
+    };
+    let changes = match split_blob(env, env_len) {
+        Some(changes) => changes,
+        None => {
+            set_error(error, libc::EINVAL);
+            return -1;
+        }
+    };
+    let stdio: [c_int; 3] = if stdio.is_null() {
+        [-1; 3]
+    } else {
+        unsafe { [*stdio, *stdio.add(1), *stdio.add(2)] }
+    };
+    if stdio.iter().any(|&fd| fd < -2) {
+        set_error(error, libc::EINVAL);
+        return -1;
+    }
+
+    let mut vars: Vec<(Vec<u8>, Vec<u8>)> = if env_clear != 0 {
+        Vec::new()
+    } else {
+        env::vars_os()
+            .map(|(k, v)| (k.into_vec(), v.into_vec()))
+            .collect()
+    };
+    for change in &changes {
+        let change = change.as_bytes();
+        let (key, value) = match change.iter().position(|&b| b == b'=') {
+            Some(pos) => (&change[..pos], Some(&change[pos + 1..])),
+            None => (change, None),
+        };
+        vars.retain(|(k, _)| k.as_slice() != key);
+        if let Some(value) = value {
+            vars.push((key.to_vec(), value.to_vec()));
+        }
+    }
+    let envp: Vec<CString> = vars
+        .into_iter()
+        .filter_map(|(mut k, v)| {
+            k.push(b'=');
+            k.extend_from_slice(&v);
+            CString::new(k).ok()
+        })
+        .collect();
+
+    let mut argv_ptrs: Vec<*const c_char> = argv.iter().map(|s| s.as_ptr()).collect();
+    argv_ptrs.push(ptr::null());
+    let mut envp_ptrs: Vec<*const c_char> = envp.iter().map(|s| s.as_ptr()).collect();
+    envp_ptrs.push(ptr::null());
+    let dev_null = b"/dev/null\0";
+
+    // The child reports a failure to exec through this pipe, which is closed
+    // without being written to when the exec succeeds.
+    let mut report = [0 as c_int; 2];
+    if unsafe { libc::pipe2(report.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+        set_error(error, last_errno());
+        return -1;
+    }
+
+    // Only async-signal-safe functions may be called in the child, so
+    // everything it needs is prepared above.
+    let pid = unsafe { libc::fork() };
+    if pid == 0 {
+        unsafe {
+            let fail = |report: c_int| -> ! {
+                let errno = last_errno().to_ne_bytes();
+                libc::write(report, errno.as_ptr() as *const c_void, errno.len());
+                libc::_exit(127)
+            };
+            // Move the sources out of the way first, so that installing one
+            // descriptor cannot overwrite the source of another.
+            let mut fds = [-1 as c_int; 3];
+            for (i, &fd) in stdio.iter().enumerate() {
+                fds[i] = match fd {
+                    -1 => -1,
+                    -2 => libc::open(dev_null.as_ptr() as *const c_char, libc::O_RDWR),
+                    fd => libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 3),
+                };
+                if fd != -1 && fds[i] < 0 {
+                    fail(report[1]);
+                }
+            }
+            for (i, &fd) in fds.iter().enumerate() {
+                if fd >= 0 && libc::dup2(fd, i as c_int) < 0 {
+                    fail(report[1]);
+                }
+            }
+            if !cwd.is_null() && libc::chdir(cwd) < 0 {
+                fail(report[1]);
+            }
+            let mut set: libc::sigset_t = mem::zeroed();
+            libc::sigemptyset(&mut set);
+            libc::sigprocmask(libc::SIG_SETMASK, &set, ptr::null_mut());
+            libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+            libc::execvpe(argv_ptrs[0], argv_ptrs.as_ptr(), envp_ptrs.as_ptr());
+            fail(report[1]);
+        }
+    }
+
+    let errno = if pid < 0 { last_errno() } else { 0 };
+    unsafe { libc::close(report[1]) };
+    if pid < 0 {
+        unsafe { libc::close(report[0]) };
+        set_error(error, errno);
+        return -1;
+    }
+
+    let mut buf = [0u8; 4];
+    let n = loop {
+        let n = unsafe { libc::read(report[0], buf.as_mut_ptr() as *mut c_void, buf.len()) };
+        if n >= 0 || last_errno() != libc::EINTR {
+            break n;
+        }
+    };
+    unsafe { libc::close(report[0]) };
+    if n == 0 {
+        set_error(error, 0);
+        return pid;
+    }
+    // The exec failed, so reap the child before reporting why.
+    let mut status = 0;
+    unsafe { libc::waitpid(pid, &mut status, 0) };
+    let errno = if n == buf.len() as isize {
+        c_int::from_ne_bytes(buf)
+    } else {
+        libc::EIO
+    };
+    set_error(error, errno);
+    -1
+}
+
+#[no_mangle]
+pub extern "C" fn u_waitpid_ocall(
+    error: *mut c_int,
+    pid: pid_t,
+    status: *mut c_int,
+    options: c_int,
+) -> pid_t {
+    let mut errno = 0;
+    let ret = unsafe { libc::waitpid(pid, status, options) };
+    if ret < 0 {
+        errno = last_errno();
+    }
+    set_error(error, errno);
+    ret
+}
+
+#[no_mangle]
+pub extern "C" fn u_kill_ocall(error: *mut c_int, pid: pid_t, sig: c_int) -> c_int {
+    let mut errno = 0;
+    let ret = unsafe { libc::kill(pid, sig) };
+    if ret < 0 {
+        errno = last_errno();
+    }
+    set_error(error, errno);
+    ret
+}
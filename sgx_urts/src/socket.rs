@@ -382,6 +382,52 @@ pub extern "C" fn u_setsockopt_ocall(
     ret
 }
 
+#[no_mangle]
+pub extern "C" fn u_setsockopts_ocall(
+    error: *mut c_int,
+    failed: *mut size_t,
+    sockfd: c_int,
+    levels: *const c_int,
+    optnames: *const c_int,
+    optlens: *const socklen_t,
+    optvals: *const c_void,
+    optvals_len: size_t,
+    count: size_t,
+) -> c_int {
+    let mut errno = 0;
+    let mut ret = 0;
+    let mut offset: size_t = 0;
+    let mut index: size_t = 0;
+    while index < count {
+        let (level, optname, optlen) =
+            unsafe { (*levels.add(index), *optnames.add(index), *optlens.add(index)) };
+        if optvals_len - offset < optlen as size_t {
+            errno = libc::EINVAL;
+            ret = -1;
+            break;
+        }
+        let optval = unsafe { (optvals as *const u8).add(offset) as *const c_void };
+        ret = unsafe { libc::setsockopt(sockfd, level, optname, optval, optlen) };
+        if ret < 0 {
+            errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+            break;
+        }
+        offset += optlen as size_t;
+        index += 1;
+    }
+    if !error.is_null() {
+        unsafe {
+            *error = errno;
+        }
+    }
+    if !failed.is_null() {
+        unsafe {
+            *failed = index;
+        }
+    }
+    ret
+}
+
 #[no_mangle]
 pub extern "C" fn u_getsockname_ocall(
     error: *mut c_int,
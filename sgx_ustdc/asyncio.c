@@ -21,8 +21,15 @@
 
 #include <sys/types.h>
 #include <sys/epoll.h>
+#include <sys/eventfd.h>
 #include <poll.h>
 #include <errno.h>
+#include <fcntl.h>
+#include <pthread.h>
+#include <sched.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include <unistd.h>
 
 int u_poll_ocall(int *error, struct pollfd *fds, nfds_t nfds, int timeout)
 {
@@ -58,4 +65,279 @@ int u_epoll_wait_ocall(int *error, int epfd, struct epoll_event *events, int max
         *error = ret == -1 ? errno : 0;
     }
     return ret;
-}
\ No newline at end of file
+}
+
+/* Operation codes of u_aio_submit_ocall. */
+#define AIO_OP_READ       0
+#define AIO_OP_WRITE      1
+#define AIO_OP_FSYNC      2
+#define AIO_OP_FDATASYNC  3
+
+/*
+ * The completion ring shared with the enclave: this header followed by
+ * `entries` completions. Workers advance `tail` as they post completions
+ * and the enclave advances `head` as it consumes them.
+ */
+struct aio_ring {
+    uint32_t head;
+    uint32_t tail;
+    uint32_t entries;
+    uint32_t pad;
+};
+
+struct aio_completion {
+    uint64_t user_data;
+    /* Bytes transferred, or a negated errno. */
+    int64_t res;
+};
+
+struct aio_job {
+    struct aio_job *next;
+    uint64_t user_data;
+    int opcode;
+    /*
+     * A duplicate owned by the job, so that closing the file in the enclave
+     * cannot redirect a queued operation to another file.
+     */
+    int fd;
+    void *buf;
+    size_t len;
+    int64_t offset;
+};
+
+struct aio_context {
+    struct aio_ring *ring;
+    uint32_t entries;
+    int notify_fd;
+    pthread_mutex_t lock;
+    pthread_cond_t queued;
+    struct aio_job *head;
+    struct aio_job *tail;
+    int shutdown;
+    /* Serializes workers posting to the ring. */
+    pthread_mutex_t post;
+    size_t nthreads;
+    pthread_t *threads;
+};
+
+static int64_t aio_execute(struct aio_job *job)
+{
+    for (;;) {
+        int64_t ret;
+        switch (job->opcode) {
+        case AIO_OP_READ:
+            ret = pread64(job->fd, job->buf, job->len, job->offset);
+            break;
+        case AIO_OP_WRITE:
+            ret = pwrite64(job->fd, job->buf, job->len, job->offset);
+            break;
+        case AIO_OP_FSYNC:
+            ret = fsync(job->fd);
+            break;
+        default:
+            ret = fdatasync(job->fd);
+            break;
+        }
+        if (ret >= 0) {
+            return ret;
+        }
+        if (errno != EINTR) {
+            return -(int64_t)errno;
+        }
+    }
+}
+
+static int aio_is_shutdown(struct aio_context *ctx)
+{
+    pthread_mutex_lock(&ctx->lock);
+    int shutdown = ctx->shutdown;
+    pthread_mutex_unlock(&ctx->lock);
+    return shutdown;
+}
+
+static void aio_post(struct aio_context *ctx, uint64_t user_data, int64_t res)
+{
+    struct aio_ring *ring = ctx->ring;
+    struct aio_completion *completions = (struct aio_completion *)(ring + 1);
+    uint64_t one = 1;
+
+    pthread_mutex_lock(&ctx->post);
+    uint32_t tail = __atomic_load_n(&ring->tail, __ATOMIC_RELAXED);
+    /*
+     * The enclave keeps no more operations in flight than the ring has
+     * entries, so this only waits for it to catch up.
+     */
+    while (tail - __atomic_load_n(&ring->head, __ATOMIC_ACQUIRE) >= ctx->entries) {
+        if (aio_is_shutdown(ctx)) {
+            pthread_mutex_unlock(&ctx->post);
+            return;
+        }
+        sched_yield();
+    }
+    completions[tail & (ctx->entries - 1)].user_data = user_data;
+    completions[tail & (ctx->entries - 1)].res = res;
+    __atomic_store_n(&ring->tail, tail + 1, __ATOMIC_RELEASE);
+    pthread_mutex_unlock(&ctx->post);
+
+    (void)write(ctx->notify_fd, &one, sizeof(one));
+}
+
+static void *aio_worker(void *arg)
+{
+    struct aio_context *ctx = arg;
+
+    for (;;) {
+        pthread_mutex_lock(&ctx->lock);
+        while (!ctx->shutdown && ctx->head == NULL) {
+            pthread_cond_wait(&ctx->queued, &ctx->lock);
+        }
+        if (ctx->shutdown) {
+            pthread_mutex_unlock(&ctx->lock);
+            return NULL;
+        }
+        struct aio_job *job = ctx->head;
+        ctx->head = job->next;
+        if (ctx->head == NULL) {
+            ctx->tail = NULL;
+        }
+        pthread_mutex_unlock(&ctx->lock);
+
+        int64_t res = aio_execute(job);
+        close(job->fd);
+        aio_post(ctx, job->user_data, res);
+        free(job);
+    }
+}
+
+static void aio_shutdown(struct aio_context *ctx)
+{
+    pthread_mutex_lock(&ctx->lock);
+    ctx->shutdown = 1;
+    struct aio_job *job = ctx->head;
+    ctx->head = ctx->tail = NULL;
+    pthread_cond_broadcast(&ctx->queued);
+    pthread_mutex_unlock(&ctx->lock);
+
+    while (job != NULL) {
+        struct aio_job *next = job->next;
+        close(job->fd);
+        free(job);
+        job = next;
+    }
+    for (size_t i = 0; i < ctx->nthreads; i++) {
+        pthread_join(ctx->threads[i], NULL);
+    }
+    pthread_cond_destroy(&ctx->queued);
+    pthread_mutex_destroy(&ctx->lock);
+    pthread_mutex_destroy(&ctx->post);
+    close(ctx->notify_fd);
+    free(ctx->threads);
+    free(ctx->ring);
+    free(ctx);
+}
+
+void *u_aio_setup_ocall(int *error, size_t entries, size_t threads, int *notify_fd, void **ring)
+{
+    struct aio_context *ctx = NULL;
+    int err = 0;
+
+    if (notify_fd == NULL || ring == NULL || threads == 0 || entries == 0 ||
+        (entries & (entries - 1)) != 0 || entries > UINT32_MAX / 2) {
+        err = EINVAL;
+        goto out;
+    }
+    ctx = calloc(1, sizeof(*ctx));
+    if (ctx == NULL) {
+        err = ENOMEM;
+        goto out;
+    }
+    ctx->entries = (uint32_t)entries;
+    ctx->ring = calloc(1, sizeof(struct aio_ring) + entries * sizeof(struct aio_completion));
+    ctx->threads = calloc(threads, sizeof(pthread_t));
+    if (ctx->ring == NULL || ctx->threads == NULL) {
+        free(ctx->ring);
+        free(ctx->threads);
+        free(ctx);
+        ctx = NULL;
+        err = ENOMEM;
+        goto out;
+    }
+    ctx->ring->entries = (uint32_t)entries;
+    ctx->notify_fd = eventfd(0, EFD_NONBLOCK | EFD_CLOEXEC);
+    if (ctx->notify_fd == -1) {
+        err = errno;
+        free(ctx->ring);
+        free(ctx->threads);
+        free(ctx);
+        ctx = NULL;
+        goto out;
+    }
+    pthread_mutex_init(&ctx->lock, NULL);
+    pthread_mutex_init(&ctx->post, NULL);
+    pthread_cond_init(&ctx->queued, NULL);
+    for (; ctx->nthreads < threads; ctx->nthreads++) {
+        err = pthread_create(&ctx->threads[ctx->nthreads], NULL, aio_worker, ctx);
+        if (err != 0) {
+            aio_shutdown(ctx);
+            ctx = NULL;
+            goto out;
+        }
+    }
+    *notify_fd = ctx->notify_fd;
+    *ring = ctx->ring;
+out:
+    if (error) {
+        *error = err;
+    }
+    return ctx;
+}
+
+int u_aio_submit_ocall(int *error, void *ctx, uint64_t user_data, int opcode, int fd, void *buf, size_t len, int64_t offset)
+{
+    struct aio_context *aio = ctx;
+    struct aio_job *job = NULL;
+    int err = 0;
+
+    if (aio == NULL || opcode < AIO_OP_READ || opcode > AIO_OP_FDATASYNC) {
+        err = EINVAL;
+        goto out;
+    }
+    job = calloc(1, sizeof(*job));
+    if (job == NULL) {
+        err = ENOMEM;
+        goto out;
+    }
+    job->fd = fcntl(fd, F_DUPFD_CLOEXEC, 0);
+    if (job->fd == -1) {
+        err = errno;
+        free(job);
+        goto out;
+    }
+    job->user_data = user_data;
+    job->opcode = opcode;
+    job->buf = buf;
+    job->len = len;
+    job->offset = offset;
+
+    pthread_mutex_lock(&aio->lock);
+    if (aio->tail != NULL) {
+        aio->tail->next = job;
+    } else {
+        aio->head = job;
+    }
+    aio->tail = job;
+    pthread_cond_signal(&aio->queued);
+    pthread_mutex_unlock(&aio->lock);
+out:
+    if (error) {
+        *error = err;
+    }
+    return err == 0 ? 0 : -1;
+}
+
+void u_aio_destroy_ocall(void *ctx)
+{
+    if (ctx != NULL) {
+        aio_shutdown(ctx);
+    }
+}
@@ -18,6 +18,8 @@
 #define _LARGEFILE64_SOURCE
 
 #include <sys/types.h>
+#include <sys/file.h>
+#include <sys/inotify.h>
 #include <sys/ioctl.h>
 #include <sys/stat.h>
 #include <sys/syscall.h>
@@ -173,6 +175,15 @@ int u_fdatasync_ocall(int *error, int fd)
     return ret;
 }
 
+int u_flock_ocall(int *error, int fd, int operation)
+{
+    int ret = flock(fd, operation);
+    if (error) {
+        *error = ret == -1 ? errno : 0;
+    }
+    return ret;
+}
+
 int u_fchmod_ocall(int *error, int fd, mode_t mode)
 {
     int ret = fchmod(fd, mode);
@@ -315,4 +326,31 @@ int u_fstatat64_ocall(int *error,
         *error = ret == -1 ? errno : 0;
     }
     return ret;
-}
\ No newline at end of file
+}
+
+int u_inotify_init1_ocall(int *error, int flags)
+{
+    int ret = inotify_init1(flags);
+    if (error) {
+        *error = ret == -1 ? errno : 0;
+    }
+    return ret;
+}
+
+int u_inotify_add_watch_ocall(int *error, int fd, const char *pathname, uint32_t mask)
+{
+    int ret = inotify_add_watch(fd, pathname, mask);
+    if (error) {
+        *error = ret == -1 ? errno : 0;
+    }
+    return ret;
+}
+
+int u_inotify_rm_watch_ocall(int *error, int fd, int wd)
+{
+    int ret = inotify_rm_watch(fd, wd);
+    if (error) {
+        *error = ret == -1 ? errno : 0;
+    }
+    return ret;
+}
@@ -19,11 +19,268 @@
 #define _GNU_SOURCE
 #endif
 #include <sys/types.h>
+#include <sys/wait.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <pthread.h>
+#include <signal.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
 #include <unistd.h>
 
+extern char **environ;
+
 pid_t u_getpid_ocall()
 {
     return getpid();
 }
 
+/* How enclaves ended through process::exit or process::abort, by enclave id. */
+struct exit_status {
+    uint64_t eid;
+    int code;
+    int aborted;
+    struct exit_status *next;
+};
+
+static pthread_mutex_t exit_lock = PTHREAD_MUTEX_INITIALIZER;
+static struct exit_status *exit_statuses = NULL;
+
+void u_exit_ocall(uint64_t eid, int code, int aborted)
+{
+    struct exit_status *status;
+
+    pthread_mutex_lock(&exit_lock);
+    /* Only the first report counts, the enclave is locked right after it. */
+    for (status = exit_statuses; status != NULL; status = status->next) {
+        if (status->eid == eid) {
+            break;
+        }
+    }
+    if (status == NULL) {
+        status = malloc(sizeof(*status));
+        if (status != NULL) {
+            status->eid = eid;
+            status->code = code;
+            status->aborted = aborted != 0;
+            status->next = exit_statuses;
+            exit_statuses = status;
+        }
+    }
+    pthread_mutex_unlock(&exit_lock);
+}
+
+/* Returns 1 and fills in `code` and `aborted` if the enclave `eid` called
+ * process::exit or process::abort, and 0 otherwise. */
+int sgx_enclave_exit_status(uint64_t eid, int *code, int *aborted)
+{
+    struct exit_status *status;
+    int found = 0;
+
+    pthread_mutex_lock(&exit_lock);
+    for (status = exit_statuses; status != NULL; status = status->next) {
+        if (status->eid == eid) {
+            if (code) {
+                *code = status->code;
+            }
+            if (aborted) {
+                *aborted = status->aborted;
+            }
+            found = 1;
+            break;
+        }
+    }
+    pthread_mutex_unlock(&exit_lock);
+    return found;
+}
+
+/* Splits a buffer of NUL-terminated strings into a NULL-terminated array,
+ * leaving room for `extra` more entries. */
+static char **split_blob(const char *blob, size_t len, size_t extra, size_t *count)
+{
+    size_t n = 0;
+    size_t i;
+    char **list;
+
+    if (len > 0 && blob[len - 1] != '\0') {
+        return NULL;
+    }
+    for (i = 0; i < len; i++) {
+        if (blob[i] == '\0') {
+            n++;
+        }
+    }
+    list = calloc(n + extra + 1, sizeof(char *));
+    if (list == NULL) {
+        return NULL;
+    }
+    n = 0;
+    for (i = 0; i < len; i += strlen(blob + i) + 1) {
+        list[n++] = (char *)(blob + i);
+    }
+    *count = n;
+    return list;
+}
 
+static size_t key_len(const char *entry)
+{
+    const char *eq = strchr(entry, '=');
+    return eq ? (size_t)(eq - entry) : strlen(entry);
+}
+
+static void env_remove(char **envp, size_t *count, const char *key, size_t len)
+{
+    size_t i = 0;
+    while (i < *count) {
+        if (key_len(envp[i]) == len && strncmp(envp[i], key, len) == 0) {
+            envp[i] = envp[--*count];
+            envp[*count] = NULL;
+        } else {
+            i++;
+        }
+    }
+}
+
+/* `args` holds the program followed by its arguments, and `env` holds
+ * `KEY=VALUE` entries to set and `KEY` entries to remove, applied on top of
+ * this process's environment unless `env_clear` is set. Each `stdio` entry is
+ * a descriptor to install as the child's stdin, stdout or stderr, -1 to
+ * inherit it, or -2 to connect it to /dev/null. */
+pid_t u_spawn_ocall(int *error, const char *args, size_t args_len, const char *env,
+                    size_t env_len, int env_clear, const char *cwd, const int *stdio)
+{
+    char **argv = NULL;
+    char **changes = NULL;
+    char **envp = NULL;
+    size_t argc = 0;
+    size_t nchanges = 0;
+    size_t nenv = 0;
+    size_t nhost = 0;
+    size_t i;
+    int fds[3] = {-1, -1, -1};
+    int report[2];
+    int err = EINVAL;
+    pid_t pid = -1;
+
+    if (stdio != NULL) {
+        for (i = 0; i < 3; i++) {
+            fds[i] = stdio[i];
+            if (fds[i] < -2) {
+                goto out;
+            }
+        }
+    }
+    argv = split_blob(args, args ? args_len : 0, 0, &argc);
+    changes = split_blob(env, env ? env_len : 0, 0, &nchanges);
+    if (argv == NULL || changes == NULL || argc == 0 || argv[0][0] == '\0') {
+        goto out;
+    }
+
+    if (!env_clear && environ != NULL) {
+        while (environ[nhost] != NULL) {
+            nhost++;
+        }
+    }
+    envp = calloc(nhost + nchanges + 1, sizeof(char *));
+    if (envp == NULL) {
+        err = ENOMEM;
+        goto out;
+    }
+    for (i = 0; i < nhost; i++) {
+        envp[nenv++] = environ[i];
+    }
+    for (i = 0; i < nchanges; i++) {
+        env_remove(envp, &nenv, changes[i], key_len(changes[i]));
+        if (strchr(changes[i], '=') != NULL) {
+            envp[nenv++] = changes[i];
+        }
+    }
+
+    /* The child reports a failure to exec through this pipe, which is closed
+     * without being written to when the exec succeeds. */
+    if (pipe2(report, O_CLOEXEC) == -1) {
+        err = errno;
+        goto out;
+    }
+
+    pid = fork();
+    if (pid == 0) {
+        int moved[3] = {-1, -1, -1};
+        sigset_t set;
+
+        /* Move the sources out of the way first, so that installing one
+         * descriptor cannot overwrite the source of another. */
+        for (i = 0; i < 3; i++) {
+            if (fds[i] == -2) {
+                moved[i] = open("/dev/null", O_RDWR);
+            } else if (fds[i] >= 0) {
+                moved[i] = fcntl(fds[i], F_DUPFD_CLOEXEC, 3);
+            }
+            if (fds[i] != -1 && moved[i] == -1) {
+                goto fail;
+            }
+        }
+        for (i = 0; i < 3; i++) {
+            if (moved[i] >= 0 && dup2(moved[i], (int)i) == -1) {
+                goto fail;
+            }
+        }
+        if (cwd != NULL && chdir(cwd) == -1) {
+            goto fail;
+        }
+        sigemptyset(&set);
+        sigprocmask(SIG_SETMASK, &set, NULL);
+        signal(SIGPIPE, SIG_DFL);
+        execvpe(argv[0], argv, envp);
+fail:
+        err = errno;
+        (void)!write(report[1], &err, sizeof(err));
+        _exit(127);
+    }
+
+    err = pid == -1 ? errno : 0;
+    close(report[1]);
+    if (pid != -1) {
+        int child_err;
+        ssize_t n;
+        do {
+            n = read(report[0], &child_err, sizeof(child_err));
+        } while (n == -1 && errno == EINTR);
+        if (n != 0) {
+            /* The exec failed, so reap the child before reporting why. */
+            int status;
+            waitpid(pid, &status, 0);
+            err = n == sizeof(child_err) ? child_err : EIO;
+            pid = -1;
+        }
+    }
+    close(report[0]);
+
+out:
+    free(argv);
+    free(changes);
+    free(envp);
+    if (error) {
+        *error = pid == -1 ? err : 0;
+    }
+    return pid;
+}
+
+pid_t u_waitpid_ocall(int *error, pid_t pid, int *status, int options)
+{
+    pid_t ret = waitpid(pid, status, options);
+    if (error) {
+        *error = ret == -1 ? errno : 0;
+    }
+    return ret;
+}
+
+int u_kill_ocall(int *error, pid_t pid, int sig)
+{
+    int ret = kill(pid, sig);
+    if (error) {
+        *error = ret == -1 ? errno : 0;
+    }
+    return ret;
+}